@@ -0,0 +1,43 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use database::{id::PermissionId, managers::PermissionRepository};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// The prefix telling the personal access tokens from the tokens of the logins
+pub const ACCESS_TOKEN_PREFIX: &str = "pat_";
+
+/// Generates a personal access token, only its hash is stored
+pub fn generate() -> String {
+    let mut bytes = [0; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{ACCESS_TOKEN_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes))
+}
+
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Returns the ids of the permissions named by the scopes, the permissions removed meanwhile are left out
+pub async fn scope_ids(permissions: &dyn PermissionRepository, scopes: &[String]) -> Vec<PermissionId> {
+    let mut ids = Vec::new();
+    for scope in scopes {
+        if let Ok(id) = permissions.get_permission_id(scope).await {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, hash, ACCESS_TOKEN_PREFIX};
+
+    #[test]
+    fn test_generate() {
+        let token = generate();
+        assert!(token.starts_with(ACCESS_TOKEN_PREFIX));
+        assert_ne!(token, generate());
+        assert_eq!(hash(&token), hash(&token));
+        assert_ne!(hash(&token), hash(&generate()));
+    }
+}
@@ -2,43 +2,98 @@
 use rocket::{fairing::AdHoc, *};
 
 use database::*;
+use database::migrations::{MigrationOutcome, Migrator};
 use rocket_okapi::mount_endpoints_and_merged_docs;
 use rocket_okapi::rapidoc::*;
 use rocket_okapi::settings::UrlObject;
 
 use telemetry::TelemetrySettings;
 
-use crate::api_telemetry::TelemetryFairing;
+use crate::api_telemetry::{self, ApiMetrics, TelemetryFairing};
+use crate::mail::MailFairing;
+use crate::oidc::OidcClient;
+use crate::rate_limit::RateLimitFairing;
 use crate::settings::ApiSettings;
-use crate::{cors::CORS, route::ApiRoute, Server};
+use crate::{cors::CORS, metrics, route::{ApiRoute, Draining}, Server};
 
-fn init_telemetry(settings: TelemetrySettings) -> AdHoc {
-        AdHoc::on_ignite("Launching telemetry", |rocket| async {
-           telemetry::start_telemetry(settings);
+fn init_telemetry(settings: TelemetrySettings, prometheus: bool) -> AdHoc {
+        AdHoc::on_ignite("Launching telemetry", move |rocket| async move {
+           let telemetry = telemetry::start_telemetry(settings, prometheus);
+            let metrics = ApiMetrics::new(telemetry.meter("api"));
 
-            rocket.attach(TelemetryFairing)
+            let rocket = rocket.attach(TelemetryFairing).manage(telemetry.health).manage(metrics);
+            match telemetry.prometheus {
+                Some(exporter) => rocket.manage(exporter),
+                None => rocket,
+            }
         })
 }
 
 fn init_db(settings: DatabaseSettings) -> AdHoc {
-    AdHoc::on_ignite("Connecting to MongoDB", |rocket| async {
+    // The launch is aborted when the database stays unreachable after the retries
+    AdHoc::try_on_ignite("Connecting to MongoDB", |rocket| async {
         match Database::init(&settings).await {
-            Ok(database) => {
-                rocket.manage(settings).manage(database)
-            },
+            Ok(database) => Ok(rocket.manage(settings).manage(database)),
             Err(error) => {
-                panic!("Cannot connect to MongoDB instance:: {error:?}");
+                tracing::error!("Cannot connect to MongoDB instance: {error}");
+                Err(rocket)
             }
         }
     })
 }
 
-pub fn get_rocket() -> Rocket<Build> {
-    let settings = ApiSettings::retrieve();
+fn init_oidc(settings: ApiSettings) -> AdHoc {
+    AdHoc::try_on_ignite("Configuring the OpenID Connect providers", |rocket| async move {
+        match OidcClient::new(settings.oidc, &settings.mail.public_url) {
+            Ok(client) => Ok(rocket.manage(client)),
+            Err(error) => {
+                tracing::error!("Cannot create the OpenID Connect client: {error}");
+                Err(rocket)
+            }
+        }
+    })
+}
+
+// drain reports the API as not ready once the shutdown is triggered, Rocket then stops accepting
+// connections and waits for the in-flight requests during the grace period of Rocket.toml
+fn drain() -> AdHoc {
+    AdHoc::on_shutdown("Draining requests", |rocket| {
+        Box::pin(async move {
+            if let Some(draining) = rocket.state::<Draining>() {
+                draining.start();
+            }
+        })
+    })
+}
+
+/// Installs the logger of the process, as configured by the `logging` settings
+pub fn init_logging(settings: &ApiSettings) -> Result<(), String> {
+    telemetry::init_logging(&settings.logging)
+}
+
+/// Applies the pending database migrations, or only lists them with `dry_run`
+pub async fn migrate(settings: &ApiSettings, dry_run: bool) -> Result<Vec<MigrationOutcome>, String> {
+    let db = Database::connect(&settings.database)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    Migrator::new(&db)
+        .run(dry_run)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+pub fn get_rocket(settings: ApiSettings) -> Rocket<Build> {
+    let metrics_enabled = settings.metrics.enabled;
     let mut rocket_builder = Rocket::build()
         .attach(init_db(settings.database.clone()))
-        .attach(init_telemetry(settings.telemetry.clone()))
-        .attach(CORS)
+        .attach(RateLimitFairing)
+        .attach(MailFairing)
+        .attach(init_oidc(settings.clone()))
+        .attach(init_telemetry(settings.telemetry.clone(), settings.metrics.enabled))
+        .attach(CORS::new(settings.cors.allowed_origins.clone()))
+        .attach(drain())
+        .manage(Draining::default())
         .manage(settings)
         .mount(
             "/rapidoc/",
@@ -63,6 +118,11 @@ pub fn get_rocket() -> Rocket<Build> {
         "/user" => ApiRoute::User.retrieve_routes(),
         "/organization" => ApiRoute::Organization.retrieve_routes(),
         "/asset" => ApiRoute::Asset.retrieve_routes(),
+        "/moderation" => ApiRoute::Moderation.retrieve_routes(),
+        "/health" => ApiRoute::Health.retrieve_routes(),
     };
+    if metrics_enabled {
+        rocket_builder = rocket_builder.mount("/", api_telemetry::traced(routes![metrics::metrics]));
+    }
     rocket_builder.manage(Server::default())
 }
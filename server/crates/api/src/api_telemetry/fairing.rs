@@ -1,10 +1,34 @@
 use database::Database;
-use opentelemetry::{global, KeyValue, trace::{Tracer, Span}};
+use opentelemetry::{
+    global,
+    trace::{SpanKind, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use rand::{distributions::Alphanumeric, Rng};
 use rocket::fairing::{Fairing, Info, Kind};
-use rocket::{Request, Response};
+use rocket::http::Header;
+use rocket::{Data, Request, Response};
 use rocket::*;
+use std::time::{Duration, Instant};
 
-use crate::api_telemetry;
+use crate::api_telemetry::{self, ApiMetrics};
+use crate::model::user_token::RequestUser;
+use crate::settings::ApiSettings;
+
+/// The header carrying the identifier of the request, it is generated when the client does not send one
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The header carrying the trace id of the request span, the `traceresponse` header carries the full context
+pub const TRACE_ID_HEADER: &str = "X-Trace-Id";
+
+/// The identifier of the request, kept in the request cache
+pub struct RequestId(pub String);
+
+/// The context holding the span of the request, opened when it is received and closed with the response
+pub struct RequestContext(pub Context);
+
+/// When the request was received, to measure its duration
+struct RequestStart(Instant);
 
 pub struct TelemetryFairing;
 
@@ -13,27 +37,154 @@ impl Fairing for TelemetryFairing {
     fn info(&self) -> Info {
         Info {
             name: "Logging requests to SigNoz",
-            kind: Kind::Response | Kind::Liftoff,
+            kind: Kind::Request | Kind::Response | Kind::Liftoff,
         }
     }
 
-    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
-        
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let request_id = request
+            .headers()
+            .get_one(REQUEST_ID_HEADER)
+            .filter(|id| !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_graphic()))
+            .map(str::to_string)
+            .unwrap_or_else(generate_request_id);
+
+        let mut attributes = vec![
+            KeyValue::new("http.request.method", request.method().as_str()),
+            KeyValue::new("request.id", request_id.clone()),
+        ];
+        if let Some(client) = request.client_ip() {
+            attributes.push(KeyValue::new("client.address", client.to_string()));
+        }
+        if let Some(user_agent) = request.headers().get_one("User-Agent") {
+            attributes.push(KeyValue::new("user_agent.original", user_agent.to_string()));
+        }
+
+        // The span continues the trace of the caller when it sends a `traceparent` header
+        let trace_headers = ["traceparent", "tracestate"]
+            .into_iter()
+            .filter_map(|name| Some((name, request.headers().get_one(name)?)));
+        let parent = telemetry::extract_context(trace_headers);
+
+        // The route is not known yet, the name and `http.route` are completed with its template on response,
+        // the path itself is not recorded as some routes carry a token in it
         let tracer = global::tracer("api");
-        
-        let mut span = tracer.start("request");
+        let span = tracer
+            .span_builder(request.method().as_str())
+            .with_kind(SpanKind::Server)
+            .with_attributes(attributes)
+            .start_with_context(&tracer, &parent);
 
-        span.set_attribute(KeyValue::new("request", format!("{:#}", request)));
-        span.set_attribute(KeyValue::new("response", format!("{:?}", response)));
+        request.local_cache(|| RequestId(request_id));
+        request.local_cache(|| RequestContext(Context::current_with_span(span)));
+        request.local_cache(|| RequestStart(Instant::now()));
     }
-    
-    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
-        // Retrieve the State<Database> and pass it to the telemetry start function
-        if let Some(database) = rocket.state::<Database>() {
-            api_telemetry::start(database.clone());
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let request_id = request.local_cache(|| RequestId(generate_request_id()));
+        response.set_header(Header::new(REQUEST_ID_HEADER, request_id.0.clone()));
+
+        let context = &request.local_cache(|| RequestContext(Context::new())).0;
+        let span = context.span();
+        if let Some(trace_response) = telemetry::trace_response(context) {
+            response.set_header(Header::new(TRACE_ID_HEADER, span.span_context().trace_id().to_string()));
+            response.set_header(Header::new("traceresponse", trace_response));
+        }
+        if let Some(route) = request.route() {
+            span.update_name(format!("{} {}", request.method(), route.uri.path()));
+            span.set_attribute(KeyValue::new("http.route", route.uri.path().to_string()));
+        }
+        if let RequestUser(Some(user_id)) = request.local_cache(|| RequestUser(None)) {
+            span.set_attribute(KeyValue::new("enduser.id", user_id.to_string()));
+        }
+
+        let status = response.status();
+        let duration = request.local_cache(|| RequestStart(Instant::now())).0.elapsed();
+        let route = request.route().map(|route| route.uri.path().to_string());
+        // The route template is logged rather than the path, which carries the tokens of some routes
+        let (method, duration_ms) = (request.method(), duration.as_millis() as u64);
+        let logged_route = route.as_deref().unwrap_or("unmatched");
+        if status.code >= 500 {
+            tracing::error!(request_id = %request_id.0, %method, route = logged_route, status = status.code, duration_ms, "request failed");
         } else {
-            eprintln!("Database state not found");
+            tracing::info!(request_id = %request_id.0, %method, route = logged_route, status = status.code, duration_ms, "request completed");
+        }
+        if let Some(metrics) = request.rocket().state::<ApiMetrics>() {
+            metrics.request(request.method().as_str(), route, status.code, duration);
+        }
+        span.set_attribute(KeyValue::new("http.response.status_code", i64::from(status.code)));
+        // The client errors are the client's fault, they are not errors of the server span
+        if status.code >= 500 {
+            span.set_status(Status::error(status.reason_lossy().to_string()));
         }
+        span.end();
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (Some(database), Some(metrics)) = (rocket.state::<Database>(), rocket.state::<ApiMetrics>()) else {
+            tracing::warn!("The database or the metrics are not managed, the gauges are not measured");
+            return;
+        };
+        let interval = rocket
+            .state::<ApiSettings>()
+            .map_or(15_000, |settings| settings.metrics.collect_interval_ms);
+        api_telemetry::start(database.clone(), metrics, Duration::from_millis(interval), rocket.shutdown());
+    }
+
+}
+
+fn generate_request_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rocket::http::{Header, Status};
+
+    use super::{REQUEST_ID_HEADER, TRACE_ID_HEADER};
+    use crate::testing::run_test;
+
+    #[rocket::async_test]
+    async fn test_request_id() {
+        run_test(|client| async move {
+            let response = client.get("/health/live").dispatch().await;
+            assert_eq!(response.status(), Status::Ok);
+            let generated = response.headers().get_one(REQUEST_ID_HEADER).unwrap();
+            assert_eq!(generated.len(), 16);
+
+            let response = client
+                .get("/health/live")
+                .header(Header::new(REQUEST_ID_HEADER, "client-id-42"))
+                .dispatch()
+                .await;
+            assert_eq!(response.headers().get_one(REQUEST_ID_HEADER), Some("client-id-42"));
+        })
+        .await;
     }
 
+    #[rocket::async_test]
+    async fn test_trace_context() {
+        run_test(|client| async move {
+            let response = client
+                .get("/health/live")
+                .header(Header::new("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"))
+                .dispatch()
+                .await;
+
+            assert_eq!(
+                response.headers().get_one(TRACE_ID_HEADER),
+                Some("4bf92f3577b34da6a3ce929d0e0e4736")
+            );
+            let trace_response = response.headers().get_one("traceresponse").unwrap();
+            assert!(trace_response.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+            // The span of the API is a child of the caller's span
+            assert!(!trace_response.contains("00f067aa0ba902b7"));
+        })
+        .await;
+    }
 }
@@ -0,0 +1,46 @@
+use opentelemetry::trace::{FutureExt, TraceContextExt};
+use opentelemetry::Context;
+use rocket::route::{Handler, Outcome};
+use rocket::{Data, Request, Route};
+use tracing::Instrument;
+
+use super::{RequestContext, RequestId};
+
+/// Runs the handler of a route, and its request guards, within the span of the request,
+/// so that the spans they start, such as the ones of the MongoDB commands, are its children
+///
+/// They also run within a `tracing` span carrying the request id, for every log line to carry it.
+/// The span carries the template of the route and not the path, which holds the tokens of some routes.
+#[derive(Clone)]
+pub struct TracedHandler(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for TracedHandler {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        let context = request.local_cache(|| RequestContext(Context::new())).0.clone();
+        let request_id = &request.local_cache(|| RequestId(String::new())).0;
+        let span_context = context.span().span_context().clone();
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id,
+            method = %request.method(),
+            route = request.route().map_or("unmatched", |route| route.uri.path()),
+            trace_id = tracing::field::Empty,
+        );
+        if span_context.is_valid() {
+            span.record("trace_id", tracing::field::display(span_context.trace_id()));
+        }
+        self.0.handle(request, data).with_context(context).instrument(span).await
+    }
+}
+
+/// Wraps the handlers of the routes in a `TracedHandler`
+pub fn traced(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(TracedHandler(route.handler));
+            route
+        })
+        .collect()
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use database::authentication::Authentication;
+use opentelemetry::{
+    metrics::{Counter, Histogram, Meter, Unit},
+    KeyValue,
+};
+
+/// How a license check ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LicenseCheckOutcome {
+    Valid,
+    Invalid,
+    UnknownUser,
+    Error,
+}
+
+impl LicenseCheckOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Valid => "valid",
+            Self::Invalid => "invalid",
+            Self::UnknownUser => "unknown_user",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// The instruments recording the business events and the requests, created once when the API starts.
+pub struct ApiMetrics {
+    // Kept to register the gauges once the database is connected
+    pub(crate) meter: Meter,
+    logins: Counter<u64>,
+    registrations: Counter<u64>,
+    license_checks: Counter<u64>,
+    request_duration: Histogram<f64>,
+}
+
+impl ApiMetrics {
+    pub fn new(meter: Meter) -> Self {
+        Self {
+            logins: meter
+                .u64_counter("logins")
+                .with_description("Number of tokens created by a login, by authentication method")
+                .init(),
+            registrations: meter
+                .u64_counter("registrations")
+                .with_description("Number of users registered, by authentication method")
+                .init(),
+            license_checks: meter
+                .u64_counter("license_checks")
+                .with_description("Number of license checks, by outcome")
+                .init(),
+            request_duration: meter
+                .f64_histogram("http.server.duration")
+                .with_description("Duration of the requests, by method, route and status")
+                .with_unit(Unit::new("ms"))
+                .init(),
+            meter,
+        }
+    }
+
+    pub fn login(&self, method: &Authentication) {
+        self.logins.add(1, &[KeyValue::new("method", method.get_name())]);
+    }
+
+    pub fn registration(&self, method: &Authentication) {
+        self.registrations.add(1, &[KeyValue::new("method", method.get_name())]);
+    }
+
+    pub fn license_check(&self, outcome: LicenseCheckOutcome) {
+        self.license_checks.add(1, &[KeyValue::new("outcome", outcome.as_str())]);
+    }
+
+    /// Records a request, `route` is the template of the route, None when no route matched
+    pub fn request(&self, method: &str, route: Option<String>, status: u16, duration: Duration) {
+        let attributes = [
+            KeyValue::new("http.request.method", method.to_string()),
+            KeyValue::new("http.route", route.unwrap_or_default()),
+            KeyValue::new("http.response.status_code", i64::from(status)),
+        ];
+        self.request_duration.record(duration.as_secs_f64() * 1000.0, &attributes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rocket::http::{Header, Method};
+
+    use crate::settings::ApiSettings;
+    use crate::testing::{self, dispatch_request, run_test_with_settings};
+
+    fn enable_metrics(settings: &mut ApiSettings) {
+        settings.metrics.enabled = true;
+    }
+
+    async fn scrape(client: &rocket::local::asynchronous::Client) -> String {
+        client
+            .get("/metrics")
+            .header(Header::new("Accept", "text/plain"))
+            .dispatch()
+            .await
+            .into_string()
+            .await
+            .unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn test_request_duration() {
+        run_test_with_settings(enable_metrics, |client| async move {
+            client.get("/health/live").dispatch().await;
+
+            let metrics = scrape(&client).await;
+            assert!(metrics.contains("# TYPE http_server_duration histogram"));
+            assert!(metrics.contains(r#"http_route="/health/live""#));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_license_checks() {
+        run_test_with_settings(enable_metrics, |client| async move {
+            let database = client.rocket().state::<database::Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = user.get_token().unwrap();
+
+            dispatch_request(
+                &client,
+                Method::Post,
+                format!("/user/{}/license/unknown", user.unique_id),
+                None,
+                Some(token.to_string()),
+            )
+            .await;
+
+            let metrics = scrape(&client).await;
+            assert!(metrics.contains(r#"license_checks_total{outcome="invalid"} 1"#));
+        })
+        .await;
+    }
+}
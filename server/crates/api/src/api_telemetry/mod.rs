@@ -1,61 +1,211 @@
 mod system_usage;
 mod fairing;
+mod handler;
+mod instruments;
 
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use database::id::OrganizationId;
 use database::Database;
-use rocket::tokio::runtime::Runtime;
+use opentelemetry::KeyValue;
+use rocket::Shutdown;
 use system_usage::*;
-use std::thread;
-use opentelemetry::global;
 
 pub use fairing::*;
+pub use handler::*;
+pub use instruments::*;
+
+/// The values of the gauges, measured in the background and read when the metrics are collected
+#[derive(Default)]
+struct Measures {
+    users: u64,
+    peers: u64,
+    active_peers: Vec<(OrganizationId, u64)>,
+    cpu_usage: f64,
+    ram_usage: f64,
+}
 
-pub fn start(database: Database) {
-    thread::spawn(move || {
-        let rt = Runtime::new().unwrap();
-        
-        rt.block_on(async {
-            loop {
-                cpu_telemetry();
-                ram_telemetry();
-                user_telemetry(&database).await;
-                peer_telemetry(&database).await;
+/// Registers the gauges, then measures their values every `interval` until the shutdown
+pub fn start(database: Database, metrics: &ApiMetrics, interval: Duration, shutdown: Shutdown) {
+    let measures = Arc::new(Mutex::new(Measures::default()));
+    register_gauges(metrics, &database, &measures);
+
+    rocket::tokio::spawn(async move {
+        let mut ticks = rocket::tokio::time::interval(interval);
+        rocket::tokio::pin!(shutdown);
+        loop {
+            rocket::tokio::select! {
+                _ = ticks.tick() => measure(&database, &measures).await,
+                _ = &mut shutdown => break,
             }
-        });
+        }
     });
 }
 
-async fn user_telemetry(database: &Database) {
-    let number_of_users = database.user_manager.users.count_documents(None, None).await.unwrap();
-    let meter = global::meter("api");
-    let cpu_gauge = meter.u64_observable_gauge("users")
+fn register_gauges(metrics: &ApiMetrics, database: &Database, measures: &Arc<Mutex<Measures>>) {
+    let meter = &metrics.meter;
+
+    let values = measures.clone();
+    meter
+        .u64_observable_gauge("users")
         .with_description("Number of users")
+        .with_callback(move |observer| observer.observe(values.lock().unwrap().users, &[]))
         .init();
-    cpu_gauge.observe(number_of_users, [].as_ref());
-}
 
-async fn peer_telemetry(database: &Database) {
-    let number_of_peers = database.peers_manager.peers.count_documents(None, None).await.unwrap();
-    let meter = global::meter("api");
-    let cpu_gauge = meter.u64_observable_gauge("peers")
+    let values = measures.clone();
+    meter
+        .u64_observable_gauge("peers")
         .with_description("Number of peers")
+        .with_callback(move |observer| observer.observe(values.lock().unwrap().peers, &[]))
         .init();
-    cpu_gauge.observe(number_of_peers, [].as_ref());
-}
 
-fn cpu_telemetry() {
-    let cpu_usage = get_cpu_usage();
-    let meter = global::meter("api");
-    let cpu_gauge = meter.f64_observable_gauge("cpu_usage")
+    let values = measures.clone();
+    meter
+        .u64_observable_gauge("active_peers")
+        .with_description("Number of peers of the servers of each organization")
+        .with_callback(move |observer| {
+            for (organization, peers) in &values.lock().unwrap().active_peers {
+                observer.observe(*peers, &[KeyValue::new("organization.id", organization.to_string())]);
+            }
+        })
+        .init();
+
+    let values = measures.clone();
+    meter
+        .f64_observable_gauge("cpu_usage")
         .with_description("CPU usage percentage")
+        .with_callback(move |observer| observer.observe(values.lock().unwrap().cpu_usage, &[]))
         .init();
-    cpu_gauge.observe(cpu_usage, [].as_ref());
-}
 
-fn ram_telemetry() {
-    let ram_usage = get_ram_usage();
-    let meter = global::meter("api");
-    let ram_gauge = meter.f64_observable_gauge("ram_usage")
+    let values = measures.clone();
+    meter
+        .f64_observable_gauge("ram_usage")
         .with_description("RAM usage in megabytes")
+        .with_callback(move |observer| observer.observe(values.lock().unwrap().ram_usage, &[]))
+        .init();
+
+    // The pool is read when the metrics are collected, it counts the driver events as they come
+    if database.pool_stats().is_none() {
+        return;
+    }
+    let pool = database.clone();
+    meter
+        .u64_observable_gauge("db.client.connections.usage")
+        .with_description("Number of MongoDB connections, by state")
+        .with_callback(move |observer| {
+            if let Some(stats) = pool.pool_stats() {
+                observer.observe(stats.in_use, &[KeyValue::new("state", "used")]);
+                observer.observe(stats.open.saturating_sub(stats.in_use), &[KeyValue::new("state", "idle")]);
+            }
+        })
+        .init();
+
+    let pool = database.clone();
+    meter
+        .u64_observable_counter("db.client.connections.checkout_failures")
+        .with_description("Number of operations that could not get a MongoDB connection")
+        .with_callback(move |observer| {
+            if let Some(stats) = pool.pool_stats() {
+                observer.observe(stats.checkout_failures, &[]);
+            }
+        })
         .init();
-    ram_gauge.observe(ram_usage, [].as_ref());
-}
\ No newline at end of file
+
+    let pool = database.clone();
+    meter
+        .u64_observable_counter("db.client.connections.pool_cleared")
+        .with_description("Number of times a MongoDB connection pool was cleared after an error")
+        .with_callback(move |observer| {
+            if let Some(stats) = pool.pool_stats() {
+                observer.observe(stats.cleared, &[]);
+            }
+        })
+        .init();
+}
+
+// measure keeps the previous values of the measures that fail
+async fn measure(database: &Database, measures: &Mutex<Measures>) {
+    let users = database.user_manager.count().await;
+    let peers = database.peers_manager.count().await;
+    let active_peers = active_peers(database).await;
+
+    let mut measures = measures.lock().unwrap();
+    match users {
+        Ok(users) => measures.users = users,
+        Err(err) => tracing::warn!("Failed to count the users: {err}"),
+    }
+    match peers {
+        Ok(peers) => measures.peers = peers,
+        Err(err) => tracing::warn!("Failed to count the peers: {err}"),
+    }
+    match active_peers {
+        Ok(active_peers) => measures.active_peers = active_peers,
+        Err(err) => tracing::warn!("Failed to count the peers of the organizations: {err}"),
+    }
+    measures.cpu_usage = get_cpu_usage();
+    measures.ram_usage = get_ram_usage();
+}
+
+async fn active_peers(database: &Database) -> Result<Vec<(OrganizationId, u64)>, mongodb::error::Error> {
+    let mut peers_by_server = HashMap::new();
+    for server_id in database.peers_manager.server_ids().await? {
+        *peers_by_server.entry(server_id).or_insert(0) += 1;
+    }
+
+    let organizations = database.organization_manager.servers_by_organization().await?;
+    Ok(organizations
+        .into_iter()
+        .map(|(organization, server_ids)| {
+            let peers = server_ids.iter().filter_map(|server_id| peers_by_server.get(server_id)).sum();
+            (organization, peers)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use database::{
+        id::{OrganizationId, RoomId, UserId},
+        organization::Organization,
+        peer::Peer,
+        timestamp::Timestamp,
+        Database,
+    };
+
+    use super::active_peers;
+
+    #[rocket::async_test]
+    async fn test_active_peers() {
+        let database = Database::in_memory().await.unwrap();
+        let server_id = UserId::generate();
+        let organization = Organization {
+            unique_id: OrganizationId::generate(),
+            creation_date: Timestamp::now(),
+            name: "Areation".to_string(),
+            member_ids: Vec::new(),
+            owner_id: UserId::generate(),
+            server_ids: vec![server_id, UserId::generate()],
+            projects_ids: Vec::new(),
+            require_two_factor: false,
+        };
+        database.organization_manager.create_organization(&organization).await.unwrap();
+        database
+            .peers_manager
+            .create_peer(&Peer {
+                room_id: RoomId::generate(),
+                creation_date: Timestamp::now(),
+                signaling_hostname: "localhost".to_string(),
+                signaling_port: 3536,
+                server_unique_id: server_id,
+            })
+            .await
+            .unwrap();
+
+        let peers = active_peers(&database).await.unwrap();
+
+        assert_eq!(peers, vec![(organization.unique_id, 1)]);
+    }
+}
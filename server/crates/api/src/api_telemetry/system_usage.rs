@@ -16,9 +16,11 @@ pub fn get_cpu_usage() -> f64 {
     (load / num_cpus * 100.0).min(100.0)
 }
 
+/// Returns the memory in use, in megabytes
 pub fn get_ram_usage() -> f64 {
     match mem_info() {
-        Ok(mem) => mem.total as f64 - mem.free as f64, // Total - Free memory
+        // The sizes are in kilobytes
+        Ok(mem) => (mem.total as f64 - mem.free as f64) / 1024.0,
         Err(_) => 0.0,
     }
 }
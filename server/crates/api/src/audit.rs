@@ -0,0 +1,118 @@
+use database::{
+    audit::{AuditAction, AuditEvent, AuditTargetKind},
+    id::{AuditEventId, UserId},
+    timestamp::Timestamp,
+    Database,
+};
+use rocket::request::{self, FromRequest, Outcome, Request};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::api_telemetry::RequestId;
+
+/// Where a request comes from, recorded with the audit events it causes
+pub struct AuditContext {
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuditContext {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let request_id = &request.local_cache(|| RequestId(String::new())).0;
+        Outcome::Success(AuditContext {
+            ip: request.client_ip().map(|ip| ip.to_string()),
+            request_id: (!request_id.is_empty()).then(|| request_id.clone()),
+        })
+    }
+}
+
+impl<'a> OpenApiFromRequest<'a> for AuditContext {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+impl AuditContext {
+    /// Creates an event of the request, without organization nor changes
+    pub fn event(
+        &self,
+        action: AuditAction,
+        actor_id: Option<UserId>,
+        target_kind: AuditTargetKind,
+        target_id: impl ToString,
+    ) -> AuditEvent {
+        AuditEvent {
+            unique_id: AuditEventId::generate(),
+            creation_date: Timestamp::now(),
+            action,
+            actor_id,
+            target_kind,
+            target_id: target_id.to_string(),
+            organization_id: None,
+            ip: self.ip.clone(),
+            request_id: self.request_id.clone(),
+            changes: None,
+        }
+    }
+
+    /// Appends the event to the audit log
+    ///
+    /// The action it records already happened, so a failure is logged rather than failing the request.
+    pub async fn record(&self, database: &Database, event: AuditEvent) {
+        if let Err(err) = database.audit_manager.record(&event).await {
+            tracing::error!(action = ?event.action, target_id = %event.target_id, "Cannot record the audit event: {err}");
+        }
+    }
+}
+
+/// Serializes the value for the audit log, without its secrets such as the passwords and the tokens
+pub fn snapshot(value: &impl Serialize) -> Value {
+    let mut value = serde_json::to_value(value).unwrap_or(Value::Null);
+    strip_secrets(&mut value);
+    value
+}
+
+fn strip_secrets(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            fields.retain(|name, _| !telemetry::is_sensitive(name));
+            fields.values_mut().for_each(strip_secrets);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_secrets),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::snapshot;
+
+    #[test]
+    fn test_snapshot() {
+        let user = json!({
+            "authentication": { "Credentials": { "email": "a@b.c", "password": "hunter2" } },
+            "logins": [{ "ip": "127.0.0.1", "token": "abc" }],
+        });
+
+        assert_eq!(
+            snapshot(&user),
+            json!({
+                "authentication": { "Credentials": { "email": "a@b.c" } },
+                "logins": [{ "ip": "127.0.0.1" }],
+            })
+        );
+    }
+}
@@ -5,7 +5,27 @@ use rocket::{Request, Response};
 use rocket::*;
 use rocket_okapi::openapi;
 
-pub struct CORS;
+/// Attaches the CORS headers, the `Origin` of the request is echoed when it is allowed
+pub struct CORS {
+    allowed_origins: Vec<String>,
+}
+
+impl CORS {
+    pub fn new(allowed_origins: Vec<String>) -> Self {
+        Self { allowed_origins }
+    }
+
+    fn allow_origin(&self, origin: Option<&str>) -> Option<String> {
+        if self.allowed_origins.iter().any(|allowed| allowed == "*") {
+            return Some("*".to_string());
+        }
+        let origin = origin?;
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == origin)
+            .then(|| origin.to_string())
+    }
+}
 
 #[rocket::async_trait]
 impl Fairing for CORS {
@@ -16,8 +36,17 @@ impl Fairing for CORS {
         }
     }
 
-    async fn on_response<'r>(&self, _request: &'r Request<'_>, response: &mut Response<'r>) {
-        response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        match self.allow_origin(request.headers().get_one("Origin")) {
+            Some(origin) if origin == "*" => {
+                response.set_header(Header::new("Access-Control-Allow-Origin", origin));
+            }
+            Some(origin) => {
+                response.set_header(Header::new("Access-Control-Allow-Origin", origin));
+                response.set_header(Header::new("Vary", "Origin"));
+            }
+            None => return,
+        }
         response.set_header(Header::new(
             "Access-Control-Allow-Methods",
             "POST, GET, PATCH, DELETE, OPTIONS",
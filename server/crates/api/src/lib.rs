@@ -3,17 +3,29 @@
 // #![feature(async_closure)]
 
 mod api;
-mod server;
 
+pub mod access_token;
 pub mod api_telemetry;
+pub mod audit;
 pub mod cors;
+pub mod mail;
+pub mod metrics;
 pub mod model;
+pub mod oidc;
+pub mod personal_data;
+pub mod rate_limit;
 pub mod route;
 pub mod settings;
 pub mod testing;
+pub mod two_factor;
 
-pub use crate::{api::*, server::*};
+pub use crate::api::*;
+pub use database::server::Server;
 
+use database::pagination::PaginationError;
+use oidc::OidcError;
+use personal_data::ErasureError;
+use rocket::http::Status;
 use rocket::response::status::Custom;
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::JsonSchema;
@@ -33,3 +45,25 @@ impl From<Custom<String>> for RequestError {
         }
     }
 }
+
+impl From<OidcError> for RequestError {
+    fn from(value: OidcError) -> Self {
+        Self::from(Custom(value.status(), value.to_string()))
+    }
+}
+
+impl From<ErasureError> for RequestError {
+    fn from(value: ErasureError) -> Self {
+        Self::from(Custom(value.status(), value.to_string()))
+    }
+}
+
+impl From<PaginationError> for RequestError {
+    fn from(value: PaginationError) -> Self {
+        let status = match value {
+            PaginationError::Database(_) => Status::InternalServerError,
+            _ => Status::BadRequest,
+        };
+        Self::from(Custom(status, value.to_string()))
+    }
+}
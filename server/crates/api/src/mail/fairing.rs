@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use database::Database;
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::{Build, Orbit, Rocket};
+
+use super::{transport, LinkSigner, Mailer};
+use crate::settings::ApiSettings;
+
+/// Builds the mailer on ignite, and starts sending the outbox on liftoff
+pub struct MailFairing;
+
+#[rocket::async_trait]
+impl Fairing for MailFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Sending mails",
+            kind: Kind::Ignite | Kind::Liftoff,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let (Some(settings), Some(database)) = (rocket.state::<ApiSettings>(), rocket.state::<Database>()) else {
+            tracing::error!("The mails cannot be queued before connecting to the database");
+            return Err(rocket);
+        };
+        let settings = settings.mail.clone();
+        let transport = match transport::from_settings(&settings) {
+            Ok(transport) => Arc::from(transport),
+            Err(err) => {
+                tracing::error!("Cannot configure the mail transport: {err}");
+                return Err(rocket);
+            }
+        };
+        let signer = match &settings.link_secret {
+            Some(secret) => LinkSigner::new(secret.as_bytes()),
+            None => {
+                tracing::warn!("mail.link_secret is not set, the links sent by mail stop working when the API restarts");
+                LinkSigner::random()
+            }
+        };
+
+        let mailer = Mailer::new(database.outbox_manager.clone(), transport, settings, signer);
+        Ok(rocket.manage(mailer))
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        if let Some(mailer) = rocket.state::<Mailer>() {
+            mailer.start(rocket.shutdown());
+        }
+    }
+}
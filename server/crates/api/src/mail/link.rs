@@ -0,0 +1,124 @@
+use std::fmt;
+
+use database::timestamp::Timestamp;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs the one-time links sent by mail, so that they are checked without being stored
+///
+/// A link carries its subject, usually a user id, and its expiry. The signature also covers the purpose
+/// of the link and a binding, a value of the subject that the use of the link changes: the link can then
+/// be used only once, as the binding no longer matches afterwards.
+pub struct LinkSigner {
+    key: Vec<u8>,
+}
+
+/// The parts of a signed link token, `<subject>.<expiry in milliseconds>.<signature>`
+#[derive(Debug, PartialEq, Eq)]
+pub struct SignedLink {
+    pub subject: String,
+    pub expires_at: Timestamp,
+    signature: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkError {
+    Malformed,
+    Expired,
+    /// The signature does not match, the link was forged or already used
+    Invalid,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "The link is malformed."),
+            Self::Expired => write!(f, "The link has expired."),
+            Self::Invalid => write!(f, "The link is invalid or was already used."),
+        }
+    }
+}
+
+impl LinkSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self { key: secret.to_vec() }
+    }
+
+    /// Generates a random key, the links then stop working when the API restarts
+    pub fn random() -> Self {
+        let mut key = vec![0; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self { key }
+    }
+
+    fn mac(&self, purpose: &str, subject: &str, binding: &str, expires_at: Timestamp) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        // The lengths separate the fields, which may contain any character
+        for field in [purpose, subject, binding, &expires_at.timestamp_millis().to_string()] {
+            mac.update(&(field.len() as u64).to_be_bytes());
+            mac.update(field.as_bytes());
+        }
+        mac
+    }
+
+    pub fn sign(&self, purpose: &str, subject: &str, binding: &str, expires_at: Timestamp) -> String {
+        let signature = self.mac(purpose, subject, binding, expires_at).finalize().into_bytes();
+        format!("{subject}.{}.{}", expires_at.timestamp_millis(), hex::encode(signature))
+    }
+
+    /// Splits a token into its parts, the subject is then used to find the binding the token is verified with
+    pub fn parse(token: &str) -> Result<SignedLink, LinkError> {
+        let mut parts = token.rsplitn(3, '.');
+        let (Some(signature), Some(expires_at), Some(subject)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(LinkError::Malformed);
+        };
+        Ok(SignedLink {
+            subject: subject.to_string(),
+            expires_at: Timestamp::from_millis(expires_at.parse().map_err(|_| LinkError::Malformed)?),
+            signature: hex::decode(signature).map_err(|_| LinkError::Malformed)?,
+        })
+    }
+
+    pub fn verify(&self, purpose: &str, link: &SignedLink, binding: &str, now: Timestamp) -> Result<(), LinkError> {
+        // The signature is checked first, so that an expired link is only reported as such when it is genuine
+        self.mac(purpose, &link.subject, binding, link.expires_at)
+            .verify_slice(&link.signature)
+            .map_err(|_| LinkError::Invalid)?;
+        if link.expires_at <= now {
+            return Err(LinkError::Expired);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use database::timestamp::Timestamp;
+
+    use super::{LinkError, LinkSigner};
+
+    #[test]
+    fn test_signed_link() {
+        let signer = LinkSigner::new(b"secret");
+        let now = Timestamp::from_millis(1_000_000);
+        let expires_at = now.plus(Duration::from_secs(60));
+        let token = signer.sign("verify_email", "user.id", "a@b.c", expires_at);
+
+        let link = LinkSigner::parse(&token).unwrap();
+        assert_eq!(link.subject, "user.id");
+        assert_eq!(link.expires_at, expires_at);
+        assert_eq!(signer.verify("verify_email", &link, "a@b.c", now), Ok(()));
+        assert_eq!(signer.verify("verify_email", &link, "other@b.c", now), Err(LinkError::Invalid));
+        assert_eq!(signer.verify("reset_password", &link, "a@b.c", now), Err(LinkError::Invalid));
+        assert_eq!(signer.verify("verify_email", &link, "a@b.c", expires_at), Err(LinkError::Expired));
+        assert_eq!(LinkSigner::new(b"other").verify("verify_email", &link, "a@b.c", now), Err(LinkError::Invalid));
+
+        assert_eq!(LinkSigner::parse("user.123"), Err(LinkError::Malformed));
+        assert_eq!(LinkSigner::parse("user.abc.00"), Err(LinkError::Malformed));
+    }
+}
@@ -0,0 +1,261 @@
+mod fairing;
+mod link;
+mod template;
+mod transport;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use database::{
+    authentication::Authentication,
+    id::MailId,
+    mail::OutboxMessage,
+    managers::OutboxRepository,
+    timestamp::Timestamp,
+    user::User,
+};
+use rocket::tokio::sync::Notify;
+use rocket::Shutdown;
+
+use crate::settings::MailSettings;
+
+pub use fairing::*;
+pub use link::*;
+pub use template::*;
+pub use transport::*;
+
+/// The purpose signed in the email verification links
+pub const VERIFY_EMAIL_PURPOSE: &str = "verify_email";
+
+/// A sender claims a message for this long, it is retried afterwards if the sender did not report on it
+const LEASE: Duration = Duration::from_secs(300);
+
+/// The longest wait between two attempts to send a message
+const MAX_BACKOFF: Duration = Duration::from_secs(6 * 3600);
+
+/// Queues the messages in the outbox and sends them in the background
+///
+/// The routes only queue the messages, so that a slow or unreachable mail server does not slow them down.
+/// The messages are retried with an exponential backoff until they are sent or `max_attempts` is reached.
+pub struct Mailer {
+    outbox: Arc<dyn OutboxRepository>,
+    transport: Arc<dyn MailTransport>,
+    settings: MailSettings,
+    signer: LinkSigner,
+    wake: Arc<Notify>,
+}
+
+impl Mailer {
+    pub fn new(
+        outbox: Arc<dyn OutboxRepository>,
+        transport: Arc<dyn MailTransport>,
+        settings: MailSettings,
+        signer: LinkSigner,
+    ) -> Self {
+        Self {
+            outbox,
+            transport,
+            settings,
+            signer,
+            wake: Arc::default(),
+        }
+    }
+
+    pub fn signer(&self) -> &LinkSigner {
+        &self.signer
+    }
+
+    /// Returns the public address of the API path, with the token as its query
+    pub fn link(&self, path: &str, token: &str) -> String {
+        format!("{}/{path}?token={token}", self.settings.public_url.trim_end_matches('/'))
+    }
+
+    /// Renders the template and queues the message, which is sent right away by the worker
+    pub async fn queue(&self, to: &str, template: &MailTemplate, values: &[(&str, &str)]) -> Result<MailId, String> {
+        let rendered = template.render(values).map_err(|err| err.to_string())?;
+        let message = OutboxMessage::new(
+            to.to_string(),
+            rendered.subject,
+            rendered.text,
+            rendered.html,
+            Timestamp::now(),
+        );
+        self.outbox.enqueue(&message).await.map_err(|err| err.to_string())?;
+        self.wake.notify_one();
+        Ok(message.unique_id)
+    }
+
+    /// Queues the link verifying the email of the user, nothing is sent when there is nothing to verify
+    pub async fn send_verification(&self, user: &User) -> Result<Option<MailId>, String> {
+        let Some(email) = user.email().filter(|_| !user.email_verified) else {
+            return Ok(None);
+        };
+        let hours = self.settings.verification_link_hours;
+        let expires_at = Timestamp::now().plus(Duration::from_secs(hours * 3600));
+        let token = self
+            .signer
+            .sign(VERIFY_EMAIL_PURPOSE, &user.unique_id.to_string(), email, expires_at);
+        let link = self.link("user/verify_email", &token);
+        let values = [("name", display_name(user, email)), ("email", email), ("link", &link), ("hours", &hours.to_string())];
+        self.queue(email, &VERIFY_EMAIL, &values).await.map(Some)
+    }
+
+    /// Queues the link resetting the password of the user, with the token of the reset
+    pub async fn send_password_reset(&self, user: &User, token: &str) -> Result<Option<MailId>, String> {
+        let Some(email) = user.email() else {
+            return Ok(None);
+        };
+        let separator = if self.settings.password_reset_url.contains('?') { '&' } else { '?' };
+        let link = format!("{}{separator}token={token}", self.settings.password_reset_url);
+        let minutes = self.settings.password_reset_minutes.to_string();
+
+        let values = [("name", display_name(user, email)), ("link", &link), ("minutes", &minutes)];
+        self.queue(email, &RESET_PASSWORD, &values).await.map(Some)
+    }
+
+    /// Returns how long the password reset tokens stay valid
+    pub fn password_reset_lifetime(&self) -> Duration {
+        Duration::from_secs(self.settings.password_reset_minutes * 60)
+    }
+
+    /// Sends the due messages whenever a message is queued, and every poll interval for the retries,
+    /// until the shutdown
+    pub fn start(&self, shutdown: Shutdown) {
+        let outbox = self.outbox.clone();
+        let transport = self.transport.clone();
+        let settings = self.settings.clone();
+        let wake = self.wake.clone();
+
+        rocket::tokio::spawn(async move {
+            let mut ticks = rocket::tokio::time::interval(Duration::from_millis(settings.poll_interval_ms));
+            rocket::tokio::pin!(shutdown);
+            loop {
+                rocket::tokio::select! {
+                    _ = ticks.tick() => {}
+                    _ = wake.notified() => {}
+                    _ = &mut shutdown => break,
+                }
+                send_due(outbox.as_ref(), transport.as_ref(), &settings).await;
+            }
+        });
+    }
+}
+
+// display_name greets the user by their username, or by their email when they have none
+fn display_name<'a>(user: &'a User, email: &'a str) -> &'a str {
+    match &user.authentication {
+        Authentication::Credentials(credentials) => credentials.username.as_deref().unwrap_or(email),
+        Authentication::Oidc(identity) => identity.username.as_deref().unwrap_or(email),
+        Authentication::None => email,
+    }
+}
+
+/// Sends the messages of the outbox that are due, one at a time
+pub async fn send_due(outbox: &dyn OutboxRepository, transport: &dyn MailTransport, settings: &MailSettings) {
+    let retention = Duration::from_secs(settings.retention_hours * 3600);
+    loop {
+        let now = Timestamp::now();
+        let message = match outbox.claim_due(now, now.plus(LEASE)).await {
+            Ok(Some(message)) => message,
+            Ok(None) => return,
+            Err(err) => {
+                tracing::warn!("Cannot read the outbox: {err}");
+                return;
+            }
+        };
+
+        let result = match transport.send(&message).await {
+            Ok(()) => outbox.mark_sent(message.unique_id, now.plus(retention)).await,
+            Err(error) => {
+                let retry_at = (message.attempts < settings.max_attempts)
+                    .then(|| Timestamp::now().plus(retry_backoff(settings.retry_backoff_secs, message.attempts)));
+                match retry_at {
+                    Some(retry_at) => tracing::warn!(mail_id = %message.unique_id, attempts = message.attempts, %retry_at, "Cannot send a mail: {error}"),
+                    None => tracing::error!(mail_id = %message.unique_id, attempts = message.attempts, "Giving up sending a mail: {error}"),
+                }
+                outbox.mark_failed(message.unique_id, &error, retry_at, now.plus(retention)).await
+            }
+        };
+        if let Err(err) = result {
+            tracing::warn!(mail_id = %message.unique_id, "Cannot update the outbox: {err}");
+        }
+    }
+}
+
+// retry_backoff doubles the wait after each failed attempt
+fn retry_backoff(base_secs: u64, attempts: u32) -> Duration {
+    let factor = 2u32.checked_pow(attempts.saturating_sub(1)).unwrap_or(u32::MAX);
+    Duration::from_secs(base_secs).saturating_mul(factor).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use database::{
+        mail::{MailStatus, OutboxMessage},
+        managers::OutboxRepository,
+        memory::MemoryOutboxManager,
+        timestamp::Timestamp,
+    };
+
+    use super::{retry_backoff, send_due, MailTransport, MAX_BACKOFF};
+    use crate::settings::MailSettings;
+
+    // FailingTransport fails the given number of attempts, then sends the messages
+    struct FailingTransport {
+        failures: AtomicU32,
+    }
+
+    #[async_trait]
+    impl MailTransport for FailingTransport {
+        async fn send(&self, _message: &OutboxMessage) -> Result<(), String> {
+            match self.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)) {
+                Ok(_) => Err("421 try again later".to_string()),
+                Err(_) => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        assert_eq!(retry_backoff(60, 1), Duration::from_secs(60));
+        assert_eq!(retry_backoff(60, 3), Duration::from_secs(240));
+        assert_eq!(retry_backoff(60, 40), MAX_BACKOFF);
+    }
+
+    #[rocket::async_test]
+    async fn test_send_due() {
+        let outbox = MemoryOutboxManager::new();
+        let settings = MailSettings {
+            max_attempts: 2,
+            retry_backoff_secs: 0,
+            ..Default::default()
+        };
+        let message = OutboxMessage::new("a@b.c".into(), "Subject".into(), "Text".into(), None, Timestamp::now());
+        outbox.enqueue(&message).await.unwrap();
+
+        // The first attempt fails and the retry is due at once, the second one succeeds
+        let transport = FailingTransport {
+            failures: AtomicU32::new(1),
+        };
+        send_due(&outbox, &transport, &settings).await;
+        let sent = outbox.get_message(message.unique_id).await.unwrap().unwrap();
+        assert_eq!(sent.status, MailStatus::Sent);
+        assert_eq!(sent.attempts, 2);
+
+        // The message is given up after max_attempts
+        let message = OutboxMessage::new("a@b.c".into(), "Subject".into(), "Text".into(), None, Timestamp::now());
+        outbox.enqueue(&message).await.unwrap();
+        let transport = FailingTransport {
+            failures: AtomicU32::new(5),
+        };
+        send_due(&outbox, &transport, &settings).await;
+        let failed = outbox.get_message(message.unique_id).await.unwrap().unwrap();
+        assert_eq!(failed.status, MailStatus::Failed);
+        assert_eq!(failed.attempts, 2);
+        assert_eq!(failed.last_error.as_deref(), Some("421 try again later"));
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+
+/// A message whose subject and bodies hold `{{name}}` placeholders, replaced by the values given when rendering
+///
+/// The values are escaped in the HTML body.
+pub struct MailTemplate {
+    pub subject: &'static str,
+    pub text: &'static str,
+    pub html: Option<&'static str>,
+}
+
+/// A template with its placeholders replaced, ready to be queued
+#[derive(Debug, PartialEq, Eq)]
+pub struct RenderedMail {
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A placeholder has no value
+    MissingValue(String),
+    /// A placeholder is not closed
+    Unclosed,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingValue(name) => write!(f, "The template has no value for {name}"),
+            Self::Unclosed => write!(f, "The template has an unclosed placeholder"),
+        }
+    }
+}
+
+impl MailTemplate {
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<RenderedMail, TemplateError> {
+        Ok(RenderedMail {
+            subject: render(self.subject, values, |value| value.to_string())?,
+            text: render(self.text, values, |value| value.to_string())?,
+            html: self.html.map(|html| render(html, values, escape_html)).transpose()?,
+        })
+    }
+}
+
+fn render(template: &str, values: &[(&str, &str)], escape: fn(&str) -> String) -> Result<String, TemplateError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..].find("}}").ok_or(TemplateError::Unclosed)? + start;
+        let name = rest[start + 2..end].trim();
+        let (_, value) = values
+            .iter()
+            .find(|(key, _)| *key == name)
+            .ok_or_else(|| TemplateError::MissingValue(name.to_string()))?;
+        rendered.push_str(&escape(value));
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub const VERIFY_EMAIL: MailTemplate = MailTemplate {
+    subject: "Verify your email address",
+    text: "Hello {{name}},
+
+Please confirm that {{email}} is your email address by opening this link:
+{{link}}
+
+The link expires in {{hours}} hours. If you did not create an account, you can ignore this message.
+",
+    html: Some(
+        "<p>Hello {{name}},</p>
+<p>Please confirm that {{email}} is your email address by opening this link:</p>
+<p><a href=\"{{link}}\">Verify my email address</a></p>
+<p>The link expires in {{hours}} hours. If you did not create an account, you can ignore this message.</p>
+",
+    ),
+};
+
+pub const RESET_PASSWORD: MailTemplate = MailTemplate {
+    subject: "Reset your password",
+    text: "Hello {{name}},
+
+A new password was requested for your account. You can choose it by opening this link:
+{{link}}
+
+The link expires in {{minutes}} minutes and can be used once. Resetting the password signs you out everywhere.
+If you did not request it, you can ignore this message, your password is unchanged.
+",
+    html: Some(
+        "<p>Hello {{name}},</p>
+<p>A new password was requested for your account. You can choose it by opening this link:</p>
+<p><a href=\"{{link}}\">Reset my password</a></p>
+<p>The link expires in {{minutes}} minutes and can be used once. Resetting the password signs you out everywhere.</p>
+<p>If you did not request it, you can ignore this message, your password is unchanged.</p>
+",
+    ),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::{MailTemplate, TemplateError};
+
+    #[test]
+    fn test_render() {
+        let template = MailTemplate {
+            subject: "Hello {{ name }}",
+            text: "{{name}} wrote {{message}}",
+            html: Some("<b>{{name}}</b> wrote {{message}}"),
+        };
+
+        let rendered = template.render(&[("name", "Ann"), ("message", "<a & b>")]).unwrap();
+        assert_eq!(rendered.subject, "Hello Ann");
+        assert_eq!(rendered.text, "Ann wrote <a & b>");
+        assert_eq!(rendered.html.as_deref(), Some("<b>Ann</b> wrote &lt;a &amp; b&gt;"));
+
+        assert_eq!(
+            template.render(&[("name", "Ann")]),
+            Err(TemplateError::MissingValue("message".to_string()))
+        );
+        let unclosed = MailTemplate {
+            subject: "Hello {{name",
+            text: "",
+            html: None,
+        };
+        assert_eq!(unclosed.render(&[("name", "Ann")]), Err(TemplateError::Unclosed));
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use database::mail::OutboxMessage;
+use lettre::message::{header::ContentType, Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::settings::{MailSettings, MailTransportKind, SmtpSecurity};
+
+/// Delivers the messages of the outbox
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, message: &OutboxMessage) -> Result<(), String>;
+}
+
+/// Returns the transport chosen by the settings
+pub fn from_settings(settings: &MailSettings) -> Result<Box<dyn MailTransport>, String> {
+    match settings.transport {
+        MailTransportKind::Log => Ok(Box::new(LogTransport)),
+        MailTransportKind::Smtp => Ok(Box::new(SmtpTransport::new(settings)?)),
+    }
+}
+
+/// Logs the messages instead of sending them
+///
+/// Only the id, the recipient and the subject are logged, the bodies carry the reset and verification links.
+pub struct LogTransport;
+
+#[async_trait]
+impl MailTransport for LogTransport {
+    async fn send(&self, message: &OutboxMessage) -> Result<(), String> {
+        tracing::info!(
+            mail_id = %message.unique_id,
+            to = %message.to,
+            subject = %message.subject,
+            "mail not sent, the log transport is used"
+        );
+        Ok(())
+    }
+}
+
+pub struct SmtpTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpTransport {
+    pub fn new(settings: &MailSettings) -> Result<Self, String> {
+        let smtp = &settings.smtp;
+        let builder = match smtp.security {
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.hostname),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.hostname)
+                .map_err(|err| err.to_string())?,
+            SmtpSecurity::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.hostname).map_err(|err| err.to_string())?
+            }
+        };
+        let mut builder = builder
+            .port(smtp.port)
+            .timeout(Some(Duration::from_millis(smtp.timeout_ms)));
+        if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: settings.from.parse().map_err(|err| format!("Invalid sender: {err}"))?,
+        })
+    }
+
+    fn build(&self, message: &OutboxMessage) -> Result<Message, String> {
+        let to: Mailbox = message.to.parse().map_err(|err| format!("Invalid recipient: {err}"))?;
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject.clone());
+        let email = match &message.html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(message.text.clone(), html.clone())),
+            None => builder.header(ContentType::TEXT_PLAIN).body(message.text.clone()),
+        };
+        email.map_err(|err| err.to_string())
+    }
+}
+
+#[async_trait]
+impl MailTransport for SmtpTransport {
+    async fn send(&self, message: &OutboxMessage) -> Result<(), String> {
+        let email = self.build(message)?;
+        self.transport.send(email).await.map_err(|err| err.to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{mail::OutboxMessage, timestamp::Timestamp};
+    use rocket::tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use rocket::tokio::net::TcpListener;
+
+    use super::{MailTransport, SmtpTransport};
+    use crate::settings::{MailSettings, MailTransportKind, SmtpSecurity};
+
+    // sink accepts a single SMTP session and returns the data of the message it received
+    async fn sink(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"220 sink ready\r\n").await.unwrap();
+
+        let mut data = String::new();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 queued\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+            let reply: &[u8] = match line.get(..4).map(str::to_ascii_uppercase).as_deref() {
+                Some("DATA") => {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                }
+                Some("QUIT") => {
+                    writer.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                }
+                _ => b"250 ok\r\n",
+            };
+            writer.write_all(reply).await.unwrap();
+        }
+        data
+    }
+
+    #[rocket::async_test]
+    async fn test_smtp_transport() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = rocket::tokio::spawn(sink(listener));
+
+        let mut settings = MailSettings {
+            transport: MailTransportKind::Smtp,
+            from: "Areation <no-reply@example.com>".to_string(),
+            ..Default::default()
+        };
+        settings.smtp.hostname = "127.0.0.1".to_string();
+        settings.smtp.port = port;
+        settings.smtp.security = SmtpSecurity::None;
+        let transport = SmtpTransport::new(&settings).unwrap();
+
+        let message = OutboxMessage::new(
+            "user@example.com".to_string(),
+            "Greetings".to_string(),
+            "Hello in text".to_string(),
+            Some("<p>Hello in HTML</p>".to_string()),
+            Timestamp::now(),
+        );
+        transport.send(&message).await.unwrap();
+
+        let data = received.await.unwrap();
+        assert!(data.contains("From: Areation <no-reply@example.com>"));
+        assert!(data.contains("To: user@example.com"));
+        assert!(data.contains("Subject: Greetings"));
+        assert!(data.contains("Hello in text"));
+        assert!(data.contains("<p>Hello in HTML</p>"));
+    }
+}
@@ -0,0 +1,97 @@
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::*;
+use telemetry::{PrometheusExporter, PROMETHEUS_CONTENT_TYPE};
+
+use crate::settings::ApiSettings;
+
+/// The scraper of the metrics, which must send the bearer token of the settings when one is set
+pub struct MetricsScraper;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for MetricsScraper {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let settings = request.rocket().state::<ApiSettings>().unwrap();
+        let Some(token) = &settings.metrics.token else {
+            return Outcome::Success(MetricsScraper);
+        };
+
+        let bearer = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "));
+        // The tokens are compared in constant time, like the signatures
+        match bearer {
+            Some(bearer)
+                if bearer.len() == token.len()
+                    && bearer
+                        .bytes()
+                        .zip(token.bytes())
+                        .fold(0, |difference, (a, b)| difference | (a ^ b))
+                        == 0 =>
+            {
+                Outcome::Success(MetricsScraper)
+            }
+            _ => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// Exposes the metrics in the Prometheus text format, mounted when `metrics.enabled` is set
+#[get("/metrics")]
+// #[coverage(off)]
+pub fn metrics(_scraper: MetricsScraper, exporter: &State<PrometheusExporter>) -> Result<(ContentType, String), Status> {
+    let content_type = ContentType::parse_flexible(PROMETHEUS_CONTENT_TYPE).unwrap_or(ContentType::Plain);
+    match exporter.render() {
+        Ok(metrics) => Ok((content_type, metrics)),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rocket::http::{Header, Method, Status};
+
+    use crate::testing::{dispatch_request, run_test, run_test_with_settings};
+
+    #[rocket::async_test]
+    async fn test_metrics_disabled() {
+        run_test(|client| async move {
+            let response = dispatch_request(&client, Method::Get, "/metrics".to_string(), None, None).await;
+
+            assert_eq!(response.status(), Status::NotFound);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_metrics() {
+        let configure = |settings: &mut crate::settings::ApiSettings| {
+            settings.metrics.enabled = true;
+            settings.metrics.token = Some("scraper".to_string());
+        };
+        run_test_with_settings(configure, |client| async move {
+            let response = client.get("/metrics").dispatch().await;
+            assert_eq!(response.status(), Status::Unauthorized);
+
+            for bearer in ["Bearer scrapes", "Bearer scrape", "Bearer scraper2"] {
+                let response = client.get("/metrics").header(Header::new("Authorization", bearer)).dispatch().await;
+                assert_eq!(response.status(), Status::Unauthorized);
+            }
+
+            let response = client
+                .get("/metrics")
+                .header(Header::new("Authorization", "Bearer scraper"))
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            let content_type = response.content_type().unwrap();
+            assert_eq!(content_type.sub(), "plain");
+            assert_eq!(content_type.param("version"), Some("0.0.4"));
+        })
+        .await;
+    }
+}
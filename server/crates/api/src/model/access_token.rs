@@ -0,0 +1,43 @@
+use database::{access_token::AccessToken, id::AccessTokenId, timestamp::Timestamp};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A personal access token to create, its scopes are names of permissions the user holds
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct AccessTokenInit {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: u64,
+}
+
+/// A personal access token as it is listed, without the token itself
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct AccessTokenInfo {
+    pub unique_id: AccessTokenId,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub creation_date: Timestamp,
+    pub expires_at: Timestamp,
+    pub last_used: Option<Timestamp>,
+}
+
+impl From<AccessToken> for AccessTokenInfo {
+    fn from(token: AccessToken) -> Self {
+        Self {
+            unique_id: token.unique_id,
+            name: token.name,
+            scopes: token.scopes,
+            creation_date: token.creation_date,
+            expires_at: token.expires_at,
+            last_used: token.last_used,
+        }
+    }
+}
+
+/// The personal access token that was created, the token is only shown this once
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct CreatedAccessToken {
+    pub token: String,
+    pub access_token: AccessTokenInfo,
+}
@@ -0,0 +1,12 @@
+use rocket::FromFormField;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+/// The formats of the exports, `ndjson` writes a JSON object per line and `csv` a row per item after a header row
+#[derive(FromFormField, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Ndjson,
+    Csv,
+}
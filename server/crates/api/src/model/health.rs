@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The status of the API or of one of its dependencies, from the best to the worst
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, JsonSchema, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    /// The API still serves requests, e.g. when the telemetry cannot be exported
+    Degraded,
+    /// The API cannot serve requests
+    Down,
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema, Serialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    /// How long the check took, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, Value>,
+}
+
+impl ComponentHealth {
+    pub fn new(status: HealthStatus) -> Self {
+        Self {
+            status,
+            latency_ms: None,
+            details: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_detail(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.details.insert(name.to_string(), value.into());
+        self
+    }
+}
+
+/// The status of the API, which is the worst status of its components
+#[derive(Deserialize, Debug, Clone, JsonSchema, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+impl HealthReport {
+    pub fn new(components: BTreeMap<String, ComponentHealth>) -> Self {
+        let status = components
+            .values()
+            .map(|component| component.status)
+            .max()
+            .unwrap_or(HealthStatus::Up);
+        Self { status, components }
+    }
+}
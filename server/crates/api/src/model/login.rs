@@ -1,4 +1,4 @@
-use database::authentication::Credentials;
+use database::{authentication::Credentials, id::UserId};
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -6,5 +6,14 @@ use serde::{Deserialize, Serialize};
 #[derive(Deserialize, Debug, JsonSchema, Serialize)]
 pub enum Login {
     Credentials(Credentials),
-    UserId(String),
+    UserId(UserId),
+    /// The second step of a login with credentials, when the user enabled a second factor
+    TwoFactor(TwoFactorLogin),
+}
+
+/// The challenge answered to the credentials, with a code of the authenticator app or a recovery code
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct TwoFactorLogin {
+    pub challenge: String,
+    pub code: String,
 }
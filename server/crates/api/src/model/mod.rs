@@ -8,4 +8,15 @@ pub mod user_id;
 pub mod user_token;
 pub mod project;
 pub mod project_init;
-pub mod organisation_id;
\ No newline at end of file
+pub mod organisation_id;
+pub mod report_init;
+pub mod moderation_decision;
+pub mod page_query;
+pub mod health;
+pub mod export_format;
+pub mod password_reset;
+pub mod oidc;
+pub mod two_factor;
+pub mod access_token;
+pub mod user_archive;
+pub mod user_profile;
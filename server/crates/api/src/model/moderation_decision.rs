@@ -0,0 +1,17 @@
+use database::moderation::ModerationAction;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct ModerationDecision {
+    pub action: ModerationAction,
+    #[serde(default)]
+    pub ban_author: bool,
+}
+
+impl ModerationDecision {
+    pub fn new(action: ModerationAction, ban_author: bool) -> Self {
+        Self { action, ban_author }
+    }
+}
@@ -0,0 +1,9 @@
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The address of the provider the user signs in at, it sends the user back with a code and a state
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct OidcAuthorization {
+    pub authorization_url: String,
+}
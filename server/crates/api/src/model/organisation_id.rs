@@ -3,4 +3,4 @@ use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, JsonSchema, Serialize)]
-pub struct OrganizationId(pub String);
+pub struct OrganizationId(pub database::id::OrganizationId);
@@ -1,15 +1,16 @@
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
+use database::id::UserId;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, JsonSchema, Serialize)]
 pub struct OrganizationInit {
     pub name: String,
-    pub owner_id: String,
+    pub owner_id: UserId,
 }
 
 impl OrganizationInit {
-    pub fn new(name: String, owner_id: String) -> Self {
+    pub fn new(name: String, owner_id: UserId) -> Self {
         Self { name, owner_id }
     }
 }
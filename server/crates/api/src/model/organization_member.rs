@@ -1,8 +1,32 @@
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
+use database::{authentication::Authentication, id::UserId, user::User};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, JsonSchema, Serialize)]
 pub struct OrganizationMember {
-    pub member_id: String
+    pub member_id: UserId,
+}
+
+/// A member of an organization as the other members see them, without their credentials nor their logins
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct MemberProfile {
+    pub unique_id: UserId,
+    pub username: Option<String>,
+    pub avatar: Option<String>,
+}
+
+impl From<User> for MemberProfile {
+    fn from(user: User) -> Self {
+        let (username, avatar) = match user.authentication {
+            Authentication::Credentials(credentials) => (credentials.username, credentials.avatar),
+            Authentication::Oidc(identity) => (identity.username, None),
+            Authentication::None => (None, None),
+        };
+        Self {
+            unique_id: user.unique_id,
+            username,
+            avatar,
+        }
+    }
 }
@@ -1,9 +1,10 @@
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
+use database::id::{OrganizationId, UserId};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, JsonSchema, Serialize)]
 pub struct OrganizationServer {
-    pub organization_id: String,
-    pub server_id: String,
+    pub organization_id: OrganizationId,
+    pub server_id: UserId,
 }
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+use database::{pagination::PageRequest, timestamp::Timestamp};
+use rocket::FromForm;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+/// The query parameters of a paginated listing
+///
+/// `cursor` is the `next_cursor` of the previous page, `sort` is a field name prefixed with `-` for a descending order
+/// and `filter` is a set of equality filters, e.g. `?filter.name=Uver`.
+/// `created_after` and `created_before` are exclusive RFC 3339 bounds of the creation date, e.g. `?created_after=2024-01-01T00:00:00Z`.
+#[derive(FromForm, JsonSchema, Debug, Default)]
+pub struct PageQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+    pub sort: Option<String>,
+    pub filter: HashMap<String, String>,
+    pub created_after: Option<Timestamp>,
+    pub created_before: Option<Timestamp>,
+}
+
+impl From<PageQuery> for PageRequest {
+    fn from(value: PageQuery) -> Self {
+        Self {
+            cursor: value.cursor,
+            limit: value.limit,
+            sort: value.sort,
+            filters: value.filter,
+            created_after: value.created_after,
+            created_before: value.created_before,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The email of the account whose password was forgotten
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+/// The token received by mail, and the new password
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct PasswordResetInit {
+    pub token: String,
+    pub password: String,
+}
@@ -0,0 +1,21 @@
+use database::moderation::ContentKind;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct ReportInit {
+    pub content_kind: ContentKind,
+    pub content_id: String,
+    pub reason: String,
+}
+
+impl ReportInit {
+    pub fn new(content_kind: ContentKind, content_id: String, reason: String) -> Self {
+        Self {
+            content_kind,
+            content_id,
+            reason,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The secret of the second factor being enrolled, the authenticator apps import it from the provisioning URI
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct TwoFactorEnrolment {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+/// A code of the authenticator app, or a recovery code
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct TwoFactorCode {
+    pub code: String,
+}
@@ -0,0 +1,48 @@
+use database::{
+    asset::Asset, comment::Comment, license::License, moderation::Report, organization::Organization, project::Project,
+    timestamp::Timestamp,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::access_token::AccessTokenInfo;
+
+#[derive(Deserialize, Debug, JsonSchema, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum VoteKind {
+    Upvote,
+    Downvote,
+    Favorite,
+}
+
+/// A vote of the user on an asset
+#[derive(Deserialize, Debug, JsonSchema, Serialize, PartialEq, Eq)]
+pub struct AssetVote {
+    pub asset_id: u32,
+    pub kind: VoteKind,
+}
+
+/// Everything stored about a user, as they download it
+///
+/// The passwords, the tokens and the secrets are left out, the user cannot sign in with an archive.
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+pub struct UserArchive {
+    pub exported_at: Timestamp,
+    /// The user, without their logins
+    pub profile: Value,
+    /// The logins of the user, the tokens excepted
+    pub sessions: Vec<Value>,
+    /// The organizations the user owns, is a member or a server of
+    pub organizations: Vec<Organization>,
+    pub projects: Vec<Project>,
+    pub licenses: Vec<License>,
+    /// The assets the user authored
+    pub assets: Vec<Asset>,
+    pub comments: Vec<Comment>,
+    pub votes: Vec<AssetVote>,
+    /// The reports the user filed
+    pub reports: Vec<Report>,
+    pub access_tokens: Vec<AccessTokenInfo>,
+    pub two_factor_enabled: bool,
+}
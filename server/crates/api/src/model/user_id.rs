@@ -3,4 +3,4 @@ use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, JsonSchema, Serialize)]
-pub struct UserId(pub String);
+pub struct UserId(pub database::id::UserId);
@@ -0,0 +1,26 @@
+use database::{id::UserId, user::User};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::organization_member::MemberProfile;
+
+/// A user as the lookups return them, in full to the user themselves and as a public profile to the others
+///
+/// The public profile leaves out the logins and the credentials, which hold the tokens and the password hash.
+#[derive(Deserialize, Debug, JsonSchema, Serialize)]
+#[serde(untagged)]
+pub enum UserProfile {
+    Own(User),
+    Public(MemberProfile),
+}
+
+impl UserProfile {
+    pub fn new(user: User, viewer_id: UserId) -> Self {
+        if user.unique_id == viewer_id {
+            Self::Own(user)
+        } else {
+            Self::Public(user.into())
+        }
+    }
+}
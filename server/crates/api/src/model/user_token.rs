@@ -1,4 +1,8 @@
-use database::Database;
+use database::{
+    id::{PermissionId, UserId},
+    timestamp::Timestamp,
+    Database,
+};
 
 use rocket::request::{self, FromRequest, Outcome, Request};
 use rocket_okapi::okapi::openapi3::{
@@ -10,17 +14,48 @@ use rocket_okapi::{
 };
 
 use serde::Deserialize;
+
+use crate::{
+    access_token::{self, ACCESS_TOKEN_PREFIX},
+    settings::ApiSettings,
+};
+
+/// The refusal of the routes managing the account, which a personal access token cannot reach whatever its scopes
+pub const ACCESS_TOKEN_REFUSED: &str = "A personal access token cannot manage the account.";
+
 #[derive(Deserialize)]
 pub struct UserData {
-    pub id: Option<String>,
+    pub id: Option<UserId>,
+    /// The permissions a personal access token is limited to, `None` for the tokens of the logins
+    #[serde(default)]
+    pub scopes: Option<Vec<PermissionId>>,
 }
 
 impl UserData {
-    fn new(id: Option<String>) -> Self {
-        Self { id }
+    fn new(id: Option<UserId>) -> Self {
+        Self { id, scopes: None }
+    }
+
+    /// Whether the user holds the permission, and the personal access token of the request was given it
+    pub async fn has_permission(&self, database: &Database, permission_id: PermissionId) -> bool {
+        let Some(user_id) = self.id else {
+            return false;
+        };
+        if self.scopes.as_ref().is_some_and(|scopes| !scopes.contains(&permission_id)) {
+            return false;
+        }
+        database.user_manager.has_permission(user_id, permission_id).await
+    }
+
+    /// Whether the request is authenticated by a personal access token
+    pub fn is_access_token(&self) -> bool {
+        self.scopes.is_some()
     }
 }
 
+/// The user authenticated by the token of the request, kept in the request cache for its span
+pub struct RequestUser(pub Option<UserId>);
+
 #[derive(Debug)]
 pub enum UserDataError {
     BadCount,
@@ -40,11 +75,27 @@ impl<'r> FromRequest<'r> for UserData {
                 let token = keys.first().unwrap();
 
                 let db = request.rocket().state::<Database>().unwrap();
+                let settings = request.rocket().state::<ApiSettings>().unwrap();
+
+                if token.starts_with(ACCESS_TOKEN_PREFIX) {
+                    return Outcome::Success(from_access_token(request, db, token).await);
+                }
 
                 let user = db.user_manager.from_token(token).await;
 
                 if let Some(user) = user.as_ref().unwrap() {
-                    let user = user.clone();  // No need for explicit type here, `user` should be `User`
+                    // Banned users are treated as if they had no valid token.
+                    if user.banned {
+                        return Outcome::Success(UserData::new(None));
+                    }
+                    // Expired tokens are treated the same way.
+                    let expired = user
+                        .login_from_token(token)
+                        .is_some_and(|login| login.is_expired(settings.tokens.lifetime_hours, Timestamp::now()));
+                    if expired {
+                        return Outcome::Success(UserData::new(None));
+                    }
+                    request.local_cache(|| RequestUser(Some(user.unique_id)));
                     return Outcome::Success(UserData::new(Some(user.unique_id)));
                 }
                 return Outcome::Success(UserData::new(None));
@@ -56,6 +107,30 @@ impl<'r> FromRequest<'r> for UserData {
     }
 }
 
+// from_access_token authenticates the user of a personal access token, with the permissions of its scopes
+async fn from_access_token(request: &Request<'_>, database: &Database, token: &str) -> UserData {
+    let access_token = match database
+        .access_token_manager
+        .use_token(&access_token::hash(token), Timestamp::now())
+        .await
+    {
+        Ok(Some(access_token)) => access_token,
+        _ => return UserData::new(None),
+    };
+    match database.user_manager.from_id(access_token.user_id).await {
+        // Banned users are treated as if they had no valid token.
+        Ok(Some(user)) if !user.banned => {}
+        _ => return UserData::new(None),
+    }
+
+    let scopes = access_token::scope_ids(database.permission_manager.as_ref(), &access_token.scopes).await;
+    request.local_cache(|| RequestUser(Some(access_token.user_id)));
+    UserData {
+        id: Some(access_token.user_id),
+        scopes: Some(scopes),
+    }
+}
+
 impl<'a> OpenApiFromRequest<'a> for UserData {
     fn from_request_input(
         _gen: &mut OpenApiGenerator,
@@ -63,7 +138,7 @@ impl<'a> OpenApiFromRequest<'a> for UserData {
         _required: bool,
     ) -> rocket_okapi::Result<RequestHeaderInput> {
         let security_scheme = SecurityScheme {
-            description: Some("Requires a User token to access".to_string()),
+            description: Some("Requires a User token, or a personal access token, to access".to_string()),
             data: SecuritySchemeData::ApiKey {
                 name: "X-User-Token".to_owned(),
                 location: "header".to_owned(),
@@ -0,0 +1,308 @@
+mod provider;
+
+#[cfg(test)]
+pub mod mock_issuer;
+
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use database::{
+    authentication::{OidcAccount, OidcIdentity},
+    id::UserId,
+    managers::OidcLoginRepository,
+    oidc_login::OidcLogin,
+    timestamp::Timestamp,
+};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use rand::RngCore;
+use reqwest::{Client, Url};
+use rocket::http::Status;
+use rocket::tokio::sync::RwLock;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::settings::{OidcProvider, OidcSettings};
+
+pub use provider::*;
+
+/// The algorithms accepted for the ID tokens, the keys of the provider sign them
+///
+/// The HMAC algorithms would use the client secret as the key, which the public clients do not have.
+const ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OidcError {
+    UnknownProvider,
+    /// The provider could not be reached, or its answer is not understood
+    Provider(String),
+    /// The provider refused the code, which was already used, has expired or was not issued to the API
+    Rejected(String),
+    /// The ID token is not signed by the provider, has expired, or was issued for another sign in
+    InvalidToken(String),
+    Database(String),
+}
+
+impl fmt::Display for OidcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownProvider => write!(f, "The provider is unknown."),
+            Self::Provider(err) => write!(f, "The provider cannot be reached: {err}"),
+            Self::Rejected(err) => write!(f, "The provider refused the sign in: {err}"),
+            Self::InvalidToken(err) => write!(f, "The ID token is invalid: {err}"),
+            Self::Database(_) => write!(f, "A database error occured."),
+        }
+    }
+}
+
+impl OidcError {
+    pub fn status(&self) -> Status {
+        match self {
+            Self::UnknownProvider => Status::NotFound,
+            Self::Provider(_) => Status::BadGateway,
+            Self::Rejected(_) | Self::InvalidToken(_) => Status::Unauthorized,
+            Self::Database(_) => Status::InternalServerError,
+        }
+    }
+}
+
+/// Signs the users in with the OpenID Connect providers of the settings
+///
+/// The sign in follows the authorization code flow with PKCE: `authorize` stores a pending sign in and returns
+/// the address of the provider, which sends the user back with a code and the state of the sign in. `complete`
+/// then exchanges the code and verifies the ID token against the keys of the provider, which are fetched once
+/// and fetched again when a token is signed with a key they do not contain.
+pub struct OidcClient {
+    settings: OidcSettings,
+    public_url: String,
+    http: Client,
+    metadata: RwLock<HashMap<String, Arc<ProviderMetadata>>>,
+}
+
+impl OidcClient {
+    pub fn new(settings: OidcSettings, public_url: &str) -> Result<Self, reqwest::Error> {
+        let http = Client::builder()
+            .timeout(Duration::from_millis(settings.timeout_ms))
+            .build()?;
+        Ok(Self {
+            settings,
+            public_url: public_url.trim_end_matches('/').to_string(),
+            http,
+            metadata: RwLock::default(),
+        })
+    }
+
+    fn provider(&self, name: &str) -> Result<&OidcProvider, OidcError> {
+        self.settings.providers.get(name).ok_or(OidcError::UnknownProvider)
+    }
+
+    /// Returns the address the provider sends the users back to
+    fn redirect_url(&self, provider: &OidcProvider) -> String {
+        match &provider.redirect_url {
+            Some(url) => url.clone(),
+            None => format!("{}/user/oidc/callback", self.public_url),
+        }
+    }
+
+    /// Starts a sign in at the provider and returns the address the user signs in at
+    ///
+    /// The account the user signs in with is linked to `link_user` when it is set.
+    pub async fn authorize(
+        &self,
+        name: &str,
+        link_user: Option<UserId>,
+        logins: &dyn OidcLoginRepository,
+    ) -> Result<String, OidcError> {
+        let provider = self.provider(name)?;
+        let metadata = self.metadata(name, provider, false).await?;
+
+        let now = Timestamp::now();
+        let login = OidcLogin {
+            state: random_token(),
+            provider: name.to_string(),
+            nonce: random_token(),
+            code_verifier: random_token(),
+            link_user,
+            creation_date: now,
+            expires_at: now.plus(Duration::from_secs(self.settings.login_minutes * 60)),
+        };
+        logins
+            .create(&login)
+            .await
+            .map_err(|err| OidcError::Database(err.to_string()))?;
+
+        let url = Url::parse_with_params(
+            &metadata.discovery.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &provider.client_id),
+                ("redirect_uri", &self.redirect_url(provider)),
+                ("scope", &provider.scopes.join(" ")),
+                ("state", &login.state),
+                ("nonce", &login.nonce),
+                ("code_challenge", &code_challenge(&login.code_verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|err| OidcError::Provider(err.to_string()))?;
+        Ok(url.into())
+    }
+
+    /// Exchanges the code the provider sent back for the ID token of the sign in, and returns its claims
+    pub async fn complete(&self, login: &OidcLogin, code: &str) -> Result<IdClaims, OidcError> {
+        let provider = self.provider(&login.provider)?;
+        let metadata = self.metadata(&login.provider, provider, false).await?;
+
+        let redirect_url = self.redirect_url(provider);
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &redirect_url),
+            ("client_id", &provider.client_id),
+            ("code_verifier", &login.code_verifier),
+        ];
+        if let Some(secret) = &provider.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let response = self
+            .http
+            .post(&metadata.discovery.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|err| OidcError::Provider(err.to_string()))?;
+        if response.status().is_client_error() {
+            let reason = match response.json::<TokenError>().await {
+                Ok(error) => error.error_description.unwrap_or(error.error),
+                Err(_) => "the code was refused".to_string(),
+            };
+            return Err(OidcError::Rejected(reason));
+        }
+        let tokens: TokenResponse = read_json(response).await?;
+
+        let claims = self
+            .verify(&login.provider, provider, metadata, &tokens.id_token)
+            .await?;
+        // The nonce ties the token to this sign in, a token of another one cannot be replayed
+        if claims.nonce.as_deref() != Some(login.nonce.as_str()) {
+            return Err(OidcError::InvalidToken("the nonce does not match".to_string()));
+        }
+        Ok(claims)
+    }
+
+    async fn verify(
+        &self,
+        name: &str,
+        provider: &OidcProvider,
+        mut metadata: Arc<ProviderMetadata>,
+        id_token: &str,
+    ) -> Result<IdClaims, OidcError> {
+        let header = decode_header(id_token).map_err(|err| OidcError::InvalidToken(err.to_string()))?;
+        if !ALGORITHMS.contains(&header.alg) {
+            return Err(OidcError::InvalidToken(format!(
+                "the {:?} algorithm is not accepted",
+                header.alg
+            )));
+        }
+        // The provider rotated its keys since they were fetched
+        if metadata.key(&header).is_none() {
+            metadata = self.metadata(name, provider, true).await?;
+        }
+        let Some(jwk) = metadata.key(&header) else {
+            return Err(OidcError::InvalidToken("the signing key is unknown".to_string()));
+        };
+        let key = DecodingKey::from_jwk(jwk).map_err(|err| OidcError::InvalidToken(err.to_string()))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.discovery.issuer]);
+        validation.set_audience(&[&provider.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        decode::<IdClaims>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|err| OidcError::InvalidToken(err.to_string()))
+    }
+
+    /// Returns the configuration of the provider, discovered on first use or when `refresh` is set
+    async fn metadata(
+        &self,
+        name: &str,
+        provider: &OidcProvider,
+        refresh: bool,
+    ) -> Result<Arc<ProviderMetadata>, OidcError> {
+        if !refresh {
+            if let Some(metadata) = self.metadata.read().await.get(name) {
+                return Ok(metadata.clone());
+            }
+        }
+
+        let issuer = provider.issuer.trim_end_matches('/');
+        let response = self
+            .http
+            .get(format!("{issuer}/.well-known/openid-configuration"))
+            .send()
+            .await
+            .map_err(|err| OidcError::Provider(err.to_string()))?;
+        let discovery: Discovery = read_json(response).await?;
+        // A configuration served for another issuer would let it sign the tokens of this provider
+        if discovery.issuer.trim_end_matches('/') != issuer {
+            return Err(OidcError::Provider(format!(
+                "the configuration is for the issuer {}",
+                discovery.issuer
+            )));
+        }
+        let response = self
+            .http
+            .get(&discovery.jwks_uri)
+            .send()
+            .await
+            .map_err(|err| OidcError::Provider(err.to_string()))?;
+        let keys = read_json(response).await?;
+
+        let metadata = Arc::new(ProviderMetadata { discovery, keys });
+        self.metadata.write().await.insert(name.to_string(), metadata.clone());
+        Ok(metadata)
+    }
+}
+
+/// Returns the account of the claims at the provider, with the email only if the provider verified it
+pub fn identity(provider: &str, claims: &IdClaims) -> OidcIdentity {
+    OidcIdentity {
+        account: OidcAccount {
+            provider: provider.to_string(),
+            subject: claims.sub.clone(),
+        },
+        email: claims.email.clone().filter(|_| claims.email_verified),
+        username: claims.preferred_username.clone().or_else(|| claims.name.clone()),
+    }
+}
+
+async fn read_json<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, OidcError> {
+    response
+        .error_for_status()
+        .map_err(|err| OidcError::Provider(err.to_string()))?
+        .json()
+        .await
+        .map_err(|err| OidcError::Provider(err.to_string()))
+}
+
+// random_token returns 32 random bytes encoded in URL-safe base64, which fits the states, the nonces and the
+// PKCE verifiers
+fn random_token() -> String {
+    let mut bytes = [0; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Returns the S256 PKCE challenge of the verifier
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
@@ -0,0 +1,58 @@
+use jsonwebtoken::{
+    jwk::{Jwk, JwkSet},
+    Header,
+};
+use serde::Deserialize;
+
+/// The configuration a provider publishes at `<issuer>/.well-known/openid-configuration`
+#[derive(Deserialize, Debug, Clone)]
+pub struct Discovery {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// The discovered configuration of a provider, with the keys signing its ID tokens
+#[derive(Debug)]
+pub struct ProviderMetadata {
+    pub discovery: Discovery,
+    pub keys: JwkSet,
+}
+
+impl ProviderMetadata {
+    /// Returns the key of the token header, the only key of the provider when the header names none
+    pub fn key(&self, header: &Header) -> Option<&Jwk> {
+        match &header.kid {
+            Some(kid) => self.keys.find(kid),
+            None if self.keys.keys.len() == 1 => self.keys.keys.first(),
+            None => None,
+        }
+    }
+}
+
+/// The answer of the token endpoint, only the ID token is used
+#[derive(Deserialize, Debug)]
+pub struct TokenResponse {
+    pub id_token: String,
+}
+
+/// The error the token endpoint answers with, e.g. `invalid_grant` for a used or an expired code
+#[derive(Deserialize, Debug)]
+pub struct TokenError {
+    pub error: String,
+    pub error_description: Option<String>,
+}
+
+/// The claims of a verified ID token the sign in relies on
+#[derive(Deserialize, Debug, Clone)]
+pub struct IdClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    /// Whether the provider checked that the user owns the email, the unverified emails are never matched
+    #[serde(default)]
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+}
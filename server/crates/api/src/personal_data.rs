@@ -0,0 +1,173 @@
+use std::fmt;
+
+use database::{
+    id::{OrganizationId, UserId},
+    timestamp::Timestamp,
+    user::User,
+    Database,
+};
+use mongodb::error::Error;
+use rocket::http::Status;
+use serde_json::Value;
+
+use crate::{
+    audit::snapshot,
+    model::user_archive::{AssetVote, UserArchive, VoteKind},
+    rate_limit::account_key,
+    two_factor,
+};
+
+#[derive(Debug)]
+pub enum ErasureError {
+    /// The user still owns organizations, their ownership has to be transferred first
+    OwnsOrganizations(Vec<OrganizationId>),
+    Database(Error),
+}
+
+impl fmt::Display for ErasureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OwnsOrganizations(ids) => {
+                let ids: Vec<String> = ids.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "The user owns organizations, transfer their ownership first: {}",
+                    ids.join(", ")
+                )
+            }
+            Self::Database(_) => write!(f, "A database error occured."),
+        }
+    }
+}
+
+impl ErasureError {
+    pub fn status(&self) -> Status {
+        match self {
+            Self::OwnsOrganizations(_) => Status::Conflict,
+            Self::Database(_) => Status::InternalServerError,
+        }
+    }
+}
+
+impl From<Error> for ErasureError {
+    fn from(err: Error) -> Self {
+        Self::Database(err)
+    }
+}
+
+/// Assembles everything stored about the user, across every collection
+pub async fn export(database: &Database, user: &User) -> Result<UserArchive, Error> {
+    let user_id = user.unique_id;
+    let mut profile = snapshot(user);
+    let sessions = match &mut profile {
+        Value::Object(fields) => match fields.remove("logins") {
+            Some(Value::Array(logins)) => logins,
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+
+    let involved = database.asset_manager.from_user(user_id).await?;
+    let mut comments = database.comment_manager.from_user(user_id).await?;
+    let mut votes = Vec::new();
+    for asset in &involved {
+        // The comments embedded in the assets are usually also in their own collection
+        for comment in asset.comments.iter().filter(|comment| comment.user_id == user_id) {
+            if !comments.iter().any(|other| other.unique_id == comment.unique_id) {
+                comments.push(comment.clone());
+            }
+        }
+        let kinds = [
+            (VoteKind::Upvote, &asset.upvote_user_ids),
+            (VoteKind::Downvote, &asset.downvote_user_ids),
+            (VoteKind::Favorite, &asset.favorite_user_ids),
+        ];
+        for (kind, user_ids) in kinds {
+            if user_ids.contains(&user_id) {
+                votes.push(AssetVote {
+                    asset_id: asset.id,
+                    kind,
+                });
+            }
+        }
+    }
+    let assets = involved
+        .into_iter()
+        .filter(|asset| asset.author_id == Some(user_id))
+        .collect();
+
+    Ok(UserArchive {
+        exported_at: Timestamp::now(),
+        profile,
+        sessions,
+        organizations: database.organization_manager.all_from_user(user_id).await?,
+        projects: database.project_manager.from_member(user_id).await?,
+        licenses: database.license_manager.from_user(user_id).await?,
+        assets,
+        comments,
+        votes,
+        reports: database.moderation_manager.from_reporter(user_id).await?,
+        access_tokens: database
+            .access_token_manager
+            .list(user_id)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        two_factor_enabled: two_factor::is_enabled(database.two_factor_manager.as_ref(), user_id).await?,
+    })
+}
+
+/// Returns the organizations the user owns, which block the erasure of the user
+pub async fn owned_organizations(database: &Database, user_id: UserId) -> Result<Vec<OrganizationId>, Error> {
+    Ok(database
+        .organization_manager
+        .all_from_user(user_id)
+        .await?
+        .into_iter()
+        .filter(|organization| organization.owner_id == user_id)
+        .map(|organization| organization.unique_id)
+        .collect())
+}
+
+/// Erases the data of the user from every collection but their own document, which the caller deletes next
+///
+/// The memberships, the votes, the comments, the licenses, the reports, the credentials, the mails, the lockouts,
+/// the pending sign ins and the peer of the user are deleted, and the assets of the user are kept without an author.
+/// The audit events are kept as the record of the erasure, without their IPs and changes.
+pub async fn erase(database: &Database, user: &User) -> Result<(), ErasureError> {
+    let user_id = user.unique_id;
+    let owned = owned_organizations(database, user_id).await?;
+    if !owned.is_empty() {
+        return Err(ErasureError::OwnsOrganizations(owned));
+    }
+
+    database.organization_manager.remove_user(user_id).await?;
+    database.project_manager.remove_member(user_id).await?;
+    database.asset_manager.erase_user(user_id).await?;
+    database.comment_manager.delete_for_user(user_id).await?;
+    database.license_manager.delete_for_user(user_id).await?;
+    database.moderation_manager.delete_from_reporter(user_id).await?;
+    database.access_token_manager.delete_for_user(user_id).await?;
+    database.password_reset_manager.delete_for_user(user_id).await?;
+    database.two_factor_manager.disable(user_id).await?;
+    database.oidc_login_manager.delete_for_user(user_id).await?;
+    database
+        .peers_manager
+        .delete_peer(user_id)
+        .await
+        .map_err(Error::custom)?;
+
+    // The lockouts are keyed by the email of the sign ins, or by the id of the two factor challenges
+    let mut accounts = vec![user_id.to_string()];
+    if let Some(email) = user.email() {
+        database.outbox_manager.delete_to(email).await?;
+        accounts.push(email.to_string());
+    }
+    for account in accounts {
+        database.rate_limit_manager.clear_failures(&account_key(&account)).await?;
+    }
+
+    database.audit_manager.anonymize_user(user_id).await?;
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use database::managers::RateLimitRepository;
+use database::memory::MemoryRateLimitManager;
+use database::Database;
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::{Build, Request, Response, Rocket};
+
+use super::{RateLimiter, RetryAfter};
+use crate::settings::{ApiSettings, RateLimitBackend};
+
+/// Builds the rate limiter on ignite, and sends the `Retry-After` header with the refused requests
+pub struct RateLimitFairing;
+
+#[rocket::async_trait]
+impl Fairing for RateLimitFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rate limiting requests",
+            kind: Kind::Ignite | Kind::Response,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let Some(settings) = rocket.state::<ApiSettings>().map(|settings| settings.rate_limits.clone()) else {
+            return Err(rocket);
+        };
+        let store: Arc<dyn RateLimitRepository> = match settings.backend {
+            RateLimitBackend::Memory => Arc::new(MemoryRateLimitManager::new()),
+            RateLimitBackend::Database => match rocket.state::<Database>() {
+                Some(database) => database.rate_limit_manager.clone(),
+                None => {
+                    tracing::error!("The rate limits cannot be kept in the database before connecting to it");
+                    return Err(rocket);
+                }
+            },
+        };
+        Ok(rocket.manage(RateLimiter::new(store, settings)))
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if let Some(seconds) = request.local_cache(RetryAfter::default).seconds() {
+            response.set_header(Header::new("Retry-After", seconds.to_string()));
+        }
+    }
+}
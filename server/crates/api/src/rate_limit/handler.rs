@@ -0,0 +1,42 @@
+use rocket::http::Status;
+use rocket::route::{Handler, Outcome};
+use rocket::{Data, Request, Route};
+
+use super::{RateLimiter, RetryAfter, RouteClass};
+
+/// Refuses the request with a `429 Too Many Requests` when the client exceeded the limit of the route class,
+/// before its request guards run
+#[derive(Clone)]
+pub struct RateLimitedHandler {
+    class: RouteClass,
+    handler: Box<dyn Handler>,
+}
+
+#[rocket::async_trait]
+impl Handler for RateLimitedHandler {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        if let Some(limiter) = request.rocket().state::<RateLimiter>() {
+            let token = request.headers().get_one("X-User-Token");
+            if let Err(wait) = limiter.check(self.class, request.client_ip(), token).await {
+                request.local_cache(RetryAfter::default).set(wait);
+                return Outcome::Error(Status::TooManyRequests);
+            }
+        }
+        self.handler.handle(request, data).await
+    }
+}
+
+/// Wraps the handlers of the routes in a `RateLimitedHandler`, of the class given by their name
+pub fn rate_limited(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            let class = RouteClass::of(route.name.as_deref());
+            route.handler = Box::new(RateLimitedHandler {
+                class,
+                handler: route.handler,
+            });
+            route
+        })
+        .collect()
+}
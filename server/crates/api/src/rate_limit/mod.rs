@@ -0,0 +1,278 @@
+mod fairing;
+mod handler;
+
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use database::managers::RateLimitRepository;
+use database::rate_limit::{BucketLimit, LockoutPolicy};
+use database::timestamp::Timestamp;
+use rocket::request::{self, FromRequest, Outcome, Request};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+use sha2::{Digest, Sha256};
+
+use crate::settings::{RateLimitSettings, RouteLimit};
+
+pub use fairing::*;
+pub use handler::*;
+
+/// The routes sharing the same limits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteClass {
+    /// The routes checking credentials or creating accounts, the targets of brute-force attacks
+    Authentication,
+    /// The routes telling whether an account or a license exists, which allow enumerating them
+    Lookup,
+    Default,
+}
+
+impl RouteClass {
+    /// Returns the class of a route from its name
+    pub fn of(route_name: Option<&str>) -> Self {
+        match route_name {
+            Some(
+                "renew" | "register" | "update_auth" | "server_authenticate" | "send_verification" | "forgot_password"
+                | "reset_password" | "oidc_authorize" | "oidc_callback" | "two_factor_confirm" | "two_factor_disable",
+            ) => Self::Authentication,
+            Some("email_exists" | "from_email" | "check_licenses" | "verify_email") => Self::Lookup,
+            _ => Self::Default,
+        }
+    }
+}
+
+impl fmt::Display for RouteClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Authentication => write!(f, "authentication"),
+            Self::Lookup => write!(f, "lookup"),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Limits the requests of each client with token buckets, and locks the accounts after failed logins
+///
+/// A client is its IP address and, when it sends one, its token. The tokens and the accounts are hashed
+/// before being used as keys, for the shared backend not to store them.
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitRepository>,
+    settings: RateLimitSettings,
+}
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn RateLimitRepository>, settings: RateLimitSettings) -> Self {
+        Self { store, settings }
+    }
+
+    fn limit(&self, class: RouteClass) -> BucketLimit {
+        let limit = match class {
+            RouteClass::Authentication => self.settings.authentication,
+            RouteClass::Lookup => self.settings.lookup,
+            RouteClass::Default => RouteLimit {
+                requests_per_minute: self.settings.requests_per_minute,
+                burst: self.settings.burst,
+            },
+        };
+        BucketLimit {
+            capacity: f64::from(limit.burst.max(1)),
+            refill_per_second: f64::from(limit.requests_per_minute) / 60.0,
+        }
+    }
+
+    fn policy(&self) -> LockoutPolicy {
+        let lockout = &self.settings.lockout;
+        LockoutPolicy {
+            max_failures: lockout.max_failures,
+            lockout: Duration::from_secs(lockout.lockout_secs),
+            max_lockout: Duration::from_secs(lockout.max_lockout_secs),
+            reset_after: Duration::from_secs(lockout.reset_after_secs),
+        }
+    }
+
+    /// Takes a request of the client from its buckets for the class, and returns how long to wait when one is empty
+    ///
+    /// The requests are let through when the backend fails, the limiter must not take the API down with it.
+    pub async fn check(&self, class: RouteClass, ip: Option<IpAddr>, token: Option<&str>) -> Result<(), Duration> {
+        let limit = self.limit(class);
+        let ip = ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+        let mut keys = vec![format!("{class}:ip:{ip}")];
+        if let Some(token) = token {
+            keys.push(format!("{class}:token:{}", hash(token)));
+        }
+
+        let now = Timestamp::now();
+        let mut wait = None;
+        for key in keys {
+            match self.store.take_token(&key, &limit, now).await {
+                Ok(Some(duration)) => wait = wait.max(Some(duration)),
+                Ok(None) => {}
+                Err(err) => tracing::warn!("Cannot check the rate limit of {class} requests: {err}"),
+            }
+        }
+        wait.map_or(Ok(()), Err)
+    }
+
+    /// Returns how long the account is still locked, if it is
+    pub async fn check_account(&self, account: &str) -> Result<(), Duration> {
+        let now = Timestamp::now();
+        match self.store.locked_until(&account_key(account), now).await {
+            Ok(Some(locked_until)) => Err(Duration::from_millis(
+                u64::try_from(locked_until.timestamp_millis() - now.timestamp_millis()).unwrap_or(0),
+            )),
+            Ok(None) => Ok(()),
+            Err(err) => {
+                tracing::warn!("Cannot check the lockout of an account: {err}");
+                Ok(())
+            }
+        }
+    }
+
+    /// Counts a failed credential check of the account, which may lock it
+    pub async fn record_failure(&self, account: &str) {
+        match self.store.record_failure(&account_key(account), &self.policy(), Timestamp::now()).await {
+            Ok(Some(locked_until)) => tracing::warn!(%locked_until, "An account is locked after repeated failed logins"),
+            Ok(None) => {}
+            Err(err) => tracing::warn!("Cannot count the failed login of an account: {err}"),
+        }
+    }
+
+    /// Forgets the failed credential checks of the account
+    pub async fn record_success(&self, account: &str) {
+        if let Err(err) = self.store.clear_failures(&account_key(account)).await {
+            tracing::warn!("Cannot clear the failed logins of an account: {err}");
+        }
+    }
+}
+
+fn hash(value: &str) -> String {
+    Sha256::digest(value.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// account_key identifies the account of an email, whatever its case
+pub(crate) fn account_key(account: &str) -> String {
+    hash(&account.trim().to_lowercase())
+}
+
+/// How long the client has to wait before retrying, sent in the `Retry-After` header of the response
+#[derive(Default)]
+pub struct RetryAfter(AtomicU64);
+
+impl RetryAfter {
+    /// Sets the wait, rounded up to the second as the header counts seconds
+    pub fn set(&self, wait: Duration) {
+        let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        self.0.store(seconds.max(1), Ordering::Relaxed);
+    }
+
+    pub fn seconds(&self) -> Option<u64> {
+        Some(self.0.load(Ordering::Relaxed)).filter(|seconds| *seconds > 0)
+    }
+}
+
+/// Gives the routes checking credentials access to the lockouts of the accounts
+pub struct AccountLockout<'r> {
+    limiter: &'r RateLimiter,
+    retry_after: &'r RetryAfter,
+}
+
+impl AccountLockout<'_> {
+    /// Fails with the remaining lockout when the account is locked, which the response then carries
+    pub async fn check(&self, account: &str) -> Result<(), Duration> {
+        self.limiter
+            .check_account(account)
+            .await
+            .inspect_err(|wait| self.retry_after.set(*wait))
+    }
+
+    pub async fn failure(&self, account: &str) {
+        self.limiter.record_failure(account).await
+    }
+
+    pub async fn success(&self, account: &str) {
+        self.limiter.record_success(account).await
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AccountLockout<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match request.rocket().state::<RateLimiter>() {
+            Some(limiter) => Outcome::Success(AccountLockout {
+                limiter,
+                retry_after: request.local_cache(RetryAfter::default),
+            }),
+            None => Outcome::Error((rocket::http::Status::InternalServerError, ())),
+        }
+    }
+}
+
+impl<'a> OpenApiFromRequest<'a> for AccountLockout<'a> {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use database::memory::MemoryRateLimitManager;
+
+    use super::{RateLimiter, RetryAfter, RouteClass};
+    use crate::settings::{RateLimitSettings, RouteLimit};
+
+    #[test]
+    fn test_route_class() {
+        assert_eq!(RouteClass::of(Some("renew")), RouteClass::Authentication);
+        assert_eq!(RouteClass::of(Some("email_exists")), RouteClass::Lookup);
+        assert_eq!(RouteClass::of(Some("get_members")), RouteClass::Default);
+        assert_eq!(RouteClass::of(None), RouteClass::Default);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let retry_after = RetryAfter::default();
+        assert_eq!(retry_after.seconds(), None);
+        retry_after.set(Duration::from_millis(1500));
+        assert_eq!(retry_after.seconds(), Some(2));
+        retry_after.set(Duration::ZERO);
+        assert_eq!(retry_after.seconds(), Some(1));
+    }
+
+    #[rocket::async_test]
+    async fn test_check() {
+        let settings = RateLimitSettings {
+            authentication: RouteLimit {
+                requests_per_minute: 1,
+                burst: 2,
+            },
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(Arc::new(MemoryRateLimitManager::new()), settings);
+        let ip = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        assert!(limiter.check(RouteClass::Authentication, ip, None).await.is_ok());
+        assert!(limiter.check(RouteClass::Authentication, ip, Some("token")).await.is_ok());
+        let wait = limiter.check(RouteClass::Authentication, ip, None).await.unwrap_err();
+        assert!(wait > Duration::from_secs(59) && wait <= Duration::from_secs(60));
+        // The classes have their own buckets, and so do the tokens
+        assert!(limiter.check(RouteClass::Default, ip, None).await.is_ok());
+        let other_ip = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        assert!(limiter.check(RouteClass::Authentication, other_ip, Some("token")).await.is_ok());
+        assert!(limiter.check(RouteClass::Authentication, other_ip, Some("token")).await.is_err());
+    }
+}
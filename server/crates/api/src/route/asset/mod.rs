@@ -1,5 +1,9 @@
 mod route_create_asset;
+mod route_get_assets;
+mod route_get_comments;
 // mod route_delete_asset;
 
 pub use route_create_asset::*;
-// pub use route_delete_asset::*;
\ No newline at end of file
+pub use route_get_assets::*;
+pub use route_get_comments::*;
+// pub use route_delete_asset::*;
@@ -1,19 +1,40 @@
+use rand::Rng;
 use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
-use database::{managers::AssetManager, asset::Asset, Database};
-use crate::RequestError;
+use database::{asset::Asset, moderation::ModerationStatus, timestamp::Timestamp, Database};
+use crate::{model::user_token::UserData, RequestError};
 
 #[openapi(tag = "Assets")]
 #[post("/create", data = "<new_asset>", format = "application/json")]
 pub async fn create_asset(
+    user_data: UserData,
     database: &State<Database>,
     new_asset: Json<Asset>,
 ) -> Custom<Result<Json<String>, Json<RequestError>>> {
-    let asset = new_asset.into_inner();
+    let Some(author_id) = user_data.id else {
+        return Custom(
+            Status::Unauthorized,
+            Err(Json(RequestError::from(Custom(
+                Status::Unauthorized,
+                "Token is invalid".to_string(),
+            )))),
+        );
+    };
+    let mut asset = new_asset.into_inner();
+    // The id, the author, the upload date, the moderation status, the votes and the comments are never trusted
+    // from the request body: comments go through the comment routes and their moderation.
+    asset.id = rand::thread_rng().gen();
+    asset.author_id = Some(author_id);
+    asset.upload_date = Timestamp::now();
+    asset.status = ModerationStatus::Visible;
+    asset.comments = Vec::new();
+    asset.upvote_user_ids = Vec::new();
+    asset.downvote_user_ids = Vec::new();
+    asset.favorite_user_ids = Vec::new();
 
     match database.asset_manager.create_asset(&asset).await {
-        Ok(result) => {
-            let success_message = format!("Asset created with ID: {}", result.inserted_id);
+        Ok(_) => {
+            let success_message = format!("Asset created with ID: {}", asset.id);
             Custom(Status::Ok, Ok(Json(success_message)))
         }
         Err(err) => {
@@ -28,3 +49,94 @@ pub async fn create_asset(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use database::{id::CommentId, Database};
+    use rocket::http::{Method, Status};
+    use serde_json::{json, Value};
+
+    use crate::testing::{self, dispatch_request, run_test};
+
+    fn new_asset(id: u32) -> Value {
+        json!({
+            "id": id,
+            "title": "Test asset",
+            "description": "Test description",
+            "upload_date": "2024-01-01T00:00:00Z",
+            "price": "0",
+            "cover_image": "",
+            "images": [],
+            "comments": [],
+            "upvote_user_ids": [],
+            "downvote_user_ids": [],
+            "favorite_user_ids": [],
+        })
+    }
+
+    #[rocket::async_test]
+    async fn test_create_asset() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let other = testing::get_user(database).await;
+            let requested_id = rand::random();
+            let mut body = new_asset(requested_id);
+            body["comments"] = json!([{
+                "unique_id": CommentId::generate(),
+                "asset_id": requested_id,
+                "user_id": other.unique_id,
+                "content": "Unmoderated comment",
+                "timestamp": "2024-01-01T00:00:00Z",
+            }]);
+            body["upvote_user_ids"] = json!([other.unique_id]);
+            body["downvote_user_ids"] = json!([other.unique_id]);
+            body["favorite_user_ids"] = json!([other.unique_id]);
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/asset/create".to_string(),
+                Some(body.to_string()),
+                Some(author.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let message = response.into_json::<String>().await.unwrap();
+            let id: u32 = message.trim_start_matches("Asset created with ID: ").parse().unwrap();
+            let asset = database.asset_manager.get_asset_by_id(id).await.unwrap().unwrap();
+            assert_eq!(asset.author_id, Some(author.unique_id));
+            // The id, the comments and the votes of the request body are ignored
+            if id != requested_id {
+                assert!(database.asset_manager.get_asset_by_id(requested_id).await.unwrap().is_none());
+            }
+            assert!(asset.comments.is_empty());
+            assert!(asset.upvote_user_ids.is_empty());
+            assert!(asset.downvote_user_ids.is_empty());
+            assert!(asset.favorite_user_ids.is_empty());
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn unauthorized_test_create_asset() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let id = rand::random();
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/asset/create".to_string(),
+                Some(new_asset(id).to_string()),
+                None,
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Unauthorized);
+            assert!(database.asset_manager.get_asset_by_id(id).await.unwrap().is_none());
+        })
+        .await;
+    }
+}
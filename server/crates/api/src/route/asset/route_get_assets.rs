@@ -0,0 +1,75 @@
+use database::{asset::Asset, pagination::Page, Database};
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{model::page_query::PageQuery, RequestError};
+
+/// List the assets, optionally filtered by a search on their title and description
+///
+/// Assets hidden by a moderator are never returned.
+///
+/// Can be sorted on `title`, `upload_date` and `price`, and filtered on `title`, `price` and `author_id`
+/// or on the upload date with `created_after` and `created_before`
+#[openapi(tag = "Assets")]
+#[get("/?<search>&<page..>")]
+pub async fn get_assets(
+    database: &State<Database>,
+    search: Option<String>,
+    page: PageQuery,
+) -> Custom<Result<Json<Page<Asset>>, Json<RequestError>>> {
+    match database.asset_manager.get_assets(search.as_deref(), &page.into()).await {
+        Ok(assets) => Custom(Status::Ok, Ok(Json(assets))),
+        Err(err) => {
+            let error = RequestError::from(err);
+            Custom(
+                Status::from_code(error.code).unwrap_or(Status::InternalServerError),
+                Err(error.into()),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{asset::Asset, moderation::ModerationStatus, pagination::Page, Database};
+    use rocket::http::{Method, Status};
+
+    use crate::testing::{self, dispatch_request, run_test};
+
+    #[rocket::async_test]
+    async fn test_get_assets_excludes_hidden() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let visible = testing::create_asset(database, &author).await;
+            let hidden = testing::create_asset(database, &author).await;
+            database.asset_manager.set_status(hidden.id, ModerationStatus::Hidden).await.unwrap();
+
+            let response = dispatch_request(&client, Method::Get, "/asset".to_string(), None, None).await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let assets: Page<Asset> = response.into_json().await.unwrap();
+            assert!(assets.items.iter().any(|asset| asset.id == visible.id));
+            assert!(!assets.items.iter().any(|asset| asset.id == hidden.id));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_search_assets() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+
+            let response = dispatch_request(&client, Method::Get, "/asset?search=TEST%20ASSET".to_string(), None, None).await;
+            let assets: Page<Asset> = response.into_json().await.unwrap();
+            assert!(assets.items.iter().any(|found| found.id == asset.id));
+
+            let response = dispatch_request(&client, Method::Get, "/asset?search=.*".to_string(), None, None).await;
+            let assets: Page<Asset> = response.into_json().await.unwrap();
+            assert!(assets.items.is_empty());
+        })
+        .await;
+    }
+}
@@ -0,0 +1,162 @@
+use database::{comment::Comment, moderation::ModerationStatus, pagination::Page, Database};
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{model::page_query::PageQuery, RequestError};
+
+/// List the comments of an asset
+///
+/// Comments hidden by a moderator are never returned, and the comments of a hidden asset are not found.
+///
+/// Can be sorted on `timestamp`, and filtered on `user_id` or on the date with `created_after` and `created_before`
+#[openapi(tag = "Assets")]
+#[get("/<id>/comments?<page..>")]
+pub async fn get_comments(
+    database: &State<Database>,
+    id: u32,
+    page: PageQuery,
+) -> Custom<Result<Json<Page<Comment>>, Json<RequestError>>> {
+    match database.asset_manager.get_asset_by_id(id).await {
+        Ok(Some(asset)) if asset.status != ModerationStatus::Hidden => {}
+        Ok(_) => return error_response(Status::NotFound, "Asset not found"),
+        Err(_) => return error_response(Status::InternalServerError, "A database error occurred."),
+    }
+
+    match database.comment_manager.from_asset_id(id, &page.into()).await {
+        Ok(comments) => Custom(Status::Ok, Ok(Json(comments))),
+        Err(err) => {
+            let error = RequestError::from(err);
+            Custom(
+                Status::from_code(error.code).unwrap_or(Status::InternalServerError),
+                Err(error.into()),
+            )
+        }
+    }
+}
+
+fn error_response<T>(status: Status, message: &str) -> Custom<Result<T, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(Json(RequestError::from(Custom(status, message.to_string())))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{comment::Comment, moderation::ModerationStatus, pagination::Page, Database};
+    use rocket::http::{Method, Status};
+
+    use crate::testing::{self, dispatch_request, run_test};
+
+    #[rocket::async_test]
+    async fn test_get_comments_excludes_hidden() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let visible = testing::create_comment(database, &asset, &author).await;
+            let hidden = testing::create_comment(database, &asset, &author).await;
+            database
+                .comment_manager
+                .set_status(hidden.unique_id, ModerationStatus::Hidden)
+                .await
+                .unwrap();
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/asset/{}/comments", asset.id),
+                None,
+                None,
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let comments: Page<Comment> = response.into_json().await.unwrap();
+            assert!(comments.items.iter().any(|comment| comment.unique_id == visible.unique_id));
+            assert!(!comments.items.iter().any(|comment| comment.unique_id == hidden.unique_id));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_comments_pages() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let mut comment_ids = Vec::new();
+            for _ in 0..3 {
+                comment_ids.push(testing::create_comment(database, &asset, &author).await.unique_id);
+            }
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/asset/{}/comments?limit=2", asset.id),
+                None,
+                None,
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let first_page: Page<Comment> = response.into_json().await.unwrap();
+            assert_eq!(first_page.items.len(), 2);
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!(
+                    "/asset/{}/comments?limit=2&cursor={}",
+                    asset.id,
+                    first_page.next_cursor.unwrap()
+                ),
+                None,
+                None,
+            )
+            .await;
+
+            let second_page: Page<Comment> = response.into_json().await.unwrap();
+            assert_eq!(second_page.items.len(), 1);
+            assert!(second_page.next_cursor.is_none());
+
+            let mut listed: Vec<_> = first_page
+                .items
+                .into_iter()
+                .chain(second_page.items)
+                .map(|comment| comment.unique_id)
+                .collect();
+            listed.sort();
+            comment_ids.sort();
+            assert_eq!(listed, comment_ids);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_comments_hidden_asset() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            testing::create_comment(database, &asset, &author).await;
+            database
+                .asset_manager
+                .set_status(asset.id, ModerationStatus::Hidden)
+                .await
+                .unwrap();
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/asset/{}/comments", asset.id),
+                None,
+                None,
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::NotFound);
+        })
+        .await;
+    }
+}
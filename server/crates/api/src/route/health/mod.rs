@@ -0,0 +1,21 @@
+mod route_live;
+mod route_ready;
+
+pub use route_live::*;
+pub use route_ready::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the shutdown was triggered, the API is then reported as not ready while the in-flight requests complete
+#[derive(Default)]
+pub struct Draining(AtomicBool);
+
+impl Draining {
+    pub fn start(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::BTreeMap;
+
+use rocket::{get, serde::json::Json};
+use rocket_okapi::openapi;
+
+use crate::model::health::HealthReport;
+
+/// Check that the API is running
+///
+/// It does not check the dependencies, see `/health/ready`
+#[openapi(tag = "Health")]
+#[get("/live")]
+pub async fn live() -> Json<HealthReport> {
+    Json(HealthReport::new(BTreeMap::new()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::health::{HealthReport, HealthStatus},
+        testing::{dispatch_request, run_test},
+    };
+
+    #[rocket::async_test]
+    async fn test_live() {
+        run_test(|client| async move {
+            let response = dispatch_request(&client, Method::Get, "/health/live".to_string(), None, None).await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let report = response.into_json::<HealthReport>().await.unwrap();
+            assert_eq!(report.status, HealthStatus::Up);
+            assert!(report.components.is_empty());
+        })
+        .await;
+    }
+}
@@ -0,0 +1,139 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use database::Database;
+use rocket::{
+    get,
+    http::Status,
+    response::status::Custom,
+    serde::json::Json,
+    tokio::time::{timeout, Instant},
+    State,
+};
+use rocket_okapi::openapi;
+use telemetry::ExporterHealth;
+
+use super::Draining;
+use crate::model::health::{ComponentHealth, HealthReport, HealthStatus};
+
+// How long the database has to answer the ping
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Check that the API can serve requests
+///
+/// Reports the state of the database, of the migrations and of the telemetry exporters,
+/// the status is 503 when one of them is down or when the API is shutting down
+#[openapi(tag = "Health")]
+#[get("/ready")]
+pub async fn ready(
+    database: &State<Database>,
+    exporter: &State<ExporterHealth>,
+    draining: &State<Draining>,
+) -> Custom<Json<HealthReport>> {
+    let mut components = BTreeMap::new();
+    components.insert("database".to_string(), check_database(database).await);
+    components.insert("migrations".to_string(), check_migrations(database).await);
+    components.insert("telemetry".to_string(), check_telemetry(exporter));
+    if draining.is_draining() {
+        components.insert(
+            "server".to_string(),
+            ComponentHealth::new(HealthStatus::Down).with_detail("reason", "shutting down"),
+        );
+    }
+
+    let report = HealthReport::new(components);
+    let status = match report.status {
+        HealthStatus::Down => Status::ServiceUnavailable,
+        _ => Status::Ok,
+    };
+    Custom(status, Json(report))
+}
+
+async fn check_database(database: &Database) -> ComponentHealth {
+    let start = Instant::now();
+    let mut component = match timeout(PING_TIMEOUT, database.ping()).await {
+        Ok(Ok(())) => ComponentHealth::new(HealthStatus::Up),
+        Ok(Err(err)) => ComponentHealth::new(HealthStatus::Down).with_detail("error", err.to_string()),
+        Err(_) => ComponentHealth::new(HealthStatus::Down).with_detail("error", "The ping timed out"),
+    };
+    component.latency_ms = Some(start.elapsed().as_millis() as u64);
+    component
+}
+
+// check_migrations reports the database down while it is behind this build, which expects the latest schema
+async fn check_migrations(database: &Database) -> ComponentHealth {
+    match timeout(PING_TIMEOUT, database.schema_version()).await {
+        Ok(Ok(version)) => {
+            let status = match version.current.cmp(&version.latest) {
+                std::cmp::Ordering::Less => HealthStatus::Down,
+                std::cmp::Ordering::Equal => HealthStatus::Up,
+                std::cmp::Ordering::Greater => HealthStatus::Degraded,
+            };
+            ComponentHealth::new(status)
+                .with_detail("current_version", version.current)
+                .with_detail("latest_version", version.latest)
+        }
+        Ok(Err(err)) => ComponentHealth::new(HealthStatus::Down).with_detail("error", err.to_string()),
+        Err(_) => ComponentHealth::new(HealthStatus::Down).with_detail("error", "The version lookup timed out"),
+    }
+}
+
+// check_telemetry never reports the exporters down, the API keeps serving requests without them
+fn check_telemetry(exporter: &ExporterHealth) -> ComponentHealth {
+    let state = exporter.state();
+    let status = match state.started && state.consecutive_failures == 0 {
+        true => HealthStatus::Up,
+        false => HealthStatus::Degraded,
+    };
+    let component = ComponentHealth::new(status)
+        .with_detail("started", state.started)
+        .with_detail("consecutive_failures", state.consecutive_failures)
+        .with_detail("dropped", state.dropped)
+        .with_detail("spooled", state.spooled);
+    match state.last_error {
+        Some(err) if state.consecutive_failures > 0 => component.with_detail("last_error", err),
+        _ => component,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::health::{HealthReport, HealthStatus},
+        route::health::Draining,
+        testing::{dispatch_request, run_test},
+    };
+
+    #[rocket::async_test]
+    async fn test_ready() {
+        run_test(|client| async move {
+            let response = dispatch_request(&client, Method::Get, "/health/ready".to_string(), None, None).await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let report = response.into_json::<HealthReport>().await.unwrap();
+            assert_ne!(report.status, HealthStatus::Down);
+            assert_eq!(report.components["database"].status, HealthStatus::Up);
+            assert!(report.components["database"].latency_ms.is_some());
+            assert_eq!(report.components["migrations"].status, HealthStatus::Up);
+            assert!(report.components.contains_key("telemetry"));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_ready_while_draining() {
+        run_test(|client| async move {
+            client.rocket().state::<Draining>().unwrap().start();
+
+            let response = dispatch_request(&client, Method::Get, "/health/ready".to_string(), None, None).await;
+
+            assert_eq!(response.status(), Status::ServiceUnavailable);
+            let report = response.into_json::<HealthReport>().await.unwrap();
+            assert_eq!(report.status, HealthStatus::Down);
+            assert_eq!(report.components["server"].status, HealthStatus::Down);
+        })
+        .await;
+    }
+}
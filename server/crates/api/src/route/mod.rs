@@ -2,23 +2,31 @@ mod organization;
 mod user;
 mod asset;
 mod comment;
+mod health;
+mod moderation;
 
 use rocket::Route;
 use rocket_okapi::okapi::openapi3::OpenApi;
 use rocket_okapi::openapi_get_routes_spec;
 
-use crate::cors;
+pub use health::Draining;
+
+use crate::{api_telemetry::traced, cors, rate_limit::rate_limited};
 
 pub enum ApiRoute {
     Root,
     User,
     Organization,
     Asset,
+    Moderation,
+    Health,
 }
 
 impl ApiRoute {
+    /// Returns the routes, traced within the span of their request and rate limited but for the health checks,
+    /// and their documentation
     pub fn retrieve_routes(&self) -> (Vec<Route>, OpenApi) {
-        match self {
+        let (routes, spec) = match self {
             Self::Root => openapi_get_routes_spec![cors::cors_options],
             Self::User => openapi_get_routes_spec![
                 user::create_license,
@@ -41,6 +49,21 @@ impl ApiRoute {
                 user::add_perm,
                 user::remove_perm,
                 user::check_perm,
+                user::send_verification,
+                user::verify_email,
+                user::forgot_password,
+                user::reset_password,
+                user::send_password_reset,
+                user::oidc_authorize,
+                user::oidc_callback,
+                user::oidc_unlink,
+                user::two_factor_enrol,
+                user::two_factor_confirm,
+                user::two_factor_disable,
+                user::create_access_token,
+                user::get_access_tokens,
+                user::revoke_access_token,
+                user::export_data,
             ],
             Self::Organization => openapi_get_routes_spec![
                 organization::add_member,
@@ -56,11 +79,26 @@ impl ApiRoute {
                 organization::delete_project,
                 organization::project_from_id,
                 organization::update_project,
+                organization::get_members,
+                organization::get_events,
+                organization::export_events,
             ],
             Self::Asset => openapi_get_routes_spec![
                 asset::create_asset,
+                asset::get_assets,
+                asset::get_comments,
                 // asset::delete_asset,
             ],
+            Self::Moderation => openapi_get_routes_spec![
+                moderation::report,
+                moderation::get_queue,
+                moderation::resolve,
+            ],
+            Self::Health => openapi_get_routes_spec![health::live, health::ready],
+        };
+        match self {
+            Self::Health => (traced(routes), spec),
+            _ => (traced(rate_limited(routes)), spec),
         }
     }
 }
@@ -0,0 +1,37 @@
+mod route_get_queue;
+mod route_report;
+mod route_resolve;
+
+pub use route_get_queue::*;
+pub use route_report::*;
+pub use route_resolve::*;
+
+use database::{id::UserId, Database};
+use rocket::{http::Status, response::status::Custom, serde::json::Json};
+
+use crate::{model::user_token::UserData, RequestError};
+
+// check_moderator verifies that the user holds the 'content.moderate' permission
+async fn check_moderator<T>(
+    database: &Database,
+    user_data: &UserData,
+) -> Result<UserId, Custom<Result<Json<T>, Json<RequestError>>>> {
+    let user_id = user_data.id.ok_or_else(|| error_response(Status::Unauthorized, "Token is invalid"))?;
+    let permission_id = database
+        .permission_manager
+        .get_permission_id("content.moderate")
+        .await
+        .map_err(|_| error_response(Status::NotFound, "Unknown permission"))?;
+
+    if !user_data.has_permission(database, permission_id).await {
+        return Err(error_response(Status::Forbidden, "Permission denied"));
+    }
+    Ok(user_id)
+}
+
+fn error_response<T>(status: Status, message: &str) -> Custom<Result<Json<T>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.into())).into()),
+    )
+}
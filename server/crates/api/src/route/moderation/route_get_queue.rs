@@ -0,0 +1,106 @@
+use database::{moderation::Report, pagination::Page, Database};
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use super::{check_moderator, error_response};
+use crate::{
+    model::{page_query::PageQuery, user_token::UserData},
+    RequestError,
+};
+
+/// Retrieve the pending reports of the moderation queue
+///
+/// Can be sorted on `creation_date`, and filtered on `content_kind`, `content_id` and `reporter_id`
+/// or on the creation date with `created_after` and `created_before`
+///
+/// Requires the 'content.moderate' permission
+#[openapi(tag = "Moderation")]
+#[get("/queue?<page..>")]
+pub async fn get_queue(
+    user_data: UserData,
+    database: &State<Database>,
+    page: PageQuery,
+) -> Custom<Result<Json<Page<Report>>, Json<RequestError>>> {
+    if let Err(response) = check_moderator(database, &user_data).await {
+        return response;
+    }
+
+    match database.moderation_manager.get_pending(&page.into()).await {
+        Ok(reports) => Custom(Status::Ok, Ok(Json(reports))),
+        Err(err) => {
+            let error = RequestError::from(err);
+            error_response(Status::from_code(error.code).unwrap_or(Status::InternalServerError), &error.message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{authentication::Authentication, moderation::Report, pagination::Page, Database};
+    use rocket::http::{Method, Status};
+
+    use crate::testing::{self, dispatch_request, run_test};
+
+    #[rocket::async_test]
+    async fn test_get_queue() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("content.moderate").await.unwrap();
+            let moderator = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let report = testing::create_report(database, &asset, &author).await;
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                "/moderation/queue".to_string(),
+                None,
+                Some(moderator.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let reports: Page<Report> = response.into_json().await.unwrap();
+            assert!(reports.items.iter().any(|pending| pending.unique_id == report.unique_id));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn forbidden_test_get_queue() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let request_user = testing::get_user(database).await;
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                "/moderation/queue".to_string(),
+                None,
+                Some(request_user.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn unauthorized_test_get_queue() {
+        run_test(|client| async move {
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                "/moderation/queue".to_string(),
+                None,
+                None,
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Unauthorized);
+        })
+        .await;
+    }
+}
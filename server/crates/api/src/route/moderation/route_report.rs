@@ -0,0 +1,175 @@
+use database::{
+    id::{CommentId, ReportId},
+    moderation::{ContentKind, Report, ReportState},
+    timestamp::Timestamp,
+    Database,
+};
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use super::error_response;
+use crate::{
+    model::{report_init::ReportInit, user_token::UserData},
+    RequestError,
+};
+
+/// Report an asset or a comment to the moderators
+///
+/// The report is added to the moderation queue, the content stays visible until a moderator hides or removes it.
+#[openapi(tag = "Moderation")]
+#[post("/report", data = "<report>", format = "application/json")]
+pub async fn report(
+    user_data: UserData,
+    database: &State<Database>,
+    report: Json<ReportInit>,
+) -> Custom<Result<Json<ReportId>, Json<RequestError>>> {
+    let Some(reporter_id) = user_data.id else {
+        return error_response(Status::Unauthorized, "Token is invalid");
+    };
+    let report = report.0;
+
+    let exists = match report.content_kind {
+        ContentKind::Asset => match report.content_id.parse::<u32>() {
+            Ok(id) => database.asset_manager.asset_exists(id).await,
+            Err(_) => Ok(false),
+        },
+        ContentKind::Comment => match report.content_id.parse::<CommentId>() {
+            Ok(id) => database.comment_manager.comment_exists(id).await,
+            Err(_) => Ok(false),
+        },
+    };
+    match exists {
+        Ok(true) => {}
+        Ok(false) => return error_response(Status::NotFound, "Content not found"),
+        Err(_) => return error_response(Status::InternalServerError, "A database error occurred."),
+    }
+
+    let report = Report {
+        unique_id: ReportId::generate(),
+        content_kind: report.content_kind,
+        content_id: report.content_id,
+        reporter_id,
+        reason: report.reason,
+        creation_date: Timestamp::now(),
+        state: ReportState::Pending,
+    };
+
+    match database.moderation_manager.create_report(&report).await {
+        Ok(_) => Custom(Status::Created, Ok(Json(report.unique_id))),
+        Err(_) => error_response(Status::InternalServerError, "A database error occurred."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        id::ReportId,
+        moderation::{ContentKind, ReportState},
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::report_init::ReportInit,
+        testing::{self, dispatch_request, run_test},
+    };
+
+    #[rocket::async_test]
+    async fn test_report_asset() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let reporter = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let body = ReportInit::new(ContentKind::Asset, asset.id.to_string(), "Spam".to_string());
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/moderation/report".to_string(),
+                Some(serde_json::to_string(&body).unwrap()),
+                Some(reporter.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Created);
+            let report_id: ReportId = response.into_json().await.unwrap();
+            let report = database
+                .moderation_manager
+                .from_id(report_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(report.reporter_id, reporter.unique_id);
+            assert_eq!(report.state, ReportState::Pending);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_report_comment() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let reporter = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let comment = testing::create_comment(database, &asset, &author).await;
+            let body = ReportInit::new(ContentKind::Comment, comment.unique_id.to_string(), "Insult".to_string());
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/moderation/report".to_string(),
+                Some(serde_json::to_string(&body).unwrap()),
+                Some(reporter.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Created);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_report_unknown_content() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let reporter = testing::get_user(database).await;
+            let body = ReportInit::new(ContentKind::Comment, "NO_ID".to_string(), "Spam".to_string());
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/moderation/report".to_string(),
+                Some(serde_json::to_string(&body).unwrap()),
+                Some(reporter.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::NotFound);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn unauthorized_test_report() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let body = ReportInit::new(ContentKind::Asset, asset.id.to_string(), "Spam".to_string());
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/moderation/report".to_string(),
+                Some(serde_json::to_string(&body).unwrap()),
+                None,
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Unauthorized);
+        })
+        .await;
+    }
+}
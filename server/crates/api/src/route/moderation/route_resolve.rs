@@ -0,0 +1,242 @@
+use database::{
+    id::{CommentId, ReportId, UserId},
+    moderation::{ContentKind, ModerationAction, ModerationStatus, Report, ReportState},
+    Database,
+};
+use mongodb::error::Error;
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use super::{check_moderator, error_response};
+use crate::{
+    model::{moderation_decision::ModerationDecision, user_token::UserData},
+    RequestError,
+};
+
+/// Resolve a report of the moderation queue
+///
+/// The content can be approved, hidden from every listing or removed, and its author can be banned.
+/// Every pending report targeting the same content is resolved at once.
+///
+/// Requires the 'content.moderate' permission
+#[openapi(tag = "Moderation")]
+#[post("/<report_id>/resolve", data = "<decision>", format = "application/json")]
+pub async fn resolve(
+    user_data: UserData,
+    database: &State<Database>,
+    report_id: ReportId,
+    decision: Json<ModerationDecision>,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    let moderator_id = match check_moderator(database, &user_data).await {
+        Ok(moderator_id) => moderator_id,
+        Err(response) => return response,
+    };
+
+    let report = match database.moderation_manager.from_id(report_id).await {
+        Ok(Some(report)) if report.state == ReportState::Pending => report,
+        Ok(Some(_)) => return error_response(Status::Conflict, "Report already resolved"),
+        Ok(None) => return error_response(Status::NotFound, "Report not found"),
+        Err(_) => return error_response(Status::InternalServerError, "A database error occurred."),
+    };
+
+    let author_id = match apply_action(database, &report, decision.action).await {
+        Ok(author_id) => author_id,
+        Err(_) => return error_response(Status::InternalServerError, "A database error occurred."),
+    };
+
+    let author_banned = match author_id {
+        Some(author_id) if decision.ban_author => {
+            if database.user_manager.set_banned(author_id, true).await.is_err() {
+                return error_response(Status::InternalServerError, "A database error occurred.");
+            }
+            true
+        }
+        _ => false,
+    };
+
+    let state = ReportState::Resolved {
+        moderator_id,
+        action: decision.action,
+        author_banned,
+    };
+    match database
+        .moderation_manager
+        .resolve(report.content_kind, &report.content_id, &state)
+        .await
+    {
+        Ok(_) => Custom(Status::Ok, Ok(Json(true))),
+        Err(_) => error_response(Status::InternalServerError, "A database error occurred."),
+    }
+}
+
+// apply_action updates the reported content and returns its author, if the content still exists
+async fn apply_action(
+    database: &Database,
+    report: &Report,
+    action: ModerationAction,
+) -> Result<Option<UserId>, Error> {
+    match report.content_kind {
+        ContentKind::Asset => {
+            let Ok(id) = report.content_id.parse::<u32>() else {
+                return Ok(None);
+            };
+            let Some(asset) = database.asset_manager.get_asset_by_id(id).await? else {
+                return Ok(None);
+            };
+            match action {
+                ModerationAction::Approve => database.asset_manager.set_status(id, ModerationStatus::Visible).await.map(|_| ())?,
+                ModerationAction::Hide => database.asset_manager.set_status(id, ModerationStatus::Hidden).await.map(|_| ())?,
+                ModerationAction::Remove => database.asset_manager.delete_asset(id).await.map(|_| ())?,
+            }
+            Ok(asset.author_id)
+        }
+        ContentKind::Comment => {
+            let Ok(id) = report.content_id.parse::<CommentId>() else {
+                return Ok(None);
+            };
+            let Some(comment) = database.comment_manager.from_id(id).await? else {
+                return Ok(None);
+            };
+            match action {
+                ModerationAction::Approve => database.comment_manager.set_status(id, ModerationStatus::Visible).await.map(|_| ())?,
+                ModerationAction::Hide => database.comment_manager.set_status(id, ModerationStatus::Hidden).await.map(|_| ())?,
+                ModerationAction::Remove => database.comment_manager.delete_comment(id).await.map(|_| ())?,
+            }
+            Ok(Some(comment.user_id))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        authentication::Authentication,
+        moderation::{ModerationAction, ModerationStatus, ReportState},
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::moderation_decision::ModerationDecision,
+        testing::{self, dispatch_request, run_test},
+    };
+
+    #[rocket::async_test]
+    async fn test_hide_asset_and_ban_author() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("content.moderate").await.unwrap();
+            let moderator = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let report = testing::create_report(database, &asset, &author).await;
+            let body = ModerationDecision::new(ModerationAction::Hide, true);
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                format!("/moderation/{}/resolve", report.unique_id),
+                Some(serde_json::to_string(&body).unwrap()),
+                Some(moderator.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let asset = database.asset_manager.get_asset_by_id(asset.id).await.unwrap().unwrap();
+            assert_eq!(asset.status, ModerationStatus::Hidden);
+            assert!(!database
+                .asset_manager
+                .get_assets(None, &Default::default())
+                .await
+                .unwrap()
+                .items
+                .iter()
+                .any(|visible| visible.id == asset.id));
+            let author = database.user_manager.from_id(author.unique_id).await.unwrap().unwrap();
+            assert!(author.banned);
+            let report = database.moderation_manager.from_id(report.unique_id).await.unwrap().unwrap();
+            assert!(matches!(report.state, ReportState::Resolved { author_banned: true, .. }));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_remove_comment() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("content.moderate").await.unwrap();
+            let moderator = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let comment = testing::create_comment(database, &asset, &author).await;
+            let report = testing::create_comment_report(database, &comment, &author).await;
+            let body = ModerationDecision::new(ModerationAction::Remove, false);
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                format!("/moderation/{}/resolve", report.unique_id),
+                Some(serde_json::to_string(&body).unwrap()),
+                Some(moderator.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            assert!(!database.comment_manager.comment_exists(comment.unique_id).await.unwrap());
+            let author = database.user_manager.from_id(author.unique_id).await.unwrap().unwrap();
+            assert!(!author.banned);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_resolve_twice() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("content.moderate").await.unwrap();
+            let moderator = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let report = testing::create_report(database, &asset, &author).await;
+            let body = serde_json::to_string(&ModerationDecision::new(ModerationAction::Approve, false)).unwrap();
+
+            for expected in [Status::Ok, Status::Conflict] {
+                let response = dispatch_request(
+                    &client,
+                    Method::Post,
+                    format!("/moderation/{}/resolve", report.unique_id),
+                    Some(body.clone()),
+                    Some(moderator.get_token().unwrap().to_string()),
+                )
+                .await;
+
+                assert_eq!(response.status(), expected);
+            }
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn forbidden_test_resolve() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let author = testing::get_user(database).await;
+            let asset = testing::create_asset(database, &author).await;
+            let report = testing::create_report(database, &asset, &author).await;
+            let body = ModerationDecision::new(ModerationAction::Remove, true);
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                format!("/moderation/{}/resolve", report.unique_id),
+                Some(serde_json::to_string(&body).unwrap()),
+                Some(author.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+            assert!(database.asset_manager.asset_exists(asset.id).await.unwrap());
+        })
+        .await;
+    }
+}
@@ -11,6 +11,9 @@ mod route_get_projects_from_organization;
 mod route_delete_project;
 mod route_project_from_id;
 mod route_update_project;
+mod route_get_members;
+mod route_get_events;
+mod route_export_events;
 
 pub use route_add_member::*;
 pub use route_add_server::*;
@@ -24,4 +27,75 @@ pub use route_delete_member::*;
 pub use route_get_projects_from_organization::*;
 pub use route_delete_project::*;
 pub use route_project_from_id::*;
-pub use route_update_project::*;
\ No newline at end of file
+pub use route_update_project::*;
+pub use route_get_members::*;
+pub use route_get_events::*;
+pub use route_export_events::*;
+
+use database::{
+    id::{OrganizationId, UserId},
+    organization::Organization,
+    Database,
+};
+use rocket::{http::Status, response::status::Custom, serde::json::Json};
+
+use crate::{model::user_token::UserData, two_factor, RequestError};
+
+// check_events_access verifies that the user is the owner or a member of the organization,
+// and holds the 'organisation.events.see' permission, with a second factor when the organization requires it.
+// It returns the owner and the members, whose own events are part of the events of the organization.
+async fn check_events_access<T>(
+    database: &Database,
+    user_data: &UserData,
+    id: OrganizationId,
+) -> Result<Vec<UserId>, Custom<Result<T, Json<RequestError>>>> {
+    if user_data.id.is_none() {
+        return Err(error_response(Status::Unauthorized, "Token is invalid"));
+    }
+    let organization = match database.organization_manager.from_id(id).await {
+        Ok(Some(organization)) => organization,
+        Ok(None) => return Err(error_response(Status::NotFound, "Organization was not found.")),
+        Err(_) => return Err(error_response(Status::InternalServerError, "A database error occurred.")),
+    };
+    let permission_id = database
+        .permission_manager
+        .get_permission_id("organisation.events.see")
+        .await
+        .map_err(|_| error_response(Status::NotFound, "Unknown permission"))?;
+
+    check_access(database, user_data, &organization).await?;
+    if !user_data.has_permission(database, permission_id).await {
+        return Err(error_response(Status::Forbidden, "Permission denied"));
+    }
+    let mut user_ids = organization.member_ids;
+    user_ids.push(organization.owner_id);
+    Ok(user_ids)
+}
+
+// check_access verifies that the user is the owner or a member of the organization, with a second factor when the
+// organization requires it, and returns their id
+async fn check_access<T>(
+    database: &Database,
+    user_data: &UserData,
+    organization: &Organization,
+) -> Result<UserId, Custom<Result<T, Json<RequestError>>>> {
+    let user_id = user_data.id.ok_or_else(|| error_response(Status::Unauthorized, "Token is invalid"))?;
+    if organization.owner_id != user_id && !organization.member_ids.contains(&user_id) {
+        return Err(error_response(Status::Forbidden, "Permission denied"));
+    }
+    match two_factor::meets_requirement(database.two_factor_manager.as_ref(), organization, user_id).await {
+        Ok(true) => Ok(user_id),
+        Ok(false) => Err(error_response(
+            Status::Forbidden,
+            "The organization requires two-factor authentication.",
+        )),
+        Err(_) => Err(error_response(Status::InternalServerError, "A database error occurred.")),
+    }
+}
+
+fn error_response<T>(status: Status, message: &str) -> Custom<Result<T, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.into())).into()),
+    )
+}
\ No newline at end of file
@@ -1,15 +1,24 @@
-use database::{Database, organization::Organization};
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::{OrganizationId, UserId},
+    organization::Organization,
+    Database,
+};
 use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
+use serde_json::json;
 
+use super::check_access;
 use crate::{
+    audit::AuditContext,
     model::{user_token::UserData, organization_member::OrganizationMember},
-    RequestError,
+    two_factor, RequestError,
 };
 
 /// Register a new member on the organization
 ///
-/// Requires 'Website' group
+/// Requires 'Website' group, and to be the owner or a member of the organization with a second factor when the
+/// organization requires it
 #[openapi(tag = "Organizations")]
 #[post(
     "/<id>/members",
@@ -17,26 +26,42 @@ use crate::{
     format = "application/json"
 )]
 pub async fn add_member(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
+    id: OrganizationId,
     body: Json<OrganizationMember>,
+    audit: AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
     // Check if the organization exists
-    match database.organization_manager.from_id(&id).await {
-        Ok(Some(organization)) => check_member(database, organization, body.member_id.clone()).await,
-        Ok(None) => error_response(Status::NotFound, "Organization was not found."),
-        Err(_) => error_response(Status::InternalServerError, "A database error occurred."),
+    let organization = match database.organization_manager.from_id(id).await {
+        Ok(Some(organization)) => organization,
+        Ok(None) => return error_response(Status::NotFound, "Organization was not found."),
+        Err(_) => return error_response(Status::InternalServerError, "A database error occurred."),
+    };
+    if let Err(response) = check_access(database, &user_data, &organization).await {
+        return response;
+    }
+
+    let before = json!({ "member_ids": organization.member_ids });
+    let mut member_ids = organization.member_ids.clone();
+    let response = check_member(database, organization, body.member_id).await;
+    if response.1.is_ok() {
+        member_ids.push(body.member_id);
+        let changes = AuditChanges::diff(&before, &json!({ "member_ids": member_ids }));
+        let event = audit.event(AuditAction::MemberAdded, user_data.id, AuditTargetKind::User, body.member_id);
+        audit.record(database, event.in_organization(id).with_changes(changes)).await;
     }
+    response
 }
 
-// check_member checks if the member exists, if is not already a member or the owner and adds it to the organization
+// check_member checks if the member exists, if is not already a member or the owner and adds it to the organization,
+// the organizations requiring a second factor only accept the users who enabled one
 async fn check_member(
     database: &State<Database>,
     organization: Organization,
-    member_id: String,
+    member_id: UserId,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
-    match database.user_manager.from_id(&member_id).await {
+    match database.user_manager.from_id(member_id).await {
         Ok(Some(member)) => {
             if organization.owner_id == member.unique_id {
                 error_response(
@@ -49,7 +74,15 @@ async fn check_member(
                     "The user is already a member of the organization.",
                 )
             } else {
-                add_member_to_organization(database, organization.unique_id, member_id).await
+                let two_factors = database.two_factor_manager.as_ref();
+                match two_factor::meets_requirement(two_factors, &organization, member_id).await {
+                    Ok(true) => add_member_to_organization(database, organization.unique_id, member_id).await,
+                    Ok(false) => error_response(
+                        Status::Conflict,
+                        "The organization requires two-factor authentication, the user must enable it first.",
+                    ),
+                    Err(_) => error_response(Status::InternalServerError, "A database error occurred."),
+                }
             }
         }
         Ok(None) => error_response(Status::NotFound, "Member was not found."),
@@ -60,12 +93,12 @@ async fn check_member(
 // add_member_to_organization adds the member to the organization
 async fn add_member_to_organization(
     database: &State<Database>,
-    organization_id: String,
-    member_id: String,
+    organization_id: OrganizationId,
+    member_id: UserId,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
     match database
         .organization_manager
-        .add_member(&organization_id, &member_id)
+        .add_member(organization_id, member_id)
         .await
     {
         Ok(_) => Custom(Status::Ok, Ok(Json(true))),
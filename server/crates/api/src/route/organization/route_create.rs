@@ -1,27 +1,43 @@
-use database::{organization::Organization, Database};
+use database::{id::OrganizationId, organization::Organization, timestamp::Timestamp, Database};
 use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
 use crate::{
     model::{organization_init::OrganizationInit, user_token::UserData},
-    RequestError, Server,
+    RequestError,
 };
 
 /// Register a new organization
 ///
-/// Requires 'Website' group
+/// Requires to be signed in, and the 'organisation.create' permission to create an organization owned by another user
 #[openapi(tag = "Organizations")]
 #[post("/", data = "<organization>", format = "application/json")] // <- route attribute
 pub async fn create(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     organization: Json<OrganizationInit>,
-) -> Custom<Result<Json<String>, Json<RequestError>>> {
+) -> Custom<Result<Json<OrganizationId>, Json<RequestError>>> {
     let raw_organization = organization.0;
 
+    let Some(user_id) = user_data.id else {
+        return super::error_response(Status::Unauthorized, "Token is invalid");
+    };
+    if raw_organization.owner_id != user_id {
+        let can_create = match database.permission_manager.get_permission_id("organisation.create").await {
+            Ok(permission) => user_data.has_permission(database, permission).await,
+            Err(_) => false,
+        };
+        if !can_create {
+            return super::error_response(
+                Status::Forbidden,
+                "Only a user with the 'organisation.create' permission can create an organization for another user.",
+            );
+        }
+    }
+
     let user = database
         .user_manager
-        .from_id(&raw_organization.owner_id)
+        .from_id(raw_organization.owner_id)
         .await;
     if user.is_err() || user.unwrap().is_none() {
         return Custom(
@@ -38,14 +54,15 @@ pub async fn create(
     // let users = database.user_manager.get_users_by_group(Group::Server).await.unwrap();
 
     let organization = Organization {
-        unique_id: Server::generate_unique_id().to_string(),
-        creation_date: Server::current_time().to_string(),
+        unique_id: OrganizationId::generate(),
+        creation_date: Timestamp::now(),
         name: raw_organization.name,
         member_ids: Vec::new(),
         owner_id: raw_organization.owner_id,
-        // server_ids: users.iter().map(|user| user.unique_id.clone()).collect(),
+        // server_ids: users.iter().map(|user| user.unique_id).collect(),
         server_ids: Vec::new(),
         projects_ids: Vec::new(),
+        require_two_factor: false,
     };
 
     match database
@@ -64,7 +81,11 @@ pub async fn create(
 #[cfg(test)]
 mod tests {
 
-    use database::{Database};
+    use database::{
+        authentication::Authentication,
+        id::{OrganizationId, UserId},
+        Database,
+    };
     use rocket::http::{Method, Status};
 
     use crate::{
@@ -78,7 +99,8 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
+            let permission = database.permission_manager.get_permission_id("organisation.create").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
             let body = OrganizationInit::new("Test organization".to_string(), test_user.unique_id);
 
@@ -93,10 +115,10 @@ mod tests {
 
             assert_eq!(response.status(), Status::Ok);
             
-            let response_id: String = response.into_json().await.unwrap();
+            let response_id: OrganizationId = response.into_json().await.unwrap();
             let created_org = database
                 .organization_manager
-                .from_id(&response_id)
+                .from_id(response_id)
                 .await
                 .unwrap()
                 .unwrap();
@@ -107,13 +129,38 @@ mod tests {
         .await;
     }
 
+    #[rocket::async_test]
+    async fn test_create_own() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let body = OrganizationInit::new("Own organization".to_string(), test_user.unique_id);
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/organization".to_string(),
+                Some(serde_json::to_string(&body).unwrap()),
+                Some(test_user.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let response_id: OrganizationId = response.into_json().await.unwrap();
+            let created_org = database.organization_manager.from_id(response_id).await.unwrap().unwrap();
+            assert_eq!(created_org.owner_id, test_user.unique_id);
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn test_unknown_create() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
-            let request_user = testing::get_user(database).await;
+            let permission = database.permission_manager.get_permission_id("organisation.create").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
-            let body = OrganizationInit::new("Test organization".to_string(), "NO_ID".to_string());
+            let body = OrganizationInit::new("Test organization".to_string(), UserId::generate());
 
             let response = dispatch_request(
                 &client,
@@ -152,8 +199,6 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
             let body = OrganizationInit::new("Test organization".to_string(), test_user.unique_id);
 
             let response = dispatch_request(
@@ -161,7 +206,7 @@ mod tests {
                 Method::Post,
                 format!("/organization"),
                 Some(serde_json::to_string(&body).unwrap()),
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -182,6 +227,8 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
             let body = OrganizationInit::new("Test organization".to_string(), test_user.unique_id);
 
             let response = dispatch_request(
@@ -189,7 +236,7 @@ mod tests {
                 Method::Post,
                 format!("/organization"),
                 Some(serde_json::to_string(&body).unwrap()),
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
@@ -1,27 +1,33 @@
-use database::{Database, project::Project};
+use database::{
+    id::{OrganizationId, ProjectId},
+    project::Project,
+    Database,
+};
 use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
+use super::check_access;
 use crate::{
     model::{project_init::ProjectInit, user_token::UserData},
-    RequestError, Server,
+    RequestError,
 };
 
 /// Register a new project in the organization
 ///
-/// Requires 'Website' group
+/// Requires 'Website' group, and to be the owner or a member of the organization with a second factor when the
+/// organization requires it
 #[openapi(tag = "Organizations")]
 #[post("/<id>/projects", data = "<project>", format = "application/json")] // <- route attribute
 // Add new project to organization
 pub async fn create_project(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     project: Json<ProjectInit>,
-    id: String,
-) -> Custom<Result<Json<String>, Json<RequestError>>> {
+    id: OrganizationId,
+) -> Custom<Result<Json<ProjectId>, Json<RequestError>>> {
 
 
-    let organization = match database.organization_manager.from_id(&id).await {
+    let organization = match database.organization_manager.from_id(id).await {
         Ok(Some(organization)) => organization,
         Ok(None) => {
             return Custom(
@@ -44,11 +50,14 @@ pub async fn create_project(
             )
         }
     };
+    if let Err(response) = check_access(database, &user_data, &organization).await {
+        return response;
+    }
 
     let project = Project {
-        unique_id: Server::generate_unique_id().to_string(),
+        unique_id: ProjectId::generate(),
         name: project.0.name,
-        organization_id: organization.unique_id.clone(),
+        organization_id: organization.unique_id,
         member_ids: Vec::new(),
     };
 
@@ -68,7 +77,7 @@ pub async fn create_project(
 
     match database
         .organization_manager
-        .add_to_projects_ids(&organization.unique_id, &project.unique_id)
+        .add_to_projects_ids(organization.unique_id, project.unique_id)
         .await
     {
         Ok(_) => (),
@@ -1,20 +1,52 @@
-use database::{Database};
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::OrganizationId,
+    Database,
+};
 use rocket::{delete, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use super::{check_access, error_response};
+use crate::{
+    audit::{snapshot, AuditContext},
+    model::user_token::UserData,
+    RequestError,
+};
 
-/// Delete the user from its id
+/// Delete the organization from its id
+///
+/// Requires to be the owner of the organization
 #[openapi(tag = "Organizations")]
 #[delete("/<id>")] // <- route attribute
 pub async fn delete_from_id(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
+    id: OrganizationId,
+    audit: AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    if user_data.id.is_none() {
+        return error_response(Status::Unauthorized, "Token is invalid");
+    }
+    let organization = database.organization_manager.from_id(id).await.ok().flatten();
+    if let Some(organization) = &organization {
+        match check_access(database, &user_data, organization).await {
+            Ok(user_id) if user_id == organization.owner_id => {}
+            Ok(_) => return error_response(Status::Forbidden, "Only the owner can delete the organization."),
+            Err(response) => return response,
+        }
+    }
 
-    match database.organization_manager.delete_organization(&id).await {
-        Ok(Some(result)) if result.deleted_count > 0 => Custom(Status::Ok, Ok(Json(true))),
+    match database.organization_manager.delete_organization(id).await {
+        Ok(true) => {
+            let changes = AuditChanges {
+                before: organization.as_ref().map(snapshot),
+                after: None,
+            };
+            let event = audit.event(AuditAction::OrganizationDeleted, user_data.id, AuditTargetKind::Organization, id);
+            audit.record(database, event.in_organization(id).with_changes(changes)).await;
+
+            Custom(Status::Ok, Ok(Json(true)))
+        }
         Ok(_) => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -33,7 +65,7 @@ pub async fn delete_from_id(
 #[cfg(test)]
 mod tests {
 
-    use database::{Database};
+    use database::{id::OrganizationId, Database};
     use rocket::http::{Method, Status};
 
     use crate::{
@@ -46,8 +78,7 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let request_token = test_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
 
             let response = dispatch_request(
@@ -65,7 +96,7 @@ mod tests {
             // Organization should have been deleted
             assert!(database
                 .organization_manager
-                .from_id(&test_org.unique_id)
+                .from_id(test_org.unique_id)
                 .await
                 .unwrap()
                 .is_none());
@@ -80,7 +111,7 @@ mod tests {
                 testing::get_user(client.rocket().state::<Database>().unwrap())
                     .await;
             let request_token = request_user.get_token().unwrap();
-            let id = "NO_ID";
+            let id = OrganizationId::generate();
 
             let response = dispatch_request(
                 &client,
@@ -112,8 +143,6 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
 
             let response = dispatch_request(
@@ -121,7 +150,7 @@ mod tests {
                 Method::Delete,
                 format!("/organization/{}", test_org.unique_id),
                 None,
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -135,6 +164,8 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
 
             let response = dispatch_request(
@@ -142,7 +173,7 @@ mod tests {
                 Method::Delete,
                 format!("/organization/{}", test_org.unique_id),
                 None,
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
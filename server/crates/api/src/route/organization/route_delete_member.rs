@@ -1,15 +1,24 @@
-use database::{Database, organization::Organization};
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::{OrganizationId, UserId},
+    organization::Organization,
+    Database,
+};
 use rocket::{http::Status, delete, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
+use serde_json::json;
 
+use super::check_access;
 use crate::{
+    audit::AuditContext,
     model::{user_token::UserData, organization_member::OrganizationMember},
     RequestError,
 };
 
 /// Delete a member from the organization
 ///
-/// Requires 'Website' group
+/// Requires 'Website' group, and to be the owner or a member of the organization with a second factor when the
+/// organization requires it
 #[openapi(tag = "Organizations")]
 #[delete(
     "/<id>/members",
@@ -17,28 +26,43 @@ use crate::{
     format = "application/json"
 )]
 pub async fn remove_member(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
+    id: OrganizationId,
     body: Json<OrganizationMember>,
+    audit: AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
 
 
     // Check if the organization exists
-    match database.organization_manager.from_id(&id).await {
-        Ok(Some(organization)) => check_member(database, organization, body.member_id.clone()).await,
-        Ok(None) => error_response(Status::NotFound, "Organization was not found."),
-        Err(_) => error_response(Status::InternalServerError, "A database error occurred."),
+    let organization = match database.organization_manager.from_id(id).await {
+        Ok(Some(organization)) => organization,
+        Ok(None) => return error_response(Status::NotFound, "Organization was not found."),
+        Err(_) => return error_response(Status::InternalServerError, "A database error occurred."),
+    };
+    if let Err(response) = check_access(database, &user_data, &organization).await {
+        return response;
+    }
+
+    let before = json!({ "member_ids": organization.member_ids });
+    let mut member_ids = organization.member_ids.clone();
+    let response = check_member(database, organization, body.member_id).await;
+    if response.1.is_ok() {
+        member_ids.retain(|member_id| *member_id != body.member_id);
+        let changes = AuditChanges::diff(&before, &json!({ "member_ids": member_ids }));
+        let event = audit.event(AuditAction::MemberRemoved, user_data.id, AuditTargetKind::User, body.member_id);
+        audit.record(database, event.in_organization(id).with_changes(changes)).await;
     }
+    response
 }
 
 // check_member checks if the member is present in the organization and removes it
 async fn check_member(
     database: &State<Database>,
     organization: Organization,
-    member_id: String,
+    member_id: UserId,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
-    match database.user_manager.from_id(&member_id).await {
+    match database.user_manager.from_id(member_id).await {
         Ok(Some(member)) => {
             if organization.owner_id == member.unique_id {
                 error_response(
@@ -62,12 +86,12 @@ async fn check_member(
 // remove_member_from_organization removes the member from the organization
 async fn remove_member_from_organization(
     database: &State<Database>,
-    organization_id: String,
-    member_id: String,
+    organization_id: OrganizationId,
+    member_id: UserId,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
     match database
         .organization_manager
-        .remove_from_member_ids(&organization_id, &member_id)
+        .remove_from_member_ids(organization_id, member_id)
         .await
     {
         Ok(_) => Custom(Status::Ok, Ok(Json(true))),
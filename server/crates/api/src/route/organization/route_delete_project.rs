@@ -1,7 +1,13 @@
-use database::{Database};
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::{OrganizationId, ProjectId},
+    Database,
+};
 use rocket::{http::Status, delete, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
+use super::check_access;
 use crate::RequestError;
+use crate::audit::{snapshot, AuditContext};
 use crate::model::user_token::UserData;
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
@@ -9,23 +15,25 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Debug, JsonSchema, Deserialize)]
 pub struct DeleteProject {
-    pub project_id: String,
+    pub project_id: ProjectId,
 }
 
 /// Delete project of an organization.
 /// 
-/// Requires 'Website' group
+/// Requires 'Website' group, and to be the owner or a member of the organization with a second factor when the
+/// organization requires it
 #[openapi(tag = "Organizations")]
 #[delete("/<id>/projects", data = "<project_data>", format = "application/json")]
 pub async fn delete_project(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     project_data: Json<DeleteProject>,
-    id: String,
+    id: OrganizationId,
+    audit: AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
 
 
-    let organization = match database.organization_manager.from_id(&id).await {
+    let organization = match database.organization_manager.from_id(id).await {
         Ok(Some(organization)) => organization,
         Ok(None) => {
             return Custom(
@@ -48,8 +56,11 @@ pub async fn delete_project(
             )
         }
     };
+    if let Err(response) = check_access(database, &user_data, &organization).await {
+        return response;
+    }
 
-    let project = match database.project_manager.from_id(&project_data.project_id).await {
+    let project = match database.project_manager.from_id(project_data.project_id).await {
         Ok(Some(project)) => project,
         Ok(None) => {
             return Custom(
@@ -86,7 +97,7 @@ pub async fn delete_project(
 
     match database
         .organization_manager
-        .remove_from_projects_ids(&organization.unique_id, &project.unique_id)
+        .remove_from_projects_ids(organization.unique_id, project.unique_id)
         .await
     {
         Ok(_) => {}
@@ -102,8 +113,17 @@ pub async fn delete_project(
         }
     }
 
-    match database.project_manager.delete_from_id(&project_data.project_id).await {
-        Ok(Some(_)) => Custom(Status::Ok, Ok(Json(true))),
+    match database.project_manager.delete_from_id(project_data.project_id).await {
+        Ok(Some(_)) => {
+            let changes = AuditChanges {
+                before: Some(snapshot(&project)),
+                after: None,
+            };
+            let event = audit.event(AuditAction::ProjectDeleted, user_data.id, AuditTargetKind::Project, project.unique_id);
+            audit.record(database, event.in_organization(id).with_changes(changes)).await;
+
+            Custom(Status::Ok, Ok(Json(true)))
+        }
         Ok(None) => Custom(
             Status::NotFound,
             Err(RequestError::from(Custom(
@@ -0,0 +1,189 @@
+use database::{
+    audit::AuditEvent,
+    id::OrganizationId,
+    pagination::{PageRequest, MAX_PAGE_LIMIT},
+    Database,
+};
+use rocket::{
+    get,
+    http::{ContentType, Status},
+    response::status::Custom,
+    serde::json::Json,
+    State,
+};
+use rocket_okapi::openapi;
+use serde_json::Value;
+
+use super::{check_events_access, error_response};
+use crate::{
+    model::{export_format::ExportFormat, page_query::PageQuery, user_token::UserData},
+    RequestError,
+};
+
+// The columns of the CSV exports, in order
+const CSV_COLUMNS: [&str; 10] = [
+    "unique_id",
+    "creation_date",
+    "action",
+    "actor_id",
+    "target_kind",
+    "target_id",
+    "organization_id",
+    "ip",
+    "request_id",
+    "changes",
+];
+
+/// Export the audit events of an organization
+///
+/// Every event matching the sort and the filters of the query, the same as the listing of the events, as `ndjson`
+/// (the default) or `csv`. The changes are written as JSON in their CSV column.
+///
+/// Requires the 'organisation.events.see' permission, and to be the owner or a member of the organization
+#[openapi(tag = "Organizations")]
+#[get("/<id>/events/export?<format>&<page..>")]
+pub async fn export_events(
+    user_data: UserData,
+    database: &State<Database>,
+    id: OrganizationId,
+    format: Option<ExportFormat>,
+    page: PageQuery,
+) -> Custom<Result<(ContentType, String), Json<RequestError>>> {
+    let user_ids = match check_events_access(database, &user_data, id).await {
+        Ok(user_ids) => user_ids,
+        Err(response) => return response,
+    };
+
+    let mut request = PageRequest::from(page);
+    request.limit = Some(MAX_PAGE_LIMIT);
+    let mut events = Vec::new();
+    loop {
+        match database.audit_manager.get_events(id, &user_ids, &request).await {
+            Ok(page) => {
+                events.extend(page.items);
+                match page.next_cursor {
+                    Some(cursor) => request.cursor = Some(cursor),
+                    None => break,
+                }
+            }
+            Err(err) => {
+                let error = RequestError::from(err);
+                return error_response(
+                    Status::from_code(error.code).unwrap_or(Status::InternalServerError),
+                    &error.message,
+                );
+            }
+        }
+    }
+
+    let export = match format.unwrap_or_default() {
+        ExportFormat::Ndjson => (ContentType::new("application", "x-ndjson"), to_ndjson(&events)),
+        ExportFormat::Csv => (ContentType::CSV, to_csv(&events)),
+    };
+    Custom(Status::Ok, Ok(export))
+}
+
+fn to_ndjson(events: &[AuditEvent]) -> String {
+    events
+        .iter()
+        .filter_map(|event| serde_json::to_string(event).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+fn to_csv(events: &[AuditEvent]) -> String {
+    let mut csv = CSV_COLUMNS.join(",") + "\n";
+    for event in events {
+        let event = serde_json::to_value(event).unwrap_or(Value::Null);
+        let row: Vec<String> = CSV_COLUMNS
+            .iter()
+            .map(|column| match &event[*column] {
+                Value::Null => String::new(),
+                Value::String(value) => csv_field(value),
+                value => csv_field(&value.to_string()),
+            })
+            .collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// csv_field quotes the fields holding a separator, a quote or a line break, and doubles their quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{audit::AuditEvent, authentication::Authentication, Database};
+    use rocket::http::{ContentType, Method, Status};
+    use serde_json::json;
+
+    use super::csv_field;
+    use crate::testing::{self, dispatch_request, run_test};
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("MemberAdded"), "MemberAdded");
+        assert_eq!(csv_field(r#"{"a":1,"b":2}"#), r#""{""a"":1,""b"":2}""#);
+    }
+
+    #[rocket::async_test]
+    async fn test_export_events() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("organisation.events.see").await.unwrap();
+            let owner = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let organization = testing::get_org(database, &owner).await;
+            let token = owner.get_token().unwrap().to_string();
+            for _ in 0..2 {
+                let member = testing::get_user(database).await;
+                dispatch_request(
+                    &client,
+                    Method::Post,
+                    format!("/organization/{}/members", organization.unique_id),
+                    Some(json!({ "member_id": member.unique_id }).to_string()),
+                    Some(token.clone()),
+                )
+                .await;
+            }
+
+            // The export goes through every page
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/organization/{}/events/export?limit=1", organization.unique_id),
+                None,
+                Some(token.clone()),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.content_type(), Some(ContentType::new("application", "x-ndjson")));
+            let body = response.into_string().await.unwrap();
+            let events: Vec<AuditEvent> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+            assert_eq!(events.len(), 2);
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/organization/{}/events/export?format=csv", organization.unique_id),
+                None,
+                Some(token),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.content_type(), Some(ContentType::CSV));
+            let body = response.into_string().await.unwrap();
+            let lines: Vec<&str> = body.lines().collect();
+            assert_eq!(lines.len(), 3);
+            assert!(lines[0].starts_with("unique_id,creation_date,action,actor_id"));
+            assert!(lines[1].contains(",MemberAdded,"));
+        })
+        .await;
+    }
+}
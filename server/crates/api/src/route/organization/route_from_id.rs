@@ -1,22 +1,29 @@
-use database::{organization::Organization, Database};
+use database::{id::OrganizationId, organization::Organization, Database};
 use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
+use super::{check_access, error_response};
 use crate::{model::user_token::UserData, RequestError};
 
 /// Retrieve the organization informations from its unique identifier
+///
+/// Requires to be the owner or a member of the organization, with a second factor when the organization requires it
 #[openapi(tag = "Organizations")]
 #[get("/<id>")] // <- route attribute
 pub async fn from_id(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
+    id: OrganizationId,
 ) -> Custom<Result<Json<Organization>, Json<RequestError>>> {
+    if user_data.id.is_none() {
+        return error_response(Status::Unauthorized, "Token is invalid");
+    }
 
-    match database.organization_manager.from_id(&id).await {
-        Ok(organization) if organization.is_some() => {
-            Custom(Status::Ok, Ok(Json(organization.unwrap())))
-        }
+    match database.organization_manager.from_id(id).await {
+        Ok(Some(organization)) => match check_access(database, &user_data, &organization).await {
+            Ok(_) => Custom(Status::Ok, Ok(Json(organization))),
+            Err(response) => response,
+        },
         _ => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -31,7 +38,7 @@ pub async fn from_id(
 #[cfg(test)]
 mod tests {
 
-    use database::{organization::Organization, Database};
+    use database::{id::OrganizationId, organization::Organization, Database};
     use rocket::http::{Method, Status};
 
     use crate::{
@@ -44,8 +51,7 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let request_token = test_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
 
             let response = dispatch_request(
@@ -72,7 +78,7 @@ mod tests {
                 testing::get_user(client.rocket().state::<Database>().unwrap())
                     .await;
             let request_token = request_user.get_token().unwrap();
-            let id = "NO_ID";
+            let id = OrganizationId::generate();
 
             let response = dispatch_request(
                 &client,
@@ -99,8 +105,6 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
 
             let response = dispatch_request(
@@ -108,7 +112,7 @@ mod tests {
                 Method::Get,
                 format!("/organization/{}", test_org.unique_id),
                 None,
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -122,6 +126,8 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
 
             let response = dispatch_request(
@@ -129,7 +135,7 @@ mod tests {
                 Method::Get,
                 format!("/organization/{}", test_org.unique_id),
                 None,
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
@@ -0,0 +1,136 @@
+use database::{audit::AuditEvent, id::OrganizationId, pagination::Page, Database};
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use super::{check_events_access, error_response};
+use crate::{
+    model::{page_query::PageQuery, user_token::UserData},
+    RequestError,
+};
+
+/// Get the audit events of an organization
+///
+/// The events of the organization, and the ones of its owner and members such as their logins and permission changes.
+/// Can be sorted on `creation_date`, and filtered on `action`, `actor_id`, `target_kind` and `target_id`
+/// or on the creation date with `created_after` and `created_before`
+///
+/// Requires the 'organisation.events.see' permission, and to be the owner or a member of the organization
+#[openapi(tag = "Organizations")]
+#[get("/<id>/events?<page..>")]
+pub async fn get_events(
+    user_data: UserData,
+    database: &State<Database>,
+    id: OrganizationId,
+    page: PageQuery,
+) -> Custom<Result<Json<Page<AuditEvent>>, Json<RequestError>>> {
+    let user_ids = match check_events_access(database, &user_data, id).await {
+        Ok(user_ids) => user_ids,
+        Err(response) => return response,
+    };
+
+    match database.audit_manager.get_events(id, &user_ids, &page.into()).await {
+        Ok(events) => Custom(Status::Ok, Ok(Json(events))),
+        Err(err) => {
+            let error = RequestError::from(err);
+            error_response(Status::from_code(error.code).unwrap_or(Status::InternalServerError), &error.message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        audit::{AuditAction, AuditEvent, AuditTargetKind},
+        authentication::Authentication,
+        pagination::Page,
+        Database,
+    };
+    use rocket::http::{Method, Status};
+    use serde_json::json;
+
+    use crate::testing::{self, dispatch_request, run_test};
+
+    #[rocket::async_test]
+    async fn test_get_events() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("organisation.events.see").await.unwrap();
+            let owner = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let organization = testing::get_org(database, &owner).await;
+            let member = testing::get_user(database).await;
+            let token = owner.get_token().unwrap().to_string();
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                format!("/organization/{}/members", organization.unique_id),
+                Some(json!({ "member_id": member.unique_id }).to_string()),
+                Some(token.clone()),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Ok);
+            // The events of the users outside of the organization are not listed
+            let outsider = testing::get_user(database).await;
+            let outsider_organization = testing::create_org(database, &outsider, Vec::new()).await;
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                format!("/organization/{}/members", outsider_organization.unique_id),
+                Some(json!({ "member_id": testing::get_user(database).await.unique_id }).to_string()),
+                Some(outsider.get_token().unwrap().to_string()),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Ok);
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/organization/{}/events?filter.action=MemberAdded", organization.unique_id),
+                None,
+                Some(token),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let events: Page<AuditEvent> = response.into_json().await.unwrap();
+            assert_eq!(events.items.len(), 1);
+            let event = &events.items[0];
+            assert_eq!(event.action, AuditAction::MemberAdded);
+            assert_eq!(event.actor_id, Some(owner.unique_id));
+            assert_eq!(event.target_kind, AuditTargetKind::User);
+            assert_eq!(event.target_id, member.unique_id.to_string());
+            assert_eq!(event.organization_id, Some(organization.unique_id));
+            assert!(event.request_id.is_some());
+            let changes = event.changes.as_ref().unwrap();
+            assert_eq!(changes.before, Some(json!({ "member_ids": [] })));
+            assert_eq!(changes.after, Some(json!({ "member_ids": [member.unique_id] })));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn forbidden_test_get_events() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let owner = testing::get_user(database).await;
+            let organization = testing::get_org(database, &owner).await;
+            let permission = database.permission_manager.get_permission_id("organisation.events.see").await.unwrap();
+            let outsider = testing::create_user(database, Authentication::None, vec![permission]).await;
+
+            // The owner lacks the permission, and the outsider is not part of the organization
+            for user in [&owner, &outsider] {
+                let response = dispatch_request(
+                    &client,
+                    Method::Get,
+                    format!("/organization/{}/events", organization.unique_id),
+                    None,
+                    Some(user.get_token().unwrap().to_string()),
+                )
+                .await;
+
+                assert_eq!(response.status(), Status::Forbidden);
+            }
+        })
+        .await;
+    }
+}
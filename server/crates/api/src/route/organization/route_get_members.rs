@@ -0,0 +1,245 @@
+use database::{id::OrganizationId, pagination::Page, Database};
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use super::check_access;
+use crate::{
+    model::{organization_member::MemberProfile, page_query::PageQuery, user_token::UserData},
+    RequestError,
+};
+
+/// Get the members of an organization, with their usernames and avatars
+///
+/// The owner is not part of the members. Can be sorted on `creation_date` and `authentication.Credentials.username`,
+/// and filtered on `authentication.Credentials.username` or on the creation date with `created_after` and `created_before`
+///
+/// Requires to be the owner or a member of the organization, with a second factor when the organization requires it
+#[openapi(tag = "Organizations")]
+#[get("/<id>/members?<page..>")]
+pub async fn get_members(
+    user_data: UserData,
+    database: &State<Database>,
+    id: OrganizationId,
+    page: PageQuery,
+) -> Custom<Result<Json<Page<MemberProfile>>, Json<RequestError>>> {
+    if user_data.id.is_none() {
+        return error_response(Status::Unauthorized, "Token is invalid".into());
+    }
+    let organization = match database.organization_manager.from_id(id).await {
+        Ok(Some(organization)) => organization,
+        Ok(None) => return error_response(Status::NotFound, "Organization was not found.".into()),
+        Err(_) => return error_response(Status::InternalServerError, "A database error occurred.".into()),
+    };
+    if let Err(response) = check_access(database, &user_data, &organization).await {
+        return response;
+    }
+
+    match database
+        .user_manager
+        .from_ids(&organization.member_ids, &page.into())
+        .await
+    {
+        Ok(members) => Custom(
+            Status::Ok,
+            Ok(Json(Page {
+                items: members.items.into_iter().map(MemberProfile::from).collect(),
+                next_cursor: members.next_cursor,
+            })),
+        ),
+        Err(err) => {
+            let error = RequestError::from(err);
+            Custom(Status::Ok, Err(error.into()))
+        }
+    }
+}
+
+fn error_response(status: Status, message: String) -> Custom<Result<Json<Page<MemberProfile>>, Json<RequestError>>> {
+    Custom(
+        Status::Ok,
+        Err(RequestError::from(Custom(status, message)).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use database::{
+        authentication::Authentication, id::OrganizationId, organization::OrganizationUpdate, pagination::Page, Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::organization_member::MemberProfile,
+        testing::{self, dispatch_request, run_test},
+        RequestError,
+    };
+
+    #[rocket::async_test]
+    async fn test_get_members() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let owner = testing::get_user(database).await;
+            let organization = testing::get_org(database, &owner).await;
+            let mut member_ids = Vec::new();
+            for _ in 0..3 {
+                let member = testing::get_user(database).await;
+                database
+                    .organization_manager
+                    .add_member(organization.unique_id, member.unique_id)
+                    .await
+                    .unwrap();
+                member_ids.push(member.unique_id);
+            }
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/organization/{}/members?limit=2", organization.unique_id),
+                None,
+                Some(owner.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let first_page = response.into_json::<Page<MemberProfile>>().await.unwrap();
+            assert_eq!(first_page.items.len(), 2);
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!(
+                    "/organization/{}/members?limit=2&cursor={}",
+                    organization.unique_id,
+                    first_page.next_cursor.unwrap()
+                ),
+                None,
+                Some(owner.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            let second_page = response.into_json::<Page<MemberProfile>>().await.unwrap();
+            assert_eq!(second_page.items.len(), 1);
+            assert!(second_page.next_cursor.is_none());
+
+            let mut listed: Vec<_> = first_page
+                .items
+                .into_iter()
+                .chain(second_page.items)
+                .map(|member| member.unique_id)
+                .collect();
+            listed.sort();
+            member_ids.sort();
+            assert_eq!(listed, member_ids);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_members_profiles() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let owner = testing::get_user(database).await;
+            let organization = testing::get_org(database, &owner).await;
+            let credentials = database::authentication::Credentials {
+                email: format!("{}@test.fr", OrganizationId::generate()),
+                username: Some("member".to_string()),
+                avatar: Some("avatar.png".to_string()),
+                password: "hunter22".to_string(),
+            };
+            let member = testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await;
+            database
+                .organization_manager
+                .add_member(organization.unique_id, member.unique_id)
+                .await
+                .unwrap();
+            let outsider = testing::get_user(database).await;
+            let list = |token: String| {
+                dispatch_request(
+                    &client,
+                    Method::Get,
+                    format!("/organization/{}/members", organization.unique_id),
+                    None,
+                    Some(token),
+                )
+            };
+
+            // Only the owner and the members see the members
+            let response = list(outsider.get_token().unwrap().to_string()).await;
+            let request_error = response.into_json::<RequestError>().await.unwrap();
+            assert_eq!(request_error.code, 403);
+
+            let response = list(member.get_token().unwrap().to_string()).await;
+            assert_eq!(response.status(), Status::Ok);
+            let body = response.into_string().await.unwrap();
+            assert!(!body.contains("hunter22"));
+            assert!(!body.contains("password"));
+            assert!(!body.contains(member.get_token().unwrap()));
+            let page: Page<MemberProfile> = serde_json::from_str(&body).unwrap();
+            assert_eq!(page.items.len(), 1);
+            assert_eq!(page.items[0].unique_id, member.unique_id);
+            assert_eq!(page.items[0].username.as_deref(), Some("member"));
+            assert_eq!(page.items[0].avatar.as_deref(), Some("avatar.png"));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_members_two_factor_required() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let owner = testing::get_user(database).await;
+            let organization = testing::get_org(database, &owner).await;
+            let member = testing::get_user(database).await;
+            database
+                .organization_manager
+                .add_member(organization.unique_id, member.unique_id)
+                .await
+                .unwrap();
+            database
+                .organization_manager
+                .update_organization(organization.unique_id, vec![OrganizationUpdate::RequireTwoFactor(true)])
+                .await
+                .unwrap();
+            let list = || {
+                dispatch_request(
+                    &client,
+                    Method::Get,
+                    format!("/organization/{}/members", organization.unique_id),
+                    None,
+                    Some(member.get_token().unwrap().to_string()),
+                )
+            };
+
+            // The member is refused until they enable a second factor
+            let response = list().await;
+            assert_eq!(response.status(), Status::Forbidden);
+
+            testing::enable_two_factor(database, &member).await;
+            let response = list().await;
+            assert_eq!(response.status(), Status::Ok);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_members_unknown_organization() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let request_user = testing::get_user(database).await;
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/organization/{}/members", OrganizationId::generate()),
+                None,
+                Some(request_user.get_token().unwrap().to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let request_error = response.into_json::<RequestError>().await.unwrap();
+            assert_eq!(request_error.code, 404);
+        })
+        .await;
+    }
+}
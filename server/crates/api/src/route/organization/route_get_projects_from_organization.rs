@@ -1,22 +1,27 @@
-use database::{Database, project::Project};
+use database::{Database, id::OrganizationId, pagination::Page, project::Project};
 use rocket::{http::Status, get, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
+use super::check_access;
 use crate::RequestError;
-use crate::model::user_token::UserData;
+use crate::model::{page_query::PageQuery, user_token::UserData};
 
-/// Get all the projects of a specific organization.
+/// Get the projects of a specific organization.
+///
+/// Can be sorted and filtered on `name`
 /// 
-/// Requires 'Website' group
+/// Requires 'Website' group, and to be the owner or a member of the organization with a second factor when the
+/// organization requires it
 #[openapi(tag = "Organizations")]
-#[get("/<id>/projects", format = "application/json")]
+#[get("/<id>/projects?<page..>", format = "application/json")]
 pub async fn get_projects_from_organization(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
-) -> Custom<Result<Json<Vec<Project>>, Json<RequestError>>> {
+    id: OrganizationId,
+    page: PageQuery,
+) -> Custom<Result<Json<Page<Project>>, Json<RequestError>>> {
 
 
-    let organization = match database.organization_manager.from_id(&id).await {
+    let organization = match database.organization_manager.from_id(id).await {
         Ok(Some(organization)) => organization,
         Ok(None) => {
             return Custom(
@@ -39,17 +44,17 @@ pub async fn get_projects_from_organization(
             )
         }
     };
+    if let Err(response) = check_access(database, &user_data, &organization).await {
+        return response;
+    }
 
-    let projects = match database.project_manager.from_organization_id(&organization.unique_id).await {
+    let projects = match database.project_manager.from_organization_id(organization.unique_id, &page.into()).await {
         Ok(projects) => projects,
         Err(err) => {
+            let error = RequestError::from(err);
             return Custom(
-                Status::InternalServerError,
-                Err(RequestError::from(Custom(
-                    Status::InternalServerError,
-                    err.to_string(),
-                ))
-                .into()),
+                Status::from_code(error.code).unwrap_or(Status::InternalServerError),
+                Err(error.into()),
             )
         }
     };
@@ -1,22 +1,29 @@
-use database::{Database, project::Project};
+use database::{
+    id::{OrganizationId, ProjectId},
+    project::Project,
+    Database,
+};
 use rocket::{http::Status, get, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
+use super::check_access;
 use crate::RequestError;
 use crate::model::user_token::UserData;
 
 /// Retrieve the organization informations from its unique identifier
+///
+/// Requires to be the owner or a member of the organization, with a second factor when the organization requires it
 #[openapi(tag = "Organizations")]
 #[get("/<id>/projects/<project_id>", format = "application/json")]
 pub async fn project_from_id(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    project_id: String,
-    id: String,
+    project_id: ProjectId,
+    id: OrganizationId,
 ) -> Custom<Result<Json<Project>, Json<RequestError>>> {
 
 
     // If organization not found
-    let organization = match database.organization_manager.from_id(&id).await {
+    let organization = match database.organization_manager.from_id(id).await {
         Ok(Some(organization)) => organization,
         Ok(None) => {
             return Custom(
@@ -39,6 +46,9 @@ pub async fn project_from_id(
             )
         }
     };
+    if let Err(response) = check_access(database, &user_data, &organization).await {
+        return response;
+    }
 
     // check if organization contains project
     if !organization.projects_ids.contains(&project_id) {
@@ -52,7 +62,7 @@ pub async fn project_from_id(
         );
     }
 
-    match database.project_manager.from_id(&project_id).await {
+    match database.project_manager.from_id(project_id).await {
         Ok(Some(project)) => Custom(Status::Ok, Ok(Json(project))),
         Ok(None) => Custom(
             Status::NotFound,
@@ -76,7 +86,7 @@ pub async fn project_from_id(
 #[cfg(test)]
 mod tests {
 
-    use database::{Database};
+    use database::{id::ProjectId, Database};
     use rocket::http::{Method, Status};
 
     use crate::{
@@ -89,8 +99,7 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let request_token = test_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
 
             let response = dispatch_request(
@@ -98,7 +107,8 @@ mod tests {
                 Method::Get,
                 format!(
                     "/organization/{}/projects/{}",
-                    test_org.unique_id, "cdcdc"
+                    test_org.unique_id,
+                    ProjectId::generate()
                 ),
                 None,
                 Some(request_token.to_string()),
@@ -117,8 +127,7 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let request_token = test_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
 
             let response = dispatch_request(
@@ -126,7 +135,8 @@ mod tests {
                 Method::Get,
                 format!(
                     "/organization/{}/projects/{}",
-                    test_org.unique_id, "unknown_project"
+                    test_org.unique_id,
+                    ProjectId::generate()
                 ),
                 None,
                 Some(request_token.to_string()),
@@ -1,15 +1,23 @@
-use database::{Database};
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::UserId,
+    Database,
+};
 use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
+use serde_json::json;
 
+use super::check_access;
 use crate::{
+    audit::AuditContext,
     model::{organization_server::OrganizationServer, user_token::UserData},
     RequestError,
 };
 
 /// Register a new server on the organization
 ///
-/// Requires 'Website' group
+/// Requires 'Website' group, and to be the owner or a member of the organization with a second factor when the
+/// organization requires it
 #[openapi(tag = "Organizations")]
 #[post(
     "/remove_server",
@@ -17,22 +25,31 @@ use crate::{
     format = "application/json"
 )]
 pub async fn remove_server(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     organization_server: Json<OrganizationServer>,
+    audit: AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    // The unknown organizations are reported by check_organization
+    let organization_id = organization_server.organization_id;
+    if let Ok(Some(organization)) = database.organization_manager.from_id(organization_id).await {
+        if let Err(response) = check_access(database, &user_data, &organization).await {
+            return response;
+        }
+    }
 
-
-    check_organization(database, organization_server).await
+    check_organization(database, organization_server, user_data.id, &audit).await
 }
 
 async fn check_organization(
     database: &State<Database>,
     organization_server: Json<OrganizationServer>,
+    actor_id: Option<UserId>,
+    audit: &AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
     match database
         .organization_manager
-        .from_id(&organization_server.organization_id)
+        .from_id(organization_server.organization_id)
         .await
     {
         Ok(Some(organization))
@@ -49,7 +66,21 @@ async fn check_organization(
                 .into()),
             )
         }
-        Ok(Some(_organization)) => check_server(database, organization_server).await,
+        Ok(Some(organization)) => {
+            let (organization_id, server_id) = (organization_server.organization_id, organization_server.server_id);
+            let response = check_server(database, organization_server).await;
+            if response.1.is_ok() {
+                let mut server_ids = organization.server_ids.clone();
+                server_ids.retain(|id| *id != server_id);
+                let changes = AuditChanges::diff(
+                    &json!({ "server_ids": organization.server_ids }),
+                    &json!({ "server_ids": server_ids }),
+                );
+                let event = audit.event(AuditAction::ServerRemoved, actor_id, AuditTargetKind::Server, server_id);
+                audit.record(database, event.in_organization(organization_id).with_changes(changes)).await;
+            }
+            response
+        }
         Ok(None) => error_response(Status::NotFound, "Organization was not found."),
         Err(_) => error_response(Status::InternalServerError, "A database error occurred."),
     }
@@ -61,16 +92,13 @@ async fn check_server(
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
     match database
         .user_manager
-        .from_id(&organization_server.server_id)
+        .from_id(organization_server.server_id)
         .await
     {
         Ok(Some(_server)) => {
             match database
                 .organization_manager
-                .remove_from_server_ids(
-                    &organization_server.organization_id,
-                    &organization_server.server_id,
-                )
+                .remove_from_server_ids(organization_server.organization_id, organization_server.server_id)
                 .await
             {
                 Ok(_) => Custom(Status::Ok, Ok(Json(true))),
@@ -107,14 +135,13 @@ mod tests {
             let database = client.rocket().state::<Database>().unwrap();
             let test_server = testing::get_user(database).await;
             let test_user = testing::get_user(database).await;
+            let request_token = test_user.get_token().unwrap();
             let test_org =
-                testing::create_org(database, &test_user, vec![test_server.unique_id.clone()])
+                testing::create_org(database, &test_user, vec![test_server.unique_id])
                     .await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
             let body = OrganizationServer {
-                organization_id: test_org.unique_id.clone(),
-                server_id: test_server.unique_id.clone(),
+                organization_id: test_org.unique_id,
+                server_id: test_server.unique_id,
             };
 
             let response = dispatch_request(
@@ -132,7 +159,7 @@ mod tests {
 
             let updated_org = database
                 .organization_manager
-                .from_id(&test_org.unique_id)
+                .from_id(test_org.unique_id)
                 .await
                 .unwrap()
                 .unwrap();
@@ -1,34 +1,79 @@
-use database::{organization::OrganizationUpdate, Database};
+use database::{
+    id::OrganizationId,
+    organization::{Organization, OrganizationUpdate},
+    Database,
+};
 use rocket::{http::Status, patch, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use super::{check_access, error_response};
+use crate::{model::user_token::UserData, two_factor, RequestError};
 
 /// Update the organization informations from its id
+///
+/// Requires to be the owner, or a member with the 'organisation.edit' permission. Only the owner transfers the
+/// organization or changes its two-factor requirement, and they must have enabled a second factor before the
+/// organization requires one from its members
 #[openapi(tag = "Organizations")]
 #[patch("/<id>", data = "<organization_update>", format = "application/json")] // <- route attribute
 pub async fn update(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
+    id: OrganizationId,
     organization_update: Json<Vec<OrganizationUpdate>>,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    if user_data.id.is_none() {
+        return error_response(Status::Unauthorized, "Token is invalid");
+    }
+    let organization = match database.organization_manager.from_id(id).await {
+        Ok(Some(organization)) => organization,
+        Ok(None) => return not_found(id),
+        Err(err) => {
+            return Custom(
+                Status::Ok,
+                Err(RequestError::from(Custom(Status::InternalServerError, err.to_string())).into()),
+            )
+        }
+    };
+    let user_id = match check_access(database, &user_data, &organization).await {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+    if user_id != organization.owner_id {
+        let owner_only = organization_update
+            .iter()
+            .any(|update| matches!(update, OrganizationUpdate::OwnerId(_) | OrganizationUpdate::RequireTwoFactor(_)));
+        if owner_only {
+            return error_response(
+                Status::Forbidden,
+                "Only the owner can transfer the organization or change its two-factor requirement.",
+            );
+        }
+        let can_edit = match database.permission_manager.get_permission_id("organisation.edit").await {
+            Ok(permission) => user_data.has_permission(database, permission).await,
+            Err(_) => false,
+        };
+        if !can_edit {
+            return error_response(Status::Forbidden, "Permission denied");
+        }
+    }
 
+    let requires_two_factor = organization_update
+        .iter()
+        .any(|update| matches!(update, OrganizationUpdate::RequireTwoFactor(true)));
+    if requires_two_factor {
+        if let Err(response) = check_owner_two_factor(database, &organization, &organization_update).await {
+            return response;
+        }
+    }
 
     match database
         .organization_manager
-        .update_organization(&id, organization_update.0)
+        .update_organization(id, organization_update.0)
         .await
     {
-        Ok(result) if result.matched_count > 0 => Custom(Status::Ok, Ok(Json(true))),
-        Ok(_) => Custom(
-            Status::Ok,
-            Err(RequestError::from(Custom(
-                Status::NotFound,
-                format!("Organization not found with id: {id}"),
-            ))
-            .into()),
-        ),
+        Ok(true) => Custom(Status::Ok, Ok(Json(true))),
+        Ok(_) => not_found(id),
         Err(err) => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(Status::InternalServerError, err.to_string())).into()),
@@ -36,14 +81,57 @@ pub async fn update(
     }
 }
 
+fn not_found(id: OrganizationId) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(
+        Status::Ok,
+        Err(RequestError::from(Custom(
+            Status::NotFound,
+            format!("Organization not found with id: {id}"),
+        ))
+        .into()),
+    )
+}
+
+// check_owner_two_factor refuses to require a second factor from the members when the owner, the new one if the
+// update changes it, has none
+async fn check_owner_two_factor(
+    database: &Database,
+    organization: &Organization,
+    updates: &[OrganizationUpdate],
+) -> Result<(), Custom<Result<Json<bool>, Json<RequestError>>>> {
+    let error = |status: Status, message: &str| {
+        Custom(Status::Ok, Err(RequestError::from(Custom(status, message.to_string())).into()))
+    };
+    let owner_id = updates
+        .iter()
+        .rev()
+        .find_map(|update| match update {
+            OrganizationUpdate::OwnerId(owner_id) => Some(*owner_id),
+            _ => None,
+        })
+        .unwrap_or(organization.owner_id);
+    match two_factor::is_enabled(database.two_factor_manager.as_ref(), owner_id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(error(
+            Status::Conflict,
+            "The owner must enable two-factor authentication before requiring it.",
+        )),
+        Err(err) => Err(error(Status::InternalServerError, &err.to_string())),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use database::{
+        authentication::Authentication,
+        id::{OrganizationId, UserId},
         organization::{Organization, OrganizationUpdate},
+        user::User,
         Database,
     };
     use rocket::http::{Method, Status};
+    use serde_json::json;
 
     use crate::{
         testing::{self, dispatch_request, run_test},
@@ -55,12 +143,11 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let request_token = test_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
             let updates = vec![
                 OrganizationUpdate::Name("Another name".to_string()),
-                OrganizationUpdate::OwnerId("SOME_OTHER_ID".to_string()),
+                OrganizationUpdate::OwnerId(UserId::generate()),
             ];
 
             let response = dispatch_request(
@@ -77,7 +164,7 @@ mod tests {
             assert_eq!(response, true);
             let updated_org = database
                 .organization_manager
-                .from_id(&test_org.unique_id)
+                .from_id(test_org.unique_id)
                 .await
                 .unwrap()
                 .unwrap();
@@ -87,6 +174,50 @@ mod tests {
         .await;
     }
 
+    #[rocket::async_test]
+    async fn test_member_update() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let owner = testing::get_user(database).await;
+            let permission = database.permission_manager.get_permission_id("organisation.edit").await.unwrap();
+            let editor = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let member = testing::get_user(database).await;
+            let test_org = testing::get_org(database, &owner).await;
+            for user in [&editor, &member] {
+                database.organization_manager.add_member(test_org.unique_id, user.unique_id).await.unwrap();
+            }
+            let update = |user: &User, updates: Vec<OrganizationUpdate>| {
+                dispatch_request(
+                    &client,
+                    Method::Patch,
+                    format!("/organization/{}", test_org.unique_id),
+                    Some(serde_json::to_string(&updates).unwrap()),
+                    user.get_token().cloned(),
+                )
+            };
+
+            let response = update(&member, vec![OrganizationUpdate::Name("Another name".to_string())]).await;
+            assert_eq!(response.status(), Status::Forbidden);
+
+            let response = update(&editor, vec![OrganizationUpdate::Name("Another name".to_string())]).await;
+            assert_eq!(response.status(), Status::Ok);
+            assert!(response.into_json::<bool>().await.unwrap());
+
+            // Only the owner transfers the organization or changes its requirements
+            for updates in [
+                vec![OrganizationUpdate::OwnerId(editor.unique_id)],
+                vec![OrganizationUpdate::RequireTwoFactor(false)],
+            ] {
+                let response = update(&editor, updates).await;
+                assert_eq!(response.status(), Status::Forbidden);
+            }
+            let updated_org = database.organization_manager.from_id(test_org.unique_id).await.unwrap().unwrap();
+            assert_eq!(updated_org.name, "Another name");
+            assert_eq!(updated_org.owner_id, owner.unique_id);
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn test_unknown_update() {
         run_test(|client| async move {
@@ -95,10 +226,10 @@ mod tests {
             let request_token = request_user.get_token().unwrap();
             let updates = vec![
                 OrganizationUpdate::Name("Another name".to_string()),
-                OrganizationUpdate::OwnerId("SOME_OTHER_ID".to_string()),
+                OrganizationUpdate::OwnerId(UserId::generate()),
             ];
 
-            let id = "NO_ID";
+            let id = OrganizationId::generate();
             let response = dispatch_request(
                 &client,
                 Method::Patch,
@@ -119,6 +250,51 @@ mod tests {
         .await;
     }
 
+    #[rocket::async_test]
+    async fn test_require_two_factor() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let owner = testing::get_user(database).await;
+            let token = owner.get_token().cloned();
+            let organization = testing::get_org(database, &owner).await;
+            let require = || {
+                dispatch_request(
+                    &client,
+                    Method::Patch,
+                    format!("/organization/{}", organization.unique_id),
+                    Some(serde_json::to_string(&[OrganizationUpdate::RequireTwoFactor(true)]).unwrap()),
+                    token.clone(),
+                )
+            };
+
+            // The owner would be locked out of the organization
+            let response = require().await.into_json::<RequestError>().await.unwrap();
+            assert_eq!(response.code, 409);
+
+            testing::enable_two_factor(database, &owner).await;
+            let response = require().await;
+            assert_eq!(response.status(), Status::Ok);
+            assert!(response.into_json::<bool>().await.unwrap());
+
+            // Only the users with a second factor join the organization
+            let member = testing::get_user(database).await;
+            let add_member = || {
+                dispatch_request(
+                    &client,
+                    Method::Post,
+                    format!("/organization/{}/members", organization.unique_id),
+                    Some(json!({ "member_id": member.unique_id }).to_string()),
+                    token.clone(),
+                )
+            };
+            let response = add_member().await.into_json::<RequestError>().await.unwrap();
+            assert_eq!(response.code, 409);
+            testing::enable_two_factor(database, &member).await;
+            assert!(add_member().await.into_json::<bool>().await.unwrap());
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn unauthorized_test_update() {
         _unauthorized_test_update().await;
@@ -129,12 +305,10 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
             let updates = vec![
                 OrganizationUpdate::Name("Another name".to_string()),
-                OrganizationUpdate::OwnerId("SOME_OTHER_ID".to_string()),
+                OrganizationUpdate::RequireTwoFactor(false),
             ];
 
             let response = dispatch_request(
@@ -142,14 +316,14 @@ mod tests {
                 Method::Patch,
                 format!("/organization/{}", test_org.unique_id),
                 Some(serde_json::to_string(&updates).unwrap()),
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
             assert_eq!(response.status(), Status::Unauthorized);
             let updated_org = database
                 .organization_manager
-                .from_id(&test_org.unique_id)
+                .from_id(test_org.unique_id)
                 .await
                 .unwrap()
                 .unwrap();
@@ -164,10 +338,12 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
             let updates = vec![
                 OrganizationUpdate::Name("Another name".to_string()),
-                OrganizationUpdate::OwnerId("SOME_OTHER_ID".to_string()),
+                OrganizationUpdate::OwnerId(UserId::generate()),
             ];
 
             let response = dispatch_request(
@@ -175,14 +351,14 @@ mod tests {
                 Method::Patch,
                 format!("/organization/{}", test_org.unique_id),
                 Some(serde_json::to_string(&updates).unwrap()),
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
             assert_eq!(response.status(), Status::Forbidden);
             let updated_org = database
                 .organization_manager
-                .from_id(&test_org.unique_id)
+                .from_id(test_org.unique_id)
                 .await
                 .unwrap()
                 .unwrap();
@@ -1,16 +1,19 @@
-use database::{project::ProjectUpdateData, Database};
+use database::{id::OrganizationId, project::ProjectUpdateData, Database};
 use rocket::{http::Status, patch, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
+use super::check_access;
 use crate::{model::user_token::UserData, RequestError};
 
 /// Update the project informations from its id
+///
+/// Requires to be the owner or a member of the organization, with a second factor when the organization requires it
 #[openapi(tag = "Organizations")]
 #[patch("/<id>/projects", data = "<project_update>", format = "application/json")] // <- route attribute
 pub async fn update_project(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
+    id: OrganizationId,
     project_update: Json<ProjectUpdateData>,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
 
@@ -18,13 +21,17 @@ pub async fn update_project(
     // If organization not found 
     match database
         .organization_manager
-        .from_id(id.as_str())
+        .from_id(id)
         .await
     {
-        Ok(Some(_)) => (),
+        Ok(Some(organization)) => {
+            if let Err(response) = check_access(database, &user_data, &organization).await {
+                return response;
+            }
+        }
         Ok(None) => {
             return Custom(
-                Status::Ok,
+                Status::NotFound,
                 Err(RequestError::from(Custom(
                     Status::NotFound,
                     format!("Organization not found with id: {id}"),
@@ -43,7 +50,7 @@ pub async fn update_project(
     // if project not found
     match database
         .project_manager
-        .from_id(project_update.0.project_id.as_str())
+        .from_id(project_update.0.project_id)
         .await
     {
         Ok(Some(_)) => (),
@@ -67,7 +74,7 @@ pub async fn update_project(
 
     match database
         .project_manager
-        .update_project(&project_update.0.project_id, project_update.0.project_update)
+        .update_project(project_update.0.project_id, project_update.0.project_update)
         .await
     {
         Ok(_)=> Custom(Status::Ok, Ok(Json(true))),
@@ -87,6 +94,7 @@ pub async fn update_project(
 mod tests {
 
     use database::{
+        id::ProjectId,
         project::ProjectUpdateData,
         Database,
     };
@@ -102,7 +110,7 @@ mod tests {
             let request_user = testing::get_user(database).await;
             let request_token = request_user.get_token().unwrap();
             let updates = ProjectUpdateData {
-                project_id: "cdcdgr".to_string(),
+                project_id: ProjectId::generate(),
                 project_update: vec![],
             };
 
@@ -125,11 +133,10 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let request_token = test_user.get_token().unwrap();
             let test_org = testing::get_org(database, &test_user).await;
             let updates = ProjectUpdateData {
-                project_id: "cdcdgr".to_string(),
+                project_id: ProjectId::generate(),
                 project_update: vec![],
             };
 
@@ -18,6 +18,21 @@ mod route_check_license;
 mod route_add_perm;
 mod route_remove_perm;
 mod route_check_perm;
+mod route_send_verification;
+mod route_forgot_password;
+mod route_reset_password;
+mod route_send_password_reset;
+mod route_verify_email;
+mod route_oidc_authorize;
+mod route_oidc_callback;
+mod route_oidc_unlink;
+mod route_two_factor_enrol;
+mod route_two_factor_confirm;
+mod route_two_factor_disable;
+mod route_create_access_token;
+mod route_get_access_tokens;
+mod route_revoke_access_token;
+mod route_export_data;
 
 pub use route_get_licenses::*;
 pub use route_create_license::*;
@@ -38,4 +53,19 @@ pub use route_from_email::*;
 pub use route_check_license::*;
 pub use route_add_perm::*;
 pub use route_remove_perm::*;
-pub use route_check_perm::*;
\ No newline at end of file
+pub use route_check_perm::*;
+pub use route_send_verification::*;
+pub use route_forgot_password::*;
+pub use route_reset_password::*;
+pub use route_send_password_reset::*;
+pub use route_verify_email::*;
+pub use route_oidc_authorize::*;
+pub use route_oidc_callback::*;
+pub use route_oidc_unlink::*;
+pub use route_two_factor_enrol::*;
+pub use route_two_factor_confirm::*;
+pub use route_two_factor_disable::*;
+pub use route_create_access_token::*;
+pub use route_get_access_tokens::*;
+pub use route_revoke_access_token::*;
+pub use route_export_data::*;
\ No newline at end of file
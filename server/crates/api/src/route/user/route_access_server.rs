@@ -4,7 +4,7 @@ use rocket_okapi::openapi;
 
 use crate::{
     model::{organisation_id::OrganizationId, user_token::UserData},
-    RequestError,
+    two_factor, RequestError,
 };
 
 #[openapi(tag = "Users")]
@@ -14,13 +14,18 @@ pub async fn access_server(
     database: &State<Database>,
     organisation_id: Json<OrganizationId>,
 ) -> Custom<Result<Json<Peer>, Json<RequestError>>> {
-
+    let Some(user_id) = user_data.id else {
+        return Custom(
+            Status::Unauthorized,
+            Err(RequestError::from(Custom(Status::Unauthorized, "Token is invalid".to_string())).into()),
+        );
+    };
 
     let organisation_id = organisation_id.0.0;
 
     let is_in_org = database
         .organization_manager
-        .is_in_organization(&organisation_id, &user_data.id.unwrap())
+        .is_in_organization(organisation_id, user_id)
         .await
         .unwrap();
 
@@ -35,14 +40,28 @@ pub async fn access_server(
         );
     }
 
+    if let Ok(Some(organization)) = database.organization_manager.from_id(organisation_id).await {
+        let two_factors = database.two_factor_manager.as_ref();
+        if !two_factor::meets_requirement(two_factors, &organization, user_id).await.unwrap_or(false) {
+            return Custom(
+                Status::Forbidden,
+                Err(RequestError::from(Custom(
+                    Status::Forbidden,
+                    "The organization requires two-factor authentication.".to_string(),
+                ))
+                .into()),
+            );
+        }
+    }
+
     let servers_ids = database
         .organization_manager
-        .get_servers_ids_from_organisation(&organisation_id)
+        .get_servers_ids_from_organisation(organisation_id)
         .await
         .unwrap();
     
     for server_id in servers_ids {
-        match database.peers_manager.from_server_id(&server_id).await {
+        match database.peers_manager.from_server_id(server_id).await {
             Ok(Some(peer)) => return Custom(Status::Ok, Ok(Json(peer))),
             _ => continue,
         }
@@ -61,13 +80,12 @@ pub async fn access_server(
 #[cfg(test)]
 mod tests {
 
-    use database::{peer::Peer, Database};
+    use database::{id::RoomId, peer::Peer, timestamp::Timestamp, Database};
     use rocket::http::{Method, Status};
 
     use crate::{
-        model::server_id::ServerId,
+        model::organisation_id::OrganizationId,
         testing::{self, dispatch_request, run_test},
-        Server,
     };
 
     #[rocket::async_test]
@@ -76,19 +94,19 @@ mod tests {
             let database = client.rocket().state::<Database>().unwrap();
             let test_server = testing::get_user(database).await;
             let test_user = testing::get_user(database).await;
-            let _test_org =
-                testing::create_org(database, &test_user, vec![test_server.unique_id.clone()])
+            let test_org =
+                testing::create_org(database, &test_user, vec![test_server.unique_id])
                     .await;
 
             let server_peer = Peer {
-                room_id: Server::generate_unique_id().to_string(),
-                creation_date: Server::current_time().to_string(),
+                room_id: RoomId::generate(),
+                creation_date: Timestamp::now(),
                 signaling_hostname: "127.0.0.1".to_string(),
                 signaling_port: 3536,
-                server_unique_id: test_server.unique_id.clone(),
+                server_unique_id: test_server.unique_id,
             };
 
-            let _ = database
+            database
                 .peers_manager
                 .create_peer(&server_peer)
                 .await
@@ -98,7 +116,7 @@ mod tests {
                 &client,
                 Method::Post,
                 format!("/user/access_server"),
-                Some(serde_json::to_string(&ServerId(test_server.unique_id)).unwrap()),
+                Some(serde_json::to_string(&OrganizationId(test_org.unique_id)).unwrap()),
                 Some(test_user.get_token().unwrap().to_string()),
             )
             .await;
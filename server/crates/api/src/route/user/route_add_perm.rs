@@ -1,8 +1,13 @@
-use database::Database;
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::{PermissionId, UserId},
+    Database,
+};
 use rocket::{post, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
+use serde_json::json;
 
-use crate::{model::user_token::UserData, RequestError};
+use crate::{audit::AuditContext, model::user_token::UserData, RequestError};
 
 // A route to add a permission to a user.
 #[openapi(tag = "Users")]
@@ -10,8 +15,9 @@ use crate::{model::user_token::UserData, RequestError};
 pub async fn add_perm(
     user_data: UserData,
     database: &State<Database>,
-    user_id: String,
-    permission_id: String,
+    user_id: UserId,
+    permission_id: PermissionId,
+    audit: AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
     if user_data.id.is_none() {
         return Custom(
@@ -24,24 +30,23 @@ pub async fn add_perm(
         );
     }
 
-    if !database
-        .user_manager
-        .user_exists(&user_id)
-        .await
-    {
-        return Custom(
-            Status::NotFound,
-            Err(RequestError::from(Custom(
+    let user = match database.user_manager.from_id(user_id).await {
+        Ok(Some(user)) => user,
+        _ => {
+            return Custom(
                 Status::NotFound,
-                format!("User not found"),
-            ))
-            .into()),
-        );
-    }
+                Err(RequestError::from(Custom(
+                    Status::NotFound,
+                    "User not found".to_string(),
+                ))
+                .into()),
+            )
+        }
+    };
 
     if !database
         .permission_manager
-        .permission_exists(&permission_id)
+        .permission_exists(permission_id)
         .await
     {
         return Custom(
@@ -72,7 +77,7 @@ pub async fn add_perm(
         }
     };
 
-    if !database.user_manager.has_permission(&user_data.id.unwrap(), &permission_add).await {
+    if !user_data.has_permission(database, permission_add).await {
         return Custom(
             Status::Forbidden,
             Err(RequestError::from(Custom(
@@ -83,14 +88,25 @@ pub async fn add_perm(
         );
     }
 
-    let permission_id_clone = permission_id.clone();
-    
     match database
         .user_manager
-        .add_permission(user_id, permission_id_clone)
+        .add_permission(user_id, permission_id)
         .await
     {
-        Ok(_) => Custom(Status::Created, Ok(Json(true))),
+        Ok(_) => {
+            let mut permissions = user.permissions.clone();
+            if !permissions.contains(&permission_id) {
+                permissions.push(permission_id);
+            }
+            let changes = AuditChanges::diff(
+                &json!({ "permissions": user.permissions }),
+                &json!({ "permissions": permissions }),
+            );
+            let event = audit.event(AuditAction::PermissionGranted, user_data.id, AuditTargetKind::User, user_id);
+            audit.record(database, event.with_changes(changes)).await;
+
+            Custom(Status::Created, Ok(Json(true)))
+        }
         Err(_err) => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -104,8 +120,16 @@ pub async fn add_perm(
 
 #[cfg(test)]
 mod tests {
-    use database::{authentication::Authentication, server::Server, Database};
+    use database::{
+        audit::AuditAction,
+        authentication::Authentication,
+        server::Server,
+        id::{OrganizationId, PermissionId, UserId},
+        pagination::PageRequest,
+        Database,
+    };
     use rocket::{data, http::{Method, Status}};
+    use serde_json::json;
 
     use crate::testing::{self, dispatch_request, run_test};
 
@@ -114,13 +138,13 @@ mod tests {
         run_test(|rocket | async move {
             let database = rocket.rocket().state::<Database>().unwrap();
             let test_permission = database.permission_manager.get_permission_id("permission.add").await.unwrap();
-            let test_user = testing::create_user(database, Authentication::None, vec![test_permission.clone()]).await;
+            let test_user = testing::create_user(database, Authentication::None, vec![test_permission]).await;
             let request_token = test_user.get_token().unwrap();
             
             let response = dispatch_request(
                 &rocket,
                 Method::Post,
-                format!("/user/{}/permissions/{}", test_user.unique_id, test_permission.clone()),
+                format!("/user/{}/permissions/{}", test_user.unique_id, test_permission),
                 None,
                 Some(request_token.to_string()),
             ).await;
@@ -129,6 +153,39 @@ mod tests {
         }).await;
     }
 
+    #[rocket::async_test]
+    async fn test_add_perm_audit() {
+        run_test(|rocket | async move {
+            let database = rocket.rocket().state::<Database>().unwrap();
+            let permission_add = database.permission_manager.get_permission_id("permission.add").await.unwrap();
+            let admin = testing::create_user(database, Authentication::None, vec![permission_add]).await;
+            let test_user = testing::get_user(database).await;
+            let test_permission = testing::create_permission(database, "test_perm").await;
+
+            let response = dispatch_request(
+                &rocket,
+                Method::Post,
+                format!("/user/{}/permissions/{}", test_user.unique_id, test_permission.unique_id),
+                None,
+                Some(admin.get_token().unwrap().to_string()),
+            ).await;
+            assert_eq!(response.status(), Status::Created);
+
+            let events = database
+                .audit_manager
+                .get_events(OrganizationId::generate(), &[test_user.unique_id], &PageRequest::default())
+                .await
+                .unwrap();
+            assert_eq!(events.items.len(), 1);
+            let event = &events.items[0];
+            assert_eq!(event.action, AuditAction::PermissionGranted);
+            assert_eq!(event.actor_id, Some(admin.unique_id));
+            let changes = event.changes.as_ref().unwrap();
+            assert_eq!(changes.before, Some(json!({ "permissions": [] })));
+            assert_eq!(changes.after, Some(json!({ "permissions": [test_permission.unique_id] })));
+        }).await;
+    }
+
     #[rocket::async_test]
     async fn test_add_perm_unauthorized() {
         run_test(|rocket | async move {
@@ -153,13 +210,13 @@ mod tests {
         run_test(|rocket | async move {
             let database = rocket.rocket().state::<Database>().unwrap();
             let test_permission = database.permission_manager.get_permission_id("permission.add").await.unwrap();
-            let test_user = testing::create_user(database, Authentication::None, vec![test_permission.clone()]).await;
+            let test_user = testing::create_user(database, Authentication::None, vec![test_permission]).await;
             let request_token = test_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &rocket,
                 Method::Post,
-                format!("/user/{}/permissions/{}", "invalid", test_permission.clone()),
+                format!("/user/{}/permissions/{}", UserId::generate(), test_permission),
                 None,
                 Some(request_token.to_string()),
             )
@@ -174,13 +231,13 @@ mod tests {
         run_test(|rocket | async move {
             let database = rocket.rocket().state::<Database>().unwrap();
             let test_permission = database.permission_manager.get_permission_id("permission.add").await.unwrap();
-            let test_user = testing::create_user(database, Authentication::None, vec![test_permission.clone()]).await;
+            let test_user = testing::create_user(database, Authentication::None, vec![test_permission]).await;
             let request_token = test_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &rocket,
                 Method::Post,
-                format!("/user/{}/permissions/{}", test_user.unique_id, "invalid"),
+                format!("/user/{}/permissions/{}", test_user.unique_id, PermissionId::generate()),
                 None,
                 Some(request_token.to_string()),
 
@@ -1,9 +1,13 @@
 use database::license::License;
-use database::{Database};
+use database::{id::UserId, Database};
 use rocket::post;
 use rocket::{http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
-use crate::{model::user_token::UserData, RequestError};
+use crate::{
+    api_telemetry::{ApiMetrics, LicenseCheckOutcome},
+    model::user_token::UserData,
+    RequestError,
+};
 
 
 /// Verify a license and if it is valid
@@ -14,24 +18,29 @@ use crate::{model::user_token::UserData, RequestError};
 pub async fn check_licenses(
     _user_data: UserData,
     database: &State<Database>,
-    id: String,
+    metrics: &State<ApiMetrics>,
+    id: UserId,
     license_id: String,
 ) -> Custom<Result<Json<License>, Json<RequestError>>> {
 
 
-    let user = match database.user_manager.from_id(&id).await {
+    let user = match database.user_manager.from_id(id).await {
         Ok(user) => user,
-        Err(_) => return Custom(
-            Status::InternalServerError,
-            Err(RequestError::from(Custom(
+        Err(_) => {
+            metrics.license_check(LicenseCheckOutcome::Error);
+            return Custom(
                 Status::InternalServerError,
-                "Failed to retrieve user.".to_string(),
-            ))
-            .into()),
-        ),
+                Err(RequestError::from(Custom(
+                    Status::InternalServerError,
+                    "Failed to retrieve user.".to_string(),
+                ))
+                .into()),
+            );
+        }
     };
 
     if user.is_none() {
+        metrics.license_check(LicenseCheckOutcome::UnknownUser);
         return Custom(
             Status::NotFound,
             Err(RequestError::from(Custom(
@@ -45,6 +54,7 @@ pub async fn check_licenses(
     let license = match database.license_manager.get_license(&license_id).await {
         Ok(license) => license,
         Err(err) => {
+            metrics.license_check(LicenseCheckOutcome::Error);
             return Custom(
                 Status::InternalServerError,
                 Err(RequestError::from(Custom(
@@ -58,20 +68,28 @@ pub async fn check_licenses(
 
     match license {
         Some(license) => {
+            metrics.license_check(LicenseCheckOutcome::Valid);
             Custom(Status::Ok, Ok(Json(license)))
         }
-        None => Custom(Status::Forbidden, Err(RequestError::from(Custom(
-            Status::Forbidden,
-            "License not valid.".to_string(),
-        ))
-        .into())),
+        None => {
+            metrics.license_check(LicenseCheckOutcome::Invalid);
+            Custom(Status::Forbidden, Err(RequestError::from(Custom(
+                Status::Forbidden,
+                "License not valid.".to_string(),
+            ))
+            .into()))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use database::{Database, license::License};
+    use database::{
+        id::{LicenseId, UserId},
+        license::License,
+        Database,
+    };
     use rocket::http::{Method, Status};
 
     use crate::testing::{self, dispatch_request, run_test};
@@ -84,8 +102,8 @@ mod tests {
             let request_user = testing::get_user(database).await;
             let request_token = request_user.get_token().unwrap();
             let license = License {
-                unique_id: "test".to_string(),
-                user_id: test_user.unique_id.clone(),
+                unique_id: LicenseId::generate(),
+                user_id: test_user.unique_id,
                 license: "UVW-1234-5678-9012-345".to_string(),
             };
 
@@ -114,8 +132,8 @@ mod tests {
             let request_user = testing::get_user(database).await;
             let request_token = request_user.get_token().unwrap();
             let license = License {
-                unique_id: "test".to_string(),
-                user_id: test_user.unique_id.clone(),
+                unique_id: LicenseId::generate(),
+                user_id: test_user.unique_id,
                 license: "UVW-1234-5678-9012-345".to_string(),
             };
             database.license_manager.create(&license).await.unwrap();
@@ -144,7 +162,7 @@ mod tests {
             let response = dispatch_request(
                 &client,
                 Method::Post,
-                format!("/user/unknow/license/unknow"),
+                format!("/user/{}/license/unknow", UserId::generate()),
                 None,
                 Some(request_token.to_string()),
             )
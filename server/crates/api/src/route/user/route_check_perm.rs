@@ -1,4 +1,4 @@
-use database::Database;
+use database::{id::UserId, Database};
 use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
@@ -10,7 +10,7 @@ use crate::{model::user_token::UserData, RequestError};
 pub async fn check_perm(
     user_data: UserData,
     database: &State<Database>,
-    user_id: String,
+    user_id: UserId,
     permission_name: String,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
     if user_data.id.is_none() {
@@ -26,7 +26,7 @@ pub async fn check_perm(
 
     if !database
         .user_manager
-        .user_exists(&user_id)
+        .user_exists(user_id)
         .await
     {
         return Custom(
@@ -75,7 +75,7 @@ pub async fn check_perm(
         }
     };
 
-    if !database.user_manager.has_permission(&user_data.id.unwrap(), &permission_check).await {
+    if !user_data.has_permission(database, permission_check).await {
         return Custom(
             Status::Forbidden,
             Err(RequestError::from(Custom(
@@ -88,7 +88,7 @@ pub async fn check_perm(
 
     let has_perm = database
         .user_manager
-        .has_permission(&user_id, &permission_id)
+        .has_permission(user_id, permission_id)
         .await;
 
     Custom(
@@ -99,7 +99,7 @@ pub async fn check_perm(
 
 #[cfg(test)]
 mod tests {
-    use database::{authentication::Authentication, Database};
+    use database::{authentication::Authentication, id::UserId, Database};
     use rocket::{http::{Method, Status}, local::asynchronous::LocalResponse};
 
     use crate::testing::{self, dispatch_request, run_test};
@@ -109,18 +109,19 @@ mod tests {
         run_test(|rocket | async move {
             let database = rocket.rocket().state::<Database>().unwrap();
             let test_permission = database.permission_manager.get_permission_id("permission.see").await.unwrap();
-            let test_user = testing::create_user(database, Authentication::None, vec![test_permission.clone()]).await;
+            let test_user = testing::create_user(database, Authentication::None, vec![test_permission]).await;
             let request_token = test_user.get_token().unwrap();
             
             let response = dispatch_request(
                 &rocket,
                 Method::Get,
-                format!("/user/check-permission/{}/permissions/{}", test_user.unique_id, test_permission.clone()),
+                format!("/user/check-permission/{}/permissions/{}", test_user.unique_id, "permission.see"),
                 None,
                 Some(request_token.to_string()),
             ).await;
 
             assert_eq!(response.status(), Status::Ok);
+            assert!(response.into_json::<bool>().await.unwrap());
         }).await;
     }
 
@@ -148,13 +149,13 @@ mod tests {
         run_test(|rocket | async move {
             let database = rocket.rocket().state::<Database>().unwrap();
             let test_permission = database.permission_manager.get_permission_id("permission.see").await.unwrap();
-            let test_user = testing::create_user(database, Authentication::None, vec![test_permission.clone()]).await;
+            let test_user = testing::create_user(database, Authentication::None, vec![test_permission]).await;
             let request_token = test_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &rocket,
                 Method::Get,
-                format!("/user/check-permission/{}/permissions/{}", "invalid", test_permission.clone()),
+                format!("/user/check-permission/{}/permissions/{}", UserId::generate(), test_permission),
                 None,
                 Some(request_token.to_string()),
             )
@@ -169,7 +170,7 @@ mod tests {
         run_test(|rocket | async move {
             let database = rocket.rocket().state::<Database>().unwrap();
             let test_permission = database.permission_manager.get_permission_id("permission.see").await.unwrap();
-            let test_user = testing::create_user(database, Authentication::None, vec![test_permission.clone()]).await;
+            let test_user = testing::create_user(database, Authentication::None, vec![test_permission]).await;
             let request_token = test_user.get_token().unwrap();
 
             let response = dispatch_request(
@@ -185,4 +186,33 @@ mod tests {
             assert_eq!(response.status(), Status::NotFound);
         }).await;
     }
+
+    #[rocket::async_test]
+    async fn test_check_perm_unverified_email() {
+        run_test(|rocket | async move {
+            let database = rocket.rocket().state::<Database>().unwrap();
+            let test_permission = database.permission_manager.get_permission_id("permission.see").await.unwrap();
+            let test_user = testing::create_user(database, Authentication::None, vec![test_permission]).await;
+            let request_token = test_user.get_token().unwrap();
+            let mut unverified_user = test_user.clone();
+            unverified_user.unique_id = UserId::generate();
+            unverified_user.logins.clear();
+            unverified_user.email_verified = false;
+            database.user_manager.create_user(&unverified_user).await.unwrap();
+
+            for (user_id, expected) in [(test_user.unique_id, true), (unverified_user.unique_id, false)] {
+                let response = dispatch_request(
+                    &rocket,
+                    Method::Get,
+                    format!("/user/check-permission/{}/permissions/permission.see", user_id),
+                    None,
+                    Some(request_token.to_string()),
+                )
+                .await;
+
+                assert_eq!(response.status(), Status::Ok);
+                assert_eq!(response.into_json::<bool>().await.unwrap(), expected);
+            }
+        }).await;
+    }
 }
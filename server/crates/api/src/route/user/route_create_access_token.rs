@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use database::{
+    access_token::AccessToken,
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::AccessTokenId,
+    timestamp::Timestamp,
+    Database,
+};
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::{
+    access_token,
+    audit::AuditContext,
+    model::{
+        access_token::{AccessTokenInfo, AccessTokenInit, CreatedAccessToken},
+        user_token::UserData,
+    },
+    settings::ApiSettings,
+    RequestError,
+};
+
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Create a personal access token for the user of the token, for the scripts acting on their behalf
+///
+/// The scopes are names of permissions the user holds, the token is only given the permissions that are both in its
+/// scopes and held by the user. The token is only shown in this answer, it is sent in `X-User-Token` like the tokens
+/// of the logins
+#[openapi(tag = "Users")]
+#[post("/access_tokens", data = "<body>", format = "application/json")]
+pub async fn create_access_token(
+    user_data: UserData,
+    database: &State<Database>,
+    settings: &State<ApiSettings>,
+    body: Json<AccessTokenInit>,
+    audit: AuditContext,
+) -> Custom<Result<Json<CreatedAccessToken>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return refuse(Status::Unauthorized, "Token is invalid");
+    };
+    // The scripts cannot give themselves new tokens
+    if user_data.is_access_token() {
+        return refuse(Status::Forbidden, "A personal access token cannot create other tokens.");
+    }
+
+    let body = body.into_inner();
+    let name = body.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return refuse(
+            Status::BadRequest,
+            &format!("The name must be between 1 and {MAX_NAME_LENGTH} characters long."),
+        );
+    }
+    let max_days = settings.tokens.access_token_max_days;
+    if body.expires_in_days == 0 || body.expires_in_days > max_days {
+        return refuse(
+            Status::BadRequest,
+            &format!("The token must expire in 1 to {max_days} days."),
+        );
+    }
+
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in body.scopes {
+        if scopes.contains(&scope) {
+            continue;
+        }
+        let Ok(permission_id) = database.permission_manager.get_permission_id(&scope).await else {
+            return refuse(Status::BadRequest, &format!("Unknown permission: {scope}"));
+        };
+        if !database.user_manager.has_permission(user_id, permission_id).await {
+            return refuse(
+                Status::Forbidden,
+                &format!("The user does not hold the permission {scope}."),
+            );
+        }
+        scopes.push(scope);
+    }
+
+    let tokens = database.access_token_manager.as_ref();
+    match tokens.list(user_id).await {
+        Ok(existing) if existing.len() >= settings.tokens.access_tokens_per_user => {
+            return refuse(
+                Status::Conflict,
+                "The user has too many access tokens, one must be revoked first.",
+            )
+        }
+        Ok(_) => {}
+        Err(err) => return database_error(err),
+    }
+
+    let token = access_token::generate();
+    let now = Timestamp::now();
+    let access_token = AccessToken {
+        unique_id: AccessTokenId::generate(),
+        user_id,
+        name: name.to_string(),
+        token_hash: access_token::hash(&token),
+        scopes,
+        creation_date: now,
+        expires_at: now.plus(Duration::from_secs(body.expires_in_days * 24 * 3600)),
+        last_used: None,
+    };
+    if let Err(err) = tokens.create(&access_token).await {
+        return database_error(err);
+    }
+
+    let changes = AuditChanges {
+        before: None,
+        after: Some(json!({ "name": access_token.name, "scopes": access_token.scopes })),
+    };
+    let event = audit.event(
+        AuditAction::AccessTokenCreated,
+        Some(user_id),
+        AuditTargetKind::User,
+        user_id,
+    );
+    audit.record(database, event.with_changes(changes)).await;
+
+    Custom(
+        Status::Ok,
+        Ok(Json(CreatedAccessToken {
+            token,
+            access_token: AccessTokenInfo::from(access_token),
+        })),
+    )
+}
+
+fn database_error(err: mongodb::error::Error) -> Custom<Result<Json<CreatedAccessToken>, Json<RequestError>>> {
+    tracing::error!("Cannot create the access token: {err}");
+    refuse(Status::InternalServerError, "A database error occured.")
+}
+
+fn refuse(status: Status, message: &str) -> Custom<Result<Json<CreatedAccessToken>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{authentication::Authentication, Database};
+    use rocket::http::{Method, Status};
+    use serde_json::json;
+
+    use crate::{
+        model::access_token::{AccessTokenInfo, CreatedAccessToken},
+        testing::{self, dispatch_request, run_test},
+        RequestError,
+    };
+
+    #[rocket::async_test]
+    async fn test_create_access_token() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database
+                .permission_manager
+                .get_permission_id("permission.see")
+                .await
+                .unwrap();
+            let user = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let create = |scopes: &[&str], token: Option<String>| {
+                dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/access_tokens".to_string(),
+                    Some(json!({ "name": "ci", "scopes": scopes, "expires_in_days": 30 }).to_string()),
+                    token,
+                )
+            };
+            let check_permission = |token: String| {
+                dispatch_request(
+                    &client,
+                    Method::Get,
+                    format!("/user/check-permission/{}/permissions/permission.see", user.unique_id),
+                    None,
+                    Some(token),
+                )
+            };
+
+            let response = create(&["unknown"], user.get_token().cloned()).await;
+            assert_eq!(response.status(), Status::BadRequest);
+            // The scopes are among the permissions of the user
+            let response = create(&["content.moderate"], user.get_token().cloned()).await;
+            assert_eq!(response.status(), Status::Forbidden);
+
+            let response = create(&["permission.see"], user.get_token().cloned()).await;
+            assert_eq!(response.status(), Status::Ok);
+            let scoped: CreatedAccessToken = response.into_json().await.unwrap();
+            assert_eq!(scoped.access_token.scopes, vec!["permission.see"]);
+            let response = create(&[], user.get_token().cloned()).await;
+            let unscoped: CreatedAccessToken = response.into_json().await.unwrap();
+
+            assert_eq!(check_permission(scoped.token.clone()).await.status(), Status::Ok);
+            let response = check_permission(unscoped.token.clone()).await;
+            assert_eq!(response.status(), Status::Forbidden);
+            // The token loses the permissions the user loses
+            database
+                .user_manager
+                .remove_permission(user.unique_id, permission)
+                .await
+                .unwrap();
+            let response = check_permission(scoped.token.clone()).await;
+            assert_eq!(response.status(), Status::Forbidden);
+
+            let response = create(&[], Some(scoped.token.clone())).await;
+            assert_eq!(response.status(), Status::Forbidden);
+            let error: RequestError = response.into_json().await.unwrap();
+            assert_eq!(error.message, "A personal access token cannot create other tokens.");
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                "/user/access_tokens".to_string(),
+                None,
+                Some(unscoped.token),
+            )
+            .await;
+            let tokens: Vec<AccessTokenInfo> = response.into_json().await.unwrap();
+            assert_eq!(tokens.len(), 2);
+            assert_eq!(tokens[0].unique_id, scoped.access_token.unique_id);
+            assert!(tokens[0].last_used.is_some());
+        })
+        .await;
+    }
+}
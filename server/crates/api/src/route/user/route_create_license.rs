@@ -1,8 +1,13 @@
-use database::{Database};
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::{LicenseId, UserId},
+    Database,
+};
 use database::license::License;
 use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
-use crate::{model::user_token::UserData, RequestError, Server};
+use serde_json::json;
+use crate::{audit::AuditContext, model::user_token::UserData, RequestError};
 use rand::{thread_rng, Rng};
 use rand::distributions::Alphanumeric;
 
@@ -13,13 +18,14 @@ use rand::distributions::Alphanumeric;
 #[openapi(tag = "Users")]
 #[post("/<id>/license")]
 pub async fn create_license(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
+    id: UserId,
+    audit: AuditContext,
 ) -> Custom<Result<Json<License>, Json<RequestError>>> {
 
 
-    let user = match database.user_manager.from_id(&id).await {
+    let user = match database.user_manager.from_id(id).await {
         Ok(user) => user,
         Err(_) => return Custom(
             Status::InternalServerError,
@@ -45,13 +51,13 @@ pub async fn create_license(
     let user = user.unwrap();
 
     let license = License {
-        unique_id: Server::generate_unique_id().to_string(),
-        user_id: user.unique_id.clone(),
+        unique_id: LicenseId::generate(),
+        user_id: user.unique_id,
         license: generate_license(),
     };
 
     // if user.group == Group::Guest {
-    //     match database.user_manager.update_group(user.unique_id.clone()).await {
+    //     match database.user_manager.update_group(user.unique_id).await {
     //         Ok(_) => (),
     //         Err(_) => return Custom(
     //             Status::InternalServerError,
@@ -66,7 +72,17 @@ pub async fn create_license(
     
 
     match database.license_manager.create(&license).await {
-        Ok(_) => Custom(Status::Created, Ok(Json(license))),
+        Ok(_) => {
+            // The key of the license is a secret, only its ids are recorded
+            let changes = AuditChanges {
+                before: None,
+                after: Some(json!({ "unique_id": license.unique_id, "user_id": license.user_id })),
+            };
+            let event = audit.event(AuditAction::LicenseCreated, user_data.id, AuditTargetKind::License, license.unique_id);
+            audit.record(database, event.with_changes(changes)).await;
+
+            Custom(Status::Created, Ok(Json(license)))
+        }
         Err(_) => Custom(
             Status::InternalServerError,
             Err(RequestError::from(Custom(
@@ -89,7 +105,7 @@ fn generate_license() -> String {
 #[cfg(test)]
 mod tests {
 
-    use database::{Database, license::License};
+    use database::{id::UserId, license::License, Database};
     use rocket::http::{Method, Status};
 
     use crate::testing::{self, dispatch_request, run_test};
@@ -129,7 +145,7 @@ mod tests {
             let response = dispatch_request(
                 &client,
                 Method::Post,
-                format!("/user/unknow/license"),
+                format!("/user/{}/license", UserId::generate()),
                 None,
                 Some(request_token.to_string()),
             )
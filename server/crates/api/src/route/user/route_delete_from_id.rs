@@ -1,20 +1,76 @@
-use database::{Database};
+use database::{
+    audit::{AuditAction, AuditTargetKind},
+    id::UserId,
+    Database,
+};
 use rocket::{delete, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use crate::{
+    audit::AuditContext,
+    model::user_token::{UserData, ACCESS_TOKEN_REFUSED},
+    personal_data::{self, ErasureError},
+    RequestError,
+};
 
 /// Delete the user from its id.
+///
+/// The data of the user is erased from every collection first, which is refused while the user owns organizations.
+///
+/// Only the user themselves, or a user with the 'user.delete' permission, can delete the account.
 #[openapi(tag = "Users")]
 #[delete("/id/<id>")] // <- route attribute
 pub async fn delete_from_id(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
-    id: String,
+    id: UserId,
+    audit: AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    if user_data.is_access_token() {
+        let status = Status::Forbidden;
+        return Custom(
+            status,
+            Err(RequestError::from(Custom(status, ACCESS_TOKEN_REFUSED.to_string())).into()),
+        );
+    }
+    if user_data.id.is_none() {
+        let status = Status::Unauthorized;
+        return Custom(status, Err(RequestError::from(Custom(status, "Token is invalid".to_string())).into()));
+    }
+    if user_data.id != Some(id) {
+        let allowed = match database.permission_manager.get_permission_id("user.delete").await {
+            Ok(permission) => user_data.has_permission(database, permission).await,
+            Err(_) => false,
+        };
+        if !allowed {
+            let status = Status::Forbidden;
+            return Custom(
+                status,
+                Err(RequestError::from(Custom(status, "Only the user can delete their account.".to_string())).into()),
+            );
+        }
+    }
 
-    match database.user_manager.delete_user(Some(&id), None).await {
-        Ok(Some(_)) => Custom(Status::Ok, Ok(Json(true))),
+    if let Ok(Some(user)) = database.user_manager.from_id(id).await {
+        if let Err(err) = personal_data::erase(database, &user).await {
+            if let ErasureError::Database(err) = &err {
+                tracing::error!(user_id = %id, "Cannot erase the data of the user: {err}");
+            }
+            return Custom(Status::Ok, Err(RequestError::from(err).into()));
+        }
+    }
+
+    match database.user_manager.delete_user(Some(id), None).await {
+        Ok(true) => {
+            // Only the id of the erased user is kept, not their IP when they delete themselves
+            let mut event = audit.event(AuditAction::UserDeleted, user_data.id, AuditTargetKind::User, id);
+            if user_data.id == Some(id) {
+                event.ip = None;
+            }
+            audit.record(database, event).await;
+
+            Custom(Status::Ok, Ok(Json(true)))
+        }
         Ok(_) => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -33,10 +89,26 @@ pub async fn delete_from_id(
 #[cfg(test)]
 mod tests {
 
-    use database::{Database};
+    use std::time::Duration;
+
+    use database::{
+        audit::{AuditAction, AuditChanges, AuditEvent, AuditTargetKind},
+        authentication::{Authentication, Credentials},
+        id::{AuditEventId, RoomId, UserId},
+        mail::OutboxMessage,
+        oidc_login::OidcLogin,
+        organization::OrganizationUpdate,
+        pagination::PageRequest,
+        peer::Peer,
+        rate_limit::LockoutPolicy,
+        timestamp::Timestamp,
+        Database,
+    };
     use rocket::http::{Method, Status};
+    use serde_json::json;
 
     use crate::{
+        rate_limit::account_key,
         testing::{self, dispatch_request, run_test},
         RequestError,
     };
@@ -58,13 +130,40 @@ mod tests {
             )
             .await;
 
+            assert_eq!(response.status(), Status::Forbidden);
+            // Another user cannot delete the account
+            assert!(database
+                .user_manager
+                .from_id(test_user.unique_id)
+                .await
+                .unwrap()
+                .is_some());
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_delete_own_account() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let request_token = test_user.get_token().unwrap();
+
+            let response = dispatch_request(
+                &client,
+                Method::Delete,
+                format!("/user/id/{}", test_user.unique_id),
+                None,
+                Some(request_token.to_string()),
+            )
+            .await;
+
             assert_eq!(response.status(), Status::Ok);
-            let response = response.into_json::<bool>().await.unwrap();
-            assert_eq!(response, true);
+            assert!(response.into_json::<bool>().await.unwrap());
             // User should have been deleted, so none should be found
             assert!(database
                 .user_manager
-                .from_id(&test_user.unique_id)
+                .from_id(test_user.unique_id)
                 .await
                 .unwrap()
                 .is_none());
@@ -72,14 +171,137 @@ mod tests {
         .await;
     }
 
+    #[rocket::async_test]
+    async fn test_delete_erases_data() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let credentials = Credentials {
+                email: format!("erased-{}@test.fr", UserId::generate()),
+                username: None,
+                avatar: None,
+                password: "test".to_string(),
+            };
+            let test_user = testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await;
+            let permission = database.permission_manager.get_permission_id("user.delete").await.unwrap();
+            let owner = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let organization = testing::get_org(database, &owner).await;
+            database
+                .organization_manager
+                .add_member(organization.unique_id, test_user.unique_id)
+                .await
+                .unwrap();
+            let mut asset = testing::create_asset(database, &test_user).await;
+            asset.add_upvote(test_user.unique_id);
+            database.asset_manager.update_asset(asset.id, &asset).await.unwrap();
+
+            // The traces the user left in the other collections
+            let email = test_user.email().unwrap().to_string();
+            let now = Timestamp::now();
+            let mail = OutboxMessage::new(email.clone(), "Welcome".to_string(), "Hello".to_string(), None, now);
+            database.outbox_manager.enqueue(&mail).await.unwrap();
+            let policy = LockoutPolicy {
+                max_failures: 1,
+                lockout: Duration::from_secs(60),
+                max_lockout: Duration::from_secs(60),
+                reset_after: Duration::from_secs(60),
+            };
+            let account = account_key(&email);
+            database.rate_limit_manager.record_failure(&account, &policy, now).await.unwrap();
+            let login = OidcLogin {
+                state: "erased-state".to_string(),
+                provider: "test".to_string(),
+                nonce: "nonce".to_string(),
+                code_verifier: "verifier".to_string(),
+                link_user: Some(test_user.unique_id),
+                creation_date: now,
+                expires_at: now.plus(Duration::from_secs(600)),
+            };
+            database.oidc_login_manager.create(&login).await.unwrap();
+            let peer = Peer {
+                room_id: RoomId::generate(),
+                creation_date: now,
+                signaling_hostname: "localhost".to_string(),
+                signaling_port: 8000,
+                server_unique_id: test_user.unique_id,
+            };
+            database.peers_manager.create_peer(&peer).await.unwrap();
+            let event = AuditEvent {
+                unique_id: AuditEventId::generate(),
+                creation_date: now,
+                action: AuditAction::Login,
+                actor_id: Some(test_user.unique_id),
+                target_kind: AuditTargetKind::User,
+                target_id: test_user.unique_id.to_string(),
+                organization_id: None,
+                ip: Some("192.0.2.1".to_string()),
+                request_id: None,
+                changes: Some(AuditChanges::diff(&json!({ "age": 1 }), &json!({ "age": 2 }))),
+            };
+            database.audit_manager.record(&event).await.unwrap();
+
+            let delete = |token: String| {
+                dispatch_request(
+                    &client,
+                    Method::Delete,
+                    format!("/user/id/{}", test_user.unique_id),
+                    None,
+                    Some(token),
+                )
+            };
+
+            // The owner of an organization has to transfer it first
+            let other_organization = testing::get_org(database, &test_user).await;
+            let response = delete(owner.get_token().unwrap().to_string()).await;
+            let request_error = response.into_json::<RequestError>().await.unwrap();
+            assert_eq!(request_error.code, 409);
+            assert!(request_error.message.contains(&other_organization.unique_id.to_string()));
+            assert!(database.user_manager.user_exists(test_user.unique_id).await);
+
+            database
+                .organization_manager
+                .update_organization(other_organization.unique_id, vec![OrganizationUpdate::OwnerId(owner.unique_id)])
+                .await
+                .unwrap();
+            let response = delete(owner.get_token().unwrap().to_string()).await;
+            assert!(response.into_json::<bool>().await.unwrap());
+
+            assert!(!database.user_manager.user_exists(test_user.unique_id).await);
+            let organization = database
+                .organization_manager
+                .from_id(organization.unique_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(!organization.member_ids.contains(&test_user.unique_id));
+            let asset = database.asset_manager.get_asset_by_id(asset.id).await.unwrap().unwrap();
+            assert_eq!(asset.author_id, None);
+            assert!(asset.upvote_user_ids.is_empty());
+            assert!(database.outbox_manager.messages_to(&email).await.unwrap().is_empty());
+            assert_eq!(database.rate_limit_manager.locked_until(&account, now).await.unwrap(), None);
+            assert!(database.oidc_login_manager.consume("erased-state", now).await.unwrap().is_none());
+            assert!(!database.peers_manager.peers_exist(test_user.unique_id).await.unwrap());
+            // The events are kept with the ids only
+            let events = database
+                .audit_manager
+                .get_events(organization.unique_id, &[test_user.unique_id], &PageRequest::default())
+                .await
+                .unwrap()
+                .items;
+            assert!(events.iter().any(|other| other.unique_id == event.unique_id));
+            assert!(events.iter().any(|event| matches!(event.action, AuditAction::UserDeleted)));
+            assert!(events.iter().all(|event| event.ip.is_none() && event.changes.is_none()));
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn test_unknown_delete_from_token() {
         run_test(|client| async move {
-            let request_user =
-                testing::get_user(client.rocket().state::<Database>().unwrap())
-                    .await;
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("user.delete").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
-            let id = "NO_ID";
+            let id = UserId::generate();
 
             let response = dispatch_request(
                 &client,
@@ -111,15 +333,13 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database, ).await;
-            let request_token = request_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &client,
                 Method::Delete,
                 format!("/user/id/{}", test_user.unique_id),
                 None,
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -128,7 +348,7 @@ mod tests {
             // User should still exist in the database.
             assert!(database
                 .user_manager
-                .from_id(&test_user.unique_id)
+                .from_id(test_user.unique_id)
                 .await
                 .unwrap()
                 .is_some());
@@ -141,13 +361,15 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &client,
                 Method::Delete,
                 format!("/user/id/{}", test_user.unique_id),
                 None,
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
@@ -155,11 +377,26 @@ mod tests {
             // User should still exist in the database.
             assert!(database
                 .user_manager
-                .from_id(&test_user.unique_id)
+                .from_id(test_user.unique_id)
                 .await
                 .unwrap()
                 .is_some());
         })
         .await;
     }
+
+    #[rocket::async_test]
+    async fn access_token_test_delete_from_id() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+
+            let response = dispatch_request(&client, Method::Delete, format!("/user/id/{}", user.unique_id), None, Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+            assert!(database.user_manager.user_exists(user.unique_id).await);
+        })
+        .await;
+    }
 }
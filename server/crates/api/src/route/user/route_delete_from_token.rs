@@ -1,20 +1,80 @@
-use database::{Database};
+use database::{
+    audit::{AuditAction, AuditTargetKind},
+    Database,
+};
 use rocket::{delete, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use crate::{
+    audit::AuditContext,
+    model::user_token::{UserData, ACCESS_TOKEN_REFUSED},
+    personal_data::{self, ErasureError},
+    RequestError,
+};
 
 /// Delete the user linked to the token
+///
+/// The data of the user is erased from every collection first, which is refused while the user owns organizations.
+///
+/// Only the user themselves, or a user with the 'user.delete' permission, can delete the account.
 #[openapi(tag = "Users")]
 #[delete("/token/<token>")] // <- route attribute
 pub async fn delete_from_token(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     token: String,
+    audit: AuditContext,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    if user_data.is_access_token() {
+        let status = Status::Forbidden;
+        return Custom(
+            status,
+            Err(RequestError::from(Custom(status, ACCESS_TOKEN_REFUSED.to_string())).into()),
+        );
+    }
+
+    if user_data.id.is_none() {
+        let status = Status::Unauthorized;
+        return Custom(status, Err(RequestError::from(Custom(status, "Token is invalid".to_string())).into()));
+    }
+
+    let user = database.user_manager.from_token(&token).await.ok().flatten();
+    if user.as_ref().map(|user| user.unique_id) != user_data.id {
+        let allowed = match database.permission_manager.get_permission_id("user.delete").await {
+            Ok(permission) => user_data.has_permission(database, permission).await,
+            Err(_) => false,
+        };
+        if !allowed {
+            let status = Status::Forbidden;
+            return Custom(
+                status,
+                Err(RequestError::from(Custom(status, "Only the user can delete their account.".to_string())).into()),
+            );
+        }
+    }
+
+    if let Some(user) = &user {
+        if let Err(err) = personal_data::erase(database, user).await {
+            if let ErasureError::Database(err) = &err {
+                tracing::error!(user_id = %user.unique_id, "Cannot erase the data of the user: {err}");
+            }
+            return Custom(Status::Ok, Err(RequestError::from(err).into()));
+        }
+    }
 
     match database.user_manager.delete_user(None, Some(&token)).await {
-        Ok(Some(_)) => Custom(Status::Ok, Ok(Json(true))),
+        Ok(true) => {
+            if let Some(user) = user {
+                // Only the id of the erased user is kept, not their IP when they delete themselves
+                let mut event = audit.event(AuditAction::UserDeleted, user_data.id, AuditTargetKind::User, user.unique_id);
+                if user_data.id == Some(user.unique_id) {
+                    event.ip = None;
+                }
+                audit.record(database, event).await;
+            }
+
+            Custom(Status::Ok, Ok(Json(true)))
+        }
         Ok(_) => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -33,7 +93,7 @@ pub async fn delete_from_token(
 #[cfg(test)]
 mod tests {
 
-    use database::{Database};
+    use database::{authentication::Authentication, Database};
     use rocket::http::{Method, Status};
 
     use crate::{
@@ -46,7 +106,8 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
+            let permission = database.permission_manager.get_permission_id("user.delete").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
             let user_token = test_user.get_token().unwrap();
 
@@ -73,12 +134,34 @@ mod tests {
         .await;
     }
 
+    #[rocket::async_test]
+    async fn test_delete_own_token() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let user_token = test_user.get_token().unwrap();
+
+            let response = dispatch_request(
+                &client,
+                Method::Delete,
+                format!("/user/token/{}", user_token),
+                None,
+                Some(user_token.to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            assert!(!database.user_manager.user_exists(test_user.unique_id).await);
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn test_unknown_delete_from_token() {
         run_test(|client| async move {
-            let request_user =
-                testing::get_user(client.rocket().state::<Database>().unwrap())
-                    .await;
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("user.delete").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
             let token = "NO_TOKEN";
 
@@ -112,8 +195,6 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
             let user_token = test_user.get_token().unwrap();
 
             let response = dispatch_request(
@@ -121,7 +202,7 @@ mod tests {
                 Method::Delete,
                 format!("/user/token/{}", user_token),
                 None,
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -143,6 +224,8 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
             let user_token = test_user.get_token().unwrap();
 
             let response = dispatch_request(
@@ -150,7 +233,7 @@ mod tests {
                 Method::Delete,
                 format!("/user/token/{}", user_token),
                 None,
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
@@ -165,4 +248,19 @@ mod tests {
         })
         .await;
     }
+
+    #[rocket::async_test]
+    async fn access_token_test_delete_from_token() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+
+            let response = dispatch_request(&client, Method::Delete, format!("/user/token/{}", user.get_token().unwrap()), None, Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+            assert!(database.user_manager.user_exists(user.unique_id).await);
+        })
+        .await;
+    }
 }
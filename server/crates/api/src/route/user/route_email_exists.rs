@@ -4,14 +4,29 @@ use rocket_okapi::openapi;
 
 use crate::{model::user_token::UserData, RequestError};
 
+fn error(status: Status, message: &str) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(status, Err(RequestError::from(Custom(status, message.to_string())).into()))
+}
+
 /// Check if an email is registered or not
+///
+/// Requires the 'profile.see' permission, so that the registered emails cannot be enumerated
 #[openapi(tag = "Users")]
 #[get("/email_exists/<email>")] // <- route attribute
 pub async fn email_exists(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     email: String,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    if user_data.id.is_none() {
+        return error(Status::Unauthorized, "Token is invalid");
+    }
+    let Ok(permission) = database.permission_manager.get_permission_id("profile.see").await else {
+        return error(Status::InternalServerError, "Unknown permission");
+    };
+    if !user_data.has_permission(database, permission).await {
+        return error(Status::Forbidden, "You don't have the permission to see the users");
+    }
 
     match database.user_manager.email_exists(email).await {
         Ok(value) => Custom(Status::Ok, Ok(Json(value))),
@@ -53,7 +68,8 @@ mod tests {
                 Vec::new()
             )
             .await;
-            let request_user = testing::get_user(database).await;
+            let permission = database.permission_manager.get_permission_id("profile.see").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
 
             let response = dispatch_request(
@@ -76,7 +92,8 @@ mod tests {
     async fn test_from_unknown_email_exists() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
-            let request_user = testing::get_user(database).await;
+            let permission = database.permission_manager.get_permission_id("profile.see").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
             let email = "NO_EMAIL@EMAIL.FR".to_string();
 
@@ -105,8 +122,6 @@ mod tests {
     async fn _unauthorized_test_email_exists() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
             let credentials = Credentials {
                 email: "test@test.fr".to_string(),
                 username: Option::Some("test".to_string()),
@@ -125,7 +140,7 @@ mod tests {
                 Method::Get,
                 format!("/user/email_exists/{}", credentials.email),
                 None,
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -138,6 +153,8 @@ mod tests {
     async fn forbidden_test_email_exists() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
             let credentials = Credentials {
                 email: "test@test.fr".to_string(),
                 username: Option::Some("test".to_string()),
@@ -156,7 +173,7 @@ mod tests {
                 Method::Get,
                 format!("/user/email_exists/{}", credentials.email),
                 None,
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
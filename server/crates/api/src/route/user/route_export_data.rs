@@ -0,0 +1,131 @@
+use database::{
+    audit::{AuditAction, AuditTargetKind},
+    Database,
+};
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{
+    audit::AuditContext,
+    model::{user_archive::UserArchive, user_token::UserData},
+    personal_data, RequestError,
+};
+
+/// Download everything stored about the user of the token, as a JSON archive
+///
+/// The archive holds the profile, the sessions, the organizations, the projects, the licenses, the assets, the
+/// comments, the votes and the reports of the user, without the passwords and the tokens
+#[openapi(tag = "Users")]
+#[get("/export")]
+pub async fn export_data(
+    user_data: UserData,
+    database: &State<Database>,
+    audit: AuditContext,
+) -> Custom<Result<Json<UserArchive>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return refuse(Status::Unauthorized, "Token is invalid");
+    };
+    // The scripts only get the permissions of their scopes, not the whole account
+    if user_data.is_access_token() {
+        return refuse(
+            Status::Forbidden,
+            "A personal access token cannot export the data of the user.",
+        );
+    }
+
+    let archive = match database.user_manager.from_id(user_id).await {
+        Ok(Some(user)) => personal_data::export(database, &user).await,
+        Ok(None) => return refuse(Status::NotFound, &format!("User not found with id: {user_id}")),
+        Err(err) => Err(err),
+    };
+    match archive {
+        Ok(archive) => {
+            let event = audit.event(
+                AuditAction::UserDataExported,
+                Some(user_id),
+                AuditTargetKind::User,
+                user_id,
+            );
+            audit.record(database, event).await;
+
+            Custom(Status::Ok, Ok(Json(archive)))
+        }
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot export the data of the user: {err}");
+            refuse(Status::InternalServerError, "A database error occured.")
+        }
+    }
+}
+
+fn refuse(status: Status, message: &str) -> Custom<Result<Json<UserArchive>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{comment::Comment, id::CommentId, moderation::ModerationStatus, timestamp::Timestamp, Database};
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::user_archive::{AssetVote, UserArchive, VoteKind},
+        testing::{self, dispatch_request, run_test},
+    };
+
+    #[rocket::async_test]
+    async fn test_export_data() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = user.get_token().unwrap().to_string();
+            let organization = testing::get_org(database, &user).await;
+
+            let mut asset = testing::create_asset(database, &user).await;
+            asset.add_upvote(user.unique_id);
+            asset.add_comment(Comment {
+                unique_id: CommentId::generate(),
+                asset_id: asset.id,
+                user_id: user.unique_id,
+                content: "Nice".to_string(),
+                timestamp: Timestamp::now(),
+                status: ModerationStatus::Visible,
+            });
+            database.asset_manager.update_asset(asset.id, &asset).await.unwrap();
+
+            let response = dispatch_request(&client, Method::Get, "/user/export".to_string(), None, Some(token)).await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let archive = response.into_json::<UserArchive>().await.unwrap();
+            assert_eq!(archive.profile["unique_id"], user.unique_id.to_string());
+            assert!(archive.profile.get("logins").is_none());
+            assert!(!archive.sessions.is_empty());
+            // The tokens and the password are not in the archive
+            assert!(archive.sessions.iter().all(|session| session.get("token").is_none()));
+            assert!(!archive.profile.to_string().contains("password"));
+            assert_eq!(archive.organizations.len(), 1);
+            assert_eq!(archive.organizations[0].unique_id, organization.unique_id);
+            assert_eq!(archive.assets.len(), 1);
+            assert_eq!(archive.comments.len(), 1);
+            assert_eq!(
+                archive.votes,
+                vec![AssetVote {
+                    asset_id: asset.id,
+                    kind: VoteKind::Upvote,
+                }]
+            );
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn unauthorized_test_export_data() {
+        run_test(|client| async move {
+            let response = dispatch_request(&client, Method::Get, "/user/export".to_string(), None, None).await;
+
+            assert_eq!(response.status(), Status::Unauthorized);
+        })
+        .await;
+    }
+}
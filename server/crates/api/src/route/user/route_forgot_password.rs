@@ -0,0 +1,98 @@
+use database::{password_reset::PasswordReset, timestamp::Timestamp, user::User, Database};
+use rand::RngCore;
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+use sha2::{Digest, Sha256};
+
+use crate::{mail::Mailer, model::password_reset::ForgotPassword, RequestError};
+
+/// Send a link resetting the password to the email, if it is registered
+///
+/// The answer is the same whether the email is registered or not, so that it does not tell which ones are
+#[openapi(tag = "Users")]
+#[post("/forgot_password", data = "<request>", format = "application/json")]
+pub async fn forgot_password(
+    database: &State<Database>,
+    mailer: &State<Mailer>,
+    request: Json<ForgotPassword>,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    match database.user_manager.from_email(&request.email).await {
+        Ok(Some(user)) => {
+            if let Err(err) = start_password_reset(database, mailer, &user).await {
+                tracing::error!(user_id = %user.unique_id, "Cannot start the password reset: {err}");
+            }
+        }
+        Ok(None) => {}
+        Err(err) => tracing::error!("Cannot look up the user resetting their password: {err}"),
+    }
+    Custom(Status::Ok, Ok(Json(true)))
+}
+
+/// Stores the hash of a new reset token of the user, and sends the token by mail
+pub(crate) async fn start_password_reset(database: &Database, mailer: &Mailer, user: &User) -> Result<(), String> {
+    let mut token = [0; 32];
+    rand::thread_rng().fill_bytes(&mut token);
+    let token = hex::encode(token);
+
+    let now = Timestamp::now();
+    let reset = PasswordReset {
+        token_hash: hash_token(&token),
+        user_id: user.unique_id,
+        creation_date: now,
+        expires_at: now.plus(mailer.password_reset_lifetime()),
+    };
+    database.password_reset_manager.create(&reset).await.map_err(|err| err.to_string())?;
+    mailer.send_password_reset(user, &token).await?;
+    Ok(())
+}
+
+/// The tokens are stored hashed, a leak of the database does not allow resetting the passwords
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        authentication::{Authentication, Credentials},
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::password_reset::ForgotPassword,
+        testing::{self, dispatch_request, run_test},
+    };
+
+    #[rocket::async_test]
+    async fn test_forgot_password() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let credentials = Credentials {
+                email: "forgetful@test.fr".to_string(),
+                username: Some("forgetful".to_string()),
+                avatar: None,
+                password: "test".to_string(),
+            };
+            testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await;
+
+            let mut bodies = Vec::new();
+            for email in ["forgetful@test.fr", "unknown@test.fr"] {
+                let body = serde_json::to_string(&ForgotPassword { email: email.to_string() }).unwrap();
+                let response =
+                    dispatch_request(&client, Method::Post, "/user/forgot_password".to_string(), Some(body), None)
+                        .await;
+                bodies.push((response.status(), response.into_string().await));
+            }
+            // Nothing tells whether the email is registered
+            assert_eq!(bodies[0], (Status::Ok, Some("true".to_string())));
+            assert_eq!(bodies[0], bodies[1]);
+
+            let messages = database.outbox_manager.messages_to("forgetful@test.fr").await.unwrap();
+            assert_eq!(messages.len(), 1);
+            assert!(messages[0].text.contains("http://localhost:3000/reset_password?token="));
+            assert!(database.outbox_manager.messages_to("unknown@test.fr").await.unwrap().is_empty());
+        })
+        .await;
+    }
+}
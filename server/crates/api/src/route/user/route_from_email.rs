@@ -1,20 +1,32 @@
-use database::{user::User, Database};
+use database::Database;
 use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use crate::{
+    model::{user_profile::UserProfile, user_token::UserData},
+    RequestError,
+};
 
 /// Retrieve the user informations from its unique email
+///
+/// Requires to be signed in, the emails cannot be looked up anonymously. The user is returned in full to themselves,
+/// and as a public profile without the logins nor the credentials to the other users
 #[openapi(tag = "Users")]
 #[get("/email/<email>")] // <- route attribute
 pub async fn from_email(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     email: String,
-) -> Custom<Result<Json<User>, Json<RequestError>>> {
+) -> Custom<Result<Json<UserProfile>, Json<RequestError>>> {
+    let Some(viewer_id) = user_data.id else {
+        return Custom(
+            Status::Unauthorized,
+            Err(RequestError::from(Custom(Status::Unauthorized, "Token is invalid".to_string())).into()),
+        );
+    };
 
     match database.user_manager.from_email(&email).await {
-        Ok(user) if user.is_some() => Custom(Status::Ok, Ok(Json(user.unwrap()))),
+        Ok(Some(user)) => Custom(Status::Ok, Ok(Json(UserProfile::new(user, viewer_id)))),
         _ => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -29,26 +41,67 @@ pub async fn from_email(
 #[cfg(test)]
 mod tests {
 
-    use database::{user::User, Database};
+    use database::{
+        authentication::{Authentication, Credentials},
+        id::UserId,
+        user::User,
+        Database,
+    };
     use rocket::http::{Method, Status};
+    use serde_json::Value;
 
     use crate::{
+        model::organization_member::MemberProfile,
         testing::{self, dispatch_request, run_test},
         RequestError,
     };
 
+    async fn create_user_with_email(database: &Database) -> User {
+        let credentials = Credentials {
+            email: format!("{}@test.fr", UserId::generate()),
+            username: Some("test".to_string()),
+            avatar: None,
+            password: "test".to_string(),
+        };
+        testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await
+    }
+
     #[rocket::async_test]
     async fn test_from_email() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
-            let test_user = testing::get_user(database).await;
+            let test_user = create_user_with_email(database).await;
             let request_user = testing::get_user(database).await;
             let request_token = request_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &client,
                 Method::Get,
-                format!("/user/email/{}", test_user.authentication.credentials().email),
+                format!("/user/email/{}", test_user.email().unwrap()),
+                None,
+                Some(request_token.to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let user = response.into_json::<MemberProfile>().await.unwrap();
+            assert_eq!(user.unique_id, test_user.unique_id);
+            assert_eq!(user.username.as_deref(), Some("test"));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_from_own_email() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = create_user_with_email(database).await;
+            let request_token = test_user.get_token().unwrap();
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/user/email/{}", test_user.email().unwrap()),
                 None,
                 Some(request_token.to_string()),
             )
@@ -93,20 +146,18 @@ mod tests {
     #[rocket::async_test]
     async fn unauthorized_test_from_email() {
         run_test(|client| async move {
-            let database = client.rocket().state::<Database>().unwrap();
-            let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let test_user = create_user_with_email(client.rocket().state::<Database>().unwrap()).await;
 
             let response = dispatch_request(
                 &client,
                 Method::Get,
-                format!("/user/email/{}", test_user.authentication.credentials().email),
+                format!("/user/email/{}", test_user.email().unwrap()),
+                None,
                 None,
-                Some(request_token.to_string()),
             )
             .await;
 
+            // The emails cannot be enumerated anonymously
             assert_eq!(response.status(), Status::Unauthorized);
         })
         .await;
@@ -115,19 +166,24 @@ mod tests {
     #[rocket::async_test]
     async fn forbidden_test_from_email() {
         run_test(|client| async move {
-            let test_user =
-                testing::get_user(client.rocket().state::<Database>().unwrap()).await;
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = create_user_with_email(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &client,
                 Method::Get,
-                format!("/user/email/{}", test_user.get_token().unwrap()),
-                None,
+                format!("/user/email/{}", test_user.email().unwrap()),
                 None,
+                Some(request_token.to_string()),
             )
             .await;
 
-            assert_eq!(response.status(), Status::Forbidden);
+            // The other users never see the logins nor the credentials
+            let user = response.into_json::<Value>().await.unwrap();
+            assert!(user.get("logins").is_none());
+            assert!(user.get("authentication").is_none());
         })
         .await;
     }
@@ -1,8 +1,11 @@
-use database::{user::User, Database};
+use database::{id::UserId, Database};
 use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use crate::{
+    model::{user_profile::UserProfile, user_token::UserData},
+    RequestError,
+};
 
 /// Retrieve the user informations from its unique identifier
 #[openapi(tag = "Users")]
@@ -10,11 +13,17 @@ use crate::{model::user_token::UserData, RequestError};
 pub async fn from_id(
     user_data: UserData,
     database: &State<Database>,
-    id: String,
-) -> Custom<Result<Json<User>, Json<RequestError>>> {
+    id: UserId,
+) -> Custom<Result<Json<UserProfile>, Json<RequestError>>> {
+    let Some(viewer_id) = user_data.id else {
+        return Custom(
+            Status::Unauthorized,
+            Err(RequestError::from(Custom(Status::Unauthorized, "Token is invalid".to_string())).into()),
+        );
+    };
 
-    match database.user_manager.from_id(&id).await {
-        Ok(user) if user.is_some() => Custom(Status::Ok, Ok(Json(user.unwrap()))),
+    match database.user_manager.from_id(id).await {
+        Ok(Some(user)) => Custom(Status::Ok, Ok(Json(UserProfile::new(user, viewer_id)))),
         _ => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
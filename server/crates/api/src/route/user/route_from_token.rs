@@ -1,8 +1,11 @@
-use database::{user::User, Database};
+use database::Database;
 use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use crate::{
+    model::{user_profile::UserProfile, user_token::UserData},
+    RequestError,
+};
 
 /// Retrieve the user informations from its token
 #[openapi(tag = "Users")]
@@ -11,10 +14,16 @@ pub async fn from_token(
     user_data: UserData,
     database: &State<Database>,
     token: String,
-) -> Custom<Result<Json<User>, Json<RequestError>>> {
+) -> Custom<Result<Json<UserProfile>, Json<RequestError>>> {
+    let Some(viewer_id) = user_data.id else {
+        return Custom(
+            Status::Unauthorized,
+            Err(RequestError::from(Custom(Status::Unauthorized, "Token is invalid".to_string())).into()),
+        );
+    };
 
     match database.user_manager.from_token(&token).await {
-        Ok(user) if user.is_some() => Custom(Status::Ok, Ok(Json(user.unwrap()))),
+        Ok(Some(user)) => Custom(Status::Ok, Ok(Json(UserProfile::new(user, viewer_id)))),
         _ => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -1,31 +1,42 @@
-use database::{user::User, Database};
+use database::{id::UserId, Database};
 use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use crate::{
+    model::{user_profile::UserProfile, user_token::UserData},
+    RequestError,
+};
 
-/// Retrieve the user informations from its unique identifier
+/// Retrieve the user informations from its unique identifier or its token
+///
+/// The user is returned in full to themselves, and as a public profile without the logins nor the credentials
+/// to the other users
 #[openapi(tag = "Users")]
 #[get("/<token_or_id>")] // <- route attribute
 pub async fn get(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     token_or_id: String,
-) -> Custom<Result<Json<User>, Json<RequestError>>> {
-
-    if token_or_id.chars().all(|c| c.is_numeric()) {
-        from_id(database, token_or_id).await
-    } else {
-        from_token(database, token_or_id).await
+) -> Custom<Result<Json<UserProfile>, Json<RequestError>>> {
+    let Some(viewer_id) = user_data.id else {
+        return Custom(
+            Status::Unauthorized,
+            Err(RequestError::from(Custom(Status::Unauthorized, "Token is invalid".to_string())).into()),
+        );
+    };
+    match token_or_id.parse::<UserId>() {
+        Ok(id) => from_id(database, id, viewer_id).await,
+        Err(_) => from_token(database, token_or_id, viewer_id).await,
     }
 }
 
 async fn from_token(
     database: &State<Database>,
     token: String,
-) -> Custom<Result<Json<User>, Json<RequestError>>> {
+    viewer_id: UserId,
+) -> Custom<Result<Json<UserProfile>, Json<RequestError>>> {
     match database.user_manager.from_token(&token).await {
-        Ok(user) if user.is_some() => Custom(Status::Ok, Ok(Json(user.unwrap()))),
+        Ok(Some(user)) => Custom(Status::Ok, Ok(Json(UserProfile::new(user, viewer_id)))),
         _ => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -39,10 +50,11 @@ async fn from_token(
 
 async fn from_id(
     database: &State<Database>,
-    id: String,
-) -> Custom<Result<Json<User>, Json<RequestError>>> {
-    match database.user_manager.from_id(&id).await {
-        Ok(user) if user.is_some() => Custom(Status::Ok, Ok(Json(user.unwrap()))),
+    id: UserId,
+    viewer_id: UserId,
+) -> Custom<Result<Json<UserProfile>, Json<RequestError>>> {
+    match database.user_manager.from_id(id).await {
+        Ok(Some(user)) => Custom(Status::Ok, Ok(Json(UserProfile::new(user, viewer_id)))),
         _ => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -57,10 +69,12 @@ async fn from_id(
 #[cfg(test)]
 mod tests {
 
-    use database::{user::User, Database};
+    use database::{id::UserId, user::User, Database};
     use rocket::http::{Method, Status};
+    use serde_json::Value;
 
     use crate::{
+        model::organization_member::MemberProfile,
         testing::{self, dispatch_request, run_test},
         RequestError,
     };
@@ -83,12 +97,36 @@ mod tests {
             .await;
 
             assert_eq!(response.status(), Status::Ok);
-            let user = response.into_json::<User>().await.unwrap();
+            let user = response.into_json::<MemberProfile>().await.unwrap();
             assert_eq!(user.unique_id, test_user.unique_id);
         })
         .await;
     }
 
+    #[rocket::async_test]
+    async fn test_from_own_id() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/user/{}", request_user.unique_id),
+                None,
+                Some(request_token.to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let user = response.into_json::<User>().await.unwrap();
+            assert_eq!(user.unique_id, request_user.unique_id);
+            assert_eq!(user.get_token(), Some(request_token));
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn test_from_unknown_id() {
         run_test(|client| async move {
@@ -96,7 +134,7 @@ mod tests {
                 testing::get_user(client.rocket().state::<Database>().unwrap())
                     .await;
             let request_token = request_user.get_token().unwrap();
-            let id = "000000";
+            let id = UserId::generate();
 
             let response = dispatch_request(
                 &client,
@@ -121,17 +159,15 @@ mod tests {
     #[rocket::async_test]
     async fn unauthorized_test_from_id() {
         run_test(|client| async move {
-            let database = client.rocket().state::<Database>().unwrap();
-            let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let test_user =
+                testing::get_user(client.rocket().state::<Database>().unwrap()).await;
 
             let response = dispatch_request(
                 &client,
                 Method::Get,
                 format!("/user/{}", test_user.unique_id),
                 None,
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -143,19 +179,24 @@ mod tests {
     #[rocket::async_test]
     async fn forbidden_test_from_id() {
         run_test(|client| async move {
-            let test_user =
-                testing::get_user(client.rocket().state::<Database>().unwrap()).await;
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &client,
                 Method::Get,
-                format!("/user/{}", test_user.get_token().unwrap()),
-                None,
+                format!("/user/{}", test_user.unique_id),
                 None,
+                Some(request_token.to_string()),
             )
             .await;
 
-            assert_eq!(response.status(), Status::Forbidden);
+            // The other users never see the logins nor the credentials
+            let user = response.into_json::<Value>().await.unwrap();
+            assert!(user.get("logins").is_none());
+            assert!(user.get("authentication").is_none());
         })
         .await;
     }
@@ -165,8 +206,7 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let request_token = test_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &client,
@@ -216,17 +256,15 @@ mod tests {
     #[rocket::async_test]
     async fn unauthorized_test_from_token() {
         run_test(|client| async move {
-            let database = client.rocket().state::<Database>().unwrap();
-            let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let test_user =
+                testing::get_user(client.rocket().state::<Database>().unwrap()).await;
 
             let response = dispatch_request(
                 &client,
                 Method::Get,
                 format!("/user/{}", test_user.get_token().unwrap()),
                 None,
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -238,19 +276,25 @@ mod tests {
     #[rocket::async_test]
     async fn forbidden_test_from_token() {
         run_test(|client| async move {
-            let test_user =
-                testing::get_user(client.rocket().state::<Database>().unwrap()).await;
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
 
             let response = dispatch_request(
                 &client,
                 Method::Get,
                 format!("/user/{}", test_user.get_token().unwrap()),
                 None,
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
-            assert_eq!(response.status(), Status::Forbidden);
+            // The token of another user only gives their public profile
+            let user = response.into_json::<Value>().await.unwrap();
+            assert_eq!(user["unique_id"], test_user.unique_id.to_string());
+            assert!(user.get("logins").is_none());
+            assert!(user.get("authentication").is_none());
         })
         .await;
     }
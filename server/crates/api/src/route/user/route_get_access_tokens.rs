@@ -0,0 +1,39 @@
+use database::Database;
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{
+    model::{access_token::AccessTokenInfo, user_token::UserData},
+    RequestError,
+};
+
+/// List the personal access tokens of the user of the token, the oldest first
+#[openapi(tag = "Users")]
+#[get("/access_tokens")]
+pub async fn get_access_tokens(
+    user_data: UserData,
+    database: &State<Database>,
+) -> Custom<Result<Json<Vec<AccessTokenInfo>>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        let status = Status::Unauthorized;
+        return Custom(
+            status,
+            Err(RequestError::from(Custom(status, "Token is invalid".to_string())).into()),
+        );
+    };
+
+    match database.access_token_manager.list(user_id).await {
+        Ok(tokens) => Custom(
+            Status::Ok,
+            Ok(Json(tokens.into_iter().map(AccessTokenInfo::from).collect())),
+        ),
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot list the access tokens: {err}");
+            let status = Status::InternalServerError;
+            Custom(
+                status,
+                Err(RequestError::from(Custom(status, "A database error occured.".to_string())).into()),
+            )
+        }
+    }
+}
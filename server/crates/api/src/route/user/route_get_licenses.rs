@@ -1,24 +1,26 @@
-use database::{Database};
+use database::{id::UserId, Database};
 use database::license::License;
+use database::pagination::Page;
 use rocket::get;
 use rocket::{http::Status, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
-use crate::{model::user_token::UserData, RequestError};
+use crate::{model::{page_query::PageQuery, user_token::UserData}, RequestError};
 
 
 /// Get all licenses of a user
 ///
 /// Requires 'Website', 'Server', 'User', 'Guest' group
 #[openapi(tag = "Users")]
-#[get("/id/<id>/license")]
+#[get("/id/<id>/license?<page..>")]
 pub async fn get_licenses(
     _user_data: UserData,
     database: &State<Database>,
-    id: String,
-) -> Custom<Result<Json<Vec<License>>, Json<RequestError>>> {
+    id: UserId,
+    page: PageQuery,
+) -> Custom<Result<Json<Page<License>>, Json<RequestError>>> {
 
 
-    let user = match database.user_manager.from_id(&id).await {
+    let user = match database.user_manager.from_id(id).await {
         Ok(user) => user,
         Err(_) => return Custom(
             Status::InternalServerError,
@@ -44,16 +46,13 @@ pub async fn get_licenses(
     let user = user.unwrap();
 
 
-    let licenses = match database.license_manager.get_licenses(&user.unique_id).await {
+    let licenses = match database.license_manager.get_licenses(user.unique_id, &page.into()).await {
         Ok(licenses) => licenses,
         Err(err) => {
+            let error = RequestError::from(err);
             return Custom(
-                Status::InternalServerError,
-                Err(RequestError::from(Custom(
-                    Status::InternalServerError,
-                    err.to_string(),
-                ))
-                .into()),
+                Status::from_code(error.code).unwrap_or(Status::InternalServerError),
+                Err(error.into()),
             )
         }
     };
@@ -64,7 +63,7 @@ pub async fn get_licenses(
 #[cfg(test)]
 mod tests {
 
-    use database::{Database, license::License};
+    use database::{id::UserId, license::License, pagination::Page, Database};
     use rocket::http::{Method, Status};
 
     use crate::testing::{self, dispatch_request, run_test};
@@ -87,8 +86,8 @@ mod tests {
             .await;
 
             assert_eq!(response.status(), Status::Ok);
-            let licenses = response.into_json::<Vec<License>>().await.unwrap();
-            assert_eq!(licenses.len(), 0);
+            let licenses = response.into_json::<Page<License>>().await.unwrap();
+            assert_eq!(licenses.items.len(), 0);
         })
         .await;
     }
@@ -98,7 +97,7 @@ mod tests {
         run_test(|client| async move {
             let request_user = testing::get_user(client.rocket().state::<Database>().unwrap()).await;
             let request_token = request_user.get_token().unwrap();
-            let id = "NO_ID";
+            let id = UserId::generate();
 
             let response = dispatch_request(
                 &client,
@@ -17,11 +17,11 @@ pub async fn has_access(
 
     let server_id = user_data.id.unwrap();
     let user_id = user_id.0;
-    match database.user_manager.from_id(&server_id).await {
+    match database.user_manager.from_id(server_id).await {
         Ok(user) if user.is_some() => {
             match database
                 .organization_manager
-                .has_access_to_server(&server_id, &user_id.0)
+                .has_access_to_server(server_id, user_id.0)
                 .await
             {
                 Ok(result) => Custom(Status::Ok, Ok(Json(result))),
@@ -64,7 +64,7 @@ mod tests {
             let test_server = testing::get_user(database).await;
             let test_user = testing::get_user(database).await;
             let _test_org =
-                testing::create_org(database, &test_user, vec![test_server.unique_id.clone()])
+                testing::create_org(database, &test_user, vec![test_server.unique_id])
                     .await;
 
             let response = dispatch_request(
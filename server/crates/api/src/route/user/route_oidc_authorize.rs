@@ -0,0 +1,131 @@
+use database::Database;
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{
+    model::{
+        oidc::OidcAuthorization,
+        user_token::{UserData, ACCESS_TOKEN_REFUSED},
+    },
+    oidc::{OidcClient, OidcError},
+    RequestError,
+};
+
+/// Start signing in with an OpenID Connect provider of the settings
+///
+/// The answer holds the address of the provider the user signs in at, the provider then sends the user back to
+/// /user/oidc/callback. With a token, the account the user signs in with is linked to the user of the token,
+/// who can then sign in with it too
+#[openapi(tag = "Users")]
+#[post("/oidc/authorize/<provider>")]
+pub async fn oidc_authorize(
+    provider: &str,
+    user_data: UserData,
+    database: &State<Database>,
+    oidc: &State<OidcClient>,
+) -> Custom<Result<Json<OidcAuthorization>, Json<RequestError>>> {
+    // The account linked by a script would then sign in as the user, with every permission
+    if user_data.is_access_token() {
+        let status = Status::Forbidden;
+        return Custom(
+            status,
+            Err(RequestError::from(Custom(status, ACCESS_TOKEN_REFUSED.to_string())).into()),
+        );
+    }
+
+    match oidc
+        .authorize(provider, user_data.id, database.oidc_login_manager.as_ref())
+        .await
+    {
+        Ok(authorization_url) => Custom(Status::Ok, Ok(Json(OidcAuthorization { authorization_url }))),
+        Err(err) => {
+            if matches!(err, OidcError::Provider(_) | OidcError::Database(_)) {
+                tracing::error!(provider, "Cannot start the sign in: {err:?}");
+            }
+            Custom(err.status(), Err(RequestError::from(err).into()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::Database;
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::oidc::OidcAuthorization,
+        oidc::{
+            code_challenge,
+            mock_issuer::{MockIssuer, CLIENT_ID},
+        },
+        testing::{self, dispatch_request, run_test, run_test_with_settings},
+        RequestError,
+    };
+
+    #[rocket::async_test]
+    async fn test_oidc_authorize() {
+        let issuer = MockIssuer::start().await;
+        let issuer = &issuer;
+        run_test_with_settings(
+            |settings| issuer.configure(settings),
+            |client| async move {
+                let response = dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/oidc/authorize/mock".to_string(),
+                    None,
+                    None,
+                )
+                .await;
+                assert_eq!(response.status(), Status::Ok);
+                let authorization: OidcAuthorization = response.into_json().await.unwrap();
+
+                let url = reqwest::Url::parse(&authorization.authorization_url).unwrap();
+                assert!(url.as_str().starts_with(&format!("{}/authorize?", issuer.url())));
+                let query: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+                assert_eq!(query["response_type"], "code");
+                assert_eq!(query["client_id"], CLIENT_ID);
+                assert_eq!(query["code_challenge_method"], "S256");
+                assert_eq!(query["redirect_uri"], "http://localhost:8080/user/oidc/callback");
+
+                let database = client.rocket().state::<Database>().unwrap();
+                let login = database
+                    .oidc_login_manager
+                    .consume(&query["state"], database::timestamp::Timestamp::now())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(query["code_challenge"], code_challenge(&login.code_verifier));
+                assert_eq!(query["nonce"], login.nonce);
+                assert!(login.link_user.is_none());
+
+                let response = dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/oidc/authorize/other".to_string(),
+                    None,
+                    None,
+                )
+                .await;
+                assert_eq!(response.status(), Status::NotFound);
+                let error: RequestError = response.into_json().await.unwrap();
+                assert_eq!(error.message, "The provider is unknown.");
+            },
+        )
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn access_token_test_oidc_authorize() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+
+            let response = dispatch_request(&client, Method::Post, "/user/oidc/authorize/mock".to_string(), None, Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+        })
+        .await;
+    }
+}
@@ -0,0 +1,422 @@
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    authentication::{Authentication, OidcIdentity},
+    id::UserId,
+    indexes::is_duplicate_key,
+    oidc_login::OidcLogin,
+    timestamp::Timestamp,
+    user::User,
+    Database,
+};
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::{
+    api_telemetry::ApiMetrics,
+    audit::AuditContext,
+    mail::Mailer,
+    model::api_socket_addr::ApiSocketAddr,
+    oidc::{self, OidcClient, OidcError},
+    settings::ApiSettings,
+    RequestError,
+};
+
+use super::route_renew::{login_user, renew_token};
+
+type CallbackResponse = Custom<Result<String, Json<RequestError>>>;
+
+/// Complete the sign in at an OpenID Connect provider, with the query the provider sent the user back with
+///
+/// The answer is a token of the user, like /user/renew. The account of the provider is the one of the user it is
+/// linked to, otherwise it is linked to the user who started the sign in with a token, or to the user having the same
+/// email when the provider verified it. A user is registered when none matches
+///
+/// The users who enabled a second factor are answered with a challenge, like the credentials of /user/renew, unless
+/// they started the sign in with a token to link the account
+///
+/// A sign in is completed once, the provider must be asked again after a failure
+#[openapi(tag = "Users")]
+#[get("/oidc/callback?<code>&<state>&<error>&<error_description>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn oidc_callback(
+    code: Option<&str>,
+    state: &str,
+    error: Option<&str>,
+    error_description: Option<&str>,
+    database: &State<Database>,
+    metrics: &State<ApiMetrics>,
+    oidc: &State<OidcClient>,
+    mailer: &State<Mailer>,
+    settings: &State<ApiSettings>,
+    remote_addr: ApiSocketAddr,
+    audit: AuditContext,
+) -> CallbackResponse {
+    let ip = remote_addr.0.ip().to_string();
+
+    // The sign in is removed even when the provider refused it
+    let login = match database.oidc_login_manager.consume(state, Timestamp::now()).await {
+        Ok(Some(login)) => login,
+        Ok(None) => return refuse(Status::Unauthorized, "The sign in is unknown or has expired."),
+        Err(err) => {
+            tracing::error!("Cannot look up the sign in: {err}");
+            return refuse(Status::InternalServerError, "A database error occured.");
+        }
+    };
+    let code = match (code, error) {
+        (Some(code), None) => code,
+        (_, error) => {
+            let reason = error_description.or(error).unwrap_or("no code was sent");
+            return refuse(
+                Status::Unauthorized,
+                &format!("The provider refused the sign in: {reason}"),
+            );
+        }
+    };
+
+    let claims = match oidc.complete(&login, code).await {
+        Ok(claims) => claims,
+        Err(err) => {
+            if matches!(err, OidcError::Provider(_) | OidcError::Database(_)) {
+                tracing::error!(provider = login.provider, "Cannot complete the sign in: {err:?}");
+            }
+            return Custom(err.status(), Err(RequestError::from(err).into()));
+        }
+    };
+    let identity = oidc::identity(&login.provider, &claims);
+
+    let user = match find_user(database, metrics, &login, &identity, &audit).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let auth = Authentication::Oidc(identity);
+    if login.link_user.is_some() {
+        return renew_token(Ok(Some(user)), ip, auth, None, database, metrics, &audit).await;
+    }
+    login_user(user, ip, auth, database, metrics, &audit, mailer, &settings.two_factor).await
+}
+
+// find_user returns the user the account signs in, linking the account or registering a user when needed
+async fn find_user(
+    database: &Database,
+    metrics: &ApiMetrics,
+    login: &OidcLogin,
+    identity: &OidcIdentity,
+    audit: &AuditContext,
+) -> Result<User, CallbackResponse> {
+    let users = database.user_manager.as_ref();
+    let linked = users.from_identity(&identity.account).await.map_err(database_error)?;
+    match (linked, login.link_user) {
+        (Some(user), Some(link_user)) if user.unique_id != link_user => {
+            Err(refuse(Status::Conflict, "The account is linked to another user."))
+        }
+        (Some(user), _) => Ok(user),
+        (None, Some(link_user)) => link(database, link_user, identity, audit).await,
+        (None, None) => {
+            let same_email = match &identity.email {
+                Some(email) => users.from_email(email).await.map_err(database_error)?,
+                None => None,
+            };
+            match same_email {
+                // Both the provider and the user proved owning the email
+                Some(user) if user.email_verified => link(database, user.unique_id, identity, audit).await,
+                Some(_) => Err(refuse(
+                    Status::Conflict,
+                    "A user with this email exists, sign in to link the account.",
+                )),
+                None => register(database, metrics, identity).await,
+            }
+        }
+    }
+}
+
+async fn link(
+    database: &Database,
+    user_id: UserId,
+    identity: &OidcIdentity,
+    audit: &AuditContext,
+) -> Result<User, CallbackResponse> {
+    let users = database.user_manager.as_ref();
+    match users.link_identity(user_id, identity).await {
+        Ok(true) => {}
+        Ok(false) => return Err(refuse(Status::NotFound, "User not found")),
+        Err(err) if is_duplicate_key(&err) => {
+            return Err(refuse(Status::Conflict, "The account is linked to another user."))
+        }
+        Err(err) => return Err(database_error(err)),
+    }
+
+    let changes = AuditChanges {
+        before: None,
+        after: Some(json!({ "identity": identity.account })),
+    };
+    let event = audit.event(
+        AuditAction::IdentityLinked,
+        Some(user_id),
+        AuditTargetKind::User,
+        user_id,
+    );
+    audit.record(database, event.with_changes(changes)).await;
+
+    users
+        .from_id(user_id)
+        .await
+        .map_err(database_error)?
+        .ok_or_else(|| refuse(Status::NotFound, "User not found"))
+}
+
+async fn register(
+    database: &Database,
+    metrics: &ApiMetrics,
+    identity: &OidcIdentity,
+) -> Result<User, CallbackResponse> {
+    let auth = Authentication::Oidc(identity.clone());
+    match auth
+        .register(Timestamp::now(), UserId::generate(), database.user_manager.as_ref())
+        .await
+    {
+        Ok(Some(user)) => {
+            metrics.registration(&auth);
+            Ok(user)
+        }
+        // Another sign in registered the account meanwhile
+        Ok(None) => Err(refuse(Status::Conflict, "The account is linked to another user.")),
+        Err(err) => {
+            tracing::error!("Cannot register the user of the provider: {err}");
+            Err(refuse(Status::InternalServerError, "A database error occured."))
+        }
+    }
+}
+
+fn database_error(err: mongodb::error::Error) -> CallbackResponse {
+    tracing::error!("Cannot sign the user of the provider in: {err}");
+    refuse(Status::InternalServerError, "A database error occured.")
+}
+
+fn refuse(status: Status, message: &str) -> CallbackResponse {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        authentication::{Authentication, Credentials, OidcAccount},
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        oidc::mock_issuer::{sign_in, MockIssuer, MockUser, PROVIDER},
+        testing::{self, dispatch_request, run_test_with_settings},
+        RequestError,
+    };
+
+    fn credentials(email: &str) -> Authentication {
+        Authentication::Credentials(Credentials {
+            email: email.to_string(),
+            username: None,
+            avatar: None,
+            password: "password".to_string(),
+        })
+    }
+
+    fn account(subject: &str) -> OidcAccount {
+        OidcAccount {
+            provider: PROVIDER.to_string(),
+            subject: subject.to_string(),
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_oidc_sign_up() {
+        let issuer = MockIssuer::start().await;
+        let issuer = &issuer;
+        run_test_with_settings(
+            |settings| issuer.configure(settings),
+            |client| async move {
+                let database = client.rocket().state::<Database>().unwrap();
+
+                let response = sign_in(&client, issuer, None, MockUser::new("alice", "alice@example.com")).await;
+                assert_eq!(response.status(), Status::Ok);
+                let token = response.into_string().await.unwrap();
+                let user = database.user_manager.from_token(&token).await.unwrap().unwrap();
+                assert!(matches!(user.authentication, Authentication::Oidc(_)));
+                assert_eq!(user.email(), Some("alice@example.com"));
+                assert!(user.email_verified);
+                assert!(user.identity(&account("alice")).is_some());
+
+                // The account signs the same user in afterwards
+                let response = sign_in(&client, issuer, None, MockUser::new("alice", "alice@example.com")).await;
+                let token = response.into_string().await.unwrap();
+                let same_user = database.user_manager.from_token(&token).await.unwrap().unwrap();
+                assert_eq!(same_user.unique_id, user.unique_id);
+
+                // The emails the provider did not verify are not kept
+                let mut unverified = MockUser::new("bob", "alice@example.com");
+                unverified.email_verified = false;
+                let response = sign_in(&client, issuer, None, unverified).await;
+                let token = response.into_string().await.unwrap();
+                let other_user = database.user_manager.from_token(&token).await.unwrap().unwrap();
+                assert_ne!(other_user.unique_id, user.unique_id);
+                assert_eq!(other_user.email(), None);
+            },
+        )
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_oidc_link_by_email() {
+        let issuer = MockIssuer::start().await;
+        let issuer = &issuer;
+        run_test_with_settings(
+            |settings| issuer.configure(settings),
+            |client| async move {
+                let database = client.rocket().state::<Database>().unwrap();
+                let user = testing::create_user(database, credentials("carol@example.com"), Vec::new()).await;
+
+                let response = sign_in(&client, issuer, None, MockUser::new("carol", "carol@example.com")).await;
+                assert_eq!(response.status(), Status::Ok);
+                let token = response.into_string().await.unwrap();
+                let signed_in = database.user_manager.from_token(&token).await.unwrap().unwrap();
+                assert_eq!(signed_in.unique_id, user.unique_id);
+                assert!(matches!(signed_in.authentication, Authentication::Credentials(_)));
+                assert_eq!(signed_in.identities.len(), 1);
+
+                // The owner of an unverified email may not be the owner of the account
+                let user = testing::create_user(database, credentials("dave@example.com"), Vec::new()).await;
+                database
+                    .user_manager
+                    .update_auth(user.unique_id, &credentials("dave@example.org"))
+                    .await
+                    .unwrap();
+                database
+                    .user_manager
+                    .update_auth(user.unique_id, &credentials("dave@example.com"))
+                    .await
+                    .unwrap();
+                let response = sign_in(&client, issuer, None, MockUser::new("dave", "dave@example.com")).await;
+                assert_eq!(response.status(), Status::Conflict);
+                let user = database.user_manager.from_id(user.unique_id).await.unwrap().unwrap();
+                assert!(user.identities.is_empty());
+            },
+        )
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_oidc_link_with_token() {
+        let issuer = MockIssuer::start().await;
+        let issuer = &issuer;
+        run_test_with_settings(
+            |settings| issuer.configure(settings),
+            |client| async move {
+                let database = client.rocket().state::<Database>().unwrap();
+                let user = testing::create_user(database, credentials("erin@example.com"), Vec::new()).await;
+                let token = user.get_token().unwrap();
+
+                // A user may link several accounts, whatever their email
+                for subject in ["erin-work", "erin-home"] {
+                    let email = format!("{subject}@example.net");
+                    let response = sign_in(&client, issuer, Some(token), MockUser::new(subject, &email)).await;
+                    assert_eq!(response.status(), Status::Ok);
+                }
+                let linked = database.user_manager.from_id(user.unique_id).await.unwrap().unwrap();
+                assert_eq!(linked.identities.len(), 2);
+                let response = sign_in(
+                    &client,
+                    issuer,
+                    None,
+                    MockUser::new("erin-home", "erin-home@example.net"),
+                )
+                .await;
+                let token = response.into_string().await.unwrap();
+                assert_eq!(
+                    database
+                        .user_manager
+                        .from_token(&token)
+                        .await
+                        .unwrap()
+                        .unwrap()
+                        .unique_id,
+                    user.unique_id
+                );
+
+                let other = testing::create_user(database, credentials("frank@example.com"), Vec::new()).await;
+                let response = sign_in(
+                    &client,
+                    issuer,
+                    other.get_token().map(String::as_str),
+                    MockUser::new("erin-work", "erin-work@example.net"),
+                )
+                .await;
+                assert_eq!(response.status(), Status::Conflict);
+                let error: RequestError = response.into_json().await.unwrap();
+                assert_eq!(error.message, "The account is linked to another user.");
+            },
+        )
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_oidc_callback_refused() {
+        let issuer = MockIssuer::start().await;
+        let issuer = &issuer;
+        run_test_with_settings(
+            |settings| issuer.configure(settings),
+            |client| async move {
+                let response = dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/oidc/authorize/mock".to_string(),
+                    None,
+                    None,
+                )
+                .await;
+                let authorization: crate::model::oidc::OidcAuthorization = response.into_json().await.unwrap();
+                let query = issuer.authenticate(
+                    &authorization.authorization_url,
+                    MockUser::new("grace", "grace@example.com"),
+                );
+                let state = query.split("state=").nth(1).unwrap();
+
+                // The code must come with the verifier of the sign in
+                let forged = format!("/user/oidc/callback?code=forged&state={state}");
+                let response = dispatch_request(&client, Method::Get, forged, None, None).await;
+                assert_eq!(response.status(), Status::Unauthorized);
+                let error: RequestError = response.into_json().await.unwrap();
+                assert_eq!(error.message, "The provider refused the sign in: The code is invalid.");
+
+                // The sign in was used by the failed attempt
+                let callback = format!("/user/oidc/callback?{query}");
+                let response = dispatch_request(&client, Method::Get, callback, None, None).await;
+                assert_eq!(response.status(), Status::Unauthorized);
+                let error: RequestError = response.into_json().await.unwrap();
+                assert_eq!(error.message, "The sign in is unknown or has expired.");
+
+                let response = dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/oidc/authorize/mock".to_string(),
+                    None,
+                    None,
+                )
+                .await;
+                let authorization: crate::model::oidc::OidcAuthorization = response.into_json().await.unwrap();
+                let query = issuer.authenticate(
+                    &authorization.authorization_url,
+                    MockUser::new("grace", "grace@example.com"),
+                );
+                let state = query.split("state=").nth(1).unwrap();
+                let denied = format!("/user/oidc/callback?state={state}&error=access_denied");
+                let response = dispatch_request(&client, Method::Get, denied, None, None).await;
+                assert_eq!(response.status(), Status::Unauthorized);
+                let error: RequestError = response.into_json().await.unwrap();
+                assert_eq!(error.message, "The provider refused the sign in: access_denied");
+            },
+        )
+        .await;
+    }
+}
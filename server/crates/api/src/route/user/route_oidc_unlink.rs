@@ -0,0 +1,203 @@
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    authentication::{Authentication, OidcAccount},
+    Database,
+};
+use rocket::{delete, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::{audit::AuditContext, model::user_token::{UserData, ACCESS_TOKEN_REFUSED}, RequestError};
+
+/// Unlink an OpenID Connect account from the user of the token
+///
+/// The last account of a user without credentials is kept, the user could no longer sign in without it
+#[openapi(tag = "Users")]
+#[delete("/oidc/accounts/<provider>/<subject>")]
+pub async fn oidc_unlink(
+    provider: &str,
+    subject: &str,
+    user_data: UserData,
+    database: &State<Database>,
+    audit: AuditContext,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return refuse(Status::Unauthorized, "Token is invalid");
+    };
+    if user_data.is_access_token() {
+        return refuse(Status::Forbidden, ACCESS_TOKEN_REFUSED);
+    }
+    let user = match database.user_manager.from_id(user_id).await {
+        Ok(Some(user)) => user,
+        _ => return refuse(Status::NotFound, "User not found"),
+    };
+
+    let account = OidcAccount {
+        provider: provider.to_string(),
+        subject: subject.to_string(),
+    };
+    if user.identity(&account).is_none() {
+        return refuse(Status::NotFound, "The account is not linked to the user.");
+    }
+    let has_credentials = matches!(user.authentication, Authentication::Credentials(_));
+    if !has_credentials && user.identities.len() == 1 {
+        return refuse(
+            Status::Conflict,
+            "The last account of a user without credentials cannot be unlinked.",
+        );
+    }
+
+    match database.user_manager.unlink_identity(user_id, &account).await {
+        Ok(unlinked) => {
+            if unlinked {
+                let changes = AuditChanges {
+                    before: Some(json!({ "identity": account })),
+                    after: None,
+                };
+                let event = audit.event(
+                    AuditAction::IdentityUnlinked,
+                    Some(user_id),
+                    AuditTargetKind::User,
+                    user_id,
+                );
+                audit.record(database, event.with_changes(changes)).await;
+            }
+            Custom(Status::Ok, Ok(Json(unlinked)))
+        }
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot unlink the account: {err}");
+            refuse(Status::InternalServerError, "A database error occured.")
+        }
+    }
+}
+
+fn refuse(status: Status, message: &str) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        audit::AuditAction,
+        authentication::{Authentication, Credentials, OidcAccount, OidcIdentity},
+        id::OrganizationId,
+        pagination::PageRequest,
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        testing::{self, dispatch_request, run_test},
+        RequestError,
+    };
+
+    fn identity(subject: &str) -> OidcIdentity {
+        OidcIdentity {
+            account: OidcAccount {
+                provider: "mock".to_string(),
+                subject: subject.to_string(),
+            },
+            email: None,
+            username: None,
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_oidc_unlink() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::create_user(database, Authentication::Oidc(identity("first")), Vec::new()).await;
+            let token = user.get_token().cloned();
+
+            // The only account of the user is kept
+            let response = dispatch_request(
+                &client,
+                Method::Delete,
+                "/user/oidc/accounts/mock/first".to_string(),
+                None,
+                token.clone(),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Conflict);
+
+            database
+                .user_manager
+                .link_identity(user.unique_id, &identity("second"))
+                .await
+                .unwrap();
+            let response = dispatch_request(
+                &client,
+                Method::Delete,
+                "/user/oidc/accounts/mock/first".to_string(),
+                None,
+                token.clone(),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Ok);
+            let user = database.user_manager.from_id(user.unique_id).await.unwrap().unwrap();
+            assert_eq!(user.identities, vec![identity("second")]);
+            let events = database
+                .audit_manager
+                .get_events(OrganizationId::generate(), &[user.unique_id], &PageRequest::default())
+                .await
+                .unwrap();
+            assert!(events
+                .items
+                .iter()
+                .any(|event| event.action == AuditAction::IdentityUnlinked));
+
+            let response = dispatch_request(
+                &client,
+                Method::Delete,
+                "/user/oidc/accounts/mock/first".to_string(),
+                None,
+                token,
+            )
+            .await;
+            assert_eq!(response.status(), Status::NotFound);
+            let error: RequestError = response.into_json().await.unwrap();
+            assert_eq!(error.message, "The account is not linked to the user.");
+
+            // The users with credentials can unlink all their accounts
+            let credentials = Credentials {
+                email: "heidi@example.com".to_string(),
+                username: None,
+                avatar: None,
+                password: "password".to_string(),
+            };
+            let user = testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await;
+            database
+                .user_manager
+                .link_identity(user.unique_id, &identity("third"))
+                .await
+                .unwrap();
+            let response = dispatch_request(
+                &client,
+                Method::Delete,
+                "/user/oidc/accounts/mock/third".to_string(),
+                None,
+                user.get_token().cloned(),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Ok);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn access_token_test_oidc_unlink() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+
+            let response = dispatch_request(&client, Method::Delete, "/user/oidc/accounts/mock/subject".to_string(), None, Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+        })
+        .await;
+    }
+}
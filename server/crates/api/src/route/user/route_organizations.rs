@@ -1,44 +1,47 @@
-use database::{Database, organization::Organization};
+use database::{Database, id::UserId, organization::Organization, pagination::Page};
 use rocket::{http::Status, get, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
 use crate::{
-    model::user_token::UserData,
+    model::{page_query::PageQuery, user_token::UserData},
     RequestError,
 };
 
+/// List the organizations the user owns or is a member of
+///
+/// Can be sorted on `name` and `creation_date`, and filtered on `name` and `owner_id`
+/// or on the creation date with `created_after` and `created_before`
 #[openapi(tag = "Users")]
-#[get("/id/<user_id>/organizations")]
+#[get("/id/<user_id>/organizations?<page..>")]
 pub async fn get_organizations(
     _user_data: UserData,
     database: &State<Database>,
-    user_id: String,
-) -> Custom<Result<Json<Vec<Organization>>, Json<RequestError>>> {
+    user_id: UserId,
+    page: PageQuery,
+) -> Custom<Result<Json<Page<Organization>>, Json<RequestError>>> {
 
     match database
         .organization_manager
-        .get_organizations_from_user(&user_id)
+        .get_organizations_from_user(user_id, &page.into())
         .await
     {
         Ok(result) => Custom(Status::Ok, Ok(Json(result))),
-        Err(_) => Custom(
-            Status::Ok,
-            Err(RequestError::from(Custom(
-                Status::InternalServerError,
-                format!("A database error occurred."),
-            ))
-            .into()),
-        ),
+        Err(err) => Custom(Status::Ok, Err(RequestError::from(err).into())),
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use database::{Database, organization::Organization};
+    use std::time::Duration;
+
+    use database::{Database, organization::Organization, pagination::Page, timestamp::Timestamp};
     use rocket::http::{Method, Status};
 
-    use crate::testing::{self, dispatch_request, run_test};
+    use crate::{
+        testing::{self, dispatch_request, run_test},
+        RequestError,
+    };
 
     #[rocket::async_test]
     async fn test_get_organizations() {
@@ -58,8 +61,124 @@ mod tests {
             .await;
 
             assert_eq!(response.status(), Status::Ok);
-            let organizations = response.into_json::<Vec<Organization>>().await.unwrap();
-            assert_eq!(organizations.len(), 0);
+            let organizations = response.into_json::<Page<Organization>>().await.unwrap();
+            assert_eq!(organizations.items.len(), 0);
+            assert!(organizations.next_cursor.is_none());
+        }).await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_organizations_pages() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let request_token = test_user.get_token().unwrap();
+            for _ in 0..3 {
+                testing::get_org(database, &test_user).await;
+            }
+
+            let mut organizations = Vec::new();
+            let mut cursor: Option<String> = None;
+            loop {
+                let query = match &cursor {
+                    Some(cursor) => format!("limit=2&sort=-name&cursor={cursor}"),
+                    None => "limit=2&sort=-name".to_string(),
+                };
+                let response = dispatch_request(
+                    &client,
+                    Method::Get,
+                    format!("/user/id/{}/organizations?{query}", test_user.unique_id),
+                    None,
+                    Some(request_token.to_string()),
+                )
+                .await;
+
+                assert_eq!(response.status(), Status::Ok);
+                let page = response.into_json::<Page<Organization>>().await.unwrap();
+                assert!(page.items.len() <= 2);
+                organizations.extend(page.items);
+                cursor = page.next_cursor;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+
+            assert_eq!(organizations.len(), 3);
+            assert!(organizations.windows(2).all(|pair| pair[0].name >= pair[1].name));
+        }).await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_organizations_filter() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let request_token = test_user.get_token().unwrap();
+            let organization = testing::get_org(database, &test_user).await;
+            testing::get_org(database, &test_user).await;
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/user/id/{}/organizations?filter.name={}", test_user.unique_id, organization.name),
+                None,
+                Some(request_token.to_string()),
+            )
+            .await;
+
+            let page = response.into_json::<Page<Organization>>().await.unwrap();
+            assert_eq!(page.items.len(), 1);
+            assert_eq!(page.items[0].unique_id, organization.unique_id);
+        }).await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_organizations_created_between() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let request_token = test_user.get_token().unwrap();
+            let older = testing::get_org(database, &test_user).await;
+            rocket::tokio::time::sleep(Duration::from_millis(5)).await;
+            let middle = Timestamp::now();
+            rocket::tokio::time::sleep(Duration::from_millis(5)).await;
+            let newer = testing::get_org(database, &test_user).await;
+
+            for (bound, expected) in [("created_after", &newer), ("created_before", &older)] {
+                let response = dispatch_request(
+                    &client,
+                    Method::Get,
+                    format!("/user/id/{}/organizations?{bound}={middle}", test_user.unique_id),
+                    None,
+                    Some(request_token.to_string()),
+                )
+                .await;
+
+                let page = response.into_json::<Page<Organization>>().await.unwrap();
+                assert_eq!(page.items.len(), 1);
+                assert_eq!(page.items[0].unique_id, expected.unique_id);
+            }
+        }).await;
+    }
+
+    #[rocket::async_test]
+    async fn test_get_organizations_invalid_sort() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = testing::get_user(database).await;
+            let request_token = test_user.get_token().unwrap();
+
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                format!("/user/id/{}/organizations?sort=member_ids", test_user.unique_id),
+                None,
+                Some(request_token.to_string()),
+            )
+            .await;
+
+            let request_error = response.into_json::<RequestError>().await.unwrap();
+            assert_eq!(request_error.code, 400);
         }).await;
     }
 }
\ No newline at end of file
@@ -1,25 +1,55 @@
+use std::time::Duration;
+
 use database::{
-    authentication::Authentication, managers::UserManager, user::User, Database,
+    audit::{AuditAction, AuditTargetKind},
+    authentication::Authentication,
+    id::UserId,
+    timestamp::Timestamp,
+    user::User,
+    Database,
 };
 use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
 use crate::{
-    model::{api_socket_addr::ApiSocketAddr, login::Login, user_token::UserData},
-    RequestError, Server,
+    api_telemetry::ApiMetrics,
+    audit::AuditContext,
+    mail::{LinkError, LinkSigner, Mailer},
+    model::{
+        api_socket_addr::ApiSocketAddr,
+        login::{Login, TwoFactorLogin},
+        user_token::{UserData, ACCESS_TOKEN_REFUSED},
+    },
+    rate_limit::AccountLockout,
+    settings::{ApiSettings, TwoFactorSettings},
+    two_factor::{self, TWO_FACTOR_PURPOSE},
+    RequestError,
 };
 
-/// Renew an user token with either the user credentials, or with the serverid
+type RenewResponse = Custom<Result<String, Json<RequestError>>>;
+
+/// Renew an user token with either the user credentials, or with the user id
+///
+/// The user id only renews the token of the user sending it, with their current token
+///
+/// When the user enabled a second factor, the credentials are answered with `202 Accepted` and a challenge instead
+/// of a token. The token is then sent for the challenge and a code of the authenticator app or a recovery code
 ///
-/// To regenerate a server's token, you have to be part of the Website group
+/// The accounts are locked for a while after repeated failed logins, the response then carries a `Retry-After` header
 #[openapi(tag = "Users")]
 #[post("/renew", data = "<login>", format = "application/json")] // <- route attribute
+#[allow(clippy::too_many_arguments)]
 pub async fn renew(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
+    metrics: &State<ApiMetrics>,
+    mailer: &State<Mailer>,
+    settings: &State<ApiSettings>,
     login: Option<Json<Login>>,
     remot_addr: ApiSocketAddr,
-) -> Custom<Result<String, Json<RequestError>>> {
+    audit: AuditContext,
+    lockout: AccountLockout<'_>,
+) -> RenewResponse {
     let ip = remot_addr.0.ip().to_string();
 
     if login.is_none() {
@@ -35,35 +65,163 @@ pub async fn renew(
 
     match login.unwrap().0 {
         Login::Credentials(credentials) => {
+            let email = credentials.email.clone();
+            if lockout.check(&email).await.is_err() {
+                return locked();
+            }
+
             let auth = Authentication::Credentials(credentials);
-            let user = auth.get(&database.user_manager.users).await;
+            let user = auth.get(database.user_manager.as_ref()).await;
+            match user {
+                Ok(Some(_)) => lockout.success(&email).await,
+                Ok(None) => lockout.failure(&email).await,
+                Err(_) => {}
+            }
 
-            renew_token(user, ip, auth, &database.user_manager).await
+            match user {
+                Ok(Some(user)) => {
+                    login_user(user, ip, auth, database, metrics, &audit, mailer, &settings.two_factor).await
+                }
+                // The user logs in by themselves
+                user => renew_token(user, ip, auth, None, database, metrics, &audit).await,
+            }
         }
+        Login::TwoFactor(login) => answer_challenge(login, ip, database, metrics, &audit, mailer, &lockout).await,
         Login::UserId(user_id) => {
-            let user = database.user_manager.from_id(&user_id).await;
+            // The id alone proves nothing, the second factor would be skipped for any user
+            let Some(caller_id) = user_data.id else {
+                return refuse(Status::Forbidden, "A valid token is required to renew it.");
+            };
+            if user_data.is_access_token() {
+                return refuse(Status::Forbidden, ACCESS_TOKEN_REFUSED);
+            }
+            if caller_id != user_id {
+                return refuse(Status::Unauthorized, "Only the user can renew their token.");
+            }
+            let user = database.user_manager.from_id(user_id).await;
             renew_token(
                 user.map_err(|err| err.to_string()),
                 ip,
                 Authentication::None,
-                &database.user_manager,
+                user_data.id,
+                database,
+                metrics,
+                &audit,
             )
             .await
         }
     }
 }
 
-async fn renew_token(
+/// Answers a challenge instead of a token when the user enabled a second factor
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn login_user(
+    user: User,
+    ip: String,
+    auth: Authentication,
+    database: &Database,
+    metrics: &ApiMetrics,
+    audit: &AuditContext,
+    mailer: &Mailer,
+    settings: &TwoFactorSettings,
+) -> RenewResponse {
+    match two_factor::is_enabled(database.two_factor_manager.as_ref(), user.unique_id).await {
+        Ok(false) => renew_token(Ok(Some(user)), ip, auth, None, database, metrics, audit).await,
+        Ok(true) => {
+            let lifetime = Duration::from_secs(settings.challenge_minutes * 60);
+            let challenge = two_factor::challenge(mailer.signer(), &user, lifetime, Timestamp::now());
+            Custom(Status::Accepted, Ok(challenge))
+        }
+        Err(err) => renew_token(Err(err.to_string()), ip, auth, None, database, metrics, audit).await,
+    }
+}
+
+// answer_challenge logs the user in once the code matches the second factor, the failed codes lock the account
+// like the failed passwords do
+async fn answer_challenge(
+    login: TwoFactorLogin,
+    ip: String,
+    database: &Database,
+    metrics: &ApiMetrics,
+    audit: &AuditContext,
+    mailer: &Mailer,
+    lockout: &AccountLockout<'_>,
+) -> RenewResponse {
+    let invalid = || refuse(Status::Unauthorized, "The challenge is invalid or was already used.");
+    let Ok(challenge) = LinkSigner::parse(&login.challenge) else {
+        return invalid();
+    };
+    let Ok(user_id) = challenge.subject.parse::<UserId>() else {
+        return invalid();
+    };
+    let account = user_id.to_string();
+    if lockout.check(&account).await.is_err() {
+        return locked();
+    }
+
+    let user = match database.user_manager.from_id(user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return invalid(),
+        Err(err) => return renew_token(Err(err.to_string()), ip, Authentication::None, None, database, metrics, audit).await,
+    };
+    let binding = two_factor::challenge_binding(&user);
+    match mailer.signer().verify(TWO_FACTOR_PURPOSE, &challenge, binding, Timestamp::now()) {
+        Ok(()) => {}
+        Err(LinkError::Expired) => {
+            return refuse(Status::Unauthorized, "The challenge has expired, the credentials must be sent again.")
+        }
+        Err(_) => return invalid(),
+    }
+
+    let two_factors = database.two_factor_manager.as_ref();
+    let verified = match two_factors.get(user_id).await {
+        Ok(Some(second_factor)) if second_factor.enabled => {
+            two_factor::verify(two_factors, &second_factor, &login.code, Timestamp::now()).await
+        }
+        // The second factor was disabled meanwhile
+        Ok(_) => return invalid(),
+        Err(err) => Err(err),
+    };
+    match verified {
+        Ok(true) => lockout.success(&account).await,
+        Ok(false) => {
+            lockout.failure(&account).await;
+            return refuse(Status::Unauthorized, "The code is invalid.");
+        }
+        Err(err) => return renew_token(Err(err.to_string()), ip, Authentication::None, None, database, metrics, audit).await,
+    }
+
+    let auth = user.authentication.clone();
+    renew_token(Ok(Some(user)), ip, auth, None, database, metrics, audit).await
+}
+
+fn refuse(status: Status, message: &str) -> RenewResponse {
+    Custom(status, Err(RequestError::from(Custom(status, message.to_string())).into()))
+}
+
+fn locked() -> RenewResponse {
+    refuse(Status::TooManyRequests, "Too many failed logins, the account is locked for a while.")
+}
+
+pub(crate) async fn renew_token(
     user: Result<Option<User>, String>,
     ip: String,
     auth: Authentication,
-    usermanager: &UserManager,
-) -> Custom<Result<String, Json<RequestError>>> {
+    actor_id: Option<UserId>,
+    database: &Database,
+    metrics: &ApiMetrics,
+    audit: &AuditContext,
+) -> RenewResponse {
     match user {
-        Ok(user) if user.is_some() => {
-            let login = database::login::Login::new(ip, Server::current_time(), auth);
+        Ok(Some(user)) => {
+            metrics.login(&auth);
+            let login = database::login::Login::new(ip, Timestamp::now(), auth);
+
+            user.upload_token(&login, database.user_manager.as_ref()).await;
 
-            user.unwrap().upload_token(&login, &usermanager.users).await;
+            let actor_id = actor_id.or(Some(user.unique_id));
+            let event = audit.event(AuditAction::Login, actor_id, AuditTargetKind::User, user.unique_id);
+            audit.record(database, event).await;
 
             Custom(Status::Ok, Ok(login.token.0))
         }
@@ -91,14 +249,17 @@ mod tests {
 
     use database::{
         authentication::{Authentication, Credentials},
+        id::UserId,
+        timestamp::Timestamp,
         Database,
     };
     use rocket::http::{Method, Status};
 
     use crate::{
-        model::login::Login,
-        testing::{self, dispatch_request, run_test},
-        RequestError,
+        model::login::{Login, TwoFactorLogin},
+        settings::{ApiSettings, RouteLimit},
+        testing::{self, dispatch_request, run_test, run_test_with_settings},
+        two_factor, RequestError,
     };
 
     #[rocket::async_test]
@@ -106,19 +267,18 @@ mod tests {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let server_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let request_token = server_user.get_token().unwrap();
 
             println!(
                 "body = {:?}",
-                serde_json::to_string(&Login::UserId(server_user.unique_id.clone()))
+                serde_json::to_string(&Login::UserId(server_user.unique_id))
             );
 
             let response = dispatch_request(
                 &client,
                 Method::Post,
                 "/user/renew".to_string(),
-                Some(serde_json::to_string(&Login::UserId(server_user.unique_id.clone())).unwrap()),
+                Some(serde_json::to_string(&Login::UserId(server_user.unique_id)).unwrap()),
                 Some(request_token.to_string()),
             )
             .await;
@@ -182,13 +342,90 @@ mod tests {
         .await;
     }
 
+    #[rocket::async_test]
+    async fn test_renew_two_factor() {
+        let configure = |settings: &mut ApiSettings| settings.rate_limits.authentication.burst = 100;
+        run_test_with_settings(configure, |client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let credentials = Credentials {
+                email: "second@test.fr".to_string(),
+                username: Option::Some("test".to_string()),
+                avatar: Option::Some("test".to_string()),
+                password: "test".to_string(),
+            };
+            let test_user = testing::create_user(
+                database,
+                Authentication::Credentials(credentials.clone()),
+                Vec::new(),
+            )
+            .await;
+            let (secret, recovery_code) = testing::enable_two_factor(database, &test_user).await;
+            let renew = |login: Login| {
+                dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/renew".to_string(),
+                    Some(serde_json::to_string(&login).unwrap()),
+                    None,
+                )
+            };
+
+            // The credentials are answered with a challenge
+            let response = renew(Login::Credentials(credentials.clone())).await;
+            assert_eq!(response.status(), Status::Accepted);
+            let challenge = response.into_string().await.unwrap();
+            assert!(database.user_manager.from_token(&challenge).await.unwrap().is_none());
+
+            let response = renew(Login::TwoFactor(TwoFactorLogin {
+                challenge: challenge.clone(),
+                code: "wrong".to_string(),
+            }))
+            .await;
+            assert_eq!(response.status(), Status::Unauthorized);
+
+            let code = two_factor::code(&secret, Timestamp::now()).unwrap();
+            let response = renew(Login::TwoFactor(TwoFactorLogin {
+                challenge: challenge.clone(),
+                code,
+            }))
+            .await;
+            assert_eq!(response.status(), Status::Ok);
+            let token = response.into_string().await.unwrap();
+            let user = database.user_manager.from_token(&token).await.unwrap().unwrap();
+            assert_eq!(user.unique_id, test_user.unique_id);
+
+            // A challenge is answered once
+            let response = renew(Login::TwoFactor(TwoFactorLogin {
+                challenge,
+                code: recovery_code.clone(),
+            }))
+            .await;
+            assert_eq!(response.status(), Status::Unauthorized);
+            let request_error = response.into_json::<RequestError>().await.unwrap();
+            assert_eq!(request_error.message, "The challenge is invalid or was already used.");
+
+            // A recovery code replaces the code of the app once
+            for status in [Status::Ok, Status::Unauthorized] {
+                let response = renew(Login::Credentials(credentials.clone())).await;
+                let challenge = response.into_string().await.unwrap();
+                let response = renew(Login::TwoFactor(TwoFactorLogin {
+                    challenge,
+                    code: recovery_code.to_uppercase(),
+                }))
+                .await;
+                assert_eq!(response.status(), status);
+            }
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn test_unknown_admin_renew() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
             let request_user = testing::get_user(database).await;
             let request_token = request_user.get_token().unwrap();
-            let id = "NO_ID".to_string();
+            let id = UserId::generate();
 
             let response = dispatch_request(
                 &client,
@@ -199,10 +436,10 @@ mod tests {
             )
             .await;
 
-            assert_eq!(response.status(), Status::Ok);
+            // The unknown ids are not told apart from the ids of the other users
+            assert_eq!(response.status(), Status::Unauthorized);
             let request_error = response.into_json::<RequestError>().await.unwrap();
-            assert_eq!(request_error.code, 404);
-            assert_eq!(request_error.message, format!("User could not be found."));
+            assert_eq!(request_error.message, "Only the user can renew their token.");
         })
         .await;
     }
@@ -250,7 +487,7 @@ mod tests {
                 &client,
                 Method::Post,
                 "/user/renew".to_string(),
-                Some(serde_json::to_string(&Login::UserId(server_user.unique_id.clone())).unwrap()),
+                Some(serde_json::to_string(&Login::UserId(server_user.unique_id)).unwrap()),
                 Some(request_token.to_string()),
             )
             .await;
@@ -261,7 +498,7 @@ mod tests {
             assert_eq!(
                 database
                     .user_manager
-                    .from_id(&server_user.unique_id)
+                    .from_id(server_user.unique_id)
                     .await
                     .unwrap()
                     .unwrap()
@@ -273,6 +510,34 @@ mod tests {
         .await;
     }
 
+    #[rocket::async_test]
+    async fn unauthorized_test_renew_two_factor() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let victim = testing::get_user(database).await;
+            testing::enable_two_factor(database, &victim).await;
+            let attacker = testing::get_user(database).await;
+            let renew = |token: String| {
+                dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/renew".to_string(),
+                    Some(serde_json::to_string(&Login::UserId(victim.unique_id)).unwrap()),
+                    Some(token),
+                )
+            };
+
+            // The id of another user does not skip their second factor
+            let response = renew(attacker.get_token().unwrap().to_string()).await;
+            assert_eq!(response.status(), Status::Unauthorized);
+            let response = renew(testing::create_access_token(database, &victim, &["profile.edit"]).await).await;
+            assert_eq!(response.status(), Status::Forbidden);
+            let user = database.user_manager.from_id(victim.unique_id).await.unwrap().unwrap();
+            assert_eq!(user.logins.len(), victim.logins.len());
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn forbidden_test_renew_admin() {
         run_test(|client| async move {
@@ -283,7 +548,7 @@ mod tests {
                 &client,
                 Method::Post,
                 "/user/renew".to_string(),
-                Some(serde_json::to_string(&Login::UserId(server_user.unique_id.clone())).unwrap()),
+                Some(serde_json::to_string(&Login::UserId(server_user.unique_id)).unwrap()),
                 None,
             )
             .await;
@@ -294,7 +559,7 @@ mod tests {
             assert_eq!(
                 database
                     .user_manager
-                    .from_id(&server_user.unique_id)
+                    .from_id(server_user.unique_id)
                     .await
                     .unwrap()
                     .unwrap()
@@ -305,4 +570,95 @@ mod tests {
         })
         .await;
     }
+
+    #[rocket::async_test]
+    async fn test_renew_lockout() {
+        let configure = |settings: &mut ApiSettings| {
+            settings.rate_limits.lockout.max_failures = 2;
+            settings.rate_limits.lockout.lockout_secs = 60;
+        };
+        run_test_with_settings(configure, |client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let credentials = Credentials {
+                email: "locked@test.fr".to_string(),
+                username: Option::Some("test".to_string()),
+                avatar: Option::Some("test".to_string()),
+                password: "test".to_string(),
+            };
+            testing::create_user(database, Authentication::Credentials(credentials.clone()), Vec::new()).await;
+            let wrong_credentials = Credentials {
+                password: "wrong".to_string(),
+                ..credentials.clone()
+            };
+
+            for _ in 0..2 {
+                let response = dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/renew".to_string(),
+                    Some(serde_json::to_string(&Login::Credentials(wrong_credentials.clone())).unwrap()),
+                    None,
+                )
+                .await;
+                let request_error = response.into_json::<RequestError>().await.unwrap();
+                assert_eq!(request_error.code, 404);
+            }
+
+            // Even the right password is refused while the account is locked, whatever the case of the email
+            let locked_credentials = Credentials {
+                email: "Locked@Test.fr".to_string(),
+                ..credentials
+            };
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/user/renew".to_string(),
+                Some(serde_json::to_string(&Login::Credentials(locked_credentials)).unwrap()),
+                None,
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::TooManyRequests);
+            let retry_after: u64 = response.headers().get_one("Retry-After").unwrap().parse().unwrap();
+            assert!(retry_after > 0 && retry_after <= 60);
+            let request_error = response.into_json::<RequestError>().await.unwrap();
+            assert_eq!(request_error.code, 429);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_renew_rate_limit() {
+        let configure = |settings: &mut ApiSettings| {
+            settings.rate_limits.authentication = RouteLimit {
+                requests_per_minute: 1,
+                burst: 1,
+            };
+        };
+        run_test_with_settings(configure, |client| async move {
+            let login = Login::UserId(UserId::generate());
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/user/renew".to_string(),
+                Some(serde_json::to_string(&login).unwrap()),
+                None,
+            )
+            .await;
+            // The request passes the limiter, and is refused without a token
+            assert_eq!(response.status(), Status::Forbidden);
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/user/renew".to_string(),
+                Some(serde_json::to_string(&login).unwrap()),
+                None,
+            )
+            .await;
+            assert_eq!(response.status(), Status::TooManyRequests);
+            assert_eq!(response.headers().get_one("Retry-After"), Some("60"));
+        })
+        .await;
+    }
 }
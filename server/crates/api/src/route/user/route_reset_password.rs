@@ -0,0 +1,169 @@
+use database::{
+    audit::{AuditAction, AuditTargetKind},
+    timestamp::Timestamp,
+    Database,
+};
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use super::hash_token;
+use crate::{
+    audit::AuditContext, model::password_reset::PasswordResetInit, rate_limit::AccountLockout, RequestError,
+};
+
+fn error(status: Status, message: &str) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(status, Err(RequestError::from(Custom(status, message.to_string())).into()))
+}
+
+/// Reset the password with the token sent by mail
+///
+/// The token is used once, and every token of the user is revoked, the personal access tokens included: the user has
+/// to log in again everywhere
+#[openapi(tag = "Users")]
+#[post("/reset_password", data = "<reset>", format = "application/json")]
+pub async fn reset_password(
+    database: &State<Database>,
+    reset: Json<PasswordResetInit>,
+    lockout: AccountLockout<'_>,
+    audit: AuditContext,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    const INVALID: &str = "The reset token is invalid or has expired.";
+
+    if reset.password.is_empty() {
+        return error(Status::BadRequest, "The password must not be empty.");
+    }
+    let pending = match database
+        .password_reset_manager
+        .consume(&hash_token(&reset.token), Timestamp::now())
+        .await
+    {
+        Ok(Some(pending)) => pending,
+        Ok(None) => return error(Status::BadRequest, INVALID),
+        Err(_) => return error(Status::InternalServerError, "A database error occured."),
+    };
+    let user = match database.user_manager.from_id(pending.user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return error(Status::BadRequest, INVALID),
+        Err(_) => return error(Status::InternalServerError, "A database error occured."),
+    };
+
+    // The access tokens are revoked first, a failure leaves the old password rather than live tokens
+    if let Err(err) = database.access_token_manager.delete_for_user(user.unique_id).await {
+        tracing::error!(user_id = %user.unique_id, "Cannot revoke the access tokens: {err}");
+        return error(Status::InternalServerError, "A database error occured.");
+    }
+    match database.user_manager.reset_password(user.unique_id, &reset.password).await {
+        Ok(true) => {}
+        Ok(false) => return error(Status::BadRequest, INVALID),
+        Err(_) => return error(Status::InternalServerError, "A database error occured."),
+    }
+    // The other tokens sent before are no longer needed
+    if let Err(err) = database.password_reset_manager.delete_for_user(user.unique_id).await {
+        tracing::warn!(user_id = %user.unique_id, "Cannot remove the pending password resets: {err}");
+    }
+    if let Some(email) = user.email() {
+        lockout.success(email).await;
+    }
+
+    let event = audit.event(AuditAction::PasswordReset, Some(user.unique_id), AuditTargetKind::User, user.unique_id);
+    audit.record(database, event).await;
+
+    Custom(Status::Ok, Ok(Json(true)))
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        authentication::{Authentication, Credentials},
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        model::{
+            login::Login,
+            password_reset::{ForgotPassword, PasswordResetInit},
+        },
+        testing::{self, dispatch_request, run_test},
+        RequestError,
+    };
+
+    #[rocket::async_test]
+    async fn test_reset_password() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let credentials = Credentials {
+                email: "reset@test.fr".to_string(),
+                username: Some("reset".to_string()),
+                avatar: None,
+                password: "old".to_string(),
+            };
+            let user =
+                testing::create_user(database, Authentication::Credentials(credentials.clone()), Vec::new()).await;
+            let old_token = user.get_token().unwrap().to_string();
+            let access_token = testing::create_access_token(database, &user, &[]).await;
+            let list_access_tokens = |token: String| {
+                dispatch_request(&client, Method::Get, "/user/access_tokens".to_string(), None, Some(token))
+            };
+            assert_eq!(list_access_tokens(access_token.clone()).await.status(), Status::Ok);
+
+            let body = serde_json::to_string(&ForgotPassword { email: credentials.email.clone() }).unwrap();
+            dispatch_request(&client, Method::Post, "/user/forgot_password".to_string(), Some(body), None).await;
+            let messages = database.outbox_manager.messages_to("reset@test.fr").await.unwrap();
+            let token = messages[0].text.split("token=").nth(1).unwrap().split_whitespace().next().unwrap();
+
+            let reset = PasswordResetInit {
+                token: token.to_string(),
+                password: "new".to_string(),
+            };
+            let body = serde_json::to_string(&reset).unwrap();
+            let response =
+                dispatch_request(&client, Method::Post, "/user/reset_password".to_string(), Some(body.clone()), None)
+                    .await;
+            assert_eq!(response.status(), Status::Ok);
+
+            // The sessions and the access tokens are revoked, and only the new password works
+            assert!(database.user_manager.from_token(&old_token).await.unwrap().is_none());
+            assert_eq!(list_access_tokens(access_token).await.status(), Status::Unauthorized);
+            let stored = database.user_manager.from_id(user.unique_id).await.unwrap().unwrap();
+            assert_ne!(stored.authentication.credentials().password, "new");
+            assert!(stored.authentication.credentials().verify_password("new"));
+            for (password, code) in [("old", Some(404)), ("new", None)] {
+                let login = Login::Credentials(Credentials {
+                    password: password.to_string(),
+                    ..credentials.clone()
+                });
+                let body = serde_json::to_string(&login).unwrap();
+                let response =
+                    dispatch_request(&client, Method::Post, "/user/renew".to_string(), Some(body), None).await;
+                match code {
+                    Some(code) => assert_eq!(response.into_json::<RequestError>().await.unwrap().code, code),
+                    None => assert!(response.into_json::<RequestError>().await.is_none()),
+                }
+            }
+
+            // The token is used once
+            let response =
+                dispatch_request(&client, Method::Post, "/user/reset_password".to_string(), Some(body), None).await;
+            assert_eq!(response.status(), Status::BadRequest);
+            let request_error = response.into_json::<RequestError>().await.unwrap();
+            assert_eq!(request_error.message, "The reset token is invalid or has expired.");
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_reset_password_unknown_token() {
+        run_test(|client| async move {
+            let reset = PasswordResetInit {
+                token: "unknown".to_string(),
+                password: "new".to_string(),
+            };
+            let body = serde_json::to_string(&reset).unwrap();
+            let response =
+                dispatch_request(&client, Method::Post, "/user/reset_password".to_string(), Some(body), None).await;
+            assert_eq!(response.status(), Status::BadRequest);
+        })
+        .await;
+    }
+}
@@ -0,0 +1,125 @@
+use database::{
+    audit::{AuditAction, AuditChanges, AuditTargetKind},
+    id::AccessTokenId,
+    Database,
+};
+use rocket::{delete, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+use serde_json::json;
+
+use crate::{audit::AuditContext, model::user_token::{UserData, ACCESS_TOKEN_REFUSED}, RequestError};
+
+/// Revoke a personal access token of the user of the token
+#[openapi(tag = "Users")]
+#[delete("/access_tokens/<id>")]
+pub async fn revoke_access_token(
+    id: AccessTokenId,
+    user_data: UserData,
+    database: &State<Database>,
+    audit: AuditContext,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return refuse(Status::Unauthorized, "Token is invalid");
+    };
+    if user_data.is_access_token() {
+        return refuse(Status::Forbidden, ACCESS_TOKEN_REFUSED);
+    }
+
+    match database.access_token_manager.revoke(user_id, id).await {
+        Ok(true) => {
+            let changes = AuditChanges {
+                before: Some(json!({ "access_token": id })),
+                after: None,
+            };
+            let event = audit.event(
+                AuditAction::AccessTokenRevoked,
+                Some(user_id),
+                AuditTargetKind::User,
+                user_id,
+            );
+            audit.record(database, event.with_changes(changes)).await;
+            Custom(Status::Ok, Ok(Json(true)))
+        }
+        Ok(false) => refuse(Status::NotFound, "The access token was not found."),
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot revoke the access token: {err}");
+            refuse(Status::InternalServerError, "A database error occured.")
+        }
+    }
+}
+
+fn refuse(status: Status, message: &str) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{authentication::Authentication, Database};
+    use rocket::http::{Method, Status};
+    use serde_json::json;
+
+    use crate::{
+        model::access_token::CreatedAccessToken,
+        testing::{self, dispatch_request, run_test},
+    };
+
+    #[rocket::async_test]
+    async fn test_revoke_access_token() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::create_user(database, Authentication::None, Vec::new()).await;
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/user/access_tokens".to_string(),
+                Some(json!({ "name": "ci", "scopes": [], "expires_in_days": 1 }).to_string()),
+                user.get_token().cloned(),
+            )
+            .await;
+            let created: CreatedAccessToken = response.into_json().await.unwrap();
+            let revoke = |token: Option<String>| {
+                dispatch_request(
+                    &client,
+                    Method::Delete,
+                    format!("/user/access_tokens/{}", created.access_token.unique_id),
+                    None,
+                    token,
+                )
+            };
+
+            // Only the owner revokes the token
+            let other = testing::get_user(database).await;
+            assert_eq!(revoke(other.get_token().cloned()).await.status(), Status::NotFound);
+
+            assert_eq!(revoke(user.get_token().cloned()).await.status(), Status::Ok);
+            // The token no longer authenticates the user
+            let response = dispatch_request(
+                &client,
+                Method::Get,
+                "/user/access_tokens".to_string(),
+                None,
+                Some(created.token),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Unauthorized);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn access_token_test_revoke_access_token() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+
+            let response = dispatch_request(&client, Method::Delete, format!("/user/access_tokens/{}", database::id::AccessTokenId::generate()), None, Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+        })
+        .await;
+    }
+}
@@ -0,0 +1,89 @@
+use database::{id::UserId, Database};
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use super::start_password_reset;
+use crate::{mail::Mailer, model::user_token::UserData, RequestError};
+
+fn error(status: Status, message: &str) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(status, Err(RequestError::from(Custom(status, message.to_string())).into()))
+}
+
+/// Send a link resetting the password to a user
+///
+/// Requires the 'profile.reset_password' permission
+#[openapi(tag = "Users")]
+#[post("/<user_id>/password_reset")]
+pub async fn send_password_reset(
+    user_data: UserData,
+    database: &State<Database>,
+    mailer: &State<Mailer>,
+    user_id: UserId,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    if user_data.id.is_none() {
+        return error(Status::Unauthorized, "Token is invalid");
+    }
+    let Ok(permission) = database.permission_manager.get_permission_id("profile.reset_password").await else {
+        return error(Status::InternalServerError, "Unknown permission");
+    };
+    if !user_data.has_permission(database, permission).await {
+        return error(Status::Forbidden, "You don't have the permission to reset passwords");
+    }
+
+    let user = match database.user_manager.from_id(user_id).await {
+        Ok(Some(user)) => user,
+        _ => return error(Status::NotFound, "User not found"),
+    };
+    if user.email().is_none() {
+        return error(Status::BadRequest, "The user has no password to reset.");
+    }
+    match start_password_reset(database, mailer, &user).await {
+        Ok(()) => Custom(Status::Ok, Ok(Json(true))),
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot start the password reset: {err}");
+            error(Status::InternalServerError, "The link could not be sent.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        authentication::{Authentication, Credentials},
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::testing::{self, dispatch_request, run_test};
+
+    #[rocket::async_test]
+    async fn test_send_password_reset() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("profile.reset_password").await.unwrap();
+            let admin = testing::create_user(database, Authentication::None, vec![permission]).await;
+            let outsider = testing::get_user(database).await;
+            let credentials = Credentials {
+                email: "member@test.fr".to_string(),
+                username: None,
+                avatar: None,
+                password: "test".to_string(),
+            };
+            let user = testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await;
+            let uri = format!("/user/{}/password_reset", user.unique_id);
+
+            let response =
+                dispatch_request(&client, Method::Post, uri.clone(), None, Some(outsider.get_token().unwrap().clone()))
+                    .await;
+            assert_eq!(response.status(), Status::Forbidden);
+
+            let response =
+                dispatch_request(&client, Method::Post, uri, None, Some(admin.get_token().unwrap().clone())).await;
+            assert_eq!(response.status(), Status::Ok);
+            let messages = database.outbox_manager.messages_to("member@test.fr").await.unwrap();
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].subject, "Reset your password");
+        })
+        .await;
+    }
+}
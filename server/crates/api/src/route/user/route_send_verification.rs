@@ -0,0 +1,114 @@
+use database::Database;
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{mail::Mailer, model::user_token::UserData, RequestError};
+
+/// Send another link verifying the email of the user of the token
+///
+/// The previous links stay valid until they expire
+#[openapi(tag = "Users")]
+#[post("/verification")]
+pub async fn send_verification(
+    user_data: UserData,
+    database: &State<Database>,
+    mailer: &State<Mailer>,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return Custom(
+            Status::Unauthorized,
+            Err(RequestError::from(Custom(Status::Unauthorized, "Token is invalid".to_string())).into()),
+        );
+    };
+
+    let user = match database.user_manager.from_id(user_id).await {
+        Ok(Some(user)) => user,
+        _ => {
+            return Custom(
+                Status::NotFound,
+                Err(RequestError::from(Custom(Status::NotFound, "User not found".to_string())).into()),
+            )
+        }
+    };
+    if user.email_verified {
+        return Custom(
+            Status::Conflict,
+            Err(RequestError::from(Custom(Status::Conflict, "The email is already verified.".to_string())).into()),
+        );
+    }
+
+    match mailer.send_verification(&user).await {
+        Ok(_) => Custom(Status::Ok, Ok(Json(true))),
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot queue the email verification: {err}");
+            Custom(
+                Status::InternalServerError,
+                Err(RequestError::from(Custom(
+                    Status::InternalServerError,
+                    "The link could not be sent.".to_string(),
+                ))
+                .into()),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        authentication::{Authentication, Credentials},
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        testing::{self, dispatch_request, run_test},
+        RequestError,
+    };
+
+    #[rocket::async_test]
+    async fn test_send_verification() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let credentials = Credentials {
+                email: "unverified@test.fr".to_string(),
+                username: Some("test".to_string()),
+                avatar: None,
+                password: "test".to_string(),
+            };
+            let user = testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await;
+            let token = user.get_token().unwrap().to_string();
+
+            // The users created for the tests are verified
+            let response =
+                dispatch_request(&client, Method::Post, "/user/verification".to_string(), None, Some(token.clone()))
+                    .await;
+            assert_eq!(response.status(), Status::Conflict);
+            assert_eq!(response.into_json::<RequestError>().await.unwrap().code, 409);
+
+            // Changing the email makes it unverified
+            let changed = Authentication::Credentials(Credentials {
+                email: "changed@test.fr".to_string(),
+                ..user.authentication.credentials().clone()
+            });
+            database.user_manager.update_auth(user.unique_id, &changed).await.unwrap();
+
+            let response =
+                dispatch_request(&client, Method::Post, "/user/verification".to_string(), None, Some(token)).await;
+            assert_eq!(response.status(), Status::Ok);
+            let messages = database.outbox_manager.messages_to("changed@test.fr").await.unwrap();
+            assert_eq!(messages.len(), 1);
+            assert!(messages[0].text.contains("/user/verify_email?token="));
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn unauthorized_test_send_verification() {
+        run_test(|client| async move {
+            let response = dispatch_request(&client, Method::Post, "/user/verification".to_string(), None, None).await;
+            assert_eq!(response.status(), Status::Unauthorized);
+        })
+        .await;
+    }
+}
@@ -14,7 +14,7 @@ pub async fn server_disconnect(
 
     let server_unique_id = user_data.id.unwrap();
 
-    match database.peers_manager.peers_exist(&server_unique_id).await {
+    match database.peers_manager.peers_exist(server_unique_id).await {
         Ok(exists) if !exists => Custom(
             Status::Ok,
             Err(RequestError::from(Custom(
@@ -23,8 +23,8 @@ pub async fn server_disconnect(
             ))
             .into()),
         ),
-        _ => match database.peers_manager.delete_peer(&server_unique_id).await {
-            Ok(Some(result)) if result.deleted_count > 0 => Custom(Status::Ok, Ok(Json(true))),
+        _ => match database.peers_manager.delete_peer(server_unique_id).await {
+            Ok(true) => Custom(Status::Ok, Ok(Json(true))),
             Ok(_) => Custom(
                 Status::Ok,
                 Err(RequestError::from(Custom(
@@ -48,13 +48,10 @@ pub async fn server_disconnect(
 #[cfg(test)]
 mod tests {
 
-    use database::{peer::Peer, Database};
+    use database::{id::RoomId, peer::Peer, timestamp::Timestamp, Database};
     use rocket::http::{Method, Status};
 
-    use crate::{
-        testing::{self, dispatch_request, run_test},
-        Server,
-    };
+    use crate::testing::{self, dispatch_request, run_test};
 
     #[rocket::async_test]
     async fn test_server_disconnect() {
@@ -63,17 +60,17 @@ mod tests {
             let test_server = testing::get_user(database).await;
             let test_user = testing::get_user(database).await;
             let _test_org =
-                testing::create_org(database, &test_user, vec![test_server.unique_id.clone()]);
+                testing::create_org(database, &test_user, vec![test_server.unique_id]);
 
             let server_peer = Peer {
-                room_id: Server::generate_unique_id().to_string(),
-                creation_date: Server::current_time().to_string(),
+                room_id: RoomId::generate(),
+                creation_date: Timestamp::now(),
                 signaling_hostname: "127.0.0.1".to_string(),
                 signaling_port: 3536,
-                server_unique_id: test_server.unique_id.clone(),
+                server_unique_id: test_server.unique_id,
             };
 
-            let _ = database
+            database
                 .peers_manager
                 .create_peer(&server_peer)
                 .await
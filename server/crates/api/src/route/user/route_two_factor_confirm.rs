@@ -0,0 +1,191 @@
+use database::{
+    audit::{AuditAction, AuditTargetKind},
+    timestamp::Timestamp,
+    Database,
+};
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{
+    audit::AuditContext,
+    model::{two_factor::TwoFactorCode, user_token::{UserData, ACCESS_TOKEN_REFUSED}},
+    settings::ApiSettings,
+    two_factor, RequestError,
+};
+
+/// Enable the second factor being enrolled, with a first code of the authenticator app
+///
+/// The answer holds the recovery codes, which are only shown once: each one replaces a code of the app once, when the
+/// phone is lost
+#[openapi(tag = "Users")]
+#[post("/two_factor/confirm", data = "<body>", format = "application/json")]
+pub async fn two_factor_confirm(
+    user_data: UserData,
+    database: &State<Database>,
+    settings: &State<ApiSettings>,
+    body: Json<TwoFactorCode>,
+    audit: AuditContext,
+) -> Custom<Result<Json<Vec<String>>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return refuse(Status::Unauthorized, "Token is invalid");
+    };
+    if user_data.is_access_token() {
+        return refuse(Status::Forbidden, ACCESS_TOKEN_REFUSED);
+    }
+    let pending = match database.two_factor_manager.get(user_id).await {
+        Ok(Some(two_factor)) if two_factor.enabled => {
+            return refuse(Status::Conflict, "Two-factor authentication is already enabled.")
+        }
+        Ok(Some(two_factor)) => two_factor,
+        Ok(None) => return refuse(Status::NotFound, "No second factor is being enrolled."),
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot read the second factor: {err}");
+            return refuse(Status::InternalServerError, "A database error occured.");
+        }
+    };
+
+    let now = Timestamp::now();
+    let Some(step) = two_factor::check_code(&pending.secret, &body.code, now) else {
+        return refuse(Status::BadRequest, "The code is invalid.");
+    };
+    let recovery_codes = two_factor::generate_recovery_codes(settings.two_factor.recovery_codes);
+    let hashes = recovery_codes
+        .iter()
+        .map(|code| two_factor::hash_recovery_code(code))
+        .collect();
+    match database.two_factor_manager.enable(user_id, hashes, step, now).await {
+        Ok(true) => {
+            let event = audit.event(
+                AuditAction::TwoFactorEnabled,
+                Some(user_id),
+                AuditTargetKind::User,
+                user_id,
+            );
+            audit.record(database, event).await;
+            Custom(Status::Ok, Ok(Json(recovery_codes)))
+        }
+        // The enrolment was replaced or confirmed meanwhile
+        Ok(false) => refuse(Status::BadRequest, "The code is invalid."),
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot enable the second factor: {err}");
+            refuse(Status::InternalServerError, "A database error occured.")
+        }
+    }
+}
+
+fn refuse(status: Status, message: &str) -> Custom<Result<Json<Vec<String>>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        audit::AuditAction,
+        authentication::{Authentication, Credentials},
+        id::OrganizationId,
+        pagination::PageRequest,
+        timestamp::Timestamp,
+        Database,
+    };
+    use rocket::http::{Method, Status};
+    use serde_json::json;
+
+    use crate::{
+        model::two_factor::TwoFactorEnrolment,
+        testing::{self, dispatch_request, run_test},
+        two_factor,
+    };
+
+    #[rocket::async_test]
+    async fn test_two_factor_confirm() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let credentials = Credentials {
+                email: "ivan@example.com".to_string(),
+                username: None,
+                avatar: None,
+                password: "password".to_string(),
+            };
+            let user = testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await;
+            let token = user.get_token().cloned();
+
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/user/two_factor".to_string(),
+                None,
+                token.clone(),
+            )
+            .await;
+            assert_eq!(response.status(), Status::Ok);
+            let enrolment: TwoFactorEnrolment = response.into_json().await.unwrap();
+            assert!(enrolment
+                .provisioning_uri
+                .starts_with("otpauth://totp/Areation:ivan@example.com?secret="));
+
+            let confirm = |code: String| {
+                dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/two_factor/confirm".to_string(),
+                    Some(json!({ "code": code }).to_string()),
+                    token.clone(),
+                )
+            };
+            let response = confirm("12345".to_string()).await;
+            assert_eq!(response.status(), Status::BadRequest);
+            assert!(
+                !two_factor::is_enabled(database.two_factor_manager.as_ref(), user.unique_id)
+                    .await
+                    .unwrap()
+            );
+
+            let code = two_factor::code(&enrolment.secret, Timestamp::now()).unwrap();
+            let response = confirm(code.clone()).await;
+            assert_eq!(response.status(), Status::Ok);
+            let recovery_codes: Vec<String> = response.into_json().await.unwrap();
+            assert_eq!(recovery_codes.len(), 10);
+            let stored = database.two_factor_manager.get(user.unique_id).await.unwrap().unwrap();
+            assert!(stored.enabled);
+            // Only the hashes of the recovery codes are kept
+            assert_eq!(
+                stored.recovery_codes[0],
+                two_factor::hash_recovery_code(&recovery_codes[0])
+            );
+
+            let events = database
+                .audit_manager
+                .get_events(OrganizationId::generate(), &[user.unique_id], &PageRequest::default())
+                .await
+                .unwrap();
+            assert!(events
+                .items
+                .iter()
+                .any(|event| event.action == AuditAction::TwoFactorEnabled));
+
+            // The enabled second factor is not replaced
+            let response = confirm(code).await;
+            assert_eq!(response.status(), Status::Conflict);
+            let response = dispatch_request(&client, Method::Post, "/user/two_factor".to_string(), None, token).await;
+            assert_eq!(response.status(), Status::Conflict);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn access_token_test_two_factor_confirm() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+
+            let response = dispatch_request(&client, Method::Post, "/user/two_factor/confirm".to_string(), Some(json!({ "code": "000000" }).to_string()), Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+        })
+        .await;
+    }
+}
@@ -0,0 +1,184 @@
+use database::{
+    audit::{AuditAction, AuditTargetKind},
+    timestamp::Timestamp,
+    Database,
+};
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{
+    audit::AuditContext,
+    model::{two_factor::TwoFactorCode, user_token::{UserData, ACCESS_TOKEN_REFUSED}},
+    rate_limit::AccountLockout,
+    two_factor, RequestError,
+};
+
+/// Disable the second factor of the user of the token, with a code of the authenticator app or a recovery code
+///
+/// A pending enrolment is cancelled without a code. The users belonging to an organization that requires a second
+/// factor cannot disable it
+#[openapi(tag = "Users")]
+#[post("/two_factor/disable", data = "<body>", format = "application/json")]
+pub async fn two_factor_disable(
+    user_data: UserData,
+    database: &State<Database>,
+    body: Json<TwoFactorCode>,
+    audit: AuditContext,
+    lockout: AccountLockout<'_>,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return refuse(Status::Unauthorized, "Token is invalid");
+    };
+    if user_data.is_access_token() {
+        return refuse(Status::Forbidden, ACCESS_TOKEN_REFUSED);
+    }
+    let two_factors = database.two_factor_manager.as_ref();
+    let current = match two_factors.get(user_id).await {
+        Ok(Some(two_factor)) => two_factor,
+        Ok(None) => return refuse(Status::NotFound, "Two-factor authentication is not enabled."),
+        Err(err) => return database_error(err),
+    };
+
+    if current.enabled {
+        match database.organization_manager.requires_two_factor(user_id).await {
+            Ok(false) => {}
+            Ok(true) => {
+                return refuse(
+                    Status::Conflict,
+                    "An organization of the user requires two-factor authentication.",
+                )
+            }
+            Err(err) => return database_error(err),
+        }
+
+        // The codes are guessed no faster than the passwords
+        let account = user_id.to_string();
+        if lockout.check(&account).await.is_err() {
+            return refuse(
+                Status::TooManyRequests,
+                "Too many failed logins, the account is locked for a while.",
+            );
+        }
+        match two_factor::verify(two_factors, &current, &body.code, Timestamp::now()).await {
+            Ok(true) => lockout.success(&account).await,
+            Ok(false) => {
+                lockout.failure(&account).await;
+                return refuse(Status::Unauthorized, "The code is invalid.");
+            }
+            Err(err) => return database_error(err),
+        }
+    }
+
+    match two_factors.disable(user_id).await {
+        Ok(disabled) => {
+            if disabled && current.enabled {
+                let event = audit.event(
+                    AuditAction::TwoFactorDisabled,
+                    Some(user_id),
+                    AuditTargetKind::User,
+                    user_id,
+                );
+                audit.record(database, event).await;
+            }
+            Custom(Status::Ok, Ok(Json(disabled)))
+        }
+        Err(err) => database_error(err),
+    }
+}
+
+fn database_error(err: mongodb::error::Error) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    tracing::error!("Cannot update the second factor: {err}");
+    refuse(Status::InternalServerError, "A database error occured.")
+}
+
+fn refuse(status: Status, message: &str) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{
+        audit::AuditAction, id::OrganizationId, organization::OrganizationUpdate, pagination::PageRequest, Database,
+    };
+    use rocket::http::{Method, Status};
+    use serde_json::json;
+
+    use crate::{
+        testing::{self, dispatch_request, run_test},
+        two_factor,
+    };
+
+    #[rocket::async_test]
+    async fn test_two_factor_disable() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let (_, recovery_code) = testing::enable_two_factor(database, &user).await;
+            let organization = testing::get_org(database, &user).await;
+            let require = |required: bool| {
+                database.organization_manager.update_organization(
+                    organization.unique_id,
+                    vec![OrganizationUpdate::RequireTwoFactor(required)],
+                )
+            };
+            let disable = |code: &str| {
+                dispatch_request(
+                    &client,
+                    Method::Post,
+                    "/user/two_factor/disable".to_string(),
+                    Some(json!({ "code": code }).to_string()),
+                    user.get_token().cloned(),
+                )
+            };
+
+            // The organization of the user requires the second factor
+            require(true).await.unwrap();
+            let response = disable(&recovery_code).await;
+            assert_eq!(response.status(), Status::Conflict);
+
+            require(false).await.unwrap();
+            let response = disable("wrong").await;
+            assert_eq!(response.status(), Status::Unauthorized);
+            let response = disable(&recovery_code).await;
+            assert_eq!(response.status(), Status::Ok);
+            assert!(
+                !two_factor::is_enabled(database.two_factor_manager.as_ref(), user.unique_id)
+                    .await
+                    .unwrap()
+            );
+
+            let events = database
+                .audit_manager
+                .get_events(OrganizationId::generate(), &[user.unique_id], &PageRequest::default())
+                .await
+                .unwrap();
+            assert!(events
+                .items
+                .iter()
+                .any(|event| event.action == AuditAction::TwoFactorDisabled));
+
+            let response = disable(&recovery_code).await;
+            assert_eq!(response.status(), Status::NotFound);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn access_token_test_two_factor_disable() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+            let (_, recovery_code) = testing::enable_two_factor(database, &user).await;
+
+            let response = dispatch_request(&client, Method::Post, "/user/two_factor/disable".to_string(), Some(json!({ "code": recovery_code }).to_string()), Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+            assert!(two_factor::is_enabled(database.two_factor_manager.as_ref(), user.unique_id).await.unwrap());
+        })
+        .await;
+    }
+}
@@ -0,0 +1,92 @@
+use database::{timestamp::Timestamp, two_factor::TwoFactor, Database};
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{
+    model::{two_factor::TwoFactorEnrolment, user_token::{UserData, ACCESS_TOKEN_REFUSED}},
+    settings::ApiSettings,
+    two_factor, RequestError,
+};
+
+/// Start enrolling a second factor for the user of the token
+///
+/// The secret is imported in an authenticator app, usually by scanning the provisioning URI as a QR code. The second
+/// factor is enabled once a first code is sent to /user/two_factor/confirm, a new enrolment replaces a pending one
+#[openapi(tag = "Users")]
+#[post("/two_factor")]
+pub async fn two_factor_enrol(
+    user_data: UserData,
+    database: &State<Database>,
+    settings: &State<ApiSettings>,
+) -> Custom<Result<Json<TwoFactorEnrolment>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return refuse(Status::Unauthorized, "Token is invalid");
+    };
+    if user_data.is_access_token() {
+        return refuse(Status::Forbidden, ACCESS_TOKEN_REFUSED);
+    }
+    let user = match database.user_manager.from_id(user_id).await {
+        Ok(Some(user)) => user,
+        _ => return refuse(Status::NotFound, "User not found"),
+    };
+
+    let secret = two_factor::generate_secret();
+    let pending = TwoFactor {
+        user_id,
+        secret: secret.clone(),
+        enabled: false,
+        recovery_codes: Vec::new(),
+        last_step: 0,
+        creation_date: Timestamp::now(),
+        enabled_at: None,
+    };
+    match database.two_factor_manager.start(&pending).await {
+        Ok(true) => {}
+        Ok(false) => return refuse(Status::Conflict, "Two-factor authentication is already enabled."),
+        Err(err) => {
+            tracing::error!(%user_id, "Cannot start the enrolment of the second factor: {err}");
+            return refuse(Status::InternalServerError, "A database error occured.");
+        }
+    }
+
+    // The apps show the account under its email, which the users recognize
+    let account = user.email().map(str::to_string).unwrap_or_else(|| user_id.to_string());
+    let provisioning_uri = two_factor::provisioning_uri(&settings.two_factor.issuer, &account, &secret);
+    Custom(
+        Status::Ok,
+        Ok(Json(TwoFactorEnrolment {
+            secret,
+            provisioning_uri,
+        })),
+    )
+}
+
+fn refuse(status: Status, message: &str) -> Custom<Result<Json<TwoFactorEnrolment>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use database::Database;
+    use rocket::http::{Method, Status};
+
+    use crate::testing::{self, dispatch_request, run_test};
+
+    #[rocket::async_test]
+    async fn access_token_test_two_factor_enrol() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+
+            let response = dispatch_request(&client, Method::Post, "/user/two_factor".to_string(), None, Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+            assert!(database.two_factor_manager.get(user.unique_id).await.unwrap().is_none());
+        })
+        .await;
+    }
+}
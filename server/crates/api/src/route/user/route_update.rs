@@ -2,19 +2,52 @@ use database::{user::UserUpdate, Database};
 use rocket::{http::Status, patch, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
-use crate::{model::user_token::UserData, RequestError};
+use crate::{
+    model::user_token::{UserData, ACCESS_TOKEN_REFUSED},
+    RequestError,
+};
 
 /// Update the user informations from its token
+///
+/// Only the user themselves, or a user with the 'profile.edit' permission, can update the account.
 #[openapi(tag = "Users")]
 #[patch("/token/<token>", data = "<user_update>", format = "application/json")] // <- route attribute
 pub async fn update(
-    _user_data: UserData,
+    user_data: UserData,
     database: &State<Database>,
     token: String,
     user_update: Json<Vec<UserUpdate>>,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    if user_data.is_access_token() {
+        let status = Status::Forbidden;
+        return Custom(
+            status,
+            Err(RequestError::from(Custom(status, ACCESS_TOKEN_REFUSED.to_string())).into()),
+        );
+    }
+
+    if user_data.id.is_none() {
+        let status = Status::Unauthorized;
+        return Custom(status, Err(RequestError::from(Custom(status, "Token is invalid".to_string())).into()));
+    }
+
+    let user = database.user_manager.from_token(&token).await;
+    let user_id = user.as_ref().ok().and_then(|user| user.as_ref()).map(|user| user.unique_id);
+    if user_id != user_data.id {
+        let allowed = match database.permission_manager.get_permission_id("profile.edit").await {
+            Ok(permission) => user_data.has_permission(database, permission).await,
+            Err(_) => false,
+        };
+        if !allowed {
+            let status = Status::Forbidden;
+            return Custom(
+                status,
+                Err(RequestError::from(Custom(status, "Only the user can update their account.".to_string())).into()),
+            );
+        }
+    }
 
-    match database.user_manager.from_token(&token).await {
+    match user {
         Ok(user) if user.is_some() => {
             let uuid = user.unwrap().unique_id;
             match database.user_manager.update_user(uuid, user_update.0).await {
@@ -44,6 +77,8 @@ pub async fn update(
 mod tests {
 
     use database::{
+        authentication::{Authentication, Credentials},
+        id::UserId,
         user::{User, UserUpdate},
         Database,
     };
@@ -58,8 +93,9 @@ mod tests {
     async fn test_update() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
-            let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
+            let test_user = create_user_with_credentials(database).await;
+            let permission = database.permission_manager.get_permission_id("profile.edit").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
             let user_token = test_user.get_token().unwrap();
             let updates = vec![UserUpdate::Username("Another username".to_string())];
@@ -88,12 +124,39 @@ mod tests {
         .await;
     }
 
+    #[rocket::async_test]
+    async fn test_update_own() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let test_user = create_user_with_credentials(database).await;
+            let user_token = test_user.get_token().unwrap();
+            let updates = vec![UserUpdate::Username("Another username".to_string())];
+
+            let response = dispatch_request(
+                &client,
+                Method::Patch,
+                format!("/user/token/{}", user_token),
+                Some(serde_json::to_string(&updates).unwrap()),
+                Some(user_token.to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), Status::Ok);
+            let updated_user = database.user_manager.from_id(test_user.unique_id).await.unwrap().unwrap();
+            assert_eq!(
+                updated_user.authentication.credentials().username.as_deref(),
+                Some("Another username")
+            );
+        })
+        .await;
+    }
+
     #[rocket::async_test]
     async fn test_unknown_update() {
         run_test(|client| async move {
-            let request_user =
-                testing::get_user(client.rocket().state::<Database>().unwrap())
-                    .await;
+            let database = client.rocket().state::<Database>().unwrap();
+            let permission = database.permission_manager.get_permission_id("profile.edit").await.unwrap();
+            let request_user = testing::create_user(database, Authentication::None, vec![permission]).await;
             let request_token = request_user.get_token().unwrap();
             let token = "NO_TOKEN";
             let updates = vec![UserUpdate::Username("Another username".to_string())];
@@ -127,9 +190,7 @@ mod tests {
     async fn _unauthorized_test_update() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
-            let test_user = testing::get_user(database).await;
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
+            let test_user = create_user_with_credentials(database).await;
             let user_token = test_user.get_token().unwrap();
             let updates = vec![UserUpdate::Username("Another username".to_string())];
 
@@ -138,7 +199,7 @@ mod tests {
                 Method::Patch,
                 format!("/user/token/{}", user_token),
                 Some(serde_json::to_string(&updates).unwrap()),
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
@@ -160,7 +221,9 @@ mod tests {
     async fn forbidden_test_update() {
         run_test(|client| async move {
             let database = client.rocket().state::<Database>().unwrap();
-            let test_user = testing::get_user(database).await;
+            let test_user = create_user_with_credentials(database).await;
+            let request_user = testing::get_user(database).await;
+            let request_token = request_user.get_token().unwrap();
             let user_token = test_user.get_token().unwrap();
             let updates = vec![UserUpdate::Username("Another username".to_string())];
 
@@ -169,7 +232,7 @@ mod tests {
                 Method::Patch,
                 format!("/user/token/{}", user_token),
                 Some(serde_json::to_string(&updates).unwrap()),
-                None,
+                Some(request_token.to_string()),
             )
             .await;
 
@@ -187,10 +250,36 @@ mod tests {
         .await;
     }
 
+    /// Creates a user signing in with credentials, the username being part of them
+    async fn create_user_with_credentials(database: &Database) -> User {
+        let credentials = Credentials {
+            email: format!("{}@test.fr", UserId::generate()),
+            username: Some("test".to_string()),
+            avatar: None,
+            password: "test".to_string(),
+        };
+        testing::create_user(database, Authentication::Credentials(credentials), Vec::new()).await
+    }
+
     /// Check if the user has changed between the request and after it.
     /// If is_same is set false, then it will assert_ne! instead of asserting equal.
     fn check_user_difference(user1: &User, user2: &User) {
         // first we're checking if they're of the same id.
         assert_eq!(user1.unique_id, user2.unique_id);
     }
+
+    #[rocket::async_test]
+    async fn access_token_test_update() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+            let updates = vec![UserUpdate::Username("Script".to_string())];
+
+            let response = dispatch_request(&client, Method::Patch, format!("/user/token/{}", user.get_token().unwrap()), Some(serde_json::to_string(&updates).unwrap()), Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+        })
+        .await;
+    }
 }
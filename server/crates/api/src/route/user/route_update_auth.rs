@@ -1,9 +1,12 @@
-use database::{authentication::Credentials, managers::UserManager, Database};
+use database::{authentication::Credentials, id::UserId, managers::UserRepository, Database};
 use rocket::{http::Status, patch, response::status::Custom, serde::json::Json, State};
 use rocket_okapi::openapi;
 
 use crate::{
-    model::{login::Login, user_token::UserData},
+    model::{
+        login::Login,
+        user_token::{UserData, ACCESS_TOKEN_REFUSED},
+    },
     RequestError,
 };
 
@@ -17,14 +20,28 @@ pub async fn update_auth(
     database: &State<Database>,
     login: Json<Login>,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    let Some(user_id) = user_data.id else {
+        return refuse(Status::Unauthorized, "Token is invalid");
+    };
+    // A script could otherwise replace the password and take the account over
+    if user_data.is_access_token() {
+        return refuse(Status::Forbidden, ACCESS_TOKEN_REFUSED);
+    }
+
+    _update_auth(user_id, login, database.user_manager.as_ref()).await
+}
 
-    _update_auth(user_data.id.unwrap(), login, &database.user_manager).await
+fn refuse(status: Status, message: &str) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(
+        status,
+        Err(RequestError::from(Custom(status, message.to_string())).into()),
+    )
 }
 
 async fn _update_auth(
-    id: String,
+    id: UserId,
     login: Json<Login>,
-    usermanager: &UserManager,
+    usermanager: &dyn UserRepository,
 ) -> Custom<Result<Json<bool>, Json<RequestError>>> {
     match login.0 {
         Login::Credentials(credentials) => {
@@ -56,7 +73,7 @@ async fn _update_auth(
             Status::Ok,
             Err(RequestError::from(Custom(
                 Status::BadRequest,
-                "Credentials is the only login method currently supported".into(),
+                "Credentials is the only login method that can be set, the OpenID Connect accounts are linked through /user/oidc".into(),
             ))
             .into()),
         ),
@@ -68,6 +85,7 @@ mod tests {
 
     use database::{
         authentication::{Authentication, Credentials},
+        id::UserId,
         Database,
     };
     use rocket::http::{Method, Status};
@@ -105,13 +123,19 @@ mod tests {
             assert_eq!(response, true);
             let user = database
                 .user_manager
-                .from_id(&test_user.unique_id)
+                .from_id(test_user.unique_id)
                 .await
                 .unwrap()
                 .unwrap();
+            // The password is stored hashed
+            let stored = user.authentication.credentials();
+            assert!(stored.verify_password(&credentials.password));
             assert_eq!(
                 user.authentication,
-                Authentication::Credentials(credentials)
+                Authentication::Credentials(Credentials {
+                    password: stored.password.clone(),
+                    ..credentials
+                })
             );
         })
         .await;
@@ -125,9 +149,6 @@ mod tests {
 
     async fn _unauthorized_test_update_auth() {
         run_test(|client| async move {
-            let database = client.rocket().state::<Database>().unwrap();
-            let request_user = testing::get_user(database).await;
-            let request_token = request_user.get_token().unwrap();
             let credentials = Credentials {
                 email: "test@test.fr".to_string(),
                 username: Option::Some("test".to_string()),
@@ -140,21 +161,11 @@ mod tests {
                 Method::Patch,
                 format!("/user/update_auth"),
                 Some(serde_json::to_string(&Login::Credentials(credentials.clone())).unwrap()),
-                Some(request_token.to_string()),
+                None,
             )
             .await;
 
             assert_eq!(response.status(), Status::Unauthorized);
-            let user = database
-                .user_manager
-                .from_id(&request_user.unique_id)
-                .await
-                .unwrap()
-                .unwrap();
-            assert_ne!(
-                user.authentication,
-                Authentication::Credentials(credentials)
-            );
         })
         .await;
     }
@@ -170,7 +181,7 @@ mod tests {
                 &client,
                 Method::Patch,
                 format!("/user/update_auth"),
-                Some(serde_json::to_string(&Login::UserId("NO_ID".to_string())).unwrap()),
+                Some(serde_json::to_string(&Login::UserId(UserId::generate())).unwrap()),
                 Some(request_token.to_string()),
             )
             .await;
@@ -185,6 +196,10 @@ mod tests {
     #[rocket::async_test]
     async fn forbidden_test_update_auth() {
         run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let request_user = testing::get_user(database).await;
+            // Only the user signed in can change how they sign in, a token scoped to another permission is refused too
+            let request_token = testing::create_access_token(database, &request_user, &["permission.see"]).await;
             let credentials = Credentials {
                 email: "test@test.fr".to_string(),
                 username: Option::Some("test".to_string()),
@@ -197,11 +212,43 @@ mod tests {
                 Method::Patch,
                 format!("/user/update_auth"),
                 Some(serde_json::to_string(&Login::Credentials(credentials.clone())).unwrap()),
-                None,
+                Some(request_token),
             )
             .await;
 
             assert_eq!(response.status(), Status::Forbidden);
+            let user = database
+                .user_manager
+                .from_id(request_user.unique_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_ne!(
+                user.authentication,
+                Authentication::Credentials(credentials)
+            );
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn access_token_test_update_auth() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let user = testing::get_user(database).await;
+            let token = testing::create_access_token(database, &user, &["profile.edit"]).await;
+            let credentials = Credentials {
+                email: "script@test.fr".to_string(),
+                username: None,
+                avatar: None,
+                password: "script".to_string(),
+            };
+
+            let response = dispatch_request(&client, Method::Patch, "/user/update_auth".to_string(), Some(serde_json::to_string(&Login::Credentials(credentials)).unwrap()), Some(token)).await;
+
+            assert_eq!(response.status(), Status::Forbidden);
+            let user = database.user_manager.from_id(user.unique_id).await.unwrap().unwrap();
+            assert_eq!(user.authentication, Authentication::None);
         })
         .await;
     }
@@ -0,0 +1,163 @@
+use database::{
+    audit::{AuditAction, AuditTargetKind},
+    id::UserId,
+    timestamp::Timestamp,
+    Database,
+};
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+use rocket_okapi::openapi;
+
+use crate::{
+    audit::AuditContext,
+    mail::{LinkError, LinkSigner, Mailer, VERIFY_EMAIL_PURPOSE},
+    RequestError,
+};
+
+fn error(status: Status, message: String) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    Custom(status, Err(RequestError::from(Custom(status, message)).into()))
+}
+
+/// Verify the email of a user, with the link sent by mail
+///
+/// A link only verifies the email it was sent to, and only once
+#[openapi(tag = "Users")]
+#[get("/verify_email?<token>")]
+pub async fn verify_email(
+    database: &State<Database>,
+    mailer: &State<Mailer>,
+    token: &str,
+    audit: AuditContext,
+) -> Custom<Result<Json<bool>, Json<RequestError>>> {
+    let invalid = || error(Status::BadRequest, LinkError::Invalid.to_string());
+    let link = match LinkSigner::parse(token) {
+        Ok(link) => link,
+        Err(err) => return error(Status::BadRequest, err.to_string()),
+    };
+    let Ok(user_id) = link.subject.parse::<UserId>() else {
+        return invalid();
+    };
+    let user = match database.user_manager.from_id(user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return invalid(),
+        Err(_) => return error(Status::InternalServerError, "A database error occured.".to_string()),
+    };
+    let Some(email) = user.email() else {
+        return invalid();
+    };
+
+    if let Err(err) = mailer.signer().verify(VERIFY_EMAIL_PURPOSE, &link, email, Timestamp::now()) {
+        return error(Status::BadRequest, err.to_string());
+    }
+    match database.user_manager.verify_email(user_id, email).await {
+        Ok(true) => {
+            let event = audit.event(AuditAction::EmailVerified, Some(user_id), AuditTargetKind::User, user_id);
+            audit.record(database, event).await;
+            Custom(Status::Ok, Ok(Json(true)))
+        }
+        // The email was verified meanwhile, by this link or another one
+        Ok(false) => invalid(),
+        Err(_) => error(Status::InternalServerError, "A database error occured.".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use database::{
+        authentication::{Authentication, Credentials},
+        timestamp::Timestamp,
+        Database,
+    };
+    use rocket::http::{Method, Status};
+
+    use crate::{
+        mail::{Mailer, VERIFY_EMAIL_PURPOSE},
+        model::login::Login,
+        testing::{self, dispatch_request, run_test},
+        RequestError,
+    };
+
+    #[rocket::async_test]
+    async fn test_verify_email() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let website = testing::get_user(database).await;
+            let credentials = Credentials {
+                email: "new@test.fr".to_string(),
+                username: Some("new".to_string()),
+                avatar: None,
+                password: "test".to_string(),
+            };
+            let response = dispatch_request(
+                &client,
+                Method::Post,
+                "/user".to_string(),
+                Some(serde_json::to_string(&Login::Credentials(credentials)).unwrap()),
+                Some(website.get_token().unwrap().to_string()),
+            )
+            .await;
+            let user_token = response.into_string().await.unwrap();
+            let user = database.user_manager.from_token(&user_token).await.unwrap().unwrap();
+            assert!(!user.email_verified);
+
+            // The permissions of the user are not granted until the email is verified
+            let permission = database.permission_manager.get_permission_id("profile.edit").await.unwrap();
+            database.user_manager.add_permission(user.unique_id, permission).await.unwrap();
+            assert!(!database.user_manager.has_permission(user.unique_id, permission).await);
+
+            let messages = database.outbox_manager.messages_to("new@test.fr").await.unwrap();
+            assert_eq!(messages.len(), 1);
+            assert!(messages[0].html.as_deref().unwrap().contains("Hello new"));
+            let token = messages[0].text.split("token=").nth(1).unwrap().split_whitespace().next().unwrap();
+            let uri = format!("/user/verify_email?token={token}");
+
+            let response = dispatch_request(&client, Method::Get, uri.clone(), None, None).await;
+            assert_eq!(response.status(), Status::Ok);
+            assert!(database.user_manager.from_id(user.unique_id).await.unwrap().unwrap().email_verified);
+            assert!(database.user_manager.has_permission(user.unique_id, permission).await);
+
+            // The link is used only once
+            let response = dispatch_request(&client, Method::Get, uri, None, None).await;
+            assert_eq!(response.status(), Status::BadRequest);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_invalid_link() {
+        run_test(|client| async move {
+            let database = client.rocket().state::<Database>().unwrap();
+            let mailer = client.rocket().state::<Mailer>().unwrap();
+            let user = testing::create_user(
+                database,
+                Authentication::Credentials(Credentials {
+                    email: "user@test.fr".to_string(),
+                    username: None,
+                    avatar: None,
+                    password: "test".to_string(),
+                }),
+                Vec::new(),
+            )
+            .await;
+            let id = user.unique_id.to_string();
+
+            let expired = Timestamp::now();
+            let expires_at = Timestamp::now().plus(Duration::from_secs(60));
+            for (token, message) in [
+                ("malformed".to_string(), "The link is malformed."),
+                (mailer.signer().sign(VERIFY_EMAIL_PURPOSE, &id, "user@test.fr", expired), "The link has expired."),
+                (
+                    mailer.signer().sign(VERIFY_EMAIL_PURPOSE, &id, "other@test.fr", expires_at),
+                    "The link is invalid or was already used.",
+                ),
+            ] {
+                let uri = format!("/user/verify_email?token={token}");
+                let response = dispatch_request(&client, Method::Get, uri, None, None).await;
+                assert_eq!(response.status(), Status::BadRequest);
+                assert_eq!(response.into_json::<RequestError>().await.unwrap().message, message);
+            }
+        })
+        .await;
+    }
+}
@@ -1,33 +1,818 @@
-use std::env;
+use std::{collections::BTreeMap, env, fmt};
 
-use database::DatabaseSettings;
-use telemetry::TelemetrySettings;
+use database::{DatabaseBackend, DatabaseSettings};
+use rocket::figment::{
+    providers::{Env, Format, Serialized, Toml},
+    Figment,
+};
+use serde::{Deserialize, Serialize};
+use telemetry::{LoggingSettings, TelemetrySettings};
 
-#[derive(Clone)]
+/// The environment variable holding the path of the configuration file.
+pub const CONFIG_FILE_VARIABLE: &str = "API_CONFIG";
+/// The configuration file read when `API_CONFIG` is not set, next to `Rocket.toml`.
+pub const DEFAULT_CONFIG_FILE: &str = "Api.toml";
+
+/// The environment variables read before the configuration file existed, they still override it.
+const LEGACY_VARIABLES: &[(&str, &str)] = &[
+    ("MONGODB_URI", "database.uri"),
+    ("MONGODB_HOSTNAME", "database.hostname"),
+    ("MONGODB_PORT", "database.port"),
+    ("MONGODB_USERNAME", "database.username"),
+    ("MONGODB_PASSWORD", "database.password"),
+    ("MONGODB_DATABASE", "database.database"),
+    ("MONGODB_RUN_MIGRATIONS", "database.run_migrations"),
+    ("MONGODB_AUTH_SOURCE", "database.auth_source"),
+    ("MONGODB_REPLICA_SET", "database.replica_set"),
+    ("MONGODB_TLS", "database.tls"),
+    ("MONGODB_TLS_CA_FILE", "database.tls_ca_file"),
+    ("DATABASE_BACKEND", "database.backend"),
+    ("OTEL_EXPORTER_OTLP_ENDPOINT", "telemetry.hostname"),
+    ("OTEL_RESOURCE_ATTRIBUTES", "telemetry.attributes"),
+    ("OTEL_EXPORTER_OTLP_TOKEN", "telemetry.token"),
+    ("OTEL_EXPORTER_OTLP_PROTOCOL", "telemetry.exporter"),
+    ("RUST_LOG", "logging.level"),
+];
+
+/// The standard variable holding the headers of the exports, as `name=value` pairs separated by commas.
+const HEADERS_VARIABLE: &str = "OTEL_EXPORTER_OTLP_HEADERS";
+
+/// The configuration of the API
+///
+/// It is layered from the defaults, the configuration file, the legacy environment variables
+/// and the `API_` environment variables, where `__` separates the sections, e.g. `API_DATABASE__URI`.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct ApiSettings {
     pub database: DatabaseSettings,
     pub telemetry: TelemetrySettings,
+    pub logging: LoggingSettings,
+    pub signaling: SignalingSettings,
+    pub cors: CorsSettings,
+    pub tokens: TokenSettings,
+    pub rate_limits: RateLimitSettings,
+    pub metrics: MetricsSettings,
+    pub mail: MailSettings,
+    pub oidc: OidcSettings,
+    pub two_factor: TwoFactorSettings,
 }
 
-impl ApiSettings {
-    pub fn retrieve() -> Self {
+/// A signaling server the servers and the users meet on.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SignalingServer {
+    pub hostname: String,
+    pub port: u16,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SignalingSettings {
+    // The servers are assigned one of these when they authenticate
+    pub servers: Vec<SignalingServer>,
+}
+
+impl Default for SignalingSettings {
+    fn default() -> Self {
+        Self {
+            servers: vec![SignalingServer {
+                hostname: "x2025uverworld1833467632001.francecentral.cloudapp.azure.com".to_string(),
+                port: 3536,
+            }],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CorsSettings {
+    // The origins allowed to call the API from a browser, `*` allows every origin
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TokenSettings {
+    // How long a token stays valid after the login that created it
+    pub lifetime_hours: u64,
+    // The longest lifetime of the personal access tokens
+    pub access_token_max_days: u64,
+    // How many personal access tokens a user may have at once
+    pub access_tokens_per_user: usize,
+}
+
+impl Default for TokenSettings {
+    fn default() -> Self {
+        Self {
+            lifetime_hours: 24 * 30,
+            access_token_max_days: 365,
+            access_tokens_per_user: 20,
+        }
+    }
+}
+
+/// Where the rate limiter keeps its buckets and the lockouts of the accounts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    /// Each replica of the API limits the requests it receives on its own
+    #[default]
+    Memory,
+    /// The limits are shared by every replica through the database
+    Database,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RouteLimit {
+    // The number of requests a client can make per minute
+    pub requests_per_minute: u32,
+    // The number of requests a client can make at once above that rate
+    pub burst: u32,
+}
+
+impl Default for RouteLimit {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 120,
+            burst: 30,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    pub backend: RateLimitBackend,
+    // The limit of the routes outside of the classes below
+    pub requests_per_minute: u32,
+    pub burst: u32,
+    // The limit of the routes checking credentials or creating accounts, such as renew and register
+    pub authentication: RouteLimit,
+    // The limit of the routes telling whether an account or a license exists, such as email_exists
+    pub lookup: RouteLimit,
+    pub lockout: LockoutSettings,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            backend: RateLimitBackend::default(),
+            requests_per_minute: 120,
+            burst: 30,
+            authentication: RouteLimit {
+                requests_per_minute: 10,
+                burst: 5,
+            },
+            lookup: RouteLimit {
+                requests_per_minute: 30,
+                burst: 10,
+            },
+            lockout: LockoutSettings::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LockoutSettings {
+    // The consecutive failed logins after which an account is locked
+    pub max_failures: u32,
+    // How long the account is locked the first time, then twice as long after each new failure
+    pub lockout_secs: u64,
+    pub max_lockout_secs: u64,
+    // How long the failures are remembered after the last one
+    pub reset_after_secs: u64,
+}
+
+impl Default for LockoutSettings {
+    fn default() -> Self {
         Self {
-            database: get_database(),
-            telemetry: TelemetrySettings::from_env(),
+            max_failures: 5,
+            lockout_secs: 60,
+            max_lockout_secs: 3600,
+            reset_after_secs: 86_400,
         }
     }
 }
 
-fn get_database() -> DatabaseSettings {
-    DatabaseSettings {
-        hostname: env::var("MONGODB_HOSTNAME").unwrap().trim_end().to_string(),
-        port: env::var("MONGODB_PORT")
-            .unwrap()
-            .trim_end()
-            .parse()
-            .unwrap_or(0),
-        username: env::var("MONGODB_USERNAME").unwrap().trim_end().to_string(),
-        password: env::var("MONGODB_PASSWORD").unwrap().trim_end().to_string(),
-        database: env::var("MONGODB_DATABASE").unwrap().trim_end().to_string(),
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MetricsSettings {
+    // Whether the metrics are exposed on /metrics, in the Prometheus text format
+    pub enabled: bool,
+    // The bearer token the scraper must send, the endpoint is public when it is not set
+    pub token: Option<String>,
+    // How often the users, the peers and the system usage are measured, in milliseconds
+    pub collect_interval_ms: u64,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token: None,
+            collect_interval_ms: 15_000,
+        }
+    }
+}
+
+/// How the messages of the outbox are delivered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransportKind {
+    /// The messages are logged instead of being sent, for the development
+    #[default]
+    Log,
+    Smtp,
+}
+
+/// How the connection to the SMTP server is secured
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// A plain connection, for a relay on the same host or a local sink
+    None,
+    /// A plain connection upgraded with STARTTLS, usually on port 587
+    #[default]
+    StartTls,
+    /// A TLS connection from the start, usually on port 465
+    Tls,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SmtpSettings {
+    pub hostname: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub timeout_ms: u64,
+}
+
+impl Default for SmtpSettings {
+    fn default() -> Self {
+        Self {
+            hostname: "localhost".to_string(),
+            port: 587,
+            security: SmtpSecurity::default(),
+            username: None,
+            password: None,
+            timeout_ms: 30_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MailSettings {
+    pub transport: MailTransportKind,
+    // The sender of the messages, as `Name <address>` or a bare address
+    pub from: String,
+    // The address the links of the messages point to, the API as the users reach it
+    pub public_url: String,
+    pub smtp: SmtpSettings,
+    // The attempts to send a message before it is given up
+    pub max_attempts: u32,
+    // How long the first retry waits, doubled after each new failure
+    pub retry_backoff_secs: u64,
+    // How often the outbox is checked for the messages to retry, new messages are sent right away
+    pub poll_interval_ms: u64,
+    // How long the sent and the failed messages are kept
+    pub retention_hours: u64,
+    // The key signing the links, a random key is generated at launch when it is not set
+    pub link_secret: Option<String>,
+    // How long the email verification links stay valid
+    pub verification_link_hours: u64,
+    // The page of the website resetting the password, the reset links open it with the token as their query
+    pub password_reset_url: String,
+    // How long the password reset tokens stay valid
+    pub password_reset_minutes: u64,
+}
+
+impl Default for MailSettings {
+    fn default() -> Self {
+        Self {
+            transport: MailTransportKind::default(),
+            from: "Areation <no-reply@localhost>".to_string(),
+            public_url: "http://localhost:8080".to_string(),
+            smtp: SmtpSettings::default(),
+            max_attempts: 5,
+            retry_backoff_secs: 60,
+            poll_interval_ms: 10_000,
+            retention_hours: 24 * 7,
+            link_secret: None,
+            verification_link_hours: 48,
+            password_reset_url: "http://localhost:3000/reset_password".to_string(),
+            password_reset_minutes: 60,
+        }
+    }
+}
+
+/// An OpenID Connect provider the users sign in with, through the authorization code flow with PKCE
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OidcProvider {
+    // The configuration of the provider is discovered at `<issuer>/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    // The secret of a confidential client, the public clients only rely on PKCE
+    pub client_secret: Option<String>,
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+    // The page the provider sends the users back to, which passes the code and the state to
+    // /user/oidc/callback, the callback of the API itself is used when it is not set
+    pub redirect_url: Option<String>,
+}
+
+fn default_oidc_scopes() -> Vec<String> {
+    ["openid", "email", "profile"].map(String::from).to_vec()
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OidcSettings {
+    // The providers by name, the name is part of the path that starts the sign in
+    pub providers: BTreeMap<String, OidcProvider>,
+    // How long the users have to sign in at the provider
+    pub login_minutes: u64,
+    // How long the API waits for the provider when it fetches its configuration or exchanges a code
+    pub timeout_ms: u64,
+}
+
+impl Default for OidcSettings {
+    fn default() -> Self {
+        Self {
+            providers: BTreeMap::new(),
+            login_minutes: 10,
+            timeout_ms: 10_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TwoFactorSettings {
+    // The name the authenticator apps show the accounts under
+    pub issuer: String,
+    // How long the users have to send a code once their credentials are checked
+    pub challenge_minutes: u64,
+    // The recovery codes given when the second factor is enabled, each one replaces a code once
+    pub recovery_codes: usize,
+}
+
+impl Default for TwoFactorSettings {
+    fn default() -> Self {
+        Self {
+            issuer: "Areation".to_string(),
+            challenge_minutes: 5,
+            recovery_codes: 10,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    /// The configuration could not be read, or a value has the wrong type.
+    Load(Box<rocket::figment::Error>),
+    /// Every value that does not pass the validation.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load(err) => {
+                for err in err.as_ref().clone() {
+                    writeln!(f, "- {err}")?;
+                }
+                Ok(())
+            }
+            Self::Invalid(problems) => {
+                for problem in problems {
+                    writeln!(f, "- {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl ApiSettings {
+    /// Reads and validates the configuration of the API
+    pub fn retrieve() -> Result<Self, SettingsError> {
+        let path = env::var(CONFIG_FILE_VARIABLE).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+        let figment = Figment::from(Serialized::defaults(Self::default()))
+            .merge(Toml::file(path))
+            .merge(legacy_env())
+            .merge(Env::prefixed("API_").split("__"));
+
+        Self::from_figment(figment)
+    }
+
+    pub fn from_figment(figment: Figment) -> Result<Self, SettingsError> {
+        let settings: Self = figment
+            .extract_lossy()
+            .map_err(|err| SettingsError::Load(Box::new(err)))?;
+        settings.validate().map_err(SettingsError::Invalid)?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        let database = &self.database;
+        if database.backend == DatabaseBackend::MongoDb {
+            match &database.uri {
+                Some(uri) if !uri.starts_with("mongodb://") && !uri.starts_with("mongodb+srv://") => {
+                    problems.push(format!("database.uri must start with mongodb:// or mongodb+srv://, found {uri:?}"))
+                }
+                Some(_) => {}
+                None => {
+                    if database.hostname.is_empty() {
+                        problems.push("database.hostname is required when database.uri is not set".to_string());
+                    }
+                    if database.port == 0 {
+                        problems.push("database.port must be greater than 0".to_string());
+                    }
+                }
+            }
+            if database.database.is_empty() {
+                problems.push("database.database is required".to_string());
+            }
+            if let (Some(min), Some(max)) = (database.min_pool_size, database.max_pool_size) {
+                if min > max {
+                    problems.push("database.min_pool_size must not be greater than database.max_pool_size".to_string());
+                }
+            }
+            if database.max_pool_size == Some(0) {
+                problems.push("database.max_pool_size must be greater than 0".to_string());
+            }
+        }
+
+        let telemetry = &self.telemetry;
+        let endpoint = &telemetry.hostname;
+        if telemetry.exporter.is_remote() && !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            problems.push(format!("telemetry.hostname must be an http(s) URL, found {endpoint:?}"));
+        }
+        for (name, value) in &telemetry.headers {
+            let valid_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_name || value.chars().any(|c| c.is_control()) {
+                problems.push(format!("telemetry.headers must contain valid HTTP headers, found {name:?}"));
+            }
+        }
+        if telemetry.queue_size == 0 {
+            problems.push("telemetry.queue_size must be greater than 0".to_string());
+        }
+        if telemetry.batch_size == 0 || telemetry.batch_size > telemetry.queue_size {
+            problems.push("telemetry.batch_size must be greater than 0 and not greater than telemetry.queue_size".to_string());
+        }
+        if telemetry.batch_timeout_ms == 0 || telemetry.metrics_interval_ms == 0 {
+            problems.push("telemetry.batch_timeout_ms and telemetry.metrics_interval_ms must be greater than 0".to_string());
+        }
+
+        if let Err(err) = telemetry::log_filter(&self.logging) {
+            problems.push(format!("logging.level and logging.modules must hold valid levels, {err}"));
+        }
+
+        if self.signaling.servers.is_empty() {
+            problems.push("signaling.servers must contain at least one server".to_string());
+        }
+        for (index, server) in self.signaling.servers.iter().enumerate() {
+            if server.hostname.is_empty() || server.port == 0 {
+                problems.push(format!("signaling.servers[{index}] must have a hostname and a port"));
+            }
+        }
+
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && !origin.starts_with("http://") && !origin.starts_with("https://") {
+                problems.push(format!("cors.allowed_origins must contain * or http(s) origins, found {origin:?}"));
+            }
+        }
+
+        let tokens = &self.tokens;
+        if tokens.lifetime_hours == 0 || tokens.access_token_max_days == 0 || tokens.access_tokens_per_user == 0 {
+            problems.push(
+                "tokens.lifetime_hours, tokens.access_token_max_days and tokens.access_tokens_per_user must be greater than 0"
+                    .to_string(),
+            );
+        }
+
+        if self.metrics.token.as_deref() == Some("") {
+            problems.push("metrics.token must not be empty, remove it to make the endpoint public".to_string());
+        }
+        if self.metrics.collect_interval_ms == 0 {
+            problems.push("metrics.collect_interval_ms must be greater than 0".to_string());
+        }
+
+        let rate_limits = &self.rate_limits;
+        if rate_limits.requests_per_minute == 0 {
+            problems.push("rate_limits.requests_per_minute must be greater than 0".to_string());
+        }
+        for (name, limit) in [("authentication", rate_limits.authentication), ("lookup", rate_limits.lookup)] {
+            if limit.requests_per_minute == 0 {
+                problems.push(format!("rate_limits.{name}.requests_per_minute must be greater than 0"));
+            }
+        }
+        let lockout = &rate_limits.lockout;
+        if lockout.max_failures == 0 || lockout.lockout_secs == 0 {
+            problems.push("rate_limits.lockout.max_failures and rate_limits.lockout.lockout_secs must be greater than 0".to_string());
+        }
+        if lockout.max_lockout_secs < lockout.lockout_secs {
+            problems.push("rate_limits.lockout.max_lockout_secs must not be lower than rate_limits.lockout.lockout_secs".to_string());
+        }
+
+        let mail = &self.mail;
+        if mail.from.parse::<lettre::message::Mailbox>().is_err() {
+            problems.push(format!("mail.from must be an address or `Name <address>`, found {:?}", mail.from));
+        }
+        if !mail.public_url.starts_with("http://") && !mail.public_url.starts_with("https://") {
+            problems.push(format!("mail.public_url must start with http:// or https://, found {:?}", mail.public_url));
+        }
+        if mail.transport == MailTransportKind::Smtp && mail.smtp.hostname.is_empty() {
+            problems.push("mail.smtp.hostname is required with the smtp transport".to_string());
+        }
+        if !mail.password_reset_url.starts_with("http://") && !mail.password_reset_url.starts_with("https://") {
+            problems.push(format!(
+                "mail.password_reset_url must start with http:// or https://, found {:?}",
+                mail.password_reset_url
+            ));
+        }
+        if mail.max_attempts == 0
+            || mail.poll_interval_ms == 0
+            || mail.verification_link_hours == 0
+            || mail.password_reset_minutes == 0
+        {
+            problems.push(
+                "mail.max_attempts, mail.poll_interval_ms, mail.verification_link_hours and mail.password_reset_minutes \
+                 must be greater than 0"
+                    .to_string(),
+            );
+        }
+        if mail.link_secret.as_ref().is_some_and(|secret| secret.len() < 32) {
+            problems.push("mail.link_secret must be at least 32 characters long".to_string());
+        }
+
+        let oidc = &self.oidc;
+        if oidc.login_minutes == 0 || oidc.timeout_ms == 0 {
+            problems.push("oidc.login_minutes and oidc.timeout_ms must be greater than 0".to_string());
+        }
+        for (name, provider) in &oidc.providers {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                problems.push(format!("oidc.providers names must only contain letters, digits, - and _, found {name:?}"));
+            }
+            if !provider.issuer.starts_with("https://") && !provider.issuer.starts_with("http://") {
+                problems.push(format!("oidc.providers.{name}.issuer must be an http(s) URL, found {:?}", provider.issuer));
+            }
+            if provider.client_id.is_empty() {
+                problems.push(format!("oidc.providers.{name}.client_id is required"));
+            }
+            if !provider.scopes.iter().any(|scope| scope == "openid") {
+                problems.push(format!("oidc.providers.{name}.scopes must contain openid"));
+            }
+            if let Some(url) = &provider.redirect_url {
+                if !url.starts_with("https://") && !url.starts_with("http://") {
+                    problems.push(format!("oidc.providers.{name}.redirect_url must be an http(s) URL, found {url:?}"));
+                }
+            }
+        }
+
+        let two_factor = &self.two_factor;
+        if two_factor.issuer.is_empty() || two_factor.issuer.contains(':') {
+            problems.push(format!("two_factor.issuer must not be empty nor contain ':', found {:?}", two_factor.issuer));
+        }
+        if two_factor.challenge_minutes == 0 || two_factor.recovery_codes == 0 {
+            problems.push("two_factor.challenge_minutes and two_factor.recovery_codes must be greater than 0".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+// legacy_env reads the legacy variables as strings, the values are converted to the expected types when extracted
+fn legacy_env() -> Figment {
+    let figment = LEGACY_VARIABLES
+        .iter()
+        .fold(Figment::new(), |figment, (variable, key)| match env::var(variable) {
+            Ok(value) if !value.trim().is_empty() => {
+                figment.merge(Serialized::default(key, value.trim_end().to_string()))
+            }
+            _ => figment,
+        });
+
+    match env::var(HEADERS_VARIABLE) {
+        Ok(headers) if !headers.trim().is_empty() => {
+            figment.merge(Serialized::default("telemetry.headers", parse_headers(&headers)))
+        }
+        _ => figment,
+    }
+}
+
+// parse_headers reads the `name=value` pairs of OTEL_EXPORTER_OTLP_HEADERS, the pairs without a `=` are ignored
+fn parse_headers(headers: &str) -> BTreeMap<String, String> {
+    headers
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use database::DatabaseBackend;
+    use rocket::figment::{
+        providers::{Format, Serialized, Toml},
+        Figment,
+    };
+
+    use telemetry::{ExporterKind, LogFormat};
+
+    use super::{parse_headers, ApiSettings, RateLimitBackend, SettingsError};
+
+    fn figment(toml: &str) -> Figment {
+        Figment::from(Serialized::defaults(ApiSettings::default())).merge(Toml::string(toml))
+    }
+
+    #[test]
+    fn test_layers() {
+        let settings = ApiSettings::from_figment(
+            figment(
+                r#"
+                [database]
+                uri = "mongodb+srv://cluster.example.com"
+                backend = "memory"
+
+                [[signaling.servers]]
+                hostname = "signaling.example.com"
+                port = 4000
+                "#,
+            )
+            .merge(Serialized::default("database.port", "27018")),
+        )
+        .unwrap();
+
+        assert_eq!(settings.database.uri.as_deref(), Some("mongodb+srv://cluster.example.com"));
+        assert_eq!(settings.database.backend, DatabaseBackend::Memory);
+        assert_eq!(settings.database.port, 27018);
+        assert_eq!(settings.signaling.servers[0].hostname, "signaling.example.com");
+        assert_eq!(settings.tokens.lifetime_hours, 24 * 30);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let err = ApiSettings::from_figment(figment(
+            r#"
+            telemetry.hostname = "collector:4318"
+            tokens.lifetime_hours = 0
+            cors.allowed_origins = ["example.com"]
+            "#,
+        ))
+        .err()
+        .unwrap();
+
+        let SettingsError::Invalid(problems) = err else {
+            panic!("Unexpected error: {err}");
+        };
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].starts_with("telemetry.hostname"));
+    }
+
+    #[test]
+    fn test_exporter() {
+        let settings = ApiSettings::from_figment(figment(
+            r#"
+            telemetry.exporter = "stdout"
+            telemetry.hostname = ""
+            "#,
+        ))
+        .unwrap();
+        assert_eq!(settings.telemetry.exporter, ExporterKind::Stdout);
+
+        let settings = ApiSettings::from_figment(
+            figment("")
+                .merge(Serialized::default("telemetry.exporter", "grpc"))
+                .merge(Serialized::default("telemetry.headers", parse_headers("Authorization=Bearer a=b, x-team = api"))),
+        )
+        .unwrap();
+        assert_eq!(settings.telemetry.exporter, ExporterKind::Grpc);
+        assert_eq!(settings.telemetry.headers["Authorization"], "Bearer a=b");
+        assert_eq!(settings.telemetry.headers["x-team"], "api");
+    }
+
+    #[test]
+    fn test_rate_limits() {
+        let settings = ApiSettings::from_figment(figment(
+            r#"
+            [rate_limits]
+            backend = "database"
+            authentication = { requests_per_minute = 5, burst = 2 }
+            lockout.max_failures = 3
+            "#,
+        ))
+        .unwrap();
+        assert_eq!(settings.rate_limits.backend, RateLimitBackend::Database);
+        assert_eq!(settings.rate_limits.authentication.requests_per_minute, 5);
+        assert_eq!(settings.rate_limits.lookup.requests_per_minute, 30);
+        assert_eq!(settings.rate_limits.lockout.max_failures, 3);
+        assert_eq!(settings.rate_limits.lockout.lockout_secs, 60);
+
+        let err = ApiSettings::from_figment(figment("rate_limits.lockout = { lockout_secs = 600, max_lockout_secs = 60 }"))
+            .err()
+            .unwrap();
+        let SettingsError::Invalid(problems) = err else {
+            panic!("Unexpected error: {err}");
+        };
+        assert_eq!(problems, vec!["rate_limits.lockout.max_lockout_secs must not be lower than rate_limits.lockout.lockout_secs"]);
+    }
+
+    #[test]
+    fn test_oidc_providers() {
+        let settings = ApiSettings::from_figment(figment(
+            r#"
+            [oidc.providers.example]
+            issuer = "https://accounts.example.com"
+            client_id = "areation"
+            "#,
+        ))
+        .unwrap();
+        let provider = &settings.oidc.providers["example"];
+        assert_eq!(provider.scopes, vec!["openid", "email", "profile"]);
+        assert!(provider.client_secret.is_none());
+        assert_eq!(settings.oidc.login_minutes, 10);
+
+        let err = ApiSettings::from_figment(figment(
+            r#"
+            [oidc.providers."an example"]
+            issuer = "accounts.example.com"
+            client_id = "areation"
+            scopes = ["email"]
+            "#,
+        ))
+        .err()
+        .unwrap();
+        let SettingsError::Invalid(problems) = err else {
+            panic!("Unexpected error: {err}");
+        };
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].starts_with("oidc.providers names"));
+    }
+
+    #[test]
+    fn test_two_factor() {
+        let settings = ApiSettings::from_figment(figment("")).unwrap();
+        assert_eq!(settings.two_factor.issuer, "Areation");
+        assert_eq!(settings.two_factor.recovery_codes, 10);
+
+        let err = ApiSettings::from_figment(figment(r#"two_factor = { issuer = "Are:ation", challenge_minutes = 0 }"#))
+            .err()
+            .unwrap();
+        let SettingsError::Invalid(problems) = err else {
+            panic!("Unexpected error: {err}");
+        };
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("two_factor.issuer"));
+    }
+
+    #[test]
+    fn test_logging() {
+        let settings = ApiSettings::from_figment(figment(
+            r#"
+            [logging]
+            format = "json"
+            modules = { rocket = "warn", "api::route" = "debug" }
+            "#,
+        ))
+        .unwrap();
+        assert_eq!(settings.logging.format, LogFormat::Json);
+        assert_eq!(settings.logging.directives(), "info,api::route=debug,rocket=warn");
+
+        let err = ApiSettings::from_figment(figment("logging.modules = { rocket = \"chatty\" }"))
+            .err()
+            .unwrap();
+        let SettingsError::Invalid(problems) = err else {
+            panic!("Unexpected error: {err}");
+        };
+        assert!(problems[0].starts_with("logging.level"));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let err = ApiSettings::from_figment(figment("database.port = \"not a port\"")).err().unwrap();
+
+        assert!(matches!(err, SettingsError::Load(_)));
+        assert!(err.to_string().contains("database.port"));
     }
 }
@@ -2,39 +2,54 @@ use std::collections::HashMap;
 use std::env;
 use std::future::Future;
 
+use database::access_token::AccessToken;
+use database::asset::Asset;
 use database::authentication::Authentication;
+use database::comment::Comment;
+use database::id::{AccessTokenId, CommentId, OrganizationId, PermissionId, ReportId, UserId};
 use database::login::Login;
+use database::moderation::{ContentKind, Report, ReportState};
 use database::organization::Organization;
 use database::permission::{Permission};
+use database::timestamp::Timestamp;
+use database::two_factor::TwoFactor;
 use database::user::User;
 use database::Database;
+use rand::Rng;
 use rocket::http::{Header, Method};
 use rocket::local::asynchronous::{Client, LocalResponse};
 use testcontainers::clients::Cli;
 use testcontainers::{core::WaitFor, Image};
 
-use crate::{get_rocket, Server};
+use crate::{access_token, get_rocket, settings::ApiSettings, two_factor};
 
 /// Creates an user with the desired group
-/// Adds it to the database
+/// Adds it to the database, with its email verified
 /// Returns it
 pub async fn create_user(
     database: &Database,
     authentication: Authentication,
-    permissions: Vec<String>,
+    permissions: Vec<PermissionId>,
 ) -> User {
-    let timestamp = Server::current_time();
+    let timestamp = Timestamp::now();
+    let identities = match &authentication {
+        Authentication::Oidc(identity) => vec![identity.clone()],
+        _ => Vec::new(),
+    };
 
     let user = User {
         authentication: authentication.clone(),
-        unique_id: Server::generate_unique_id().to_string(),
-        creation_date: timestamp.to_string(),
+        unique_id: UserId::generate(),
+        creation_date: timestamp,
         logins: vec![Login::new(
             "127.0.0.1".to_string(),
             timestamp,
             authentication,
         )],
-        permissions: permissions.clone(),
+        permissions,
+        banned: false,
+        email_verified: true,
+        identities,
     };
 
     let _ = database.user_manager.create_user(&user).await;
@@ -42,6 +57,50 @@ pub async fn create_user(
     user
 }
 
+/// Enables a second factor for the user, with a single recovery code
+/// Returns the secret and the recovery code
+pub async fn enable_two_factor(database: &Database, user: &User) -> (String, String) {
+    let secret = two_factor::generate_secret();
+    let recovery_code = two_factor::generate_recovery_codes(1).remove(0);
+    let pending = TwoFactor {
+        user_id: user.unique_id,
+        secret: secret.clone(),
+        enabled: false,
+        recovery_codes: Vec::new(),
+        last_step: 0,
+        creation_date: Timestamp::now(),
+        enabled_at: None,
+    };
+
+    let two_factors = database.two_factor_manager.as_ref();
+    let _ = two_factors.start(&pending).await;
+    let hashes = vec![two_factor::hash_recovery_code(&recovery_code)];
+    // The codes of the current steps are still accepted
+    let _ = two_factors.enable(user.unique_id, hashes, 1, Timestamp::now()).await;
+
+    (secret, recovery_code)
+}
+
+/// Creates a personal access token of the user, limited to the scopes
+/// Returns the token
+pub async fn create_access_token(database: &Database, user: &User, scopes: &[&str]) -> String {
+    let token = access_token::generate();
+    let now = Timestamp::now();
+    let access_token = AccessToken {
+        unique_id: AccessTokenId::generate(),
+        user_id: user.unique_id,
+        name: "test".to_string(),
+        token_hash: access_token::hash(&token),
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        creation_date: now,
+        expires_at: now.plus(std::time::Duration::from_secs(3600)),
+        last_used: None,
+    };
+    let _ = database.access_token_manager.create(&access_token).await;
+
+    token
+}
+
 /// Creates an user with the desired group
 /// Adds it to the database
 /// Returns it
@@ -49,18 +108,19 @@ pub async fn get_user(database: &Database) -> User {
     create_user(database, Authentication::None, Vec::new()).await
 }
 
-pub async fn create_org(database: &Database, user: &User, server_ids: Vec<String>) -> Organization {
-    let timestamp = Server::current_time();
-    let unique_id = Server::generate_unique_id().to_string();
+pub async fn create_org(database: &Database, user: &User, server_ids: Vec<UserId>) -> Organization {
+    let timestamp = Timestamp::now();
+    let unique_id = OrganizationId::generate();
 
     let organization = Organization {
-        unique_id: unique_id.clone(),
-        creation_date: timestamp.to_string(),
+        unique_id,
+        creation_date: timestamp,
         member_ids: Vec::new(),
         name: format!("name-{unique_id}"),
-        owner_id: user.unique_id.clone(),
+        owner_id: user.unique_id,
         server_ids,
         projects_ids: Vec::new(),
+        require_two_factor: false,
     };
 
     let _ = database
@@ -75,15 +135,118 @@ pub async fn get_org(database: &Database, user: &User) -> Organization {
     create_org(database, user, Vec::new()).await
 }
 
+/// Creates an asset published by the user
+/// Adds it to the database
+/// Returns it
+pub async fn create_asset(database: &Database, author: &User) -> Asset {
+    let asset = Asset {
+        id: rand::thread_rng().gen(),
+        author_id: Some(author.unique_id),
+        title: "Test asset".to_string(),
+        description: "Test description".to_string(),
+        upload_date: Timestamp::now(),
+        price: "0".to_string(),
+        cover_image: String::new(),
+        images: Vec::new(),
+        comments: Vec::new(),
+        upvote_user_ids: Vec::new(),
+        downvote_user_ids: Vec::new(),
+        favorite_user_ids: Vec::new(),
+        status: Default::default(),
+    };
+
+    let _ = database.asset_manager.create_asset(&asset).await;
+
+    asset
+}
+
+/// Creates a comment written by the user on the asset
+/// Adds it to the database
+/// Returns it
+pub async fn create_comment(database: &Database, asset: &Asset, author: &User) -> Comment {
+    let comment = Comment {
+        unique_id: CommentId::generate(),
+        asset_id: asset.id,
+        user_id: author.unique_id,
+        content: "Test comment".to_string(),
+        timestamp: Timestamp::now(),
+        status: Default::default(),
+    };
+
+    let _ = database.comment_manager.add_comment(&comment).await;
+
+    comment
+}
+
+/// Creates a pending report on the asset
+/// Adds it to the moderation queue
+/// Returns it
+pub async fn create_report(database: &Database, asset: &Asset, reporter: &User) -> Report {
+    insert_report(database, ContentKind::Asset, asset.id.to_string(), reporter).await
+}
+
+/// Creates a pending report on the comment
+/// Adds it to the moderation queue
+/// Returns it
+pub async fn create_comment_report(database: &Database, comment: &Comment, reporter: &User) -> Report {
+    insert_report(database, ContentKind::Comment, comment.unique_id.to_string(), reporter).await
+}
+
+async fn insert_report(
+    database: &Database,
+    content_kind: ContentKind,
+    content_id: String,
+    reporter: &User,
+) -> Report {
+    let report = Report {
+        unique_id: ReportId::generate(),
+        content_kind,
+        content_id,
+        reporter_id: reporter.unique_id,
+        reason: "Test reason".to_string(),
+        creation_date: Timestamp::now(),
+        state: ReportState::Pending,
+    };
+
+    let _ = database.moderation_manager.create_report(&report).await;
+
+    report
+}
+
+/// Runs the test against a fresh in-memory database
+///
+/// With TEST_DATABASE_BACKEND=mongodb, it runs against a MongoDB container instead, which requires Docker
 pub async fn run_test<F, Fut>(lambda_func: F)
 where
     F: Fn(Client) -> Fut,
     Fut: Future,
 {
-    let docker = Cli::docker();
-    let container = &docker.run(MongoContainer::default_env());
-    set_test_env(container.get_host_port_ipv4(27017));
-    let client = Client::tracked(get_rocket()).await.unwrap();
+    run_test_with_settings(|_| {}, lambda_func).await;
+}
+
+/// Runs the test like `run_test`, with the settings changed by `configure`
+pub async fn run_test_with_settings<C, F, Fut>(configure: C, lambda_func: F)
+where
+    C: Fn(&mut ApiSettings),
+    F: Fn(Client) -> Fut,
+    Fut: Future,
+{
+    if env::var("TEST_DATABASE_BACKEND").as_deref() == Ok("mongodb") {
+        let docker = Cli::docker();
+        let container = &docker.run(MongoContainer::default_env());
+        set_test_env("mongodb", container.get_host_port_ipv4(27017));
+        let mut settings = ApiSettings::retrieve().unwrap();
+        configure(&mut settings);
+        let client = Client::tracked(get_rocket(settings)).await.unwrap();
+
+        lambda_func(client).await;
+        return;
+    }
+
+    set_test_env("memory", 0);
+    let mut settings = ApiSettings::retrieve().unwrap();
+    configure(&mut settings);
+    let client = Client::tracked(get_rocket(settings)).await.unwrap();
 
     lambda_func(client).await;
 }
@@ -94,7 +257,7 @@ pub async fn dispatch_request(
     uri: String,
     body: Option<String>,
     token: Option<String>,
-) -> LocalResponse {
+) -> LocalResponse<'_> {
     let mut request = match method {
         Method::Get => client.get(uri),
         Method::Post => client
@@ -115,14 +278,15 @@ pub async fn dispatch_request(
     request.dispatch().await
 }
 
-fn set_test_env(mongo_port: u16) {
+fn set_test_env(backend: &str, mongo_port: u16) {
+    env::set_var("DATABASE_BACKEND", backend);
     env::set_var("MONGODB_HOSTNAME", "127.0.0.1");
     env::set_var("MONGODB_PORT", mongo_port.to_string());
     env::set_var("MONGODB_USERNAME", "test");
     env::set_var("MONGODB_PASSWORD", "test");
     env::set_var("MONGODB_DATABASE", "test");
     env::set_var("OTEL_RESOURCE_ATTRIBUTES", "test");
-    env::set_var("OTEL_EXPORTER_OTLP_ENDPOINT", "test");
+    env::set_var("OTEL_EXPORTER_OTLP_ENDPOINT", "http://127.0.0.1:4318");
     env::set_var("OTEL_EXPORTER_OTLP_TOKEN", "test");
 }
 
@@ -175,7 +339,7 @@ pub async fn create_permission(
 ) -> Permission {
 
     let perm = Permission {
-        unique_id: Server::generate_unique_id().to_string(),
+        unique_id: PermissionId::generate(),
         name: name.to_string(),
     };
 
@@ -187,7 +351,7 @@ pub async fn create_permission(
 pub async fn get_permission_id(
     database: &Database,
     name: &str,
-) -> String {
+) -> PermissionId {
     let perm = create_permission(database, name).await;
     perm.unique_id
 }
\ No newline at end of file
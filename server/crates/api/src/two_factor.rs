@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use data_encoding::BASE32_NOPAD;
+use database::{
+    id::UserId, managers::TwoFactorRepository, organization::Organization, timestamp::Timestamp, two_factor::TwoFactor,
+    user::User,
+};
+use hmac::{Hmac, Mac};
+use mongodb::error::Error;
+use rand::RngCore;
+use reqwest::Url;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::mail::LinkSigner;
+
+/// The purpose of the challenges signed for the users who still have to send a code after their password
+pub const TWO_FACTOR_PURPOSE: &str = "two_factor";
+
+// The parameters of the codes, the ones every authenticator app supports
+const STEP_SECS: i64 = 30;
+const DIGITS: usize = 6;
+const SECRET_BYTES: usize = 20;
+// The steps around the current one whose codes are accepted, for the clocks of the phones that drift
+const WINDOW: i64 = 1;
+
+/// Generates the secret shared with the authenticator app, base32 encoded
+pub fn generate_secret() -> String {
+    let mut secret = [0; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+/// Returns the `otpauth://` URI the authenticator apps import, usually shown as a QR code
+pub fn provisioning_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let mut url = Url::parse("otpauth://totp/").expect("The provisioning URI is valid");
+    url.path_segments_mut()
+        .expect("The provisioning URI has a path")
+        .pop_if_empty()
+        .push(&format!("{issuer}:{account}"));
+    url.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &STEP_SECS.to_string());
+    url.into()
+}
+
+/// Returns the time step of the date, a new code is shown for each step
+pub fn time_step(now: Timestamp) -> i64 {
+    now.timestamp_millis().div_euclid(STEP_SECS * 1000)
+}
+
+// code_at computes the code of the time step, as described by RFC 6238
+fn code_at(key: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset],
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        (binary & 0x7fff_ffff) % 10u32.pow(DIGITS as u32),
+        width = DIGITS
+    )
+}
+
+/// Returns the code the app shows at the date
+pub fn code(secret: &str, now: Timestamp) -> Option<String> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    Some(code_at(&key, time_step(now)))
+}
+
+/// Returns the time step the code of the app was shown for, if it is valid at that date
+pub fn check_code(secret: &str, code: &str, now: Timestamp) -> Option<i64> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let code = code.trim();
+    if code.len() != DIGITS || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let current = time_step(now);
+    (current - WINDOW..=current + WINDOW).find(|step| {
+        // The codes are compared in constant time, like the signatures
+        code_at(&key, *step)
+            .bytes()
+            .zip(code.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+    })
+}
+
+/// Generates the recovery codes, which replace the app once each when the phone is lost
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| {
+            let mut bytes = [0; 7];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let code = BASE32_NOPAD.encode(&bytes).to_ascii_lowercase();
+            format!("{}-{}", &code[..5], &code[5..10])
+        })
+        .collect()
+}
+
+/// Hashes a recovery code, only the hashes are stored
+///
+/// The case, the dashes and the spaces are ignored, so that the codes can be typed loosely.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+/// Checks a code of the app or a recovery code against the enabled second factor, a code is accepted once
+pub async fn verify(
+    two_factors: &dyn TwoFactorRepository,
+    two_factor: &TwoFactor,
+    code: &str,
+    now: Timestamp,
+) -> Result<bool, Error> {
+    match check_code(&two_factor.secret, code, now) {
+        Some(step) => two_factors.use_step(two_factor.user_id, step).await,
+        None => {
+            two_factors
+                .use_recovery_code(two_factor.user_id, &hash_recovery_code(code))
+                .await
+        }
+    }
+}
+
+/// Whether the user has to send a code when signing in
+pub async fn is_enabled(two_factors: &dyn TwoFactorRepository, user_id: UserId) -> Result<bool, Error> {
+    Ok(two_factors
+        .get(user_id)
+        .await?
+        .is_some_and(|two_factor| two_factor.enabled))
+}
+
+/// Whether the user meets the requirement of the organization, which may refuse the users without a second factor
+pub async fn meets_requirement(
+    two_factors: &dyn TwoFactorRepository,
+    organization: &Organization,
+    user_id: UserId,
+) -> Result<bool, Error> {
+    if !organization.require_two_factor {
+        return Ok(true);
+    }
+    is_enabled(two_factors, user_id).await
+}
+
+/// Signs the challenge the user answers with a code, it stands for the password that was checked
+///
+/// The challenge is bound to the last token of the user, so that it no longer works once the user signed in.
+pub fn challenge(signer: &LinkSigner, user: &User, lifetime: Duration, now: Timestamp) -> String {
+    let user_id = user.unique_id.to_string();
+    signer.sign(
+        TWO_FACTOR_PURPOSE,
+        &user_id,
+        challenge_binding(user),
+        now.plus(lifetime),
+    )
+}
+
+/// Returns the value the challenges of the user are bound to
+pub fn challenge_binding(user: &User) -> &str {
+    user.get_token().map(String::as_str).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use data_encoding::BASE32_NOPAD;
+    use database::timestamp::Timestamp;
+
+    use super::{check_code, code_at, generate_recovery_codes, hash_recovery_code, provisioning_uri};
+
+    #[test]
+    fn test_codes() {
+        // The SHA-1 test vectors of RFC 6238, truncated to 6 digits
+        let key = b"12345678901234567890";
+        assert_eq!(code_at(key, 59 / 30), "287082");
+        assert_eq!(code_at(key, 1_111_111_109 / 30), "081804");
+        assert_eq!(code_at(key, 1_234_567_890 / 30), "005924");
+
+        let secret = BASE32_NOPAD.encode(key);
+        let now = Timestamp::from_millis(1_111_111_109_000);
+        assert_eq!(check_code(&secret, "081804", now), Some(1_111_111_109 / 30));
+        assert_eq!(check_code(&secret, " 081804 ", now), Some(1_111_111_109 / 30));
+        // The code of the previous step is still accepted, not older ones
+        assert!(check_code(&secret, "081804", Timestamp::from_millis(1_111_111_139_000)).is_some());
+        assert!(check_code(&secret, "081804", Timestamp::from_millis(1_111_111_200_000)).is_none());
+        assert!(check_code(&secret, "081805", now).is_none());
+        assert!(check_code(&secret, "81804", now).is_none());
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes(3);
+        assert_eq!(codes.len(), 3);
+        assert_eq!(codes[0].len(), 11);
+        assert_ne!(codes[0], codes[1]);
+        assert_eq!(
+            hash_recovery_code(&codes[0]),
+            hash_recovery_code(&codes[0].to_uppercase().replace('-', " "))
+        );
+        assert_ne!(hash_recovery_code(&codes[0]), hash_recovery_code(&codes[1]));
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        assert_eq!(
+            provisioning_uri("Areation", "jane@example.com", "JBSWY3DPEHPK3PXP"),
+            "otpauth://totp/Areation:jane@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Areation&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use mongodb::{
+    bson::{self, Document},
+    error::Error,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Serializes the value like the MongoDB driver does before storing it.
+///
+/// `bson::to_document` behaves like a human readable format, where timestamps are strings,
+/// this goes through the binary format used by the driver instead, so that timestamps are BSON dates.
+pub(crate) fn to_document<T: Serialize>(value: &T) -> Result<Document, Error> {
+    let bytes = bson::to_vec(value)?;
+    Ok(bson::from_slice(&bytes)?)
+}
+
+/// Deserializes a document like the MongoDB driver does when reading it, see `to_document`.
+pub(crate) fn from_document<T: DeserializeOwned>(document: Document) -> Result<T, Error> {
+    let bytes = bson::to_vec(&document)?;
+    Ok(bson::from_slice(&bytes)?)
+}
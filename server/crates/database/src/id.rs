@@ -0,0 +1,216 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use mongodb::bson::Bson;
+use rocket::request::FromParam;
+use rocket_okapi::okapi::schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use uuid::{Uuid, Version};
+
+/// The longest id generated before the typed ids, `u64::MAX` has 20 digits.
+const LEGACY_MAX_LENGTH: usize = 20;
+
+/// An entity identifier, either a UUIDv7 or a decimal id generated before the typed ids.
+///
+/// Ids are stored as strings and sort as those strings, character by character, which is how MongoDB and the page
+/// cursors compare them. UUIDv7 start with their creation time in milliseconds and are generated in increasing order
+/// by this process, so they sort by creation date. Legacy ids are kept as they are and do not: `"10"` sorts before
+/// `"9"`, and a legacy id sorts before or after a UUIDv7 depending on their first characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum RawId {
+    Legacy(u64),
+    Uuid(Uuid),
+}
+
+impl RawId {
+    fn generate() -> Self {
+        Self::Uuid(Uuid::now_v7())
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        if !value.is_empty() && value.len() <= LEGACY_MAX_LENGTH && value.bytes().all(|byte| byte.is_ascii_digit()) {
+            return value.parse().ok().map(Self::Legacy);
+        }
+        // Only the hyphenated form is accepted, so that a 32 characters token is never taken for an id
+        if value.len() != uuid::fmt::Hyphenated::LENGTH {
+            return None;
+        }
+        let uuid = Uuid::parse_str(value).ok()?;
+        (uuid.get_version() == Some(Version::SortRand)).then_some(Self::Uuid(uuid))
+    }
+}
+
+impl Ord for RawId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            // The hyphenated lowercase form of a UUID sorts like its bytes
+            (Self::Uuid(uuid), Self::Uuid(other)) => uuid.cmp(other),
+            _ => self.to_string().cmp(&other.to_string()),
+        }
+    }
+}
+
+impl PartialOrd for RawId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for RawId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Legacy(id) => write!(f, "{id}"),
+            Self::Uuid(uuid) => write!(f, "{}", uuid.hyphenated()),
+        }
+    }
+}
+
+/// The error of a string that is not an id of the expected entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidId {
+    pub kind: &'static str,
+    pub value: String,
+}
+
+impl fmt::Display for InvalidId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {} id: {}", self.kind, self.value)
+    }
+}
+
+impl std::error::Error for InvalidId {}
+
+macro_rules! typed_id {
+    ($(#[$meta:meta])* $name:ident, $kind:literal) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(RawId);
+
+        impl $name {
+            /// Generates a new id, greater than every id previously generated by this process.
+            pub fn generate() -> Self {
+                Self(RawId::generate())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = InvalidId;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                RawId::parse(value).map(Self).ok_or_else(|| InvalidId {
+                    kind: $kind,
+                    value: value.to_string(),
+                })
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                value.parse().map_err(de::Error::custom)
+            }
+        }
+
+        impl JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+                String::json_schema(gen)
+            }
+        }
+
+        impl From<$name> for Bson {
+            fn from(id: $name) -> Self {
+                Bson::String(id.to_string())
+            }
+        }
+
+        impl<'a> FromParam<'a> for $name {
+            type Error = InvalidId;
+
+            fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+                param.parse()
+            }
+        }
+    };
+}
+
+typed_id!(UserId, "user");
+typed_id!(OrganizationId, "organization");
+typed_id!(ProjectId, "project");
+typed_id!(LicenseId, "license");
+typed_id!(PermissionId, "permission");
+typed_id!(CommentId, "comment");
+typed_id!(ReportId, "report");
+typed_id!(AuditEventId, "audit event");
+typed_id!(MailId, "mail");
+typed_id!(AccessTokenId, "access token");
+typed_id!(
+    /// The id of the signaling room opened between a user and a server.
+    RoomId,
+    "room"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{OrganizationId, UserId};
+
+    #[test]
+    fn test_generated_ids_are_sorted() {
+        let ids: Vec<UserId> = (0..1000).map(|_| UserId::generate()).collect();
+
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(ids
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_ids_sort_as_stored() {
+        let mut ids: Vec<UserId> = ["9", "10", "1708000000123"]
+            .into_iter()
+            .map(|id| id.parse().unwrap())
+            .chain((0..10).map(|_| UserId::generate()))
+            .collect();
+        ids.sort();
+
+        let stored: Vec<String> = ids.iter().map(ToString::to_string).collect();
+        let mut sorted = stored.clone();
+        sorted.sort();
+        assert_eq!(stored, sorted);
+    }
+
+    #[test]
+    fn test_parse() {
+        let id = UserId::generate();
+        assert_eq!(id.to_string().parse::<UserId>().unwrap(), id);
+        assert_eq!("1708000000123".parse::<UserId>().unwrap().to_string(), "1708000000123");
+
+        // A token, a random UUID and a number overflowing a u64 are not ids
+        assert!("aZ3kq9P0aZ3kq9P0aZ3kq9P0aZ3kq9P0".parse::<UserId>().is_err());
+        assert!("9b2f3f0e-3c1a-4c7e-8d6f-0a1b2c3d4e5f".parse::<UserId>().is_err());
+        assert!("99999999999999999999".parse::<UserId>().is_err());
+        assert_eq!(
+            "".parse::<OrganizationId>().unwrap_err().to_string(),
+            "Invalid organization id: "
+        );
+    }
+}
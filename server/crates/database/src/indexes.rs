@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use mongodb::{
+    bson::{doc, Document},
+    error::{Error, ErrorKind, WriteFailure},
+    options::IndexOptions,
+    Collection, IndexModel,
+};
+
+/// The server error code of a write violating a unique index.
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+/// The error of a write violating a unique index of the in-memory backend.
+#[derive(Debug)]
+pub(crate) struct DuplicateKey;
+
+/// Describes an index of a collection, every manager lists the indexes its queries rely on.
+///
+/// A unique index only covers the documents in which every key exists, so that documents without the field,
+/// like users registered without credentials, never conflict with each other.
+pub struct IndexSpec {
+    pub name: &'static str,
+    pub keys: &'static [&'static str],
+    pub unique: bool,
+    /// Documents are removed once the date stored in the single key is older than this duration.
+    pub expire_after: Option<Duration>,
+}
+
+impl IndexSpec {
+    pub const fn new(name: &'static str, keys: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            keys,
+            unique: false,
+            expire_after: None,
+        }
+    }
+
+    pub const fn unique(name: &'static str, keys: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            keys,
+            unique: true,
+            expire_after: None,
+        }
+    }
+
+    pub const fn ttl(name: &'static str, keys: &'static [&'static str], expire_after: Duration) -> Self {
+        Self {
+            name,
+            keys,
+            unique: false,
+            expire_after: Some(expire_after),
+        }
+    }
+
+    pub fn model(&self) -> IndexModel {
+        let mut keys = Document::new();
+        for key in self.keys {
+            keys.insert(*key, 1);
+        }
+
+        let mut options = IndexOptions::builder()
+            .name(self.name.to_string())
+            .expire_after(self.expire_after)
+            .build();
+        if self.unique {
+            let mut partial_filter = Document::new();
+            for key in self.keys {
+                partial_filter.insert(*key, doc! { "$exists": true });
+            }
+            options.unique = Some(true);
+            options.partial_filter_expression = Some(partial_filter);
+        }
+
+        IndexModel::builder().keys(keys).options(options).build()
+    }
+}
+
+/// Creates the missing indexes of the collection.
+///
+/// Fails when an index exists with different options, or when the documents already violate a unique index.
+pub async fn ensure_indexes<T>(collection: &Collection<T>, specs: &[IndexSpec]) -> Result<(), Error> {
+    if specs.is_empty() {
+        return Ok(());
+    }
+    let models = specs.iter().map(IndexSpec::model).collect::<Vec<_>>();
+    collection.create_indexes(models, None).await?;
+    Ok(())
+}
+
+/// Whether the write was rejected because it violates a unique index.
+pub fn is_duplicate_key(error: &Error) -> bool {
+    match error.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(error)) => error.code == DUPLICATE_KEY_CODE,
+        ErrorKind::Command(error) => error.code == DUPLICATE_KEY_CODE,
+        ErrorKind::BulkWrite(failure) => failure
+            .write_errors
+            .iter()
+            .flatten()
+            .any(|error| error.code == DUPLICATE_KEY_CODE),
+        _ => error.get_custom::<DuplicateKey>().is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use mongodb::bson::doc;
+
+    use super::IndexSpec;
+
+    #[test]
+    fn test_unique_index_is_partial() {
+        let model = IndexSpec::unique("email", &["authentication.Credentials.email"]).model();
+        let options = model.options.unwrap();
+
+        assert_eq!(model.keys, doc! { "authentication.Credentials.email": 1 });
+        assert_eq!(options.unique, Some(true));
+        assert_eq!(
+            options.partial_filter_expression,
+            Some(doc! { "authentication.Credentials.email": { "$exists": true } })
+        );
+    }
+
+    #[test]
+    fn test_ttl_index() {
+        let model = IndexSpec::ttl("expiration", &["expires_at"], Duration::ZERO).model();
+        let options = model.options.unwrap();
+
+        assert_eq!(options.name.as_deref(), Some("expiration"));
+        assert_eq!(options.expire_after, Some(Duration::ZERO));
+        assert_eq!(options.unique, None);
+        assert_eq!(options.partial_filter_expression, None);
+    }
+}
@@ -1,7 +1,18 @@
 mod database;
+mod document;
 mod models;
+mod pool;
+mod spans;
 
+pub mod id;
+pub mod indexes;
 pub mod managers;
+pub mod memory;
+pub mod migrations;
+pub mod pagination;
+pub mod password;
+pub mod timestamp;
 
 pub use database::*;
 pub use models::*;
+pub use pool::PoolSnapshot;
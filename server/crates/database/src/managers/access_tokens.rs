@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{bson::doc, error::Error, options::FindOptions, Collection};
+
+use crate::{
+    access_token::AccessToken,
+    id::{AccessTokenId, UserId},
+    indexes::{ensure_indexes, IndexSpec},
+    timestamp::Timestamp,
+};
+
+#[async_trait]
+pub trait AccessTokenRepository: Send + Sync {
+    async fn create(&self, token: &AccessToken) -> Result<(), Error>;
+
+    /// Returns the token of the hash if it has not expired, and records that it was used.
+    async fn use_token(&self, token_hash: &str, now: Timestamp) -> Result<Option<AccessToken>, Error>;
+
+    /// Returns the tokens of the user, the oldest first.
+    async fn list(&self, user_id: UserId) -> Result<Vec<AccessToken>, Error>;
+
+    /// Returns whether the user had the token.
+    async fn revoke(&self, user_id: UserId, id: AccessTokenId) -> Result<bool, Error>;
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error>;
+}
+
+pub struct AccessTokenManager {
+    pub tokens: Collection<AccessToken>,
+}
+
+impl AccessTokenManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("access_tokens_token_hash", &["token_hash"]),
+        IndexSpec::new("access_tokens_user_id", &["user_id"]),
+        IndexSpec::ttl("access_tokens_expires_at", &["expires_at"], Duration::ZERO),
+    ];
+
+    pub fn init(tokens: Collection<AccessToken>) -> Self {
+        Self { tokens }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.tokens, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl AccessTokenRepository for AccessTokenManager {
+    async fn create(&self, token: &AccessToken) -> Result<(), Error> {
+        self.tokens.insert_one(token, None).await?;
+        Ok(())
+    }
+
+    async fn use_token(&self, token_hash: &str, now: Timestamp) -> Result<Option<AccessToken>, Error> {
+        // The expired tokens may outlive their date until the TTL monitor removes them
+        let filter = doc! { "token_hash": token_hash, "expires_at": { "$gt": now } };
+        let update = doc! { "$set": { "last_used": now } };
+        self.tokens.find_one_and_update(filter, update, None).await
+    }
+
+    async fn list(&self, user_id: UserId) -> Result<Vec<AccessToken>, Error> {
+        let options = FindOptions::builder().sort(doc! { "creation_date": 1 }).build();
+        self.tokens
+            .find(doc! { "user_id": user_id }, options)
+            .await?
+            .try_collect()
+            .await
+    }
+
+    async fn revoke(&self, user_id: UserId, id: AccessTokenId) -> Result<bool, Error> {
+        let result = self
+            .tokens
+            .delete_one(doc! { "unique_id": id, "user_id": user_id }, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        let result = self.tokens.delete_many(doc! { "user_id": user_id }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
+
+impl Clone for AccessTokenManager {
+    fn clone(&self) -> Self {
+        Self {
+            tokens: self.tokens.clone(),
+        }
+    }
+}
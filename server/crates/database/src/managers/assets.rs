@@ -1,47 +1,171 @@
+use async_trait::async_trait;
+use futures::TryStreamExt;
 use mongodb::{
-    bson::{doc, to_bson},
+    bson::{doc, to_bson, Document},
     error::Error,
-    results::{DeleteResult, InsertOneResult, UpdateResult},
     Collection,
 };
-use crate::models::asset::Asset;
+
+use crate::{
+    document::to_document,
+    id::UserId,
+    indexes::{ensure_indexes, IndexSpec},
+    models::{asset::Asset, moderation::ModerationStatus},
+    pagination::{paginate, Page, PageRequest, PageSpec, PaginationError},
+};
+
+pub(crate) const PAGE_SPEC: PageSpec<'static> = PageSpec {
+    id_field: "id",
+    sort_fields: &["title", "upload_date", "price"],
+    filter_fields: &["title", "price", "author_id"],
+    date_field: Some("upload_date"),
+};
+
+#[async_trait]
+pub trait AssetRepository: Send + Sync {
+    async fn create_asset(&self, asset: &Asset) -> Result<(), Error>;
+
+    async fn get_asset_by_id(&self, id: u32) -> Result<Option<Asset>, Error>;
+
+    /// Returns the visible assets, optionally matching `search` in their title or description.
+    async fn get_assets(&self, search: Option<&str>, page: &PageRequest) -> Result<Page<Asset>, PaginationError>;
+
+    async fn update_asset(&self, id: u32, updated_asset: &Asset) -> Result<bool, Error>;
+
+    async fn set_status(&self, id: u32, status: ModerationStatus) -> Result<bool, Error>;
+
+    async fn delete_asset(&self, id: u32) -> Result<bool, Error>;
+
+    async fn asset_exists(&self, id: u32) -> Result<bool, Error>;
+
+    /// Returns the assets the user authored, voted on, favorited or commented, hidden ones included.
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<Asset>, Error>;
+
+    /// Removes the user from the votes, the favorites and the comments of the assets, and from their authors,
+    /// the assets themselves are kept.
+    async fn erase_user(&self, user_id: UserId) -> Result<(), Error>;
+}
 
 pub struct AssetManager {
     pub assets: Collection<Asset>,
 }
 
 impl AssetManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("assets_id", &["id"]),
+        IndexSpec::new("assets_author_id", &["author_id"]),
+        IndexSpec::new("assets_upvote_user_ids", &["upvote_user_ids"]),
+        IndexSpec::new("assets_downvote_user_ids", &["downvote_user_ids"]),
+        IndexSpec::new("assets_favorite_user_ids", &["favorite_user_ids"]),
+    ];
+
     pub fn init(assets: Collection<Asset>) -> Self {
         Self { assets }
     }
 
-    pub async fn create_asset(&self, asset: &Asset) -> Result<InsertOneResult, Error> {
-        let result = self.assets.insert_one(asset, None).await?;
-        Ok(result)
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.assets, Self::INDEXES).await
+    }
+
+    // Documents created before moderation existed have no status and are visible.
+    fn visible_filter() -> Document {
+        doc! { "status": { "$ne": to_bson(&ModerationStatus::Hidden).unwrap() } }
+    }
+
+    fn user_filter(user_id: UserId) -> Document {
+        doc! {
+            "$or": [
+                { "author_id": user_id },
+                { "upvote_user_ids": user_id },
+                { "downvote_user_ids": user_id },
+                { "favorite_user_ids": user_id },
+                { "comments.user_id": user_id },
+            ]
+        }
+    }
+}
+
+#[async_trait]
+impl AssetRepository for AssetManager {
+    async fn create_asset(&self, asset: &Asset) -> Result<(), Error> {
+        self.assets.insert_one(asset, None).await?;
+        Ok(())
     }
 
-    pub async fn get_asset_by_id(&self, id: u32) -> Result<Option<Asset>, Error> {
-        match self.assets.find_one(doc! { "id": id }, None).await? {
-            Some(asset) => Ok(Some(asset)),
-            None => Ok(None),
+    async fn get_asset_by_id(&self, id: u32) -> Result<Option<Asset>, Error> {
+        self.assets.find_one(doc! { "id": id }, None).await
+    }
+
+    async fn get_assets(&self, search: Option<&str>, page: &PageRequest) -> Result<Page<Asset>, PaginationError> {
+        let mut filter = Self::visible_filter();
+        if let Some(search) = search {
+            let pattern = escape_regex(search);
+            filter.insert(
+                "$or",
+                vec![
+                    doc! { "title": { "$regex": &pattern, "$options": "i" } },
+                    doc! { "description": { "$regex": &pattern, "$options": "i" } },
+                ],
+            );
         }
+        paginate(&self.assets, filter, page, &PAGE_SPEC).await
+    }
+
+    async fn update_asset(&self, id: u32, updated_asset: &Asset) -> Result<bool, Error> {
+        let filter = doc! { "id": id };
+        let update = doc! { "$set": to_document(updated_asset)? };
+        let result = self.assets.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn update_asset(&self, id: u32, updated_asset: &Asset) -> Result<UpdateResult, Error> {
+    async fn set_status(&self, id: u32, status: ModerationStatus) -> Result<bool, Error> {
         let filter = doc! { "id": id };
-        let update = doc! { "$set": to_bson(updated_asset).unwrap() };
-        self.assets.update_one(filter, update, None).await
+        let update = doc! { "$set": { "status": to_bson(&status).unwrap() } };
+        let result = self.assets.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn delete_asset(&self, id: u32) -> Result<DeleteResult, Error> {
+    async fn delete_asset(&self, id: u32) -> Result<bool, Error> {
         let result = self.assets.delete_one(doc! { "id": id }, None).await?;
-        Ok(result)
+        Ok(result.deleted_count > 0)
     }
 
-    pub async fn asset_exists(&self, id: u32) -> Result<bool, Error> {
+    async fn asset_exists(&self, id: u32) -> Result<bool, Error> {
         let count = self.assets.count_documents(doc! { "id": id }, None).await?;
         Ok(count > 0)
     }
+
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<Asset>, Error> {
+        self.assets.find(Self::user_filter(user_id), None).await?.try_collect().await
+    }
+
+    async fn erase_user(&self, user_id: UserId) -> Result<(), Error> {
+        let pull = doc! {
+            "$pull": {
+                "upvote_user_ids": user_id,
+                "downvote_user_ids": user_id,
+                "favorite_user_ids": user_id,
+                "comments": { "user_id": user_id },
+            }
+        };
+        self.assets.update_many(Self::user_filter(user_id), pull, None).await?;
+        // The assets of the user stay published, without an author
+        let unset = doc! { "$set": { "author_id": null } };
+        self.assets.update_many(doc! { "author_id": user_id }, unset, None).await?;
+        Ok(())
+    }
+}
+
+/// Escapes the regex metacharacters of a user provided search string.
+pub(crate) fn escape_regex(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
 impl Clone for AssetManager {
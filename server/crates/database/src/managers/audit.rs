@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use mongodb::{bson::doc, error::Error, Collection};
+
+use crate::{
+    id::{OrganizationId, UserId},
+    indexes::{ensure_indexes, IndexSpec},
+    models::audit::AuditEvent,
+    pagination::{paginate, Page, PageRequest, PageSpec, PaginationError},
+};
+
+pub(crate) const PAGE_SPEC: PageSpec<'static> = PageSpec {
+    id_field: "unique_id",
+    sort_fields: &["creation_date"],
+    filter_fields: &["action", "actor_id", "target_kind", "target_id"],
+    date_field: Some("creation_date"),
+};
+
+/// The audit log is append-only, the events are never deleted and only updated by the erasure of a user.
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    async fn record(&self, event: &AuditEvent) -> Result<(), Error>;
+
+    /// Returns the events of the organization, and the events without organization of the given users,
+    /// whether they acted or were targeted.
+    async fn get_events(
+        &self,
+        organization_id: OrganizationId,
+        user_ids: &[UserId],
+        page: &PageRequest,
+    ) -> Result<Page<AuditEvent>, PaginationError>;
+
+    /// Removes the IPs and the changes of the events the user acted in or was targeted by, the ids are kept.
+    /// Returns how many events were changed.
+    async fn anonymize_user(&self, user_id: UserId) -> Result<u64, Error>;
+}
+
+pub struct AuditManager {
+    pub events: Collection<AuditEvent>,
+}
+
+impl AuditManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("audit_events_unique_id", &["unique_id"]),
+        IndexSpec::new("audit_events_organization", &["organization_id", "creation_date"]),
+        IndexSpec::new("audit_events_target", &["target_id"]),
+        IndexSpec::new("audit_events_actor", &["actor_id"]),
+    ];
+
+    pub fn init(events: Collection<AuditEvent>) -> Self {
+        Self { events }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.events, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl AuditRepository for AuditManager {
+    async fn record(&self, event: &AuditEvent) -> Result<(), Error> {
+        self.events.insert_one(event, None).await?;
+        Ok(())
+    }
+
+    async fn get_events(
+        &self,
+        organization_id: OrganizationId,
+        user_ids: &[UserId],
+        page: &PageRequest,
+    ) -> Result<Page<AuditEvent>, PaginationError> {
+        let user_ids: Vec<String> = user_ids.iter().map(ToString::to_string).collect();
+        let filter = doc! {
+            "$or": [
+                { "organization_id": organization_id },
+                { "organization_id": null, "target_id": { "$in": &user_ids } },
+                { "organization_id": null, "actor_id": { "$in": &user_ids } },
+            ]
+        };
+        paginate(&self.events, filter, page, &PAGE_SPEC).await
+    }
+
+    async fn anonymize_user(&self, user_id: UserId) -> Result<u64, Error> {
+        let filter = doc! { "$or": [{ "actor_id": user_id }, { "target_id": user_id.to_string() }] };
+        let update = doc! { "$set": { "ip": null, "changes": null } };
+        let result = self.events.update_many(filter, update, None).await?;
+        Ok(result.modified_count)
+    }
+}
+
+impl Clone for AuditManager {
+    fn clone(&self) -> Self {
+        Self {
+            events: self.events.clone(),
+        }
+    }
+}
@@ -1,41 +1,116 @@
+use async_trait::async_trait;
+use futures::TryStreamExt;
 use mongodb::{
-    bson::{doc},
+    bson::{doc, to_bson},
     error::Error,
-    results::{DeleteResult, InsertOneResult},
     Collection,
 };
-use crate::models::comment::Comment;
+
+use crate::{
+    id::{CommentId, UserId},
+    indexes::{ensure_indexes, IndexSpec},
+    models::{comment::Comment, moderation::ModerationStatus},
+    pagination::{paginate, Page, PageRequest, PageSpec, PaginationError},
+};
+
+pub(crate) const PAGE_SPEC: PageSpec<'static> = PageSpec {
+    id_field: "unique_id",
+    sort_fields: &["timestamp"],
+    filter_fields: &["user_id"],
+    date_field: Some("timestamp"),
+};
+
+#[async_trait]
+pub trait CommentRepository: Send + Sync {
+    async fn add_comment(&self, comment: &Comment) -> Result<(), Error>;
+
+    async fn from_id(&self, id: CommentId) -> Result<Option<Comment>, Error>;
+
+    async fn get_comment_by_user(&self, user_id: UserId) -> Result<Option<Comment>, Error>;
+
+    /// Returns a page of the visible comments of an asset.
+    async fn from_asset_id(&self, asset_id: u32, page: &PageRequest) -> Result<Page<Comment>, PaginationError>;
+
+    async fn set_status(&self, id: CommentId, status: ModerationStatus) -> Result<bool, Error>;
+
+    async fn delete_comment(&self, id: CommentId) -> Result<bool, Error>;
+
+    async fn comment_exists(&self, id: CommentId) -> Result<bool, Error>;
+
+    /// Returns every comment of the user, hidden ones included.
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<Comment>, Error>;
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error>;
+}
 
 pub struct CommentManager {
     pub comments: Collection<Comment>,
 }
 
 impl CommentManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("comments_unique_id", &["unique_id"]),
+        IndexSpec::new("comments_asset_id", &["asset_id"]),
+        IndexSpec::new("comments_user_id", &["user_id"]),
+    ];
+
     pub fn init(comments: Collection<Comment>) -> Self {
         Self { comments }
     }
 
-    pub async fn add_comment(&self, comment: &Comment) -> Result<InsertOneResult, Error> {
-        let result = self.comments.insert_one(comment, None).await?;
-        Ok(result)
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.comments, Self::INDEXES).await
     }
+}
 
-    pub async fn get_comment_by_user(&self, user_id: u32) -> Result<Option<Comment>, Error> {
-        match self.comments.find_one(doc! { "user_id": user_id }, None).await? {
-            Some(comment) => Ok(Some(comment)),
-            None => Ok(None),
-        }
+#[async_trait]
+impl CommentRepository for CommentManager {
+    async fn add_comment(&self, comment: &Comment) -> Result<(), Error> {
+        self.comments.insert_one(comment, None).await?;
+        Ok(())
+    }
+
+    async fn from_id(&self, id: CommentId) -> Result<Option<Comment>, Error> {
+        self.comments.find_one(doc! { "unique_id": id }, None).await
+    }
+
+    async fn get_comment_by_user(&self, user_id: UserId) -> Result<Option<Comment>, Error> {
+        self.comments.find_one(doc! { "user_id": user_id }, None).await
+    }
+
+    async fn from_asset_id(&self, asset_id: u32, page: &PageRequest) -> Result<Page<Comment>, PaginationError> {
+        let filter = doc! {
+            "asset_id": asset_id,
+            "status": { "$ne": to_bson(&ModerationStatus::Hidden).unwrap() },
+        };
+        paginate(&self.comments, filter, page, &PAGE_SPEC).await
     }
 
-    pub async fn delete_comment(&self, user_id: u32) -> Result<DeleteResult, Error> {
-        let result = self.comments.delete_one(doc! { "user_id": user_id }, None).await?;
-        Ok(result)
+    async fn set_status(&self, id: CommentId, status: ModerationStatus) -> Result<bool, Error> {
+        let filter = doc! { "unique_id": id };
+        let update = doc! { "$set": { "status": to_bson(&status).unwrap() } };
+        let result = self.comments.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
-    
-    pub async fn comment_exists(&self, user_id: u32) -> Result<bool, Error> {
-        let count = self.comments.count_documents(doc! { "user_id": user_id }, None).await?;
+
+    async fn delete_comment(&self, id: CommentId) -> Result<bool, Error> {
+        let result = self.comments.delete_one(doc! { "unique_id": id }, None).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn comment_exists(&self, id: CommentId) -> Result<bool, Error> {
+        let count = self.comments.count_documents(doc! { "unique_id": id }, None).await?;
         Ok(count > 0)
     }
+
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<Comment>, Error> {
+        self.comments.find(doc! { "user_id": user_id }, None).await?.try_collect().await
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        let result = self.comments.delete_many(doc! { "user_id": user_id }, None).await?;
+        Ok(result.deleted_count)
+    }
 }
 
 impl Clone for CommentManager {
@@ -1,54 +1,79 @@
 pub use crate::models::license::License;
 
-use futures::StreamExt;
-use mongodb::{
-    bson::doc, error::Error, results::InsertOneResult, Collection
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{bson::doc, error::Error, Collection};
+
+use crate::{
+    id::UserId,
+    indexes::{ensure_indexes, IndexSpec},
+    pagination::{paginate, Page, PageRequest, PageSpec, PaginationError},
+};
+
+pub(crate) const PAGE_SPEC: PageSpec<'static> = PageSpec {
+    id_field: "unique_id",
+    sort_fields: &["license"],
+    filter_fields: &["license"],
+    date_field: None,
 };
 
+#[async_trait]
+pub trait LicenseRepository: Send + Sync {
+    async fn create(&self, license: &License) -> Result<(), Error>;
+
+    async fn get_licenses(&self, user_id: UserId, page: &PageRequest) -> Result<Page<License>, PaginationError>;
+
+    async fn get_license(&self, license_id: &str) -> Result<Option<License>, Error>;
+
+    /// Returns every license of the user, unpaginated.
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<License>, Error>;
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error>;
+}
+
 pub struct LicenseManager {
     pub licenses: Collection<License>,
 }
 
 impl LicenseManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("licenses_unique_id", &["unique_id"]),
+        IndexSpec::unique("licenses_license", &["license"]),
+        IndexSpec::new("licenses_user_id", &["user_id"]),
+    ];
+
     pub fn init(licenses: Collection<License>) -> Self {
         Self { licenses }
     }
 
-    pub async fn create(
-        &self,
-        license: &License,
-    ) -> Result<InsertOneResult, Error> {
-        let target = self.licenses.insert_one(license, None).await?;
-        Ok(target)
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.licenses, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl LicenseRepository for LicenseManager {
+    async fn create(&self, license: &License) -> Result<(), Error> {
+        self.licenses.insert_one(license, None).await?;
+        Ok(())
     }
 
-    pub async fn get_licenses(
-        &self,
-        user_id: &str,
-    ) -> Result<Vec<License>, Error> {
+    async fn get_licenses(&self, user_id: UserId, page: &PageRequest) -> Result<Page<License>, PaginationError> {
         let filter = doc! {"user_id": user_id};
-        let mut cursor: mongodb::Cursor<License>  = self.licenses.find(filter, None).await?;
-        let mut licenses = Vec::new();
-        
-        while let Some(project) = cursor.next().await {
-            licenses.push(project?);
-        }
+        paginate(&self.licenses, filter, page, &PAGE_SPEC).await
+    }
 
-        Ok(licenses)
+    async fn get_license(&self, license_id: &str) -> Result<Option<License>, Error> {
+        self.licenses.find_one(doc! { "license": license_id }, None).await
     }
 
-    pub async fn get_license(
-        &self,
-        license_id: &str,
-    ) -> Result<Option<License>, Error> {
-        match self
-            .licenses
-            .find_one(doc! { "license": license_id.to_string() }, None)
-            .await?
-        {
-            Some(license) => Ok(Some(license)),
-            None => Ok(None),
-        }
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<License>, Error> {
+        self.licenses.find(doc! { "user_id": user_id }, None).await?.try_collect().await
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        let result = self.licenses.delete_many(doc! { "user_id": user_id }, None).await?;
+        Ok(result.deleted_count)
     }
 }
 
@@ -58,4 +83,4 @@ impl Clone for LicenseManager {
             licenses: self.licenses.clone(),
         }
     }
-}
\ No newline at end of file
+}
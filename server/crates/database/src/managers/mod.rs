@@ -1,11 +1,22 @@
-mod organization;
-mod peer;
-mod user;
-mod projects;
-mod licenses;
-mod permission;
-mod assets;
-mod comments;
+// The repositories keep the `from_*` names of the lookups they replace
+#![allow(clippy::wrong_self_convention)]
+
+pub(crate) mod organization;
+pub(crate) mod peer;
+pub(crate) mod user;
+pub(crate) mod projects;
+pub(crate) mod licenses;
+pub(crate) mod permission;
+pub(crate) mod assets;
+pub(crate) mod comments;
+pub(crate) mod moderation;
+pub(crate) mod audit;
+pub(crate) mod rate_limits;
+pub(crate) mod outbox;
+pub(crate) mod password_resets;
+pub(crate) mod oidc_logins;
+pub(crate) mod two_factors;
+pub(crate) mod access_tokens;
 
 pub use organization::*;
 pub use peer::*;
@@ -14,4 +25,12 @@ pub use projects::*;
 pub use licenses::*;
 pub use permission::*;
 pub use assets::*;
-pub use comments::*;
\ No newline at end of file
+pub use comments::*;
+pub use moderation::*;
+pub use audit::*;
+pub use rate_limits::*;
+pub use outbox::*;
+pub use password_resets::*;
+pub use oidc_logins::*;
+pub use two_factors::*;
+pub use access_tokens::*;
\ No newline at end of file
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, to_bson},
+    error::Error,
+    Collection,
+};
+
+use crate::{
+    id::{ReportId, UserId},
+    indexes::{ensure_indexes, IndexSpec},
+    models::moderation::{ContentKind, Report, ReportState},
+    pagination::{paginate, Page, PageRequest, PageSpec, PaginationError},
+};
+
+pub(crate) const PAGE_SPEC: PageSpec<'static> = PageSpec {
+    id_field: "unique_id",
+    sort_fields: &["creation_date"],
+    filter_fields: &["content_kind", "content_id", "reporter_id"],
+    date_field: Some("creation_date"),
+};
+
+#[async_trait]
+pub trait ModerationRepository: Send + Sync {
+    async fn create_report(&self, report: &Report) -> Result<(), Error>;
+
+    async fn from_id(&self, id: ReportId) -> Result<Option<Report>, Error>;
+
+    /// Returns every report still waiting for a moderator decision.
+    async fn get_pending(&self, page: &PageRequest) -> Result<Page<Report>, PaginationError>;
+
+    /// Resolves every pending report targeting the same content, and returns how many were resolved.
+    async fn resolve(&self, content_kind: ContentKind, content_id: &str, state: &ReportState) -> Result<u64, Error>;
+
+    /// Returns every report filed by the user.
+    async fn from_reporter(&self, reporter_id: UserId) -> Result<Vec<Report>, Error>;
+
+    async fn delete_from_reporter(&self, reporter_id: UserId) -> Result<u64, Error>;
+}
+
+pub struct ModerationManager {
+    pub reports: Collection<Report>,
+}
+
+impl ModerationManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("moderation_queue_unique_id", &["unique_id"]),
+        IndexSpec::new("moderation_queue_content", &["content_kind", "content_id"]),
+        IndexSpec::new("moderation_queue_reporter_id", &["reporter_id"]),
+    ];
+
+    pub fn init(reports: Collection<Report>) -> Self {
+        Self { reports }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.reports, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl ModerationRepository for ModerationManager {
+    async fn create_report(&self, report: &Report) -> Result<(), Error> {
+        self.reports.insert_one(report, None).await?;
+        Ok(())
+    }
+
+    async fn from_id(&self, id: ReportId) -> Result<Option<Report>, Error> {
+        self.reports.find_one(doc! { "unique_id": id }, None).await
+    }
+
+    async fn get_pending(&self, page: &PageRequest) -> Result<Page<Report>, PaginationError> {
+        let filter = doc! { "state": to_bson(&ReportState::Pending).unwrap() };
+        paginate(&self.reports, filter, page, &PAGE_SPEC).await
+    }
+
+    async fn resolve(&self, content_kind: ContentKind, content_id: &str, state: &ReportState) -> Result<u64, Error> {
+        let filter = doc! {
+            "content_kind": to_bson(&content_kind).unwrap(),
+            "content_id": content_id,
+            "state": to_bson(&ReportState::Pending).unwrap(),
+        };
+        let update = doc! { "$set": { "state": to_bson(state).unwrap() } };
+        let result = self.reports.update_many(filter, update, None).await?;
+        Ok(result.modified_count)
+    }
+
+    async fn from_reporter(&self, reporter_id: UserId) -> Result<Vec<Report>, Error> {
+        self.reports.find(doc! { "reporter_id": reporter_id }, None).await?.try_collect().await
+    }
+
+    async fn delete_from_reporter(&self, reporter_id: UserId) -> Result<u64, Error> {
+        let result = self.reports.delete_many(doc! { "reporter_id": reporter_id }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
+
+impl Clone for ModerationManager {
+    fn clone(&self) -> Self {
+        Self {
+            reports: self.reports.clone(),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use mongodb::{bson::doc, error::Error, Collection};
+
+use crate::{
+    id::UserId,
+    indexes::{ensure_indexes, IndexSpec},
+    oidc_login::OidcLogin,
+    timestamp::Timestamp,
+};
+
+#[async_trait]
+pub trait OidcLoginRepository: Send + Sync {
+    async fn create(&self, login: &OidcLogin) -> Result<(), Error>;
+
+    /// Removes the sign in of the state and returns it, if it has not expired, so that a state is used once.
+    async fn consume(&self, state: &str, now: Timestamp) -> Result<Option<OidcLogin>, Error>;
+
+    /// Removes the pending sign ins linking an account to the user, and returns how many were removed.
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error>;
+}
+
+pub struct OidcLoginManager {
+    pub logins: Collection<OidcLogin>,
+}
+
+impl OidcLoginManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("oidc_logins_state", &["state"]),
+        IndexSpec::ttl("oidc_logins_expires_at", &["expires_at"], Duration::ZERO),
+    ];
+
+    pub fn init(logins: Collection<OidcLogin>) -> Self {
+        Self { logins }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.logins, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl OidcLoginRepository for OidcLoginManager {
+    async fn create(&self, login: &OidcLogin) -> Result<(), Error> {
+        self.logins.insert_one(login, None).await?;
+        Ok(())
+    }
+
+    async fn consume(&self, state: &str, now: Timestamp) -> Result<Option<OidcLogin>, Error> {
+        // The expired sign ins may outlive their date until the TTL monitor removes them
+        let filter = doc! { "state": state, "expires_at": { "$gt": now } };
+        self.logins.find_one_and_delete(filter, None).await
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        let result = self.logins.delete_many(doc! { "link_user": user_id }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
+
+impl Clone for OidcLoginManager {
+    fn clone(&self) -> Self {
+        Self {
+            logins: self.logins.clone(),
+        }
+    }
+}
@@ -1,25 +1,107 @@
 use std::collections::HashMap;
 
-use futures::StreamExt;
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
 use mongodb::{
     bson::{doc, to_bson, Bson},
     error::Error,
-    results::{DeleteResult, InsertOneResult, UpdateResult},
     Collection,
 };
 
-use crate::organization::{Organization, OrganizationUpdate};
+use crate::{
+    id::{OrganizationId, ProjectId, UserId},
+    indexes::{ensure_indexes, IndexSpec},
+    organization::{Organization, OrganizationUpdate},
+    pagination::{paginate, Page, PageRequest, PageSpec, PaginationError},
+};
+
+pub(crate) const PAGE_SPEC: PageSpec<'static> = PageSpec {
+    id_field: "unique_id",
+    sort_fields: &["name", "creation_date"],
+    filter_fields: &["name", "owner_id"],
+    date_field: Some("creation_date"),
+};
+
+#[async_trait]
+pub trait OrganizationRepository: Send + Sync {
+    async fn organization_exists(&self, name: String) -> Result<bool, Error>;
+
+    async fn create_organization(&self, organization: &Organization) -> Result<(), Error>;
+
+    async fn from_id(&self, id: OrganizationId) -> Result<Option<Organization>, Error>;
+
+    async fn delete_organization(&self, uuid: OrganizationId) -> Result<bool, String>;
+
+    async fn add_to_server_ids(&self, uuid: OrganizationId, server_id: UserId) -> Result<bool, String>;
+
+    /// Whether the user owns or is a member of an organization having access to the server.
+    async fn has_access_to_server(&self, server_unique_id: UserId, user_unique_id: UserId) -> Result<bool, String>;
+
+    async fn is_in_organization(&self, uuid: OrganizationId, user_unique_id: UserId) -> Result<bool, String>;
+
+    async fn remove_from_server_ids(&self, uuid: OrganizationId, server_id: UserId) -> Result<bool, String>;
+
+    async fn update_organization(
+        &self,
+        uuid: OrganizationId,
+        organization_update: Vec<OrganizationUpdate>,
+    ) -> Result<bool, Error>;
+
+    /// Returns the organizations owned by the user or having the user as a member.
+    async fn get_organizations_from_user(
+        &self,
+        user_id: UserId,
+        page: &PageRequest,
+    ) -> Result<Page<Organization>, PaginationError>;
+
+    async fn add_member(&self, organization_id: OrganizationId, member_id: UserId) -> Result<bool, Error>;
+
+    /// Whether the user belongs to an organization requiring a second factor
+    async fn requires_two_factor(&self, user_id: UserId) -> Result<bool, Error>;
+
+    async fn remove_from_member_ids(&self, organization_id: OrganizationId, member_id: UserId) -> Result<bool, Error>;
+
+    async fn add_to_projects_ids(&self, organization_id: OrganizationId, project_id: ProjectId) -> Result<bool, Error>;
+
+    async fn remove_from_projects_ids(&self, organization_id: OrganizationId, project_id: ProjectId) -> Result<bool, Error>;
+
+    async fn get_servers_ids_from_organisation(&self, organization_id: OrganizationId) -> Result<Vec<UserId>, Error>;
+
+    /// Returns the servers of every organization
+    async fn servers_by_organization(&self) -> Result<Vec<(OrganizationId, Vec<UserId>)>, Error>;
+
+    /// Returns every organization owned by the user or having the user as a member or a server, unpaginated.
+    async fn all_from_user(&self, user_id: UserId) -> Result<Vec<Organization>, Error>;
+
+    /// Removes the user from the members and the servers of every organization, the owners are left unchanged.
+    /// Returns how many organizations were changed.
+    async fn remove_user(&self, user_id: UserId) -> Result<u64, Error>;
+}
 
 pub struct OrganizationManager {
     pub organizations: Collection<Organization>,
 }
 
 impl OrganizationManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("organizations_unique_id", &["unique_id"]),
+        IndexSpec::new("organizations_member_ids", &["member_ids"]),
+        IndexSpec::new("organizations_name", &["name"]),
+        IndexSpec::new("organizations_owner_id", &["owner_id"]),
+    ];
+
     pub fn init(organizations: Collection<Organization>) -> Self {
         Self { organizations }
     }
 
-    pub async fn organization_exists(&self, name: String) -> Result<bool, Error> {
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.organizations, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl OrganizationRepository for OrganizationManager {
+    async fn organization_exists(&self, name: String) -> Result<bool, Error> {
         Ok(self
             .organizations
             .count_documents(doc! { "name": name }, None)
@@ -27,53 +109,37 @@ impl OrganizationManager {
             != 0)
     }
 
-    pub async fn create_organization(
-        &self,
-        organization: &Organization,
-    ) -> Result<InsertOneResult, Error> {
-        let target = self.organizations.insert_one(organization, None).await?;
-        Ok(target)
+    async fn create_organization(&self, organization: &Organization) -> Result<(), Error> {
+        self.organizations.insert_one(organization, None).await?;
+        Ok(())
     }
 
-    pub async fn from_id(&self, id: &str) -> Result<Option<Organization>, Error> {
-        match self
-            .organizations
-            .find_one(doc! { "unique_id": id.to_string() }, None)
-            .await?
-        {
-            Some(organization) => Ok(Some(organization)),
-            None => Ok(None),
-        }
+    async fn from_id(&self, id: OrganizationId) -> Result<Option<Organization>, Error> {
+        self.organizations.find_one(doc! { "unique_id": id }, None).await
     }
 
-    pub async fn delete_organization(&self, uuid: &str) -> Result<Option<DeleteResult>, String> {
-        Ok(Some(
-            self.organizations
-                .delete_one(doc! {"unique_id": uuid}, None)
-                .await
-                .map_err(|err| err.to_string())?,
-        ))
+    async fn delete_organization(&self, uuid: OrganizationId) -> Result<bool, String> {
+        let result = self
+            .organizations
+            .delete_one(doc! {"unique_id": uuid}, None)
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(result.deleted_count > 0)
     }
 
-    pub async fn add_to_server_ids(
-        &self,
-        uuid: &str,
-        server_id: &str,
-    ) -> Result<UpdateResult, String> {
+    async fn add_to_server_ids(&self, uuid: OrganizationId, server_id: UserId) -> Result<bool, String> {
         let filter = doc! { "unique_id": uuid };
         let update = doc! { "$addToSet": { "server_ids": server_id } };
 
-        self.organizations
+        let result = self
+            .organizations
             .update_one(filter, update, None)
             .await
-            .map_err(|err| err.to_string())
+            .map_err(|err| err.to_string())?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn has_access_to_server(
-        &self,
-        server_unique_id: &str,
-        user_unique_id: &str,
-    ) -> Result<bool, String> {
+    async fn has_access_to_server(&self, server_unique_id: UserId, user_unique_id: UserId) -> Result<bool, String> {
         let filter = doc! {
             { "server_ids" }: { "$in": [server_unique_id] },
             "$or": [
@@ -92,11 +158,7 @@ impl OrganizationManager {
         }
     }
 
-    pub async fn is_in_organization(
-        &self,
-        uuid: &str,
-        user_unique_id: &str,
-    ) -> Result<bool, String> {
+    async fn is_in_organization(&self, uuid: OrganizationId, user_unique_id: UserId) -> Result<bool, String> {
         let filter = doc! {
             "unique_id": uuid,
             "$or": [
@@ -115,107 +177,91 @@ impl OrganizationManager {
         }
     }
 
-    pub async fn remove_from_server_ids(
-        &self,
-        uuid: &str,
-        server_id: &str,
-    ) -> Result<UpdateResult, String> {
+    async fn remove_from_server_ids(&self, uuid: OrganizationId, server_id: UserId) -> Result<bool, String> {
         let filter = doc! { "unique_id": uuid };
         let update = doc! { "$pull": { "server_ids": server_id } };
 
-        self.organizations
+        let result = self
+            .organizations
             .update_one(filter, update, None)
             .await
-            .map_err(|err| err.to_string())
+            .map_err(|err| err.to_string())?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn update_organization(
+    async fn update_organization(
         &self,
-        uuid: &str,
+        uuid: OrganizationId,
         organization_update: Vec<OrganizationUpdate>,
-    ) -> Result<UpdateResult, Error> {
-        let filter = doc! {"unique_id": uuid.to_string()};
+    ) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid};
         let fields: HashMap<String, Bson> = organization_update
             .iter()
             .filter_map(|update| update.convert())
             .collect();
         let update = doc! {"$set": to_bson(&fields).unwrap()};
-        self.organizations.update_one(filter, update, None).await
+        let result = self.organizations.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    // Find every organizations where owner_id = user_id or member_ids contains user_id
-    pub async fn get_organizations_from_user(
+    async fn get_organizations_from_user(
         &self,
-        user_id: &str,
-    ) -> Result<Vec<Organization>, Error> {
+        user_id: UserId,
+        page: &PageRequest,
+    ) -> Result<Page<Organization>, PaginationError> {
         let filter = doc! {
             "$or": [
                 { "member_ids": { "$in": [user_id] } },
                 { "owner_id": user_id }
             ]
         };
-        let mut cursor: mongodb::Cursor<Organization> = self.organizations.find(filter, None).await?;
-        let mut organizations: Vec<Organization> = Vec::new();
-
-        while let Some(organization) = cursor.next().await {
-            organizations.push(organization?);
-        }
-
-        Ok(organizations)
+        paginate(&self.organizations, filter, page, &PAGE_SPEC).await
     }
 
-    pub async fn add_member(&self, organization_id: &str, member_id: &str) -> Result<UpdateResult, Error> {
+    async fn add_member(&self, organization_id: OrganizationId, member_id: UserId) -> Result<bool, Error> {
         let filter = doc! { "unique_id": organization_id };
         let update = doc! { "$addToSet": { "member_ids": member_id } };
 
-        self.organizations
-            .update_one(filter, update, None)
-            .await
+        let result = self.organizations.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn remove_from_member_ids(
-        &self,
-        organization_id: &str,
-        member_id: &str,
-    ) -> Result<UpdateResult, Error> {
+    async fn requires_two_factor(&self, user_id: UserId) -> Result<bool, Error> {
+        let filter = doc! {
+            "require_two_factor": true,
+            "$or": [
+                { "member_ids": { "$in": [user_id] } },
+                { "owner_id": user_id }
+            ]
+        };
+        Ok(self.organizations.count_documents(filter, None).await? > 0)
+    }
+
+    async fn remove_from_member_ids(&self, organization_id: OrganizationId, member_id: UserId) -> Result<bool, Error> {
         let filter = doc! { "unique_id": organization_id };
         let update = doc! { "$pull": { "member_ids": member_id } };
 
-        self.organizations
-            .update_one(filter, update, None)
-            .await
+        let result = self.organizations.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn add_to_projects_ids(
-        &self,
-        organization_id: &str,
-        project_id: &str,
-    ) -> Result<UpdateResult, Error> {
+    async fn add_to_projects_ids(&self, organization_id: OrganizationId, project_id: ProjectId) -> Result<bool, Error> {
         let filter = doc! { "unique_id": organization_id };
         let update = doc! { "$addToSet": { "projects_ids": project_id } };
 
-        self.organizations
-            .update_one(filter, update, None)
-            .await
+        let result = self.organizations.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn remove_from_projects_ids(
-        &self,
-        organization_id: &str,
-        project_id: &str,
-    ) -> Result<UpdateResult, Error> {
+    async fn remove_from_projects_ids(&self, organization_id: OrganizationId, project_id: ProjectId) -> Result<bool, Error> {
         let filter = doc! { "unique_id": organization_id };
         let update = doc! { "$pull": { "projects_ids": project_id } };
 
-        self.organizations
-            .update_one(filter, update, None)
-            .await
+        let result = self.organizations.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn get_servers_ids_from_organisation(
-        &self,
-        organization_id: &str,
-    ) -> Result<Vec<String>, Error> {
+    async fn get_servers_ids_from_organisation(&self, organization_id: OrganizationId) -> Result<Vec<UserId>, Error> {
         let filter = doc! { "unique_id": organization_id };
         let organization = self.organizations.find_one(filter, None).await?;
         match organization {
@@ -223,6 +269,34 @@ impl OrganizationManager {
             None => Ok(Vec::new()),
         }
     }
+
+    async fn servers_by_organization(&self) -> Result<Vec<(OrganizationId, Vec<UserId>)>, Error> {
+        let mut cursor = self.organizations.find(None, None).await?;
+        let mut servers = Vec::new();
+        while let Some(organization) = cursor.next().await {
+            let organization = organization?;
+            servers.push((organization.unique_id, organization.server_ids));
+        }
+        Ok(servers)
+    }
+
+    async fn all_from_user(&self, user_id: UserId) -> Result<Vec<Organization>, Error> {
+        let filter = doc! {
+            "$or": [
+                { "member_ids": user_id },
+                { "server_ids": user_id },
+                { "owner_id": user_id }
+            ]
+        };
+        self.organizations.find(filter, None).await?.try_collect().await
+    }
+
+    async fn remove_user(&self, user_id: UserId) -> Result<u64, Error> {
+        let filter = doc! { "$or": [{ "member_ids": user_id }, { "server_ids": user_id }] };
+        let update = doc! { "$pull": { "member_ids": user_id, "server_ids": user_id } };
+        let result = self.organizations.update_many(filter, update, None).await?;
+        Ok(result.modified_count)
+    }
 }
 
 impl Clone for OrganizationManager {
@@ -231,4 +305,4 @@ impl Clone for OrganizationManager {
             organizations: self.organizations.clone(),
         }
     }
-}
\ No newline at end of file
+}
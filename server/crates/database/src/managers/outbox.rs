@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::doc,
+    error::Error,
+    options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument},
+    Collection,
+};
+
+use crate::{
+    id::MailId,
+    indexes::{ensure_indexes, IndexSpec},
+    mail::OutboxMessage,
+    timestamp::Timestamp,
+};
+
+/// The outbound messages, queued by the routes and sent by the mail worker.
+#[async_trait]
+pub trait OutboxRepository: Send + Sync {
+    async fn enqueue(&self, message: &OutboxMessage) -> Result<(), Error>;
+
+    /// Claims a pending message due at `now`, its next attempt is pushed back to `lease_until`
+    /// so that no other worker sends it meanwhile, and its attempts are counted.
+    async fn claim_due(&self, now: Timestamp, lease_until: Timestamp) -> Result<Option<OutboxMessage>, Error>;
+
+    async fn mark_sent(&self, id: MailId, expires_at: Timestamp) -> Result<(), Error>;
+
+    /// Records the error of an attempt, the message is retried at `retry_at`, or given up without one.
+    async fn mark_failed(
+        &self,
+        id: MailId,
+        error: &str,
+        retry_at: Option<Timestamp>,
+        expires_at: Timestamp,
+    ) -> Result<(), Error>;
+
+    async fn get_message(&self, id: MailId) -> Result<Option<OutboxMessage>, Error>;
+
+    /// Returns the messages sent or to send to the address, the oldest first.
+    async fn messages_to(&self, to: &str) -> Result<Vec<OutboxMessage>, Error>;
+
+    /// Removes the messages sent or to send to the address, and returns how many were removed.
+    async fn delete_to(&self, to: &str) -> Result<u64, Error>;
+}
+
+pub struct OutboxManager {
+    pub messages: Collection<OutboxMessage>,
+}
+
+impl OutboxManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("outbox_unique_id", &["unique_id"]),
+        IndexSpec::new("outbox_due", &["status", "next_attempt"]),
+        IndexSpec::ttl("outbox_expires_at", &["expires_at"], Duration::ZERO),
+    ];
+
+    pub fn init(messages: Collection<OutboxMessage>) -> Self {
+        Self { messages }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.messages, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for OutboxManager {
+    async fn enqueue(&self, message: &OutboxMessage) -> Result<(), Error> {
+        self.messages.insert_one(message, None).await?;
+        Ok(())
+    }
+
+    async fn claim_due(&self, now: Timestamp, lease_until: Timestamp) -> Result<Option<OutboxMessage>, Error> {
+        let filter = doc! { "status": "Pending", "next_attempt": { "$lte": now } };
+        let update = doc! { "$set": { "next_attempt": lease_until }, "$inc": { "attempts": 1 } };
+        let options = FindOneAndUpdateOptions::builder()
+            .sort(doc! { "next_attempt": 1 })
+            .return_document(ReturnDocument::After)
+            .build();
+        self.messages.find_one_and_update(filter, update, options).await
+    }
+
+    async fn mark_sent(&self, id: MailId, expires_at: Timestamp) -> Result<(), Error> {
+        let update = doc! { "$set": { "status": "Sent", "last_error": null, "expires_at": expires_at } };
+        self.messages.update_one(doc! { "unique_id": id }, update, None).await?;
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: MailId,
+        error: &str,
+        retry_at: Option<Timestamp>,
+        expires_at: Timestamp,
+    ) -> Result<(), Error> {
+        let update = match retry_at {
+            Some(retry_at) => doc! { "$set": { "last_error": error, "next_attempt": retry_at } },
+            None => doc! { "$set": { "last_error": error, "status": "Failed", "expires_at": expires_at } },
+        };
+        self.messages.update_one(doc! { "unique_id": id }, update, None).await?;
+        Ok(())
+    }
+
+    async fn get_message(&self, id: MailId) -> Result<Option<OutboxMessage>, Error> {
+        self.messages.find_one(doc! { "unique_id": id }, None).await
+    }
+
+    async fn messages_to(&self, to: &str) -> Result<Vec<OutboxMessage>, Error> {
+        let options = FindOptions::builder().sort(doc! { "creation_date": 1 }).build();
+        self.messages.find(doc! { "to": to }, options).await?.try_collect().await
+    }
+
+    async fn delete_to(&self, to: &str) -> Result<u64, Error> {
+        let result = self.messages.delete_many(doc! { "to": to }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
+
+impl Clone for OutboxManager {
+    fn clone(&self) -> Self {
+        Self {
+            messages: self.messages.clone(),
+        }
+    }
+}
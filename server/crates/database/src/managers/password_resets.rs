@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use mongodb::{bson::doc, error::Error, Collection};
+
+use crate::{
+    id::UserId,
+    indexes::{ensure_indexes, IndexSpec},
+    password_reset::PasswordReset,
+    timestamp::Timestamp,
+};
+
+#[async_trait]
+pub trait PasswordResetRepository: Send + Sync {
+    async fn create(&self, reset: &PasswordReset) -> Result<(), Error>;
+
+    /// Removes the reset of the token hash and returns it, if it has not expired, so that a token is used once.
+    async fn consume(&self, token_hash: &str, now: Timestamp) -> Result<Option<PasswordReset>, Error>;
+
+    /// Removes the pending resets of the user, once the password is reset.
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error>;
+}
+
+pub struct PasswordResetManager {
+    pub resets: Collection<PasswordReset>,
+}
+
+impl PasswordResetManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("password_resets_token_hash", &["token_hash"]),
+        IndexSpec::new("password_resets_user_id", &["user_id"]),
+        IndexSpec::ttl("password_resets_expires_at", &["expires_at"], Duration::ZERO),
+    ];
+
+    pub fn init(resets: Collection<PasswordReset>) -> Self {
+        Self { resets }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.resets, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl PasswordResetRepository for PasswordResetManager {
+    async fn create(&self, reset: &PasswordReset) -> Result<(), Error> {
+        self.resets.insert_one(reset, None).await?;
+        Ok(())
+    }
+
+    async fn consume(&self, token_hash: &str, now: Timestamp) -> Result<Option<PasswordReset>, Error> {
+        // The expired resets may outlive their date until the TTL monitor removes them
+        let filter = doc! { "token_hash": token_hash, "expires_at": { "$gt": now } };
+        self.resets.find_one_and_delete(filter, None).await
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        let result = self.resets.delete_many(doc! { "user_id": user_id }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
+
+impl Clone for PasswordResetManager {
+    fn clone(&self) -> Self {
+        Self {
+            resets: self.resets.clone(),
+        }
+    }
+}
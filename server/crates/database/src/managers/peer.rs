@@ -1,55 +1,88 @@
-use mongodb::{
-    bson::doc,
-    error::Error,
-    results::{DeleteResult, InsertOneResult},
-    Collection,
+use async_trait::async_trait;
+use futures::StreamExt;
+use mongodb::{bson::doc, error::Error, Collection};
+
+use crate::{
+    id::UserId,
+    indexes::{ensure_indexes, IndexSpec},
+    peer::Peer,
 };
 
-use crate::peer::Peer;
+#[async_trait]
+pub trait PeerRepository: Send + Sync {
+    async fn peers_exist(&self, server_unique_id: UserId) -> Result<bool, Error>;
+
+    async fn create_peer(&self, peer: &Peer) -> Result<(), Error>;
+
+    async fn from_server_id(&self, server_unique_id: UserId) -> Result<Option<Peer>, Error>;
+
+    async fn delete_peer(&self, server_unique_id: UserId) -> Result<bool, String>;
+
+    async fn count(&self) -> Result<u64, Error>;
+
+    /// Returns the servers that have a peer
+    async fn server_ids(&self) -> Result<Vec<UserId>, Error>;
+}
 
 pub struct PeersManager {
     pub peers: Collection<Peer>,
 }
 
 impl PeersManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("peers_server_unique_id", &["server_unique_id"]),
+    ];
+
     pub fn init(peers: Collection<Peer>) -> Self {
         Self { peers }
     }
 
-    pub async fn peers_exist(&self, server_unique_id: impl Into<String>) -> Result<bool, Error> {
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.peers, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl PeerRepository for PeersManager {
+    async fn peers_exist(&self, server_unique_id: UserId) -> Result<bool, Error> {
         Ok(self
             .peers
-            .count_documents(doc! { "server_unique_id": server_unique_id.into() }, None)
+            .count_documents(doc! { "server_unique_id": server_unique_id }, None)
             .await?
             != 0)
     }
 
-    pub async fn create_peer(&self, peer: &Peer) -> Result<InsertOneResult, Error> {
-        let target = self.peers.insert_one(peer, None).await?;
-        Ok(target)
+    async fn create_peer(&self, peer: &Peer) -> Result<(), Error> {
+        self.peers.insert_one(peer, None).await?;
+        Ok(())
     }
 
-    pub async fn from_server_id(&self, server_unique_id: &str) -> Result<Option<Peer>, Error> {
-        match self
-            .peers
+    async fn from_server_id(&self, server_unique_id: UserId) -> Result<Option<Peer>, Error> {
+        self.peers
             .find_one(doc! { "server_unique_id": server_unique_id }, None)
-            .await?
-        {
-            Some(peer) => Ok(Some(peer)),
-            None => Ok(None),
-        }
+            .await
+    }
+
+    async fn delete_peer(&self, server_unique_id: UserId) -> Result<bool, String> {
+        let result = self
+            .peers
+            .delete_one(doc! {"server_unique_id": server_unique_id}, None)
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(result.deleted_count > 0)
     }
 
-    pub async fn delete_peer(
-        &self,
-        server_unique_id: &str,
-    ) -> Result<Option<DeleteResult>, String> {
-        Ok(Some(
-            self.peers
-                .delete_one(doc! {"server_unique_id": server_unique_id}, None)
-                .await
-                .map_err(|err| err.to_string())?,
-        ))
+    async fn count(&self) -> Result<u64, Error> {
+        self.peers.count_documents(None, None).await
+    }
+
+    async fn server_ids(&self) -> Result<Vec<UserId>, Error> {
+        let mut cursor = self.peers.find(None, None).await?;
+        let mut server_ids = Vec::new();
+        while let Some(peer) = cursor.next().await {
+            server_ids.push(peer?.server_unique_id);
+        }
+        Ok(server_ids)
     }
 }
 
@@ -59,4 +92,4 @@ impl Clone for PeersManager {
             peers: self.peers.clone(),
         }
     }
-}
\ No newline at end of file
+}
@@ -1,24 +1,51 @@
-use crate::models::permission::Permission;
+use crate::{
+    id::PermissionId,
+    indexes::{ensure_indexes, IndexSpec},
+    models::permission::Permission,
+};
 
+use async_trait::async_trait;
 use mongodb::{
-  bson::doc, error::Error, results::InsertOneResult, Collection
+  bson::doc, error::Error, Collection
 };
 
+#[async_trait]
+pub trait PermissionRepository: Send + Sync {
+  async fn create(&self, permission: &Permission) -> Result<(), Error>;
+
+  async fn permission_exists(&self, permission_id: PermissionId) -> bool;
+
+  /// Fails when no permission has this name.
+  async fn get_permission_id(&self, permission_name: &str) -> Result<PermissionId, Error>;
+}
+
 pub struct PermissionManager {
   pub permissions: Collection<Permission>,
 }
 
 impl PermissionManager {
+  pub const INDEXES: &'static [IndexSpec] = &[
+    IndexSpec::unique("permissions_unique_id", &["unique_id"]),
+    IndexSpec::unique("permissions_name", &["name"]),
+  ];
+
   pub fn init(permissions: Collection<Permission>) -> Self {
     Self { permissions }
   }
 
-  pub async fn create(&self, permission: &Permission) -> Result<InsertOneResult, Error> {
-    let target = self.permissions.insert_one(permission, None).await?;
-    Ok(target)
+  pub async fn ensure_indexes(&self) -> Result<(), Error> {
+    ensure_indexes(&self.permissions, Self::INDEXES).await
+  }
+}
+
+#[async_trait]
+impl PermissionRepository for PermissionManager {
+  async fn create(&self, permission: &Permission) -> Result<(), Error> {
+    self.permissions.insert_one(permission, None).await?;
+    Ok(())
   }
 
-  pub async fn permission_exists(&self, permission_id: &str) -> bool {
+  async fn permission_exists(&self, permission_id: PermissionId) -> bool {
     let filter = doc! { "unique_id": permission_id };
     let result = self.permissions.find_one(filter, None).await;
     match result {
@@ -30,22 +57,26 @@ impl PermissionManager {
     }
   }
 
-  pub async fn get_permission_id(&self, permission_name: &str) -> Result<String, Error> {
+  async fn get_permission_id(&self, permission_name: &str) -> Result<PermissionId, Error> {
     let filter = doc! { "name": permission_name };
     let result = self.permissions.find_one(filter, None).await;
     match result {
       Ok(permission) => match permission {
         Some(permission) => Ok(permission.unique_id),
-        None => Err(Error::from(std::io::Error::new(
-          std::io::ErrorKind::NotFound,
-          "Unknown permission",
-        ))),
+        None => Err(unknown_permission()),
       },
       Err(err) => Err(err),
     }
   }
 }
 
+pub(crate) fn unknown_permission() -> Error {
+  Error::from(std::io::Error::new(
+    std::io::ErrorKind::NotFound,
+    "Unknown permission",
+  ))
+}
+
 impl Clone for PermissionManager {
   fn clone(&self) -> Self {
     Self {
@@ -3,78 +3,115 @@ pub use crate::models::project::ProjectUpdateData;
 pub use crate::models::project::ProjectUpdate;
 use std::collections::HashMap;
 
-
-use futures::StreamExt;
+use async_trait::async_trait;
+use futures::TryStreamExt;
 use mongodb::{
     error::Error,
-    results::InsertOneResult,
     bson::{doc, to_bson, Bson},
     Collection,
 };
 
+use crate::{
+    id::{OrganizationId, ProjectId, UserId},
+    indexes::{ensure_indexes, IndexSpec},
+    pagination::{paginate, Page, PageRequest, PageSpec, PaginationError},
+};
+
+pub(crate) const PAGE_SPEC: PageSpec<'static> = PageSpec {
+    id_field: "unique_id",
+    sort_fields: &["name"],
+    filter_fields: &["name"],
+    date_field: None,
+};
+
+#[async_trait]
+pub trait ProjectRepository: Send + Sync {
+    async fn create(&self, project: &Project) -> Result<(), Error>;
+
+    async fn from_organization_id(
+        &self,
+        organization_id: OrganizationId,
+        page: &PageRequest,
+    ) -> Result<Page<Project>, PaginationError>;
+
+    async fn from_id(&self, id: ProjectId) -> Result<Option<Project>, Error>;
+
+    /// Deletes the project and returns it.
+    async fn delete_from_id(&self, id: ProjectId) -> Result<Option<Project>, Error>;
+
+    async fn update_project(&self, id: ProjectId, project_update: Vec<ProjectUpdate>) -> Result<bool, Error>;
+
+    /// Returns every project having the user as a member.
+    async fn from_member(&self, user_id: UserId) -> Result<Vec<Project>, Error>;
+
+    /// Removes the user from the members of every project, and returns how many projects were changed.
+    async fn remove_member(&self, user_id: UserId) -> Result<u64, Error>;
+}
+
 pub struct ProjectManager {
     pub projects: Collection<Project>,
 }
 
 impl ProjectManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("projects_unique_id", &["unique_id"]),
+        IndexSpec::new("projects_organization_id", &["organization_id"]),
+        IndexSpec::new("projects_member_ids", &["member_ids"]),
+    ];
+
     pub fn init(projects: Collection<Project>) -> Self {
         Self { projects }
     }
 
-    pub async fn create(
-        &self,
-        project: &Project,
-    ) -> Result<InsertOneResult, Error> {
-        let target = self.projects.insert_one(project, None).await?;
-        Ok(target)
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.projects, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for ProjectManager {
+    async fn create(&self, project: &Project) -> Result<(), Error> {
+        self.projects.insert_one(project, None).await?;
+        Ok(())
     }
 
-    pub async fn from_organization_id(
+    async fn from_organization_id(
         &self,
-        organization_id: &str,
-    ) -> Result<Vec<Project>, Error> {
+        organization_id: OrganizationId,
+        page: &PageRequest,
+    ) -> Result<Page<Project>, PaginationError> {
         let filter = doc! {"organization_id": organization_id};
-        let mut cursor: mongodb::Cursor<Project>  = self.projects.find(filter, None).await?;
-        let mut projects = Vec::new();
-        
-        while let Some(project) = cursor.next().await {
-            projects.push(project?);
-        }
-
-        Ok(projects)
+        paginate(&self.projects, filter, page, &PAGE_SPEC).await
     }
 
-    pub async fn from_id(
-        &self,
-        id: &str,
-    ) -> Result<Option<Project>, Error> {
+    async fn from_id(&self, id: ProjectId) -> Result<Option<Project>, Error> {
         Ok(self.projects.find_one(doc! {"unique_id": id}, None).await?)
     }
 
-    pub async fn delete_from_id(
-        &self,
-        id: &str
-    ) -> Result<Option<Project>, Error> {
+    async fn delete_from_id(&self, id: ProjectId) -> Result<Option<Project>, Error> {
         Ok(self.projects.find_one_and_delete(doc! {"unique_id": id}, None).await?)
     }
 
-    pub async fn update_project(
-        &self,
-        id: &str,
-        project_update: Vec<ProjectUpdate>,
-    ) -> Result<bool, Error> {
-        let filter = doc! {"unique_id": id.to_string()};
+    async fn update_project(&self, id: ProjectId, project_update: Vec<ProjectUpdate>) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": id};
         let fields: HashMap<String, Bson> = project_update
             .iter()
             .filter_map(|update| update.convert())
             .collect();
         let update = doc! {"$set": to_bson(&fields).unwrap()};
+        let result = self.projects.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
 
-        match self.projects.update_one(filter, update, None).await {
-            Ok(result) if result.matched_count > 0 => Ok(true),
-            Ok(_) => Ok(false),
-            Err(err) => Err(err),
-        }
+    async fn from_member(&self, user_id: UserId) -> Result<Vec<Project>, Error> {
+        self.projects.find(doc! { "member_ids": user_id }, None).await?.try_collect().await
+    }
+
+    async fn remove_member(&self, user_id: UserId) -> Result<u64, Error> {
+        let filter = doc! { "member_ids": user_id };
+        let update = doc! { "$pull": { "member_ids": user_id } };
+        let result = self.projects.update_many(filter, update, None).await?;
+        Ok(result.modified_count)
     }
 }
 
@@ -84,4 +121,4 @@ impl Clone for ProjectManager {
             projects: self.projects.clone(),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use mongodb::{
+    bson::{doc, Bson},
+    error::Error,
+    options::{FindOneAndUpdateOptions, ReturnDocument},
+    Collection,
+};
+
+use crate::{
+    indexes::{ensure_indexes, is_duplicate_key, IndexSpec},
+    models::rate_limit::{Bucket, BucketLimit, Lockout, LockoutPolicy},
+    timestamp::Timestamp,
+};
+
+/// The token buckets of the rate limiter and the lockouts of the accounts.
+#[async_trait]
+pub trait RateLimitRepository: Send + Sync {
+    /// Takes a token of the bucket, and returns how long to wait for one when the bucket is empty.
+    async fn take_token(&self, key: &str, limit: &BucketLimit, now: Timestamp) -> Result<Option<Duration>, Error>;
+
+    /// Counts a failed credential check of the account, and returns the date it is locked until, if it is.
+    async fn record_failure(
+        &self,
+        account: &str,
+        policy: &LockoutPolicy,
+        now: Timestamp,
+    ) -> Result<Option<Timestamp>, Error>;
+
+    /// Returns the date the account is locked until, if it is still locked.
+    async fn locked_until(&self, account: &str, now: Timestamp) -> Result<Option<Timestamp>, Error>;
+
+    /// Forgets the failed credential checks of the account, after a successful one.
+    async fn clear_failures(&self, account: &str) -> Result<(), Error>;
+}
+
+/// Keeps the buckets and the lockouts in MongoDB, for every replica of the API to share them.
+pub struct RateLimitManager {
+    pub buckets: Collection<Bucket>,
+    pub lockouts: Collection<Lockout>,
+}
+
+impl RateLimitManager {
+    pub const BUCKET_INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("rate_limit_buckets_key", &["key"]),
+        IndexSpec::ttl("rate_limit_buckets_expires_at", &["expires_at"], Duration::ZERO),
+    ];
+
+    pub const LOCKOUT_INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("account_lockouts_account", &["account"]),
+        IndexSpec::ttl("account_lockouts_expires_at", &["expires_at"], Duration::ZERO),
+    ];
+
+    pub fn init(buckets: Collection<Bucket>, lockouts: Collection<Lockout>) -> Self {
+        Self { buckets, lockouts }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.buckets, Self::BUCKET_INDEXES).await?;
+        ensure_indexes(&self.lockouts, Self::LOCKOUT_INDEXES).await
+    }
+
+    // take_token_once refills and takes a token in a single atomic update, creating the bucket full
+    async fn take_token_once(&self, key: &str, limit: &BucketLimit, now: Timestamp) -> Result<Option<Bucket>, Error> {
+        let now = Bson::from(now);
+        let elapsed = doc! { "$max": [0, { "$subtract": [&now, { "$ifNull": ["$updated", &now] }] }] };
+        let refill = doc! { "$multiply": [elapsed, limit.refill_per_second / 1000.0] };
+        let refilled = doc! { "$min": [limit.capacity, { "$add": [{ "$ifNull": ["$tokens", limit.capacity] }, refill] }] };
+        // The bucket is full again once it expires, adding milliseconds to a date gives a date
+        let full_after = i64::try_from(limit.full_after().as_millis()).unwrap_or(i64::MAX);
+        let pipeline = vec![
+            doc! { "$set": { "tokens": refilled, "updated": &now, "expires_at": { "$add": [&now, full_after] } } },
+            doc! {
+                "$set": {
+                    "allowed": { "$gte": ["$tokens", 1.0] },
+                    "tokens": { "$cond": [{ "$gte": ["$tokens", 1.0] }, { "$subtract": ["$tokens", 1.0] }, "$tokens"] },
+                }
+            },
+        ];
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        self.buckets.find_one_and_update(doc! { "key": key }, pipeline, options).await
+    }
+}
+
+#[async_trait]
+impl RateLimitRepository for RateLimitManager {
+    async fn take_token(&self, key: &str, limit: &BucketLimit, now: Timestamp) -> Result<Option<Duration>, Error> {
+        // Two replicas creating the same bucket at once conflict on its key, the update then finds the bucket
+        let bucket = match self.take_token_once(key, limit, now).await {
+            Err(err) if is_duplicate_key(&err) => self.take_token_once(key, limit, now).await?,
+            result => result?,
+        };
+        Ok(match bucket {
+            Some(bucket) if !bucket.allowed => Some(limit.retry_after(bucket.tokens)),
+            _ => None,
+        })
+    }
+
+    async fn record_failure(
+        &self,
+        account: &str,
+        policy: &LockoutPolicy,
+        now: Timestamp,
+    ) -> Result<Option<Timestamp>, Error> {
+        let update = doc! {
+            "$inc": { "failures": 1 },
+            "$set": { "expires_at": now.plus(policy.reset_after) },
+            "$setOnInsert": { "locked_until": Bson::Null },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let filter = doc! { "account": account };
+        let lockout = match self
+            .lockouts
+            .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            .await
+        {
+            Err(err) if is_duplicate_key(&err) => self.lockouts.find_one_and_update(filter, update, options).await?,
+            result => result?,
+        };
+
+        let Some(duration) = lockout.and_then(|lockout| policy.lockout_duration(lockout.failures)) else {
+            return Ok(None);
+        };
+        let locked_until = now.plus(duration);
+        self.lockouts
+            .update_one(doc! { "account": account }, doc! { "$set": { "locked_until": locked_until } }, None)
+            .await?;
+        Ok(Some(locked_until))
+    }
+
+    async fn locked_until(&self, account: &str, now: Timestamp) -> Result<Option<Timestamp>, Error> {
+        let lockout = self
+            .lockouts
+            .find_one(doc! { "account": account, "locked_until": { "$gt": now } }, None)
+            .await?;
+        Ok(lockout.and_then(|lockout| lockout.locked_until))
+    }
+
+    async fn clear_failures(&self, account: &str) -> Result<(), Error> {
+        self.lockouts.delete_one(doc! { "account": account }, None).await?;
+        Ok(())
+    }
+}
+
+impl Clone for RateLimitManager {
+    fn clone(&self) -> Self {
+        Self {
+            buckets: self.buckets.clone(),
+            lockouts: self.lockouts.clone(),
+        }
+    }
+}
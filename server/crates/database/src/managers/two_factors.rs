@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use mongodb::{bson::doc, error::Error, options::ReplaceOptions, Collection};
+
+use crate::{
+    id::UserId,
+    indexes::{ensure_indexes, is_duplicate_key, IndexSpec},
+    timestamp::Timestamp,
+    two_factor::TwoFactor,
+};
+
+#[async_trait]
+pub trait TwoFactorRepository: Send + Sync {
+    async fn get(&self, user_id: UserId) -> Result<Option<TwoFactor>, Error>;
+
+    /// Starts an enrolment, replacing the pending one of the user.
+    ///
+    /// Returns false when the user already enabled a second factor.
+    async fn start(&self, two_factor: &TwoFactor) -> Result<bool, Error>;
+
+    /// Enables the pending second factor once a code of the time step was checked.
+    async fn enable(
+        &self,
+        user_id: UserId,
+        recovery_codes: Vec<String>,
+        step: i64,
+        now: Timestamp,
+    ) -> Result<bool, Error>;
+
+    /// Records that a code of the time step was used, returns false if a code of this step or a later one was.
+    async fn use_step(&self, user_id: UserId, step: i64) -> Result<bool, Error>;
+
+    /// Removes the recovery code hash and returns whether it was unused.
+    async fn use_recovery_code(&self, user_id: UserId, code_hash: &str) -> Result<bool, Error>;
+
+    /// Removes the second factor of the user, enabled or pending.
+    async fn disable(&self, user_id: UserId) -> Result<bool, Error>;
+}
+
+pub struct TwoFactorManager {
+    pub two_factors: Collection<TwoFactor>,
+}
+
+impl TwoFactorManager {
+    pub const INDEXES: &'static [IndexSpec] = &[IndexSpec::unique("two_factors_user_id", &["user_id"])];
+
+    pub fn init(two_factors: Collection<TwoFactor>) -> Self {
+        Self { two_factors }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.two_factors, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl TwoFactorRepository for TwoFactorManager {
+    async fn get(&self, user_id: UserId) -> Result<Option<TwoFactor>, Error> {
+        self.two_factors.find_one(doc! { "user_id": user_id }, None).await
+    }
+
+    async fn start(&self, two_factor: &TwoFactor) -> Result<bool, Error> {
+        // The upsert conflicts with the unique index when the enabled second factor is not matched
+        let filter = doc! { "user_id": two_factor.user_id, "enabled": false };
+        let options = ReplaceOptions::builder().upsert(true).build();
+        match self.two_factors.replace_one(filter, two_factor, options).await {
+            Ok(_) => Ok(true),
+            Err(err) if is_duplicate_key(&err) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn enable(
+        &self,
+        user_id: UserId,
+        recovery_codes: Vec<String>,
+        step: i64,
+        now: Timestamp,
+    ) -> Result<bool, Error> {
+        let filter = doc! { "user_id": user_id, "enabled": false, "last_step": { "$lt": step } };
+        let update = doc! {
+            "$set": {
+                "enabled": true,
+                "recovery_codes": recovery_codes,
+                "last_step": step,
+                "enabled_at": now,
+            }
+        };
+        let result = self.two_factors.update_one(filter, update, None).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn use_step(&self, user_id: UserId, step: i64) -> Result<bool, Error> {
+        let filter = doc! { "user_id": user_id, "enabled": true, "last_step": { "$lt": step } };
+        let update = doc! { "$set": { "last_step": step } };
+        let result = self.two_factors.update_one(filter, update, None).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn use_recovery_code(&self, user_id: UserId, code_hash: &str) -> Result<bool, Error> {
+        let filter = doc! { "user_id": user_id, "enabled": true, "recovery_codes": code_hash };
+        let update = doc! { "$pull": { "recovery_codes": code_hash } };
+        let result = self.two_factors.update_one(filter, update, None).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn disable(&self, user_id: UserId) -> Result<bool, Error> {
+        let result = self.two_factors.delete_one(doc! { "user_id": user_id }, None).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+impl Clone for TwoFactorManager {
+    fn clone(&self) -> Self {
+        Self {
+            two_factors: self.two_factors.clone(),
+        }
+    }
+}
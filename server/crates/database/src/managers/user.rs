@@ -1,75 +1,169 @@
 use std::collections::HashMap;
 
+use async_trait::async_trait;
 use mongodb::{
-    bson::{doc, to_bson, Bson},
+    bson::{doc, to_bson, Bson, Document},
     error::Error,
-    results::{DeleteResult, InsertOneResult, UpdateResult},
     Collection,
 };
 
-use crate::{authentication::Authentication, models::user::User, user::UserUpdate};
+use crate::{
+    authentication::{Authentication, OidcAccount, OidcIdentity},
+    document::to_document,
+    id::{PermissionId, UserId},
+    indexes::{ensure_indexes, IndexSpec},
+    login::Login,
+    models::user::User,
+    pagination::{paginate, Page, PageRequest, PageSpec, PaginationError},
+    password,
+    user::UserUpdate,
+};
+
+pub(crate) const PAGE_SPEC: PageSpec<'static> = PageSpec {
+    id_field: "unique_id",
+    sort_fields: &["creation_date", "authentication.Credentials.username"],
+    filter_fields: &["authentication.Credentials.username"],
+    date_field: Some("creation_date"),
+};
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn email_exists(&self, email: String) -> Result<bool, Error>;
+
+    /// Fails with a duplicate key error when the id, the email or a token is already used.
+    ///
+    /// The password of the credentials is stored hashed, like in `update_auth` and `reset_password`.
+    async fn create_user(&self, user: &User) -> Result<(), Error>;
+
+    async fn from_token(&self, token: &str) -> Result<Option<User>, Error>;
+
+    async fn from_id(&self, id: UserId) -> Result<Option<User>, Error>;
+
+    async fn from_email(&self, email: &str) -> Result<Option<User>, Error>;
+
+    /// Returns the user of the email when the password matches its hash.
+    async fn from_credentials(&self, email: &str, password: &str) -> Result<Option<User>, Error>;
+
+    /// Returns the user the provider account is linked to.
+    async fn from_identity(&self, account: &OidcAccount) -> Result<Option<User>, Error>;
+
+    /// Returns the users matching the given ids.
+    async fn from_ids(&self, ids: &[UserId], page: &PageRequest) -> Result<Page<User>, PaginationError>;
+
+    /// Returns whether a user was deleted, the user is looked up by id first, then by token.
+    async fn delete_user(&self, uuid: Option<UserId>, token: Option<&str>) -> Result<bool, String>;
+
+    /// Replaces the authentication, the email is no longer verified when it changes.
+    async fn update_auth(&self, uuid: UserId, new_auth: &Authentication) -> Result<bool, Error>;
+
+    /// Replaces the password of the credentials by its hash and removes every login, which revokes the sessions of the
+    /// user. The personal access tokens are kept in their own collection and revoked by the caller.
+    async fn reset_password(&self, uuid: UserId, password: &str) -> Result<bool, Error>;
+
+    /// Marks the email as verified, if it is still the email of the user and it was not verified yet.
+    async fn verify_email(&self, uuid: UserId, email: &str) -> Result<bool, Error>;
+
+    async fn update_user(&self, uuid: UserId, user_update: Vec<UserUpdate>) -> Result<bool, Error>;
+
+    async fn add_login(&self, uuid: UserId, login: &Login) -> Result<bool, Error>;
+
+    /// Links the identity to the user, fails with a duplicate key error when the account is linked to another user.
+    ///
+    /// Returns false when the user does not exist or already has the account.
+    async fn link_identity(&self, uuid: UserId, identity: &OidcIdentity) -> Result<bool, Error>;
+
+    /// Returns whether the account was linked to the user.
+    async fn unlink_identity(&self, uuid: UserId, account: &OidcAccount) -> Result<bool, Error>;
+
+    async fn add_permission(&self, uuid: UserId, permission: PermissionId) -> Result<bool, Error>;
+
+    async fn remove_permission(&self, uuid: UserId, permission: PermissionId) -> Result<bool, Error>;
+
+    async fn set_banned(&self, uuid: UserId, banned: bool) -> Result<bool, Error>;
+
+    async fn user_exists(&self, uuid: UserId) -> bool;
+
+    /// Returns whether the user was granted the permission, the unverified users have none.
+    async fn has_permission(&self, uuid: UserId, permission: PermissionId) -> bool;
+
+    async fn count(&self) -> Result<u64, Error>;
+}
 
 pub struct UserManager {
     pub users: Collection<User>,
 }
 
 impl UserManager {
+    pub const INDEXES: &'static [IndexSpec] = &[
+        IndexSpec::unique("users_unique_id", &["unique_id"]),
+        IndexSpec::unique("users_email", &["authentication.Credentials.email"]),
+        IndexSpec::unique("users_login_token", &["logins.token"]),
+        IndexSpec::unique("users_identities", &["identities.account"]),
+        // The providers may not share the email of a user, so it cannot be unique
+        IndexSpec::new("users_oidc_email", &["authentication.Oidc.email"]),
+    ];
+
     pub fn init(users: Collection<User>) -> Self {
         Self { users }
     }
 
-    pub async fn email_exists(&self, email: String) -> Result<bool, Error> {
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        ensure_indexes(&self.users, Self::INDEXES).await
+    }
+}
+
+#[async_trait]
+impl UserRepository for UserManager {
+    async fn email_exists(&self, email: String) -> Result<bool, Error> {
         Ok(self
             .users
-            .count_documents(doc! { "authentication.Credentials.email": email }, None)
+            .count_documents(email_filter(&email), None)
             .await?
             != 0)
-            
     }
 
-    pub async fn create_user(&self, user: &User) -> Result<InsertOneResult, Error> {
-        let target = self.users.insert_one(user, None).await?;
-        Ok(target)
+    async fn create_user(&self, user: &User) -> Result<(), Error> {
+        let user = User {
+            authentication: user.authentication.hashed(),
+            ..user.clone()
+        };
+        self.users.insert_one(user, None).await?;
+        Ok(())
     }
 
-    pub async fn from_token(&self, token: &str) -> Result<Option<User>, Error> {
-        match self
-            .users
-            .find_one(doc! { "logins.token": token}, None)
-            .await?
-        {
-            Some(user) => Ok(Some(user)),
-            None => Ok(None),
-        }
+    async fn from_token(&self, token: &str) -> Result<Option<User>, Error> {
+        self.users.find_one(doc! { "logins.token": token }, None).await
     }
 
-    pub async fn from_id(&self, id: &str) -> Result<Option<User>, Error> {
-        match self
-            .users
-            .find_one(doc! { "unique_id": id.to_string() }, None)
-            .await?
-        {
-            Some(user) => Ok(Some(user)),
-            None => Ok(None),
-        }
+    async fn from_id(&self, id: UserId) -> Result<Option<User>, Error> {
+        self.users.find_one(doc! { "unique_id": id }, None).await
     }
 
-    pub async fn from_email(&self, email: &str) -> Result<Option<User>, Error> {
-        match self
-            .users
-            .find_one(doc! { "authentication.Credentials.email": email.to_string() }, None)
-            .await?
-        {
-            Some(user) => Ok(Some(user)),
-            None => Ok(None),
-        }
+    async fn from_email(&self, email: &str) -> Result<Option<User>, Error> {
+        self.users.find_one(email_filter(email), None).await
+    }
+
+    async fn from_credentials(&self, email: &str, password: &str) -> Result<Option<User>, Error> {
+        let filter = doc! { "authentication.Credentials.email": email };
+        let user = self.users.find_one(filter, None).await?;
+        Ok(user.filter(|user| match &user.authentication {
+            Authentication::Credentials(credentials) => credentials.verify_password(password),
+            Authentication::Oidc(_) | Authentication::None => false,
+        }))
     }
 
-    pub async fn delete_user(
-        &self,
-        uuid: Option<&str>,
-        token: Option<&str>,
-    ) -> Result<Option<DeleteResult>, String> {
+    async fn from_identity(&self, account: &OidcAccount) -> Result<Option<User>, Error> {
+        self.users
+            .find_one(doc! { "identities": { "$elemMatch": account_filter(account) } }, None)
+            .await
+    }
+
+    async fn from_ids(&self, ids: &[UserId], page: &PageRequest) -> Result<Page<User>, PaginationError> {
+        let filter = doc! { "unique_id": { "$in": ids } };
+        paginate(&self.users, filter, page, &PAGE_SPEC).await
+    }
+
+    async fn delete_user(&self, uuid: Option<UserId>, token: Option<&str>) -> Result<bool, String> {
         let user = if let Some(uuid) = uuid {
             self.from_id(uuid)
                 .await
@@ -81,61 +175,118 @@ impl UserManager {
                 .map_err(|err| err.to_string())?
                 .ok_or(format!("User not found with token: {token}"))?
         } else {
-            return Ok(None);
+            return Ok(false);
         };
-        Ok(Some(
-            self.users
-                .delete_one(doc! {"unique_id": user.unique_id}, None)
-                .await
-                .map_err(|err| err.to_string())?,
-        ))
-    }
-
-    pub async fn update_auth(
-        &self,
-        uuid: String,
-        new_auth: &Authentication,
-    ) -> Result<UpdateResult, Error> {
-        let filter = doc! {"unique_id": uuid.to_string()};
-        let update = doc! {"$set": {"authentication": to_bson(new_auth).unwrap()}};
-        self.users.update_one(filter, update, None).await
-    }
-
-    pub async fn update_user(
-        &self,
-        uuid: String,
-        user_update: Vec<UserUpdate>,
-    ) -> Result<UpdateResult, Error> {
-        let filter = doc! {"unique_id": uuid.to_string()};
+        let result = self
+            .users
+            .delete_one(doc! {"unique_id": user.unique_id}, None)
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn update_auth(&self, uuid: UserId, new_auth: &Authentication) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid};
+        let email_verified = match new_auth {
+            Authentication::Credentials(credentials) => Bson::Document(doc! {
+                "$and": [
+                    { "$ne": ["$email_verified", false] },
+                    { "$eq": ["$authentication.Credentials.email", &credentials.email] },
+                ]
+            }),
+            Authentication::Oidc(identity) => Bson::Document(doc! {
+                "$and": [
+                    { "$ne": ["$email_verified", false] },
+                    { "$eq": ["$authentication.Oidc.email", to_bson(&identity.email)?] },
+                ]
+            }),
+            Authentication::None => Bson::Boolean(true),
+        };
+        // The authentication is a literal, its values must not be read as expressions
+        let update = vec![doc! {
+            "$set": {
+                "authentication": { "$literal": to_bson(&new_auth.hashed()).unwrap() },
+                "email_verified": email_verified,
+            }
+        }];
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn reset_password(&self, uuid: UserId, password: &str) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid, "authentication.Credentials": {"$exists": true}};
+        let update = doc! {"$set": {"authentication.Credentials.password": password::hash(password), "logins": []}};
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn verify_email(&self, uuid: UserId, email: &str) -> Result<bool, Error> {
+        let mut filter = email_filter(email);
+        filter.insert("unique_id", uuid);
+        filter.insert("email_verified", false);
+        let update = doc! {"$set": {"email_verified": true}};
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn update_user(&self, uuid: UserId, user_update: Vec<UserUpdate>) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid};
         let fields: HashMap<String, Bson> = user_update
             .iter()
             .filter_map(|update| update.convert())
             .collect();
         let update = doc! {"$set": to_bson(&fields).unwrap()};
-        self.users.update_one(filter, update, None).await
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn add_login(&self, uuid: UserId, login: &Login) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid};
+        let update = doc! {"$push": {"logins": to_document(login)?}};
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn add_permission(
-        &self,
-        uuid: String,
-        permission: String,
-    ) -> Result<UpdateResult, Error> {
-        let filter = doc! {"unique_id": uuid.to_string()};
+    async fn link_identity(&self, uuid: UserId, identity: &OidcIdentity) -> Result<bool, Error> {
+        // The unique index only rejects the accounts linked to other users
+        let filter = doc! {
+            "unique_id": uuid,
+            "identities": { "$not": { "$elemMatch": account_filter(&identity.account) } },
+        };
+        let update = doc! {"$push": {"identities": to_document(identity)?}};
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn unlink_identity(&self, uuid: UserId, account: &OidcAccount) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid};
+        let update = doc! {"$pull": {"identities": account_filter(account)}};
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn add_permission(&self, uuid: UserId, permission: PermissionId) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid};
         let update = doc! {"$addToSet": {"permissions": permission}};
-        self.users.update_one(filter, update, None).await
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn remove_permission(
-        &self,
-        uuid: String,
-        permission: String,
-    ) -> Result<UpdateResult, Error> {
-        let filter = doc! {"unique_id": uuid.to_string()};
+    async fn remove_permission(&self, uuid: UserId, permission: PermissionId) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid};
         let update = doc! {"$pull": {"permissions": permission}};
-        self.users.update_one(filter, update, None).await
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn set_banned(&self, uuid: UserId, banned: bool) -> Result<bool, Error> {
+        let filter = doc! {"unique_id": uuid};
+        let update = doc! {"$set": {"banned": banned}};
+        let result = self.users.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
     }
 
-    pub async fn user_exists(&self, uuid: &str) -> bool {
+    async fn user_exists(&self, uuid: UserId) -> bool {
         let filter = doc! { "unique_id": uuid };
         let result = self.users.find_one(filter, None).await;
         match result {
@@ -147,8 +298,8 @@ impl UserManager {
         }
     }
 
-    pub async fn has_permission(&self, uuid: &str, permission: &str) -> bool {
-        let filter = doc! { "unique_id": uuid, "permissions": permission };
+    async fn has_permission(&self, uuid: UserId, permission: PermissionId) -> bool {
+        let filter = doc! { "unique_id": uuid, "permissions": permission, "email_verified": { "$ne": false } };
         let result = self.users.find_one(filter, None).await;
         match result {
             Ok(user) => match user {
@@ -158,6 +309,25 @@ impl UserManager {
             Err(_) => false,
         }
     }
+
+    async fn count(&self) -> Result<u64, Error> {
+        self.users.count_documents(None, None).await
+    }
+}
+
+// email_filter matches the users having the email in their credentials or claimed by their provider
+fn email_filter(email: &str) -> Document {
+    doc! {
+        "$or": [
+            { "authentication.Credentials.email": email },
+            { "authentication.Oidc.email": email },
+        ]
+    }
+}
+
+// account_filter matches a linked identity of the account, its fields are compared one by one rather than in order
+fn account_filter(account: &OidcAccount) -> Document {
+    doc! { "account.provider": &account.provider, "account.subject": &account.subject }
 }
 
 impl Clone for UserManager {
@@ -166,4 +336,4 @@ impl Clone for UserManager {
             users: self.users.clone(),
         }
     }
-}
\ No newline at end of file
+}
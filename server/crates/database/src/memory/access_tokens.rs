@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    access_token::AccessToken,
+    id::{AccessTokenId, UserId},
+    managers::{AccessTokenManager, AccessTokenRepository},
+    timestamp::Timestamp,
+};
+
+pub struct MemoryAccessTokenManager {
+    tokens: MemoryCollection<AccessToken>,
+}
+
+impl MemoryAccessTokenManager {
+    pub fn new() -> Self {
+        Self {
+            tokens: MemoryCollection::new(AccessTokenManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryAccessTokenManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl AccessTokenRepository for MemoryAccessTokenManager {
+    async fn create(&self, token: &AccessToken) -> Result<(), Error> {
+        self.tokens.insert(token)
+    }
+
+    async fn use_token(&self, token_hash: &str, now: Timestamp) -> Result<Option<AccessToken>, Error> {
+        let matches = |token: &AccessToken| token.token_hash == token_hash && token.expires_at > now;
+        let token = self.tokens.find_one(matches)?;
+        if token.is_some() {
+            self.tokens.update_one(matches, |token| token.last_used = Some(now))?;
+        }
+        Ok(token)
+    }
+
+    async fn list(&self, user_id: UserId) -> Result<Vec<AccessToken>, Error> {
+        let mut tokens = self.tokens.find(|token| token.user_id == user_id)?;
+        tokens.sort_by_key(|token| token.creation_date);
+        Ok(tokens)
+    }
+
+    async fn revoke(&self, user_id: UserId, id: AccessTokenId) -> Result<bool, Error> {
+        Ok(self
+            .tokens
+            .delete_one(|token| token.unique_id == id && token.user_id == user_id)?
+            .is_some())
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        self.tokens.delete_many(|token| token.user_id == user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::MemoryAccessTokenManager;
+    use crate::{
+        access_token::AccessToken,
+        id::{AccessTokenId, UserId},
+        managers::AccessTokenRepository,
+        timestamp::Timestamp,
+    };
+
+    #[rocket::async_test]
+    async fn test_use_token() {
+        let tokens = MemoryAccessTokenManager::new();
+        let now = Timestamp::from_millis(1_000_000);
+        let user_id = UserId::generate();
+        let token = AccessToken {
+            unique_id: AccessTokenId::generate(),
+            user_id,
+            name: "ci".to_string(),
+            token_hash: "hash".to_string(),
+            scopes: vec!["permission.see".to_string()],
+            creation_date: now,
+            expires_at: now.plus(Duration::from_secs(60)),
+            last_used: None,
+        };
+        tokens.create(&token).await.unwrap();
+
+        assert!(tokens.use_token("unknown", now).await.unwrap().is_none());
+        // An expired token is refused
+        assert!(tokens
+            .use_token("hash", now.plus(Duration::from_secs(60)))
+            .await
+            .unwrap()
+            .is_none());
+        let used = tokens.use_token("hash", now).await.unwrap().unwrap();
+        assert_eq!(used.unique_id, token.unique_id);
+        assert_eq!(tokens.list(user_id).await.unwrap()[0].last_used, Some(now));
+
+        // Only the owner of the token revokes it
+        assert!(!tokens.revoke(UserId::generate(), token.unique_id).await.unwrap());
+        assert!(tokens.revoke(user_id, token.unique_id).await.unwrap());
+        assert!(tokens.use_token("hash", now).await.unwrap().is_none());
+    }
+}
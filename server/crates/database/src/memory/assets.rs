@@ -0,0 +1,155 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::UserId,
+    managers::{assets::PAGE_SPEC, AssetManager, AssetRepository},
+    models::{asset::Asset, moderation::ModerationStatus},
+    pagination::{paginate_in_memory, Page, PageRequest, PaginationError},
+};
+
+pub struct MemoryAssetManager {
+    assets: MemoryCollection<Asset>,
+}
+
+impl MemoryAssetManager {
+    pub fn new() -> Self {
+        Self {
+            assets: MemoryCollection::new(AssetManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryAssetManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl AssetRepository for MemoryAssetManager {
+    async fn create_asset(&self, asset: &Asset) -> Result<(), Error> {
+        self.assets.insert(asset)
+    }
+
+    async fn get_asset_by_id(&self, id: u32) -> Result<Option<Asset>, Error> {
+        self.assets.find_one(|asset| asset.id == id)
+    }
+
+    async fn get_assets(&self, search: Option<&str>, page: &PageRequest) -> Result<Page<Asset>, PaginationError> {
+        let search = search.map(str::to_lowercase);
+        let assets = self.assets.find(|asset| {
+            asset.status != ModerationStatus::Hidden
+                && search.as_ref().is_none_or(|search| {
+                    asset.title.to_lowercase().contains(search) || asset.description.to_lowercase().contains(search)
+                })
+        })?;
+        paginate_in_memory(assets, page, &PAGE_SPEC)
+    }
+
+    async fn update_asset(&self, id: u32, updated_asset: &Asset) -> Result<bool, Error> {
+        self.assets
+            .update_one(|asset| asset.id == id, |asset| *asset = updated_asset.clone())
+    }
+
+    async fn set_status(&self, id: u32, status: ModerationStatus) -> Result<bool, Error> {
+        self.assets
+            .update_one(|asset| asset.id == id, |asset| asset.status = status)
+    }
+
+    async fn delete_asset(&self, id: u32) -> Result<bool, Error> {
+        Ok(self.assets.delete_one(|asset| asset.id == id)?.is_some())
+    }
+
+    async fn asset_exists(&self, id: u32) -> Result<bool, Error> {
+        Ok(self.assets.count(|asset| asset.id == id)? > 0)
+    }
+
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<Asset>, Error> {
+        self.assets.find(|asset| involves(asset, user_id))
+    }
+
+    async fn erase_user(&self, user_id: UserId) -> Result<(), Error> {
+        self.assets.update_many(
+            |asset| involves(asset, user_id),
+            |asset| {
+                asset.upvote_user_ids.retain(|id| *id != user_id);
+                asset.downvote_user_ids.retain(|id| *id != user_id);
+                asset.favorite_user_ids.retain(|id| *id != user_id);
+                asset.comments.retain(|comment| comment.user_id != user_id);
+                if asset.author_id == Some(user_id) {
+                    asset.author_id = None;
+                }
+            },
+        )?;
+        Ok(())
+    }
+}
+
+fn involves(asset: &Asset, user_id: UserId) -> bool {
+    asset.author_id == Some(user_id)
+        || asset.upvote_user_ids.contains(&user_id)
+        || asset.downvote_user_ids.contains(&user_id)
+        || asset.favorite_user_ids.contains(&user_id)
+        || asset.comments.iter().any(|comment| comment.user_id == user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryAssetManager;
+    use crate::{
+        comment::Comment,
+        id::{CommentId, UserId},
+        managers::AssetRepository,
+        models::{asset::Asset, moderation::ModerationStatus},
+        timestamp::Timestamp,
+    };
+
+    fn comment(user_id: UserId) -> Comment {
+        Comment {
+            unique_id: CommentId::generate(),
+            asset_id: 1,
+            user_id,
+            content: "Nice".to_string(),
+            timestamp: Timestamp::from_millis(0),
+            status: ModerationStatus::Visible,
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_erase_user() {
+        let assets = MemoryAssetManager::new();
+        let user_id = UserId::generate();
+        let other_id = UserId::generate();
+        let mut asset = Asset {
+            id: 1,
+            author_id: Some(user_id),
+            title: "Title".to_string(),
+            description: "Description".to_string(),
+            upload_date: Timestamp::from_millis(0),
+            price: "0".to_string(),
+            cover_image: String::new(),
+            images: Vec::new(),
+            comments: vec![comment(user_id), comment(other_id)],
+            upvote_user_ids: Vec::new(),
+            downvote_user_ids: Vec::new(),
+            favorite_user_ids: vec![user_id, other_id],
+            status: ModerationStatus::Visible,
+        };
+        asset.add_upvote(user_id);
+        asset.add_downvote(other_id);
+        assets.create_asset(&asset).await.unwrap();
+        assert_eq!(assets.from_user(user_id).await.unwrap().len(), 1);
+
+        assets.erase_user(user_id).await.unwrap();
+        assert!(assets.from_user(user_id).await.unwrap().is_empty());
+        let asset = assets.get_asset_by_id(1).await.unwrap().unwrap();
+        assert_eq!(asset.author_id, None);
+        assert!(asset.upvote_user_ids.is_empty());
+        assert_eq!(asset.downvote_user_ids, vec![other_id]);
+        assert_eq!(asset.favorite_user_ids, vec![other_id]);
+        assert_eq!(asset.comments.len(), 1);
+        assert_eq!(asset.comments[0].user_id, other_id);
+    }
+}
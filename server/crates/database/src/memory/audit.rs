@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::{OrganizationId, UserId},
+    managers::{audit::PAGE_SPEC, AuditManager, AuditRepository},
+    models::audit::AuditEvent,
+    pagination::{paginate_in_memory, Page, PageRequest, PaginationError},
+};
+
+pub struct MemoryAuditManager {
+    events: MemoryCollection<AuditEvent>,
+}
+
+impl MemoryAuditManager {
+    pub fn new() -> Self {
+        Self {
+            events: MemoryCollection::new(AuditManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryAuditManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MemoryAuditManager {
+    async fn record(&self, event: &AuditEvent) -> Result<(), Error> {
+        self.events.insert(event)
+    }
+
+    async fn get_events(
+        &self,
+        organization_id: OrganizationId,
+        user_ids: &[UserId],
+        page: &PageRequest,
+    ) -> Result<Page<AuditEvent>, PaginationError> {
+        let events = self.events.find(|event| match event.organization_id {
+            Some(id) => id == organization_id,
+            None => user_ids
+                .iter()
+                .any(|user_id| user_id.to_string() == event.target_id || Some(*user_id) == event.actor_id),
+        })?;
+        paginate_in_memory(events, page, &PAGE_SPEC)
+    }
+
+    async fn anonymize_user(&self, user_id: UserId) -> Result<u64, Error> {
+        self.events.update_many(
+            |event| event.actor_id == Some(user_id) || event.target_id == user_id.to_string(),
+            |event| {
+                event.ip = None;
+                event.changes = None;
+            },
+        )
+    }
+}
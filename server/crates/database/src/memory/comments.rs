@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::{CommentId, UserId},
+    managers::{comments::PAGE_SPEC, CommentManager, CommentRepository},
+    models::{comment::Comment, moderation::ModerationStatus},
+    pagination::{paginate_in_memory, Page, PageRequest, PaginationError},
+};
+
+pub struct MemoryCommentManager {
+    comments: MemoryCollection<Comment>,
+}
+
+impl MemoryCommentManager {
+    pub fn new() -> Self {
+        Self {
+            comments: MemoryCollection::new(CommentManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryCommentManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl CommentRepository for MemoryCommentManager {
+    async fn add_comment(&self, comment: &Comment) -> Result<(), Error> {
+        self.comments.insert(comment)
+    }
+
+    async fn from_id(&self, id: CommentId) -> Result<Option<Comment>, Error> {
+        self.comments.find_one(|comment| comment.unique_id == id)
+    }
+
+    async fn get_comment_by_user(&self, user_id: UserId) -> Result<Option<Comment>, Error> {
+        self.comments.find_one(|comment| comment.user_id == user_id)
+    }
+
+    async fn from_asset_id(&self, asset_id: u32, page: &PageRequest) -> Result<Page<Comment>, PaginationError> {
+        let comments = self
+            .comments
+            .find(|comment| comment.asset_id == asset_id && comment.status != ModerationStatus::Hidden)?;
+        paginate_in_memory(comments, page, &PAGE_SPEC)
+    }
+
+    async fn set_status(&self, id: CommentId, status: ModerationStatus) -> Result<bool, Error> {
+        self.comments
+            .update_one(|comment| comment.unique_id == id, |comment| comment.status = status)
+    }
+
+    async fn delete_comment(&self, id: CommentId) -> Result<bool, Error> {
+        Ok(self.comments.delete_one(|comment| comment.unique_id == id)?.is_some())
+    }
+
+    async fn comment_exists(&self, id: CommentId) -> Result<bool, Error> {
+        Ok(self.comments.count(|comment| comment.unique_id == id)? > 0)
+    }
+
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<Comment>, Error> {
+        self.comments.find(|comment| comment.user_id == user_id)
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        self.comments.delete_many(|comment| comment.user_id == user_id)
+    }
+}
@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::UserId,
+    managers::{licenses::PAGE_SPEC, License, LicenseManager, LicenseRepository},
+    pagination::{paginate_in_memory, Page, PageRequest, PaginationError},
+};
+
+pub struct MemoryLicenseManager {
+    licenses: MemoryCollection<License>,
+}
+
+impl MemoryLicenseManager {
+    pub fn new() -> Self {
+        Self {
+            licenses: MemoryCollection::new(LicenseManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryLicenseManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl LicenseRepository for MemoryLicenseManager {
+    async fn create(&self, license: &License) -> Result<(), Error> {
+        self.licenses.insert(license)
+    }
+
+    async fn get_licenses(&self, user_id: UserId, page: &PageRequest) -> Result<Page<License>, PaginationError> {
+        let licenses = self.licenses.find(|license| license.user_id == user_id)?;
+        paginate_in_memory(licenses, page, &PAGE_SPEC)
+    }
+
+    async fn get_license(&self, license_id: &str) -> Result<Option<License>, Error> {
+        self.licenses.find_one(|license| license.license == license_id)
+    }
+
+    async fn from_user(&self, user_id: UserId) -> Result<Vec<License>, Error> {
+        self.licenses.find(|license| license.user_id == user_id)
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        self.licenses.delete_many(|license| license.user_id == user_id)
+    }
+}
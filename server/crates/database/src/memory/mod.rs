@@ -0,0 +1,321 @@
+mod access_tokens;
+mod assets;
+mod audit;
+mod comments;
+mod licenses;
+mod moderation;
+mod outbox;
+mod password_resets;
+mod oidc_logins;
+mod organization;
+mod peer;
+mod permission;
+mod projects;
+mod rate_limits;
+mod two_factors;
+mod user;
+
+pub use access_tokens::*;
+pub use assets::*;
+pub use audit::*;
+pub use comments::*;
+pub use licenses::*;
+pub use moderation::*;
+pub use outbox::*;
+pub use password_resets::*;
+pub use oidc_logins::*;
+pub use organization::*;
+pub use peer::*;
+pub use permission::*;
+pub use projects::*;
+pub use rate_limits::*;
+pub use two_factors::*;
+pub use user::*;
+
+use std::{marker::PhantomData, sync::RwLock};
+
+use mongodb::{
+    bson::{Bson, Document},
+    error::Error,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    document::{from_document, to_document},
+    indexes::{DuplicateKey, IndexSpec},
+};
+
+/// A collection held in memory, enforcing the unique indexes declared by the matching MongoDB manager.
+///
+/// Items are stored as BSON documents, like MongoDB stores them, so that both backends serialize the same way.
+pub(crate) struct MemoryCollection<T> {
+    documents: RwLock<Vec<Document>>,
+    indexes: &'static [IndexSpec],
+    item: PhantomData<fn() -> T>,
+}
+
+impl<T> MemoryCollection<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub fn new(indexes: &'static [IndexSpec]) -> Self {
+        Self {
+            documents: RwLock::new(Vec::new()),
+            indexes,
+            item: PhantomData,
+        }
+    }
+
+    pub fn insert(&self, item: &T) -> Result<(), Error> {
+        let document = to_document(item)?;
+        let mut documents = self.documents.write().unwrap();
+        self.check_unique(&documents, &document, None)?;
+        documents.push(document);
+        Ok(())
+    }
+
+    pub fn find_one(&self, filter: impl Fn(&T) -> bool) -> Result<Option<T>, Error> {
+        Ok(self.find(filter)?.into_iter().next())
+    }
+
+    pub fn find(&self, filter: impl Fn(&T) -> bool) -> Result<Vec<T>, Error> {
+        let documents = self.documents.read().unwrap();
+        let mut items = Vec::new();
+        for document in documents.iter() {
+            let item: T = from_document(document.clone())?;
+            if filter(&item) {
+                items.push(item);
+            }
+        }
+        Ok(items)
+    }
+
+    pub fn count(&self, filter: impl Fn(&T) -> bool) -> Result<u64, Error> {
+        Ok(self.find(filter)?.len() as u64)
+    }
+
+    /// Updates the first item matching the filter, and returns whether an item matched.
+    pub fn update_one(&self, filter: impl Fn(&T) -> bool, update: impl FnOnce(&mut T)) -> Result<bool, Error> {
+        self.update_document(filter, |document| {
+            let mut item: T = from_document(document.clone())?;
+            update(&mut item);
+            *document = to_document(&item)?;
+            Ok(())
+        })
+    }
+
+    /// Updates every item matching the filter, and returns how many matched.
+    pub fn update_many(&self, filter: impl Fn(&T) -> bool, update: impl Fn(&mut T)) -> Result<u64, Error> {
+        let mut documents = self.documents.write().unwrap();
+        let mut updated = documents.clone();
+        let mut count = 0;
+        for position in 0..updated.len() {
+            let mut item: T = from_document(updated[position].clone())?;
+            if !filter(&item) {
+                continue;
+            }
+            update(&mut item);
+            let document = to_document(&item)?;
+            self.check_unique(&updated, &document, Some(position))?;
+            updated[position] = document;
+            count += 1;
+        }
+        *documents = updated;
+        Ok(count)
+    }
+
+    /// Sets the fields of the first item matching the filter, the fields are paths like `$set` accepts.
+    pub fn set_fields(
+        &self,
+        filter: impl Fn(&T) -> bool,
+        fields: impl IntoIterator<Item = (String, Bson)>,
+    ) -> Result<bool, Error> {
+        self.update_document(filter, |document| {
+            for (path, value) in fields {
+                set_path(document, &path, value)?;
+            }
+            // The document must still be a valid item
+            from_document::<T>(document.clone())?;
+            Ok(())
+        })
+    }
+
+    /// Deletes the first item matching the filter and returns it.
+    pub fn delete_one(&self, filter: impl Fn(&T) -> bool) -> Result<Option<T>, Error> {
+        let mut documents = self.documents.write().unwrap();
+        for position in 0..documents.len() {
+            let item: T = from_document(documents[position].clone())?;
+            if filter(&item) {
+                documents.remove(position);
+                return Ok(Some(item));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Deletes every item matching the filter, and returns how many were deleted.
+    pub fn delete_many(&self, filter: impl Fn(&T) -> bool) -> Result<u64, Error> {
+        let mut documents = self.documents.write().unwrap();
+        let mut kept = Vec::with_capacity(documents.len());
+        for document in documents.iter() {
+            let item: T = from_document(document.clone())?;
+            if !filter(&item) {
+                kept.push(document.clone());
+            }
+        }
+        let deleted = (documents.len() - kept.len()) as u64;
+        *documents = kept;
+        Ok(deleted)
+    }
+
+    fn update_document(
+        &self,
+        filter: impl Fn(&T) -> bool,
+        update: impl FnOnce(&mut Document) -> Result<(), Error>,
+    ) -> Result<bool, Error> {
+        let mut documents = self.documents.write().unwrap();
+        for position in 0..documents.len() {
+            let item: T = from_document(documents[position].clone())?;
+            if !filter(&item) {
+                continue;
+            }
+            let mut document = documents[position].clone();
+            update(&mut document)?;
+            self.check_unique(&documents, &document, Some(position))?;
+            documents[position] = document;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    // check_unique fails when the document shares the keys of a unique index with another document
+    fn check_unique(&self, documents: &[Document], document: &Document, position: Option<usize>) -> Result<(), Error> {
+        for index in self.indexes.iter().filter(|index| index.unique) {
+            let keys = index_keys(document, index);
+            let duplicate = documents
+                .iter()
+                .enumerate()
+                .filter(|(other_position, _)| Some(*other_position) != position)
+                .any(|(_, other)| index_keys(other, index).iter().any(|key| keys.contains(key)));
+            if duplicate {
+                return Err(Error::custom(DuplicateKey));
+            }
+        }
+        Ok(())
+    }
+}
+
+// index_keys returns the entries of the document in the index, one per element when the key is an array
+fn index_keys(document: &Document, index: &IndexSpec) -> Vec<Vec<Bson>> {
+    match index.keys {
+        [key] => values_at(document, key).into_iter().map(|value| vec![value]).collect(),
+        keys => {
+            let mut entry = Vec::new();
+            for key in keys {
+                match values_at(document, key).into_iter().next() {
+                    Some(value) => entry.push(value),
+                    // Unique indexes only cover the documents having every key
+                    None => return Vec::new(),
+                }
+            }
+            vec![entry]
+        }
+    }
+}
+
+// values_at returns the values found at the path, going through the arrays of documents like MongoDB does
+fn values_at(value: &Document, path: &str) -> Vec<Bson> {
+    let (field, rest) = match path.split_once('.') {
+        Some((field, rest)) => (field, Some(rest)),
+        None => (path, None),
+    };
+    let Some(value) = value.get(field) else {
+        return Vec::new();
+    };
+    match (value, rest) {
+        (Bson::Document(document), Some(rest)) => values_at(document, rest),
+        (Bson::Array(values), rest) => values
+            .iter()
+            .flat_map(|value| match (value, rest) {
+                (Bson::Document(document), Some(rest)) => values_at(document, rest),
+                (value, None) => vec![value.clone()],
+                _ => Vec::new(),
+            })
+            .collect(),
+        (value, None) => vec![value.clone()],
+        _ => Vec::new(),
+    }
+}
+
+fn set_path(document: &mut Document, path: &str, value: Bson) -> Result<(), Error> {
+    match path.split_once('.') {
+        None => {
+            document.insert(path, value);
+            Ok(())
+        }
+        Some((field, rest)) => {
+            let child = document
+                .entry(field.to_string())
+                .or_insert_with(|| Bson::Document(Document::new()));
+            match child {
+                Bson::Document(child) => set_path(child, rest, value),
+                _ => Err(Error::custom(format!("Cannot create field '{rest}' in element '{field}'"))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{doc, Bson};
+    use serde::{Deserialize, Serialize};
+
+    use super::{values_at, MemoryCollection};
+    use crate::indexes::{is_duplicate_key, IndexSpec};
+
+    #[derive(Deserialize, Serialize)]
+    struct Item {
+        id: String,
+        tokens: Vec<String>,
+    }
+
+    const INDEXES: &[IndexSpec] = &[
+        IndexSpec::unique("items_id", &["id"]),
+        IndexSpec::unique("items_tokens", &["tokens"]),
+    ];
+
+    fn item(id: &str, tokens: &[&str]) -> Item {
+        Item {
+            id: id.to_string(),
+            tokens: tokens.iter().map(|token| token.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_unique_indexes() {
+        let collection = MemoryCollection::new(INDEXES);
+        collection.insert(&item("a", &["1", "2"])).unwrap();
+        collection.insert(&item("b", &[])).unwrap();
+        collection.insert(&item("c", &[])).unwrap();
+
+        assert!(is_duplicate_key(&collection.insert(&item("a", &[])).unwrap_err()));
+        assert!(is_duplicate_key(&collection.insert(&item("d", &["2"])).unwrap_err()));
+        let error = collection
+            .update_one(|item| item.id == "b", |item| item.tokens.push("1".to_string()))
+            .unwrap_err();
+        assert!(is_duplicate_key(&error));
+        assert_eq!(collection.count(|_| true).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_values_at() {
+        let document = doc! { "logins": [{ "token": "a" }, { "token": "b" }], "name": { "first": "c" } };
+
+        assert_eq!(
+            values_at(&document, "logins.token"),
+            vec![Bson::String("a".to_string()), Bson::String("b".to_string())]
+        );
+        assert_eq!(values_at(&document, "name.first"), vec![Bson::String("c".to_string())]);
+        assert!(values_at(&document, "name.last").is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::{ReportId, UserId},
+    managers::{moderation::PAGE_SPEC, ModerationManager, ModerationRepository},
+    models::moderation::{ContentKind, Report, ReportState},
+    pagination::{paginate_in_memory, Page, PageRequest, PaginationError},
+};
+
+pub struct MemoryModerationManager {
+    reports: MemoryCollection<Report>,
+}
+
+impl MemoryModerationManager {
+    pub fn new() -> Self {
+        Self {
+            reports: MemoryCollection::new(ModerationManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryModerationManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ModerationRepository for MemoryModerationManager {
+    async fn create_report(&self, report: &Report) -> Result<(), Error> {
+        self.reports.insert(report)
+    }
+
+    async fn from_id(&self, id: ReportId) -> Result<Option<Report>, Error> {
+        self.reports.find_one(|report| report.unique_id == id)
+    }
+
+    async fn get_pending(&self, page: &PageRequest) -> Result<Page<Report>, PaginationError> {
+        let reports = self.reports.find(|report| report.state == ReportState::Pending)?;
+        paginate_in_memory(reports, page, &PAGE_SPEC)
+    }
+
+    async fn resolve(&self, content_kind: ContentKind, content_id: &str, state: &ReportState) -> Result<u64, Error> {
+        self.reports.update_many(
+            |report| {
+                report.content_kind == content_kind
+                    && report.content_id == content_id
+                    && report.state == ReportState::Pending
+            },
+            |report| report.state = state.clone(),
+        )
+    }
+
+    async fn from_reporter(&self, reporter_id: UserId) -> Result<Vec<Report>, Error> {
+        self.reports.find(|report| report.reporter_id == reporter_id)
+    }
+
+    async fn delete_from_reporter(&self, reporter_id: UserId) -> Result<u64, Error> {
+        self.reports.delete_many(|report| report.reporter_id == reporter_id)
+    }
+}
@@ -0,0 +1,44 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::UserId,
+    managers::{OidcLoginManager, OidcLoginRepository},
+    oidc_login::OidcLogin,
+    timestamp::Timestamp,
+};
+
+pub struct MemoryOidcLoginManager {
+    logins: MemoryCollection<OidcLogin>,
+}
+
+impl MemoryOidcLoginManager {
+    pub fn new() -> Self {
+        Self {
+            logins: MemoryCollection::new(OidcLoginManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryOidcLoginManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl OidcLoginRepository for MemoryOidcLoginManager {
+    async fn create(&self, login: &OidcLogin) -> Result<(), Error> {
+        self.logins.insert(login)
+    }
+
+    async fn consume(&self, state: &str, now: Timestamp) -> Result<Option<OidcLogin>, Error> {
+        self.logins
+            .delete_one(|login| login.state == state && login.expires_at > now)
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        self.logins.delete_many(|login| login.link_user == Some(user_id))
+    }
+}
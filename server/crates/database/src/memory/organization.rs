@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+use mongodb::{bson::Bson, error::Error};
+
+use super::MemoryCollection;
+use crate::{
+    id::{OrganizationId, ProjectId, UserId},
+    managers::{organization::PAGE_SPEC, OrganizationManager, OrganizationRepository},
+    organization::{Organization, OrganizationUpdate},
+    pagination::{paginate_in_memory, Page, PageRequest, PaginationError},
+};
+
+pub struct MemoryOrganizationManager {
+    organizations: MemoryCollection<Organization>,
+}
+
+impl MemoryOrganizationManager {
+    pub fn new() -> Self {
+        Self {
+            organizations: MemoryCollection::new(OrganizationManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryOrganizationManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_member(organization: &Organization, user_id: UserId) -> bool {
+    organization.owner_id == user_id || organization.member_ids.contains(&user_id)
+}
+
+fn add_to_set<T: PartialEq>(values: &mut Vec<T>, value: T) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+#[async_trait]
+impl OrganizationRepository for MemoryOrganizationManager {
+    async fn organization_exists(&self, name: String) -> Result<bool, Error> {
+        Ok(self.organizations.count(|organization| organization.name == name)? != 0)
+    }
+
+    async fn create_organization(&self, organization: &Organization) -> Result<(), Error> {
+        self.organizations.insert(organization)
+    }
+
+    async fn from_id(&self, id: OrganizationId) -> Result<Option<Organization>, Error> {
+        self.organizations.find_one(|organization| organization.unique_id == id)
+    }
+
+    async fn delete_organization(&self, uuid: OrganizationId) -> Result<bool, String> {
+        let deleted = self
+            .organizations
+            .delete_one(|organization| organization.unique_id == uuid)
+            .map_err(|err| err.to_string())?;
+        Ok(deleted.is_some())
+    }
+
+    async fn add_to_server_ids(&self, uuid: OrganizationId, server_id: UserId) -> Result<bool, String> {
+        self.organizations
+            .update_one(
+                |organization| organization.unique_id == uuid,
+                |organization| add_to_set(&mut organization.server_ids, server_id),
+            )
+            .map_err(|err| err.to_string())
+    }
+
+    async fn has_access_to_server(&self, server_unique_id: UserId, user_unique_id: UserId) -> Result<bool, String> {
+        let organization = self
+            .organizations
+            .find_one(|organization| {
+                organization.server_ids.contains(&server_unique_id)
+                    && is_member(organization, user_unique_id)
+            })
+            .map_err(|err| err.to_string())?;
+        Ok(organization.is_some())
+    }
+
+    async fn is_in_organization(&self, uuid: OrganizationId, user_unique_id: UserId) -> Result<bool, String> {
+        let organization = self
+            .organizations
+            .find_one(|organization| organization.unique_id == uuid && is_member(organization, user_unique_id))
+            .map_err(|err| err.to_string())?;
+        Ok(organization.is_some())
+    }
+
+    async fn remove_from_server_ids(&self, uuid: OrganizationId, server_id: UserId) -> Result<bool, String> {
+        self.organizations
+            .update_one(
+                |organization| organization.unique_id == uuid,
+                |organization| organization.server_ids.retain(|other| *other != server_id),
+            )
+            .map_err(|err| err.to_string())
+    }
+
+    async fn update_organization(
+        &self,
+        uuid: OrganizationId,
+        organization_update: Vec<OrganizationUpdate>,
+    ) -> Result<bool, Error> {
+        let fields: Vec<(String, Bson)> = organization_update
+            .iter()
+            .filter_map(|update| update.convert())
+            .collect();
+        self.organizations
+            .set_fields(|organization| organization.unique_id == uuid, fields)
+    }
+
+    async fn get_organizations_from_user(
+        &self,
+        user_id: UserId,
+        page: &PageRequest,
+    ) -> Result<Page<Organization>, PaginationError> {
+        let organizations = self
+            .organizations
+            .find(|organization| is_member(organization, user_id))?;
+        paginate_in_memory(organizations, page, &PAGE_SPEC)
+    }
+
+    async fn add_member(&self, organization_id: OrganizationId, member_id: UserId) -> Result<bool, Error> {
+        self.organizations.update_one(
+            |organization| organization.unique_id == organization_id,
+            |organization| add_to_set(&mut organization.member_ids, member_id),
+        )
+    }
+
+    async fn requires_two_factor(&self, user_id: UserId) -> Result<bool, Error> {
+        let count = self
+            .organizations
+            .count(|organization| organization.require_two_factor && is_member(organization, user_id))?;
+        Ok(count > 0)
+    }
+
+    async fn remove_from_member_ids(&self, organization_id: OrganizationId, member_id: UserId) -> Result<bool, Error> {
+        self.organizations.update_one(
+            |organization| organization.unique_id == organization_id,
+            |organization| organization.member_ids.retain(|other| *other != member_id),
+        )
+    }
+
+    async fn add_to_projects_ids(&self, organization_id: OrganizationId, project_id: ProjectId) -> Result<bool, Error> {
+        self.organizations.update_one(
+            |organization| organization.unique_id == organization_id,
+            |organization| add_to_set(&mut organization.projects_ids, project_id),
+        )
+    }
+
+    async fn remove_from_projects_ids(&self, organization_id: OrganizationId, project_id: ProjectId) -> Result<bool, Error> {
+        self.organizations.update_one(
+            |organization| organization.unique_id == organization_id,
+            |organization| organization.projects_ids.retain(|other| *other != project_id),
+        )
+    }
+
+    async fn get_servers_ids_from_organisation(&self, organization_id: OrganizationId) -> Result<Vec<UserId>, Error> {
+        let organization = self
+            .organizations
+            .find_one(|organization| organization.unique_id == organization_id)?;
+        Ok(organization
+            .map(|organization| organization.server_ids)
+            .unwrap_or_default())
+    }
+
+    async fn servers_by_organization(&self) -> Result<Vec<(OrganizationId, Vec<UserId>)>, Error> {
+        Ok(self
+            .organizations
+            .find(|_| true)?
+            .into_iter()
+            .map(|organization| (organization.unique_id, organization.server_ids))
+            .collect())
+    }
+
+    async fn all_from_user(&self, user_id: UserId) -> Result<Vec<Organization>, Error> {
+        self.organizations.find(|organization| {
+            is_member(organization, user_id) || organization.server_ids.contains(&user_id)
+        })
+    }
+
+    async fn remove_user(&self, user_id: UserId) -> Result<u64, Error> {
+        self.organizations.update_many(
+            |organization| organization.member_ids.contains(&user_id) || organization.server_ids.contains(&user_id),
+            |organization| {
+                organization.member_ids.retain(|other| *other != user_id);
+                organization.server_ids.retain(|other| *other != user_id);
+            },
+        )
+    }
+}
@@ -0,0 +1,152 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::MailId,
+    mail::{MailStatus, OutboxMessage},
+    managers::{OutboxManager, OutboxRepository},
+    timestamp::Timestamp,
+};
+
+pub struct MemoryOutboxManager {
+    messages: MemoryCollection<OutboxMessage>,
+}
+
+impl MemoryOutboxManager {
+    pub fn new() -> Self {
+        Self {
+            messages: MemoryCollection::new(OutboxManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryOutboxManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_due(message: &OutboxMessage, now: Timestamp) -> bool {
+    message.status == MailStatus::Pending && message.next_attempt <= now
+}
+
+#[async_trait]
+impl OutboxRepository for MemoryOutboxManager {
+    async fn enqueue(&self, message: &OutboxMessage) -> Result<(), Error> {
+        self.messages.insert(message)
+    }
+
+    async fn claim_due(&self, now: Timestamp, lease_until: Timestamp) -> Result<Option<OutboxMessage>, Error> {
+        let Some(due) = self
+            .messages
+            .find(|message| is_due(message, now))?
+            .into_iter()
+            .min_by_key(|message| message.next_attempt)
+        else {
+            return Ok(None);
+        };
+
+        // The message is only claimed if no other worker claimed it since it was found
+        let claimed = self.messages.update_one(
+            |message| message.unique_id == due.unique_id && is_due(message, now),
+            |message| {
+                message.next_attempt = lease_until;
+                message.attempts += 1;
+            },
+        )?;
+        if !claimed {
+            return Ok(None);
+        }
+        self.messages.find_one(|message| message.unique_id == due.unique_id)
+    }
+
+    async fn mark_sent(&self, id: MailId, expires_at: Timestamp) -> Result<(), Error> {
+        self.messages.update_one(
+            |message| message.unique_id == id,
+            |message| {
+                message.status = MailStatus::Sent;
+                message.last_error = None;
+                message.expires_at = Some(expires_at);
+            },
+        )?;
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: MailId,
+        error: &str,
+        retry_at: Option<Timestamp>,
+        expires_at: Timestamp,
+    ) -> Result<(), Error> {
+        self.messages.update_one(
+            |message| message.unique_id == id,
+            |message| {
+                message.last_error = Some(error.to_string());
+                match retry_at {
+                    Some(retry_at) => message.next_attempt = retry_at,
+                    None => {
+                        message.status = MailStatus::Failed;
+                        message.expires_at = Some(expires_at);
+                    }
+                }
+            },
+        )?;
+        Ok(())
+    }
+
+    async fn get_message(&self, id: MailId) -> Result<Option<OutboxMessage>, Error> {
+        self.messages.find_one(|message| message.unique_id == id)
+    }
+
+    async fn messages_to(&self, to: &str) -> Result<Vec<OutboxMessage>, Error> {
+        let mut messages = self.messages.find(|message| message.to == to)?;
+        messages.sort_by_key(|message| message.creation_date);
+        Ok(messages)
+    }
+
+    async fn delete_to(&self, to: &str) -> Result<u64, Error> {
+        self.messages.delete_many(|message| message.to == to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::MemoryOutboxManager;
+    use crate::{
+        mail::{MailStatus, OutboxMessage},
+        managers::OutboxRepository,
+        timestamp::Timestamp,
+    };
+
+    #[rocket::async_test]
+    async fn test_claim_due() {
+        let outbox = MemoryOutboxManager::new();
+        let now = Timestamp::from_millis(1_000_000);
+        let lease_until = now.plus(Duration::from_secs(60));
+        let message = OutboxMessage::new("a@b.c".into(), "Subject".into(), "Text".into(), None, now);
+        outbox.enqueue(&message).await.unwrap();
+
+        let claimed = outbox.claim_due(now, lease_until).await.unwrap().unwrap();
+        assert_eq!(claimed.unique_id, message.unique_id);
+        assert_eq!(claimed.attempts, 1);
+        // The message is leased to the worker that claimed it
+        assert!(outbox.claim_due(now, lease_until).await.unwrap().is_none());
+
+        let retry_at = now.plus(Duration::from_secs(10));
+        outbox.mark_failed(message.unique_id, "refused", Some(retry_at), lease_until).await.unwrap();
+        assert!(outbox.claim_due(now, lease_until).await.unwrap().is_none());
+        let claimed = outbox.claim_due(retry_at, lease_until).await.unwrap().unwrap();
+        assert_eq!(claimed.attempts, 2);
+        assert_eq!(claimed.last_error.as_deref(), Some("refused"));
+
+        outbox.mark_sent(message.unique_id, lease_until).await.unwrap();
+        let sent = outbox.get_message(message.unique_id).await.unwrap().unwrap();
+        assert_eq!(sent.status, MailStatus::Sent);
+        assert_eq!(sent.expires_at, Some(lease_until));
+        assert!(outbox.claim_due(lease_until, lease_until).await.unwrap().is_none());
+    }
+}
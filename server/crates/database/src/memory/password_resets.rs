@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::UserId,
+    managers::{PasswordResetManager, PasswordResetRepository},
+    password_reset::PasswordReset,
+    timestamp::Timestamp,
+};
+
+pub struct MemoryPasswordResetManager {
+    resets: MemoryCollection<PasswordReset>,
+}
+
+impl MemoryPasswordResetManager {
+    pub fn new() -> Self {
+        Self {
+            resets: MemoryCollection::new(PasswordResetManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryPasswordResetManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl PasswordResetRepository for MemoryPasswordResetManager {
+    async fn create(&self, reset: &PasswordReset) -> Result<(), Error> {
+        self.resets.insert(reset)
+    }
+
+    async fn consume(&self, token_hash: &str, now: Timestamp) -> Result<Option<PasswordReset>, Error> {
+        self.resets
+            .delete_one(|reset| reset.token_hash == token_hash && reset.expires_at > now)
+    }
+
+    async fn delete_for_user(&self, user_id: UserId) -> Result<u64, Error> {
+        self.resets.delete_many(|reset| reset.user_id == user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::MemoryPasswordResetManager;
+    use crate::{
+        id::UserId, managers::PasswordResetRepository, password_reset::PasswordReset, timestamp::Timestamp,
+    };
+
+    #[rocket::async_test]
+    async fn test_consume() {
+        let resets = MemoryPasswordResetManager::new();
+        let now = Timestamp::from_millis(1_000_000);
+        let user_id = UserId::generate();
+        for token_hash in ["a", "b"] {
+            let reset = PasswordReset {
+                token_hash: token_hash.to_string(),
+                user_id,
+                creation_date: now,
+                expires_at: now.plus(Duration::from_secs(60)),
+            };
+            resets.create(&reset).await.unwrap();
+        }
+
+        assert!(resets.consume("unknown", now).await.unwrap().is_none());
+        // An expired token is refused
+        assert!(resets.consume("a", now.plus(Duration::from_secs(60))).await.unwrap().is_none());
+        assert_eq!(resets.consume("a", now).await.unwrap().unwrap().user_id, user_id);
+        // A token is used once
+        assert!(resets.consume("a", now).await.unwrap().is_none());
+
+        assert_eq!(resets.delete_for_user(user_id).await.unwrap(), 1);
+        assert!(resets.consume("b", now).await.unwrap().is_none());
+    }
+}
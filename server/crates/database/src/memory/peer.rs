@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::UserId,
+    managers::{PeerRepository, PeersManager},
+    peer::Peer,
+};
+
+pub struct MemoryPeersManager {
+    peers: MemoryCollection<Peer>,
+}
+
+impl MemoryPeersManager {
+    pub fn new() -> Self {
+        Self {
+            peers: MemoryCollection::new(PeersManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryPeersManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl PeerRepository for MemoryPeersManager {
+    async fn peers_exist(&self, server_unique_id: UserId) -> Result<bool, Error> {
+        Ok(self.peers.count(|peer| peer.server_unique_id == server_unique_id)? != 0)
+    }
+
+    async fn create_peer(&self, peer: &Peer) -> Result<(), Error> {
+        self.peers.insert(peer)
+    }
+
+    async fn from_server_id(&self, server_unique_id: UserId) -> Result<Option<Peer>, Error> {
+        self.peers.find_one(|peer| peer.server_unique_id == server_unique_id)
+    }
+
+    async fn delete_peer(&self, server_unique_id: UserId) -> Result<bool, String> {
+        let deleted = self
+            .peers
+            .delete_one(|peer| peer.server_unique_id == server_unique_id)
+            .map_err(|err| err.to_string())?;
+        Ok(deleted.is_some())
+    }
+
+    async fn count(&self) -> Result<u64, Error> {
+        self.peers.count(|_| true)
+    }
+
+    async fn server_ids(&self) -> Result<Vec<UserId>, Error> {
+        Ok(self.peers.find(|_| true)?.into_iter().map(|peer| peer.server_unique_id).collect())
+    }
+}
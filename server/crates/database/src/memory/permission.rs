@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::PermissionId,
+    managers::{permission::unknown_permission, PermissionManager, PermissionRepository},
+    models::permission::Permission,
+};
+
+pub struct MemoryPermissionManager {
+    permissions: MemoryCollection<Permission>,
+}
+
+impl MemoryPermissionManager {
+    pub fn new() -> Self {
+        Self {
+            permissions: MemoryCollection::new(PermissionManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryPermissionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl PermissionRepository for MemoryPermissionManager {
+    async fn create(&self, permission: &Permission) -> Result<(), Error> {
+        self.permissions.insert(permission)
+    }
+
+    async fn permission_exists(&self, permission_id: PermissionId) -> bool {
+        matches!(
+            self.permissions.find_one(|permission| permission.unique_id == permission_id),
+            Ok(Some(_))
+        )
+    }
+
+    async fn get_permission_id(&self, permission_name: &str) -> Result<PermissionId, Error> {
+        match self.permissions.find_one(|permission| permission.name == permission_name)? {
+            Some(permission) => Ok(permission.unique_id),
+            None => Err(unknown_permission()),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use mongodb::{bson::Bson, error::Error};
+
+use super::MemoryCollection;
+use crate::{
+    id::{OrganizationId, ProjectId, UserId},
+    managers::{projects::PAGE_SPEC, Project, ProjectManager, ProjectRepository, ProjectUpdate},
+    pagination::{paginate_in_memory, Page, PageRequest, PaginationError},
+};
+
+pub struct MemoryProjectManager {
+    projects: MemoryCollection<Project>,
+}
+
+impl MemoryProjectManager {
+    pub fn new() -> Self {
+        Self {
+            projects: MemoryCollection::new(ProjectManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryProjectManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for MemoryProjectManager {
+    async fn create(&self, project: &Project) -> Result<(), Error> {
+        self.projects.insert(project)
+    }
+
+    async fn from_organization_id(
+        &self,
+        organization_id: OrganizationId,
+        page: &PageRequest,
+    ) -> Result<Page<Project>, PaginationError> {
+        let projects = self
+            .projects
+            .find(|project| project.organization_id == organization_id)?;
+        paginate_in_memory(projects, page, &PAGE_SPEC)
+    }
+
+    async fn from_id(&self, id: ProjectId) -> Result<Option<Project>, Error> {
+        self.projects.find_one(|project| project.unique_id == id)
+    }
+
+    async fn delete_from_id(&self, id: ProjectId) -> Result<Option<Project>, Error> {
+        self.projects.delete_one(|project| project.unique_id == id)
+    }
+
+    async fn update_project(&self, id: ProjectId, project_update: Vec<ProjectUpdate>) -> Result<bool, Error> {
+        let fields: Vec<(String, Bson)> = project_update
+            .iter()
+            .filter_map(|update| update.convert())
+            .collect();
+        self.projects.set_fields(|project| project.unique_id == id, fields)
+    }
+
+    async fn from_member(&self, user_id: UserId) -> Result<Vec<Project>, Error> {
+        self.projects.find(|project| project.member_ids.contains(&user_id))
+    }
+
+    async fn remove_member(&self, user_id: UserId) -> Result<u64, Error> {
+        self.projects.update_many(
+            |project| project.member_ids.contains(&user_id),
+            |project| project.member_ids.retain(|member_id| *member_id != user_id),
+        )
+    }
+}
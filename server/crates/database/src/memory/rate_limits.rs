@@ -0,0 +1,161 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use crate::{
+    managers::RateLimitRepository,
+    models::rate_limit::{BucketLimit, LockoutPolicy},
+    timestamp::Timestamp,
+};
+
+// The buckets are pruned of the full ones once there are that many
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct MemoryBucket {
+    tokens: f64,
+    updated: Timestamp,
+    expires_at: Timestamp,
+}
+
+struct MemoryLockout {
+    failures: u32,
+    locked_until: Option<Timestamp>,
+    expires_at: Timestamp,
+}
+
+/// Keeps the buckets and the lockouts in the process, each replica of the API then limits on its own.
+///
+/// Every request takes a token, so the buckets are kept in maps rather than in a `MemoryCollection`
+/// that would deserialize each of them.
+#[derive(Default)]
+pub struct MemoryRateLimitManager {
+    buckets: Mutex<HashMap<String, MemoryBucket>>,
+    lockouts: Mutex<HashMap<String, MemoryLockout>>,
+}
+
+impl MemoryRateLimitManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// elapsed returns the time between two timestamps, zero when the clock went backwards
+fn elapsed(from: Timestamp, to: Timestamp) -> Duration {
+    Duration::from_millis(u64::try_from(to.timestamp_millis() - from.timestamp_millis()).unwrap_or(0))
+}
+
+#[async_trait]
+impl RateLimitRepository for MemoryRateLimitManager {
+    async fn take_token(&self, key: &str, limit: &BucketLimit, now: Timestamp) -> Result<Option<Duration>, Error> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| bucket.expires_at > now);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(MemoryBucket {
+            tokens: limit.capacity,
+            updated: now,
+            expires_at: now,
+        });
+        let tokens = limit.refill(bucket.tokens, elapsed(bucket.updated, now));
+        bucket.updated = now;
+        bucket.expires_at = now.plus(limit.full_after());
+        if tokens >= 1.0 {
+            bucket.tokens = tokens - 1.0;
+            Ok(None)
+        } else {
+            bucket.tokens = tokens;
+            Ok(Some(limit.retry_after(tokens)))
+        }
+    }
+
+    async fn record_failure(
+        &self,
+        account: &str,
+        policy: &LockoutPolicy,
+        now: Timestamp,
+    ) -> Result<Option<Timestamp>, Error> {
+        let mut lockouts = self.lockouts.lock().unwrap();
+        lockouts.retain(|_, lockout| lockout.expires_at > now);
+
+        let lockout = lockouts.entry(account.to_string()).or_insert(MemoryLockout {
+            failures: 0,
+            locked_until: None,
+            expires_at: now,
+        });
+        lockout.failures += 1;
+        lockout.expires_at = now.plus(policy.reset_after);
+        if let Some(duration) = policy.lockout_duration(lockout.failures) {
+            lockout.locked_until = Some(now.plus(duration));
+        }
+        Ok(lockout.locked_until.filter(|locked_until| *locked_until > now))
+    }
+
+    async fn locked_until(&self, account: &str, now: Timestamp) -> Result<Option<Timestamp>, Error> {
+        let lockouts = self.lockouts.lock().unwrap();
+        Ok(lockouts
+            .get(account)
+            .filter(|lockout| lockout.expires_at > now)
+            .and_then(|lockout| lockout.locked_until)
+            .filter(|locked_until| *locked_until > now))
+    }
+
+    async fn clear_failures(&self, account: &str) -> Result<(), Error> {
+        self.lockouts.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::MemoryRateLimitManager;
+    use crate::{
+        managers::RateLimitRepository,
+        models::rate_limit::{BucketLimit, LockoutPolicy},
+        timestamp::Timestamp,
+    };
+
+    #[rocket::async_test]
+    async fn test_take_token() {
+        let manager = MemoryRateLimitManager::new();
+        let limit = BucketLimit {
+            capacity: 2.0,
+            refill_per_second: 1.0,
+        };
+        let now = Timestamp::from_millis(1_000_000);
+
+        assert_eq!(manager.take_token("a", &limit, now).await.unwrap(), None);
+        assert_eq!(manager.take_token("a", &limit, now).await.unwrap(), None);
+        assert_eq!(manager.take_token("a", &limit, now).await.unwrap(), Some(Duration::from_secs(1)));
+        // The buckets are independent
+        assert_eq!(manager.take_token("b", &limit, now).await.unwrap(), None);
+        let later = now.plus(Duration::from_millis(1500));
+        assert_eq!(manager.take_token("a", &limit, later).await.unwrap(), None);
+        assert_eq!(manager.take_token("a", &limit, later).await.unwrap(), Some(Duration::from_millis(500)));
+    }
+
+    #[rocket::async_test]
+    async fn test_lockout() {
+        let manager = MemoryRateLimitManager::new();
+        let policy = LockoutPolicy {
+            max_failures: 2,
+            lockout: Duration::from_secs(60),
+            max_lockout: Duration::from_secs(3600),
+            reset_after: Duration::from_secs(86_400),
+        };
+        let now = Timestamp::from_millis(1_000_000);
+
+        assert_eq!(manager.record_failure("a", &policy, now).await.unwrap(), None);
+        assert_eq!(manager.locked_until("a", now).await.unwrap(), None);
+        let locked_until = now.plus(Duration::from_secs(60));
+        assert_eq!(manager.record_failure("a", &policy, now).await.unwrap(), Some(locked_until));
+        assert_eq!(manager.locked_until("a", now).await.unwrap(), Some(locked_until));
+        assert_eq!(manager.locked_until("a", locked_until).await.unwrap(), None);
+
+        manager.clear_failures("a").await.unwrap();
+        assert_eq!(manager.record_failure("a", &policy, now).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use mongodb::error::Error;
+
+use super::MemoryCollection;
+use crate::{
+    id::UserId,
+    managers::{TwoFactorManager, TwoFactorRepository},
+    timestamp::Timestamp,
+    two_factor::TwoFactor,
+};
+
+pub struct MemoryTwoFactorManager {
+    two_factors: MemoryCollection<TwoFactor>,
+}
+
+impl MemoryTwoFactorManager {
+    pub fn new() -> Self {
+        Self {
+            two_factors: MemoryCollection::new(TwoFactorManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryTwoFactorManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl TwoFactorRepository for MemoryTwoFactorManager {
+    async fn get(&self, user_id: UserId) -> Result<Option<TwoFactor>, Error> {
+        self.two_factors.find_one(|two_factor| two_factor.user_id == user_id)
+    }
+
+    async fn start(&self, two_factor: &TwoFactor) -> Result<bool, Error> {
+        if let Some(current) = self.get(two_factor.user_id).await? {
+            if current.enabled {
+                return Ok(false);
+            }
+            self.two_factors
+                .delete_one(|pending| pending.user_id == two_factor.user_id)?;
+        }
+        self.two_factors.insert(two_factor)?;
+        Ok(true)
+    }
+
+    async fn enable(
+        &self,
+        user_id: UserId,
+        recovery_codes: Vec<String>,
+        step: i64,
+        now: Timestamp,
+    ) -> Result<bool, Error> {
+        self.two_factors.update_one(
+            |two_factor| two_factor.user_id == user_id && !two_factor.enabled && two_factor.last_step < step,
+            |two_factor| {
+                two_factor.enabled = true;
+                two_factor.recovery_codes = recovery_codes;
+                two_factor.last_step = step;
+                two_factor.enabled_at = Some(now);
+            },
+        )
+    }
+
+    async fn use_step(&self, user_id: UserId, step: i64) -> Result<bool, Error> {
+        self.two_factors.update_one(
+            |two_factor| two_factor.user_id == user_id && two_factor.enabled && two_factor.last_step < step,
+            |two_factor| two_factor.last_step = step,
+        )
+    }
+
+    async fn use_recovery_code(&self, user_id: UserId, code_hash: &str) -> Result<bool, Error> {
+        self.two_factors.update_one(
+            |two_factor| {
+                two_factor.user_id == user_id
+                    && two_factor.enabled
+                    && two_factor.recovery_codes.iter().any(|hash| hash == code_hash)
+            },
+            |two_factor| two_factor.recovery_codes.retain(|hash| hash != code_hash),
+        )
+    }
+
+    async fn disable(&self, user_id: UserId) -> Result<bool, Error> {
+        Ok(self
+            .two_factors
+            .delete_one(|two_factor| two_factor.user_id == user_id)?
+            .is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryTwoFactorManager;
+    use crate::{id::UserId, managers::TwoFactorRepository, timestamp::Timestamp, two_factor::TwoFactor};
+
+    fn pending(user_id: UserId, secret: &str) -> TwoFactor {
+        TwoFactor {
+            user_id,
+            secret: secret.to_string(),
+            enabled: false,
+            recovery_codes: Vec::new(),
+            last_step: 0,
+            creation_date: Timestamp::now(),
+            enabled_at: None,
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_enrolment() {
+        let two_factors = MemoryTwoFactorManager::new();
+        let user_id = UserId::generate();
+
+        assert!(two_factors.start(&pending(user_id, "A")).await.unwrap());
+        // A pending enrolment is replaced
+        assert!(two_factors.start(&pending(user_id, "B")).await.unwrap());
+        assert_eq!(two_factors.get(user_id).await.unwrap().unwrap().secret, "B");
+        assert!(!two_factors.use_step(user_id, 10).await.unwrap());
+
+        let codes = vec!["x".to_string(), "y".to_string()];
+        assert!(two_factors.enable(user_id, codes, 10, Timestamp::now()).await.unwrap());
+        assert!(!two_factors.start(&pending(user_id, "C")).await.unwrap());
+
+        // A code is not replayed in its time step, nor an older one
+        assert!(!two_factors.use_step(user_id, 10).await.unwrap());
+        assert!(!two_factors.use_step(user_id, 9).await.unwrap());
+        assert!(two_factors.use_step(user_id, 11).await.unwrap());
+
+        assert!(two_factors.use_recovery_code(user_id, "x").await.unwrap());
+        assert!(!two_factors.use_recovery_code(user_id, "x").await.unwrap());
+        assert_eq!(
+            two_factors.get(user_id).await.unwrap().unwrap().recovery_codes,
+            vec!["y"]
+        );
+
+        assert!(two_factors.disable(user_id).await.unwrap());
+        assert!(two_factors.get(user_id).await.unwrap().is_none());
+    }
+}
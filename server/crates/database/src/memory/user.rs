@@ -0,0 +1,281 @@
+use async_trait::async_trait;
+use mongodb::{bson::Bson, error::Error};
+
+use super::MemoryCollection;
+use crate::{
+    authentication::{Authentication, OidcAccount, OidcIdentity},
+    id::{PermissionId, UserId},
+    login::Login,
+    managers::{user::PAGE_SPEC, UserManager, UserRepository},
+    models::user::User,
+    pagination::{paginate_in_memory, Page, PageRequest, PaginationError},
+    password,
+    user::UserUpdate,
+};
+
+pub struct MemoryUserManager {
+    users: MemoryCollection<User>,
+}
+
+impl MemoryUserManager {
+    pub fn new() -> Self {
+        Self {
+            users: MemoryCollection::new(UserManager::INDEXES),
+        }
+    }
+}
+
+impl Default for MemoryUserManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryUserManager {
+    async fn email_exists(&self, email_address: String) -> Result<bool, Error> {
+        Ok(self.users.count(|user| user.email() == Some(&email_address))? != 0)
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), Error> {
+        self.users.insert(&User {
+            authentication: user.authentication.hashed(),
+            ..user.clone()
+        })
+    }
+
+    async fn from_token(&self, token: &str) -> Result<Option<User>, Error> {
+        self.users
+            .find_one(|user| user.logins.iter().any(|login| login.token.0 == token))
+    }
+
+    async fn from_id(&self, id: UserId) -> Result<Option<User>, Error> {
+        self.users.find_one(|user| user.unique_id == id)
+    }
+
+    async fn from_email(&self, email_address: &str) -> Result<Option<User>, Error> {
+        self.users.find_one(|user| user.email() == Some(email_address))
+    }
+
+    async fn from_credentials(&self, email_address: &str, password: &str) -> Result<Option<User>, Error> {
+        self.users.find_one(|user| match &user.authentication {
+            Authentication::Credentials(credentials) => {
+                credentials.email == email_address && credentials.verify_password(password)
+            }
+            Authentication::Oidc(_) | Authentication::None => false,
+        })
+    }
+
+    async fn from_identity(&self, account: &OidcAccount) -> Result<Option<User>, Error> {
+        self.users.find_one(|user| user.identity(account).is_some())
+    }
+
+    async fn from_ids(&self, ids: &[UserId], page: &PageRequest) -> Result<Page<User>, PaginationError> {
+        let users = self.users.find(|user| ids.contains(&user.unique_id))?;
+        paginate_in_memory(users, page, &PAGE_SPEC)
+    }
+
+    async fn delete_user(&self, uuid: Option<UserId>, token: Option<&str>) -> Result<bool, String> {
+        let user = if let Some(uuid) = uuid {
+            self.from_id(uuid)
+                .await
+                .map_err(|err| err.to_string())?
+                .ok_or(format!("User not found with id: {uuid}"))?
+        } else if let Some(token) = token {
+            self.from_token(token)
+                .await
+                .map_err(|err| err.to_string())?
+                .ok_or(format!("User not found with token: {token}"))?
+        } else {
+            return Ok(false);
+        };
+        let deleted = self
+            .users
+            .delete_one(|other| other.unique_id == user.unique_id)
+            .map_err(|err| err.to_string())?;
+        Ok(deleted.is_some())
+    }
+
+    async fn update_auth(&self, uuid: UserId, new_auth: &Authentication) -> Result<bool, Error> {
+        self.users.update_one(
+            |user| user.unique_id == uuid,
+            |user| {
+                user.email_verified = match new_auth {
+                    Authentication::Credentials(credentials) => {
+                        user.email_verified && user.email() == Some(&credentials.email)
+                    }
+                    Authentication::Oidc(identity) => {
+                        user.email_verified && user.email() == identity.email.as_deref()
+                    }
+                    Authentication::None => true,
+                };
+                user.authentication = new_auth.hashed();
+            },
+        )
+    }
+
+    async fn reset_password(&self, uuid: UserId, password: &str) -> Result<bool, Error> {
+        self.users.update_one(
+            |user| user.unique_id == uuid && user.email().is_some(),
+            |user| {
+                if let Authentication::Credentials(credentials) = &mut user.authentication {
+                    credentials.password = password::hash(password);
+                }
+                user.logins.clear();
+            },
+        )
+    }
+
+    async fn verify_email(&self, uuid: UserId, email_address: &str) -> Result<bool, Error> {
+        self.users.update_one(
+            |user| user.unique_id == uuid && user.email() == Some(email_address) && !user.email_verified,
+            |user| user.email_verified = true,
+        )
+    }
+
+    async fn update_user(&self, uuid: UserId, user_update: Vec<UserUpdate>) -> Result<bool, Error> {
+        let fields: Vec<(String, Bson)> = user_update
+            .iter()
+            .filter_map(|update| update.convert())
+            .collect();
+        self.users.set_fields(|user| user.unique_id == uuid, fields)
+    }
+
+    async fn add_login(&self, uuid: UserId, login: &Login) -> Result<bool, Error> {
+        self.users
+            .update_one(|user| user.unique_id == uuid, |user| user.logins.push(login.clone()))
+    }
+
+    async fn link_identity(&self, uuid: UserId, identity: &OidcIdentity) -> Result<bool, Error> {
+        self.users.update_one(
+            |user| user.unique_id == uuid && user.identity(&identity.account).is_none(),
+            |user| user.identities.push(identity.clone()),
+        )
+    }
+
+    async fn unlink_identity(&self, uuid: UserId, account: &OidcAccount) -> Result<bool, Error> {
+        self.users.update_one(
+            |user| user.unique_id == uuid && user.identity(account).is_some(),
+            |user| user.identities.retain(|identity| identity.account != *account),
+        )
+    }
+
+    async fn add_permission(&self, uuid: UserId, permission: PermissionId) -> Result<bool, Error> {
+        self.users.update_one(
+            |user| user.unique_id == uuid,
+            |user| {
+                if !user.permissions.contains(&permission) {
+                    user.permissions.push(permission);
+                }
+            },
+        )
+    }
+
+    async fn remove_permission(&self, uuid: UserId, permission: PermissionId) -> Result<bool, Error> {
+        self.users.update_one(
+            |user| user.unique_id == uuid,
+            |user| user.permissions.retain(|other| *other != permission),
+        )
+    }
+
+    async fn set_banned(&self, uuid: UserId, banned: bool) -> Result<bool, Error> {
+        self.users
+            .update_one(|user| user.unique_id == uuid, |user| user.banned = banned)
+    }
+
+    async fn user_exists(&self, uuid: UserId) -> bool {
+        matches!(self.users.find_one(|user| user.unique_id == uuid), Ok(Some(_)))
+    }
+
+    async fn has_permission(&self, uuid: UserId, permission: PermissionId) -> bool {
+        // A user stored without `email_verified` reads as verified, like the `$ne: false` of MongoDB
+        matches!(
+            self.users.find_one(|user| {
+                user.unique_id == uuid && user.permissions.contains(&permission) && user.email_verified
+            }),
+            Ok(Some(_))
+        )
+    }
+
+    async fn count(&self) -> Result<u64, Error> {
+        self.users.count(|_| true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryUserManager;
+    use crate::{
+        authentication::{Authentication, OidcAccount, OidcIdentity},
+        document::to_document,
+        id::{PermissionId, UserId},
+        indexes::is_duplicate_key,
+        managers::UserRepository,
+        timestamp::Timestamp,
+    };
+
+    fn identity(provider: &str, subject: &str) -> OidcIdentity {
+        OidcIdentity {
+            account: OidcAccount {
+                provider: provider.to_string(),
+                subject: subject.to_string(),
+            },
+            email: Some(format!("{subject}@example.com")),
+            username: None,
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_link_identity() {
+        let users = MemoryUserManager::new();
+        let now = Timestamp::from_millis(1_000_000);
+        let first = Authentication::Oidc(identity("issuer", "first"))
+            .register(now, UserId::generate(), &users)
+            .await
+            .unwrap()
+            .unwrap();
+        let second = Authentication::None
+            .register(now, UserId::generate(), &users)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // A user may link several accounts, even of the same provider
+        assert!(users.link_identity(first.unique_id, &identity("other", "first")).await.unwrap());
+        assert!(users.link_identity(first.unique_id, &identity("issuer", "third")).await.unwrap());
+        assert!(!users.link_identity(first.unique_id, &identity("issuer", "first")).await.unwrap());
+        // An account is linked to a single user
+        let error = users.link_identity(second.unique_id, &identity("issuer", "first")).await.unwrap_err();
+        assert!(is_duplicate_key(&error));
+
+        let account = identity("issuer", "first").account;
+        let user = users.from_identity(&account).await.unwrap().unwrap();
+        assert_eq!(user.unique_id, first.unique_id);
+        assert_eq!(user.identities.len(), 3);
+        assert_eq!(users.from_email("first@example.com").await.unwrap().unwrap().unique_id, first.unique_id);
+
+        assert!(users.unlink_identity(first.unique_id, &account).await.unwrap());
+        assert!(!users.unlink_identity(first.unique_id, &account).await.unwrap());
+        assert!(users.from_identity(&account).await.unwrap().is_none());
+        assert!(users.link_identity(second.unique_id, &identity("issuer", "first")).await.unwrap());
+    }
+
+    #[rocket::async_test]
+    async fn test_has_permission_stored_before_verification() {
+        let users = MemoryUserManager::new();
+        let permission = PermissionId::generate();
+        let mut user = Authentication::None
+            .register(Timestamp::from_millis(1_000_000), UserId::generate(), &users)
+            .await
+            .unwrap()
+            .unwrap();
+        user.permissions.push(permission);
+        // The user is stored like MongoDB holds the users registered before the verification
+        let mut document = to_document(&user).unwrap();
+        document.remove("email_verified");
+        *users.users.documents.write().unwrap() = vec![document];
+
+        assert!(users.from_id(user.unique_id).await.unwrap().unwrap().email_verified);
+        assert!(users.has_permission(user.unique_id, permission).await);
+    }
+}
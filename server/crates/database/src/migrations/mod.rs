@@ -0,0 +1,427 @@
+mod registry;
+
+pub use registry::*;
+
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    error::{Error, ErrorKind},
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{indexes::is_duplicate_key, timestamp::Timestamp};
+
+pub const MIGRATIONS_COLLECTION: &str = "migrations";
+
+const NAMESPACE_EXISTS_CODE: i32 = 48;
+
+/// A versioned change of the database schema.
+///
+/// Every step must be idempotent: a migration interrupted before being recorded is run again on the next start.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub steps: Vec<MigrationStep>,
+}
+
+pub enum MigrationStep {
+    CreateCollection(&'static str),
+    CreateIndex {
+        collection: &'static str,
+        index: Box<IndexModel>,
+    },
+    /// Applies an update pipeline to the documents matching the filter.
+    Transform {
+        collection: &'static str,
+        filter: Document,
+        pipeline: Vec<Document>,
+    },
+    /// Replaces every document matching the filter by the result of the function.
+    ///
+    /// Documents for which the function returns `None` are left untouched.
+    Rewrite {
+        collection: &'static str,
+        filter: Document,
+        rewrite: fn(Document) -> Option<Document>,
+    },
+    /// Inserts the documents whose `key` field is not already present in the collection.
+    Seed {
+        collection: &'static str,
+        key: &'static str,
+        documents: Vec<Document>,
+    },
+}
+
+/// A migration stored in the migrations collection once applied.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MigrationRecord {
+    pub version: u32,
+    pub name: String,
+    pub applied_at: Timestamp,
+}
+
+/// What a migration did, or would do during a dry run.
+#[derive(Serialize, Debug, Clone)]
+pub struct MigrationOutcome {
+    pub version: u32,
+    pub name: String,
+    pub applied: bool,
+    pub changes: Vec<String>,
+}
+
+/// The version of the last migration known by this build.
+pub fn latest_version() -> u32 {
+    registry::all().iter().map(|migration| migration.version).max().unwrap_or(0)
+}
+
+pub struct Migrator<'a> {
+    db: &'a mongodb::Database,
+    migrations: Vec<Migration>,
+}
+
+impl<'a> Migrator<'a> {
+    pub fn new(db: &'a mongodb::Database) -> Self {
+        Self::with_migrations(db, registry::all())
+    }
+
+    pub fn with_migrations(db: &'a mongodb::Database, mut migrations: Vec<Migration>) -> Self {
+        migrations.sort_by_key(|migration| migration.version);
+        Self { db, migrations }
+    }
+
+    /// The version of the last applied migration, 0 on a fresh database.
+    pub async fn current_version(&self) -> Result<u32, Error> {
+        let options = FindOptions::builder()
+            .sort(doc! { "version": -1 })
+            .limit(1)
+            .build();
+        let mut cursor = self.records().find(None, options).await?;
+        match cursor.next().await {
+            Some(record) => Ok(record?.version),
+            None => Ok(0),
+        }
+    }
+
+    /// The version of the last known migration.
+    pub fn latest_version(&self) -> u32 {
+        self.migrations.last().map(|migration| migration.version).unwrap_or(0)
+    }
+
+    /// Runs every migration that has not been applied yet, in order.
+    ///
+    /// With `dry_run`, nothing is written and the outcomes describe the pending changes.
+    ///
+    /// The replicas starting together may run the same migrations, the steps are idempotent and the unique index on
+    /// the versions records each migration once.
+    pub async fn run(&self, dry_run: bool) -> Result<Vec<MigrationOutcome>, Error> {
+        if !dry_run {
+            let index = IndexModel::builder()
+                .keys(doc! { "version": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build();
+            self.records().create_index(index, None).await?;
+        }
+
+        let current_version = self.current_version().await?;
+        let mut outcomes = Vec::new();
+
+        for migration in self.migrations.iter().filter(|migration| migration.version > current_version) {
+            let mut changes = Vec::new();
+            for step in &migration.steps {
+                changes.push(self.run_step(step, dry_run).await?);
+            }
+            if !dry_run {
+                self.record(migration).await?;
+            }
+            outcomes.push(MigrationOutcome {
+                version: migration.version,
+                name: migration.name.to_string(),
+                applied: !dry_run,
+                changes,
+            });
+        }
+
+        Ok(outcomes)
+    }
+
+    async fn run_step(&self, step: &MigrationStep, dry_run: bool) -> Result<String, Error> {
+        match step {
+            MigrationStep::CreateCollection(name) => {
+                let names = self.db.list_collection_names(None).await?;
+                if names.iter().any(|existing| existing == name) {
+                    return Ok(format!("collection {name} already exists"));
+                }
+                if !dry_run {
+                    match self.db.create_collection(*name, None).await {
+                        Ok(()) => {}
+                        // Another replica created it since it was listed
+                        Err(err) if is_namespace_exists(&err) => {
+                            return Ok(format!("collection {name} already exists"));
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(format!("create collection {name}"))
+            }
+            MigrationStep::CreateIndex { collection, index } => {
+                let keys = index.keys.clone();
+                if !dry_run {
+                    self.db
+                        .collection::<Document>(collection)
+                        .create_index(index.as_ref().clone(), None)
+                        .await?;
+                }
+                Ok(format!("create index {keys} on {collection}"))
+            }
+            MigrationStep::Transform { collection, filter, pipeline } => {
+                let collection_handle = self.db.collection::<Document>(collection);
+                if dry_run {
+                    let count = collection_handle.count_documents(filter.clone(), None).await?;
+                    return Ok(format!("transform {count} documents of {collection}"));
+                }
+                let result = collection_handle
+                    .update_many(filter.clone(), pipeline.clone(), None)
+                    .await?;
+                Ok(format!("transform {} documents of {collection}", result.modified_count))
+            }
+            MigrationStep::Rewrite { collection, filter, rewrite } => {
+                let collection_handle = self.db.collection::<Document>(collection);
+                let mut cursor = collection_handle.find(filter.clone(), None).await?;
+                let mut count = 0;
+                while let Some(document) = cursor.next().await {
+                    let document = document?;
+                    let Some(id) = document.get("_id").cloned() else {
+                        continue;
+                    };
+                    let Some(rewritten) = rewrite(document) else {
+                        continue;
+                    };
+                    if !dry_run {
+                        collection_handle
+                            .replace_one(doc! { "_id": id }, rewritten, None)
+                            .await?;
+                    }
+                    count += 1;
+                }
+                Ok(format!("rewrite {count} documents of {collection}"))
+            }
+            MigrationStep::Seed { collection, key, documents } => {
+                let collection_handle = self.db.collection::<Document>(collection);
+                let mut count = 0;
+                for document in documents {
+                    let Some(value) = document.get(*key) else {
+                        continue;
+                    };
+                    let filter = doc! { *key: value.clone() };
+                    if dry_run {
+                        if collection_handle.count_documents(filter, None).await? == 0 {
+                            count += 1;
+                        }
+                        continue;
+                    }
+                    let options = UpdateOptions::builder().upsert(true).build();
+                    let result = collection_handle
+                        .update_one(filter, doc! { "$setOnInsert": document.clone() }, options)
+                        .await?;
+                    if result.upserted_id.is_some() {
+                        count += 1;
+                    }
+                }
+                Ok(format!("seed {count} documents into {collection}"))
+            }
+        }
+    }
+
+    // record stores the version of the migration, a version already recorded by another replica is applied
+    async fn record(&self, migration: &Migration) -> Result<(), Error> {
+        let record = MigrationRecord {
+            version: migration.version,
+            name: migration.name.to_string(),
+            applied_at: Timestamp::now(),
+        };
+        match self.records().insert_one(record, None).await {
+            Ok(_) => Ok(()),
+            Err(err) if is_duplicate_key(&err) => {
+                tracing::info!(version = migration.version, "The migration was recorded by another replica");
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn records(&self) -> mongodb::Collection<MigrationRecord> {
+        self.db.collection(MIGRATIONS_COLLECTION)
+    }
+}
+
+fn is_namespace_exists(error: &Error) -> bool {
+    matches!(error.kind.as_ref(), ErrorKind::Command(error) if error.code == NAMESPACE_EXISTS_CODE)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, future::Future};
+
+    use futures::TryStreamExt;
+    use mongodb::Client;
+    use testcontainers::{clients::Cli, images::mongo::Mongo};
+
+    use super::*;
+
+    fn migrations() -> Vec<Migration> {
+        vec![
+            Migration {
+                version: 3,
+                name: "seed_items",
+                steps: vec![MigrationStep::Seed {
+                    collection: "items",
+                    key: "name",
+                    documents: vec![doc! { "name": "a", "count": 0 }, doc! { "name": "b", "count": 0 }],
+                }],
+            },
+            Migration {
+                version: 1,
+                name: "create_items",
+                steps: vec![MigrationStep::CreateCollection("items")],
+            },
+            Migration {
+                version: 4,
+                name: "count_items",
+                steps: vec![MigrationStep::Transform {
+                    collection: "items",
+                    filter: doc! { "counted": { "$exists": false } },
+                    pipeline: vec![doc! { "$set": { "counted": true, "count": { "$add": ["$count", 1] } } }],
+                }],
+            },
+            Migration {
+                version: 2,
+                name: "index_items",
+                steps: vec![MigrationStep::CreateIndex {
+                    collection: "items",
+                    index: Box::new(
+                        IndexModel::builder()
+                            .keys(doc! { "name": 1 })
+                            .options(IndexOptions::builder().unique(true).build())
+                            .build(),
+                    ),
+                }],
+            },
+        ]
+    }
+
+    // with_mongodb runs the test against a MongoDB container, with TEST_DATABASE_BACKEND=mongodb like the API tests,
+    // the migrations only run on MongoDB
+    async fn with_mongodb<F, Fut>(test: F)
+    where
+        F: FnOnce(mongodb::Database) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        if env::var("TEST_DATABASE_BACKEND").as_deref() != Ok("mongodb") {
+            return;
+        }
+        let docker = Cli::docker();
+        let container = docker.run(Mongo);
+        let uri = format!("mongodb://127.0.0.1:{}", container.get_host_port_ipv4(27017));
+        let client = Client::with_uri_str(uri).await.unwrap();
+        test(client.database("test")).await;
+    }
+
+    fn versions(outcomes: &[MigrationOutcome]) -> Vec<u32> {
+        outcomes.iter().map(|outcome| outcome.version).collect()
+    }
+
+    #[rocket::async_test]
+    async fn test_order() {
+        // The client only connects on the first operation
+        let client = Client::with_uri_str("mongodb://127.0.0.1:27017").await.unwrap();
+        let db = client.database("test");
+
+        let migrator = Migrator::with_migrations(&db, migrations());
+        let versions: Vec<u32> = migrator.migrations.iter().map(|migration| migration.version).collect();
+        assert_eq!(versions, vec![1, 2, 3, 4]);
+        assert_eq!(migrator.latest_version(), 4);
+
+        // The registry appends the migrations with the next version
+        let versions: Vec<u32> = registry::all().iter().map(|migration| migration.version).collect();
+        assert!(versions.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        assert_eq!(Migrator::new(&db).latest_version(), latest_version());
+    }
+
+    #[rocket::async_test]
+    async fn test_dry_run() {
+        with_mongodb(|db| async move {
+            let outcomes = Migrator::with_migrations(&db, migrations()).run(true).await.unwrap();
+
+            assert_eq!(versions(&outcomes), vec![1, 2, 3, 4]);
+            assert!(outcomes.iter().all(|outcome| !outcome.applied));
+            assert_eq!(outcomes[2].changes, vec!["seed 2 documents into items"]);
+            // Nothing is written, not even the versions
+            assert!(db.list_collection_names(None).await.unwrap().is_empty());
+            assert_eq!(Migrator::new(&db).current_version().await.unwrap(), 0);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_run() {
+        with_mongodb(|db| async move {
+            let migrator = Migrator::with_migrations(&db, migrations());
+            let outcomes = migrator.run(false).await.unwrap();
+
+            assert_eq!(versions(&outcomes), vec![1, 2, 3, 4]);
+            assert!(outcomes.iter().all(|outcome| outcome.applied));
+            assert_eq!(migrator.current_version().await.unwrap(), 4);
+            let records: Vec<MigrationRecord> =
+                migrator.records().find(None, None).await.unwrap().try_collect().await.unwrap();
+            let mut recorded: Vec<u32> = records.iter().map(|record| record.version).collect();
+            recorded.sort();
+            assert_eq!(recorded, vec![1, 2, 3, 4]);
+
+            // A second run, like the one of another replica, has nothing left to apply
+            let outcomes = Migrator::with_migrations(&db, migrations()).run(false).await.unwrap();
+            assert!(outcomes.is_empty());
+            let items = db.collection::<Document>("items");
+            assert_eq!(items.count_documents(doc! { "count": 1 }, None).await.unwrap(), 2);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_steps_run_again() {
+        with_mongodb(|db| async move {
+            let migrator = Migrator::with_migrations(&db, migrations());
+            migrator.run(false).await.unwrap();
+
+            // The steps of a migration interrupted before being recorded run again without effect
+            let mut changes = Vec::new();
+            for migration in &migrator.migrations {
+                for step in &migration.steps {
+                    changes.push(migrator.run_step(step, false).await.unwrap());
+                }
+            }
+            assert_eq!(changes[0], "collection items already exists");
+            assert_eq!(changes[2], "seed 0 documents into items");
+            assert_eq!(changes[3], "transform 0 documents of items");
+            let items = db.collection::<Document>("items");
+            assert_eq!(items.count_documents(None, None).await.unwrap(), 2);
+            assert_eq!(items.count_documents(doc! { "count": 1 }, None).await.unwrap(), 2);
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_recorded_by_another_replica() {
+        with_mongodb(|db| async move {
+            let migrator = Migrator::with_migrations(&db, migrations());
+            migrator.run(false).await.unwrap();
+
+            // The replica losing the race on the unique version finds the migration applied
+            migrator.record(&migrator.migrations[0]).await.unwrap();
+            let count = migrator.records().count_documents(doc! { "version": 1 }, None).await.unwrap();
+            assert_eq!(count, 1);
+        })
+        .await;
+    }
+}
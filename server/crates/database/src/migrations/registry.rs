@@ -0,0 +1,369 @@
+use mongodb::bson::{doc, Bson, Document};
+
+use super::{Migration, MigrationStep};
+use crate::{
+    id::{CommentId, PermissionId},
+    password,
+};
+
+/// Every migration of the database, new migrations are appended with the next version.
+pub fn all() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            name: "create_collections",
+            steps: [
+                "users",
+                "organizations",
+                "projects",
+                "licenses",
+                "permissions",
+                "peers",
+                "assets",
+                "comments",
+                "moderation_queue",
+            ]
+            .into_iter()
+            .map(MigrationStep::CreateCollection)
+            .collect(),
+        },
+        Migration {
+            version: 2,
+            name: "seed_permissions",
+            steps: vec![seed_permissions(&[
+                "organisation.all",
+                "organisation.see",
+                "organisation.edit",
+                "organisation.create",
+                "organisation.members.all",
+                "organisation.members.add",
+                "organisation.members.remove",
+                "organisation.members.edit",
+                "organisation.events.see",
+                "profile.edit",
+                "profile.see",
+                "profile.reset_password",
+                "license.create",
+                "client.download",
+                "project.see",
+                "project.edit",
+                "project.create",
+                "permission.add",
+                "permission.remove",
+                "permission.see",
+                "content.moderate",
+            ])],
+        },
+        Migration {
+            version: 3,
+            name: "organization_projects_ids",
+            steps: vec![MigrationStep::Transform {
+                collection: "organizations",
+                filter: doc! { "projects_ids": { "$exists": false } },
+                pipeline: vec![doc! { "$set": { "projects_ids": [] } }],
+            }],
+        },
+        Migration {
+            version: 4,
+            name: "remove_user_group",
+            steps: vec![MigrationStep::Transform {
+                collection: "users",
+                filter: doc! { "group": { "$exists": true } },
+                pipeline: vec![doc! { "$unset": "group" }],
+            }],
+        },
+        Migration {
+            version: 5,
+            name: "dates_to_bson_datetime",
+            steps: vec![
+                dates_to_datetime("users", &["creation_date"], Some(("logins", "timestamp"))),
+                dates_to_datetime("organizations", &["creation_date"], None),
+                dates_to_datetime("peers", &["creation_date"], None),
+                dates_to_datetime("assets", &["upload_date"], Some(("comments", "timestamp"))),
+                dates_to_datetime("comments", &["timestamp"], None),
+                dates_to_datetime("moderation_queue", &["creation_date"], None),
+            ],
+        },
+        Migration {
+            version: 6,
+            name: "create_audit_events",
+            steps: vec![MigrationStep::CreateCollection("audit_events")],
+        },
+        Migration {
+            version: 7,
+            name: "create_rate_limits",
+            steps: vec![
+                MigrationStep::CreateCollection("rate_limit_buckets"),
+                MigrationStep::CreateCollection("account_lockouts"),
+            ],
+        },
+        Migration {
+            version: 8,
+            name: "create_outbox_and_verify_existing_emails",
+            steps: vec![
+                MigrationStep::CreateCollection("outbox"),
+                // The users registered before the verification keep their permissions
+                MigrationStep::Transform {
+                    collection: "users",
+                    filter: doc! { "email_verified": { "$exists": false } },
+                    pipeline: vec![doc! { "$set": { "email_verified": true } }],
+                },
+            ],
+        },
+        Migration {
+            version: 9,
+            name: "create_password_resets",
+            steps: vec![MigrationStep::CreateCollection("password_resets")],
+        },
+        Migration {
+            version: 10,
+            name: "create_oidc_logins",
+            steps: vec![MigrationStep::CreateCollection("oidc_logins")],
+        },
+        Migration {
+            version: 11,
+            name: "create_two_factors",
+            steps: vec![MigrationStep::CreateCollection("two_factors")],
+        },
+        Migration {
+            version: 12,
+            name: "create_access_tokens",
+            steps: vec![MigrationStep::CreateCollection("access_tokens")],
+        },
+        Migration {
+            version: 13,
+            name: "asset_votes_to_user_ids",
+            steps: vec![numbers_to_ids(
+                "assets",
+                &["upvote_user_ids", "downvote_user_ids", "favorite_user_ids"],
+            )],
+        },
+        Migration {
+            version: 14,
+            name: "hash_passwords",
+            steps: vec![MigrationStep::Rewrite {
+                collection: "users",
+                filter: doc! {
+                    "authentication.Credentials.password": {
+                        "$type": "string",
+                        "$not": { "$regex": "^pbkdf2-sha256\\$" },
+                    },
+                },
+                rewrite: hash_password,
+            }],
+        },
+        Migration {
+            version: 15,
+            name: "seed_user_delete_permission",
+            steps: vec![seed_permissions(&["user.delete"])],
+        },
+        Migration {
+            version: 16,
+            name: "backfill_comment_ids",
+            steps: vec![
+                MigrationStep::Rewrite {
+                    collection: "comments",
+                    filter: doc! { "unique_id": { "$exists": false } },
+                    rewrite: backfill_comment_id,
+                },
+                MigrationStep::Rewrite {
+                    collection: "assets",
+                    filter: doc! { "comments": { "$elemMatch": { "unique_id": { "$exists": false } } } },
+                    rewrite: backfill_asset_comment_ids,
+                },
+            ],
+        },
+    ]
+}
+
+/// The names of every permission seeded by the migrations, for the backends that do not run them.
+pub fn seeded_permissions() -> Vec<String> {
+    let mut names = Vec::new();
+    for migration in all() {
+        for step in migration.steps {
+            if let MigrationStep::Seed { collection: "permissions", key, documents } = step {
+                names.extend(
+                    documents
+                        .iter()
+                        .filter_map(|document| document.get_str(key).ok())
+                        .map(str::to_string),
+                );
+            }
+        }
+    }
+    names
+}
+
+/// Converts the dates stored as strings of milliseconds to BSON dates.
+///
+/// `array` is an array of documents holding a date, like the logins of the users.
+fn dates_to_datetime(collection: &'static str, fields: &[&str], array: Option<(&str, &str)>) -> MigrationStep {
+    let mut filter = Vec::new();
+    let mut set = Document::new();
+    for field in fields {
+        filter.push(doc! { *field: { "$type": "string" } });
+        set.insert(*field, string_to_date(&format!("${field}")));
+    }
+    if let Some((array, field)) = array {
+        filter.push(doc! { format!("{array}.{field}"): { "$type": "string" } });
+        set.insert(
+            array,
+            doc! {
+                "$map": {
+                    "input": format!("${array}"),
+                    "as": "item",
+                    "in": {
+                        "$mergeObjects": [
+                            "$$item",
+                            { field: string_to_date(&format!("$$item.{field}")) },
+                        ],
+                    },
+                },
+            },
+        );
+    }
+
+    MigrationStep::Transform {
+        collection,
+        filter: doc! { "$or": filter },
+        pipeline: vec![doc! { "$set": set }],
+    }
+}
+
+/// Converts the arrays of ids stored as numbers to the strings of the typed ids, the legacy ids are decimal.
+fn numbers_to_ids(collection: &'static str, arrays: &[&str]) -> MigrationStep {
+    let mut filter = Vec::new();
+    let mut set = Document::new();
+    for array in arrays {
+        filter.push(doc! { *array: { "$elemMatch": { "$type": "number" } } });
+        set.insert(
+            *array,
+            doc! {
+                "$map": {
+                    "input": format!("${array}"),
+                    "as": "id",
+                    "in": { "$toString": "$$id" },
+                },
+            },
+        );
+    }
+
+    MigrationStep::Transform {
+        collection,
+        filter: doc! { "$or": filter },
+        pipeline: vec![doc! { "$set": set }],
+    }
+}
+
+/// Hashes the password of the credentials stored before the hashing, the user signs in with the same password.
+fn hash_password(mut user: Document) -> Option<Document> {
+    let credentials = user
+        .get_document_mut("authentication")
+        .ok()?
+        .get_document_mut("Credentials")
+        .ok()?;
+    let stored = credentials.get_str("password").ok()?;
+    if password::is_hash(stored) {
+        return None;
+    }
+    let hashed = password::hash(stored);
+    credentials.insert("password", hashed);
+    Some(user)
+}
+
+/// Gives an id to a comment stored before the comment ids, so that it can be moderated and deleted by id.
+fn backfill_comment_id(mut comment: Document) -> Option<Document> {
+    if comment.contains_key("unique_id") {
+        return None;
+    }
+    comment.insert("unique_id", CommentId::generate());
+    Some(comment)
+}
+
+/// Gives an id to the comments embedded in an asset before the comment ids.
+fn backfill_asset_comment_ids(mut asset: Document) -> Option<Document> {
+    let mut backfilled = false;
+    for comment in asset.get_array_mut("comments").ok()? {
+        if let Bson::Document(comment) = comment {
+            if !comment.contains_key("unique_id") {
+                comment.insert("unique_id", CommentId::generate());
+                backfilled = true;
+            }
+        }
+    }
+    backfilled.then_some(asset)
+}
+
+// string_to_date converts the value at the path to a date when it is a string, and keeps it otherwise
+fn string_to_date(path: &str) -> Bson {
+    Bson::Document(doc! {
+        "$cond": [
+            { "$eq": [{ "$type": path }, "string"] },
+            { "$toDate": { "$toLong": path } },
+            path,
+        ],
+    })
+}
+
+/// Seeds the permissions that do not exist yet, the ids of the existing ones are kept.
+pub(crate) fn seed_permissions(names: &[&str]) -> MigrationStep {
+    MigrationStep::Seed {
+        collection: "permissions",
+        key: "name",
+        documents: names
+            .iter()
+            .map(|name| {
+                doc! {
+                    "unique_id": PermissionId::generate(),
+                    "name": *name,
+                }
+            })
+            .collect::<Vec<Document>>(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{doc, from_document};
+
+    use super::*;
+    use crate::models::comment::Comment;
+
+    fn legacy_comment() -> Document {
+        doc! {
+            "asset_id": 1,
+            "user_id": "1",
+            "content": "Legacy comment",
+            "timestamp": "1700000000000",
+        }
+    }
+
+    #[test]
+    fn test_backfill_comment_id() {
+        let comment = backfill_comment_id(legacy_comment()).unwrap();
+        let id = comment.get_str("unique_id").unwrap().to_string();
+        assert!(from_document::<Comment>(comment.clone()).is_ok());
+        // The stored id is kept once backfilled
+        assert!(backfill_comment_id(comment).is_none());
+        assert!(id.parse::<CommentId>().is_ok());
+    }
+
+    #[test]
+    fn test_backfill_asset_comment_ids() {
+        let kept = CommentId::generate();
+        let mut with_id = legacy_comment();
+        with_id.insert("unique_id", kept);
+        let asset = doc! { "id": 1, "comments": [legacy_comment(), with_id] };
+
+        let asset = backfill_asset_comment_ids(asset).unwrap();
+        let comments = asset.get_array("comments").unwrap();
+        let ids: Vec<_> = comments
+            .iter()
+            .map(|comment| comment.as_document().unwrap().get_str("unique_id").unwrap().to_string())
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], kept.to_string());
+        assert_eq!(ids[1], kept.to_string());
+        assert!(backfill_asset_comment_ids(asset).is_none());
+    }
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    id::{AccessTokenId, UserId},
+    timestamp::Timestamp,
+};
+
+/// A personal access token, used by the scripts of a user instead of the token of a login
+///
+/// The token is only stored as its hash, it is shown once when it is created.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AccessToken {
+    pub unique_id: AccessTokenId,
+    pub user_id: UserId,
+    pub name: String,
+    pub token_hash: String,
+    /// The names of the permissions the token may use, the user must still hold them
+    pub scopes: Vec<String>,
+    pub creation_date: Timestamp,
+    /// The date the token stops working, it is then removed.
+    pub expires_at: Timestamp,
+    pub last_used: Option<Timestamp>,
+}
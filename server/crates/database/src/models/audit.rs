@@ -0,0 +1,133 @@
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    id::{AuditEventId, OrganizationId, UserId},
+    timestamp::Timestamp,
+};
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    PermissionGranted,
+    PermissionRevoked,
+    MemberAdded,
+    MemberRemoved,
+    ServerAdded,
+    ServerRemoved,
+    LicenseCreated,
+    Login,
+    EmailVerified,
+    PasswordReset,
+    IdentityLinked,
+    IdentityUnlinked,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    AccessTokenCreated,
+    AccessTokenRevoked,
+    UserDataExported,
+    UserDeleted,
+    OrganizationDeleted,
+    ProjectDeleted,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub enum AuditTargetKind {
+    User,
+    Server,
+    Organization,
+    Project,
+    License,
+}
+
+/// The fields of the target that an action changed, as they were before and after it.
+///
+/// Only the fields that differ are kept, `before` is `None` for a creation and `after` for a deletion.
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone, PartialEq, Default)]
+pub struct AuditChanges {
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditChanges {
+    /// Keeps the top level fields of the two snapshots that differ, a field missing on one side is `null`.
+    pub fn diff(before: &Value, after: &Value) -> Self {
+        let (Value::Object(before), Value::Object(after)) = (before, after) else {
+            return Self {
+                before: Some(before.clone()),
+                after: Some(after.clone()),
+            };
+        };
+
+        let mut changed_before = Map::new();
+        let mut changed_after = Map::new();
+        for key in before.keys().chain(after.keys().filter(|key| !before.contains_key(*key))) {
+            let old = before.get(key).unwrap_or(&Value::Null);
+            let new = after.get(key).unwrap_or(&Value::Null);
+            if old != new {
+                changed_before.insert(key.clone(), old.clone());
+                changed_after.insert(key.clone(), new.clone());
+            }
+        }
+        Self {
+            before: Some(Value::Object(changed_before)),
+            after: Some(Value::Object(changed_after)),
+        }
+    }
+}
+
+/// An entry of the audit log, events are only ever appended.
+///
+/// `actor_id` is the user who acted, `None` when the request was not authenticated as a user.
+/// `organization_id` is the organization the event belongs to, the events of the members without one
+/// are shown to the admins of their organizations.
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone)]
+pub struct AuditEvent {
+    pub unique_id: AuditEventId,
+    pub creation_date: Timestamp,
+    pub action: AuditAction,
+    pub actor_id: Option<UserId>,
+    pub target_kind: AuditTargetKind,
+    pub target_id: String,
+    pub organization_id: Option<OrganizationId>,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+    pub changes: Option<AuditChanges>,
+}
+
+impl AuditEvent {
+    pub fn in_organization(mut self, organization_id: OrganizationId) -> Self {
+        self.organization_id = Some(organization_id);
+        self
+    }
+
+    pub fn with_changes(mut self, changes: AuditChanges) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::AuditChanges;
+
+    #[test]
+    fn test_diff() {
+        let changes = AuditChanges::diff(
+            &json!({ "name": "a", "member_ids": ["1"], "owner_id": "2" }),
+            &json!({ "name": "a", "member_ids": ["1", "3"], "server_ids": [] }),
+        );
+
+        assert_eq!(
+            changes.before,
+            Some(json!({ "member_ids": ["1"], "owner_id": "2", "server_ids": null }))
+        );
+        assert_eq!(
+            changes.after,
+            Some(json!({ "member_ids": ["1", "3"], "owner_id": null, "server_ids": [] }))
+        );
+    }
+}
@@ -1,17 +1,16 @@
-use mongodb::{
-    bson::doc,
-    options::FindOneOptions,
-    Collection,
-};
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::{user::User};
+use crate::{
+    id::UserId, indexes::is_duplicate_key, managers::UserRepository, password, timestamp::Timestamp, user::User,
+};
 
 #[derive(Serialize, Debug, Deserialize, Clone, JsonSchema, PartialEq)]
 pub enum Authentication {
     Credentials(Credentials),
+    /// The user signed up through an OpenID Connect provider.
+    Oidc(OidcIdentity),
     None,
 }
 
@@ -27,22 +26,48 @@ impl Credentials {
     pub fn new_auth(self) -> Authentication {
         Authentication::Credentials(self)
     }
+
+    /// Returns whether the password matches the hash stored in the credentials.
+    pub fn verify_password(&self, password: &str) -> bool {
+        password::verify(password, &self.password)
+    }
+}
+
+/// The account of a user at an OpenID Connect provider, the subject is only unique within the provider.
+#[derive(Deserialize, Debug, Serialize, Clone, JsonSchema, PartialEq, Eq)]
+pub struct OidcAccount {
+    /// The name of the provider in the settings
+    pub provider: String,
+    pub subject: String,
+}
+
+/// An OpenID Connect account linked to a user, who can then sign in with it.
+#[derive(Deserialize, Debug, Serialize, Clone, JsonSchema, PartialEq)]
+pub struct OidcIdentity {
+    pub account: OidcAccount,
+    /// The email claimed by the provider when the account was linked
+    pub email: Option<String>,
+    pub username: Option<String>,
 }
 
 impl Authentication {
+    /// Returns the authentication as it is stored, with the password of the credentials hashed.
+    pub fn hashed(&self) -> Authentication {
+        match self {
+            Authentication::Credentials(credentials) => Authentication::Credentials(Credentials {
+                password: password::hash(&credentials.password),
+                ..credentials.clone()
+            }),
+            other => other.clone(),
+        }
+    }
+
     pub async fn register(
         &self,
-        timestamp: u128,
-        unique_id: String,
-        users: &Collection<User>,
+        timestamp: Timestamp,
+        unique_id: UserId,
+        users: &dyn UserRepository,
     ) -> Result<Option<User>, String> {
-        if !matches!(self, Authentication::None) {
-            let existing_user = self.get(users).await;
-            if existing_user.is_ok() && existing_user.unwrap().is_some() {
-                return Ok(None);
-            }
-        }
-
         // Store the avatar in ./uploads/avatars
         // Example:
         let avatar_filename = match &self {
@@ -53,7 +78,7 @@ impl Authentication {
                     "default_avatar.jpg" // Or any default avatar filename
                 }
             }
-            Authentication::None => "default_avatar.jpg",
+            Authentication::Oidc(_) | Authentication::None => "default_avatar.jpg",
         };
         let avatar_path = format!("./uploads/avatars/{}", avatar_filename);
         let mut self_clone = self.clone();
@@ -63,32 +88,37 @@ impl Authentication {
 
         let user = User {
             authentication: self.clone(),
-            unique_id: unique_id.clone(),
-            creation_date: timestamp.to_string(),
+            unique_id,
+            creation_date: timestamp,
             logins: Vec::new(),
             permissions: Vec::new(),
+            banned: false,
+            // The providers only share the emails they verified
+            email_verified: !matches!(self, Authentication::Credentials(_)),
+            identities: match self {
+                Authentication::Oidc(identity) => vec![identity.clone()],
+                _ => Vec::new(),
+            },
         };
 
-
-        let _ = users
-            .insert_one(&user, None)
-            .await
-            .map_err(|err| err.to_string())?;
-        Ok(Some(user))
+        // The unique index on the email rejects the registration of an existing user
+        match users.create_user(&user).await {
+            Ok(_) => Ok(Some(user)),
+            Err(err) if is_duplicate_key(&err) => Ok(None),
+            Err(err) => Err(err.to_string()),
+        }
     }
 
-    pub async fn get(&self, users: &Collection<User>) -> Result<Option<User>, String> {
+    pub async fn get(&self, users: &dyn UserRepository) -> Result<Option<User>, String> {
         match self {
-            Authentication::Credentials(credentials) => {
-                let filter = doc! {"authentication.Credentials.email": &credentials.email, "authentication.Credentials.password": &credentials.password};
-                let options = FindOneOptions::default();
-
-                if let Some(user) = users.find_one(filter, options).await.map_err(|err| err.to_string())? {
-                    Ok(Some(user))
-                } else {
-                    Ok(None)
-                }
-            }
+            Authentication::Credentials(credentials) => users
+                .from_credentials(&credentials.email, &credentials.password)
+                .await
+                .map_err(|err| err.to_string()),
+            Authentication::Oidc(identity) => users
+                .from_identity(&identity.account)
+                .await
+                .map_err(|err| err.to_string()),
             Authentication::None => Ok(None),
         }
     }
@@ -96,6 +126,7 @@ impl Authentication {
     pub fn get_name(&self) -> String {
         match self {
             Self::Credentials(_) => "Credentials",
+            Self::Oidc(_) => "Oidc",
             Self::None => "None",
         }
         .to_owned()
@@ -108,7 +139,7 @@ impl Authentication {
     pub fn credentials(&self) -> &Credentials {
         match self {
             Self::Credentials(credentials) => credentials,
-            Self::None => panic!("No credentials"),
+            Self::Oidc(_) | Self::None => panic!("No credentials"),
         }
     }
 }
\ No newline at end of file
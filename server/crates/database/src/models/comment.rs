@@ -2,11 +2,22 @@ use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    id::{CommentId, UserId},
+    moderation::ModerationStatus,
+    timestamp::Timestamp,
+};
+
 #[derive(Deserialize, Serialize, Debug, JsonSchema, Clone)]
 pub struct Comment {
-    pub user_id: u32,
+    pub unique_id: CommentId,
+    #[serde(default)]
+    pub asset_id: u32,
+    pub user_id: UserId,
     pub content: String,
-    pub timestamp: String,
+    pub timestamp: Timestamp,
+    #[serde(default)]
+    pub status: ModerationStatus,
 }
 
 impl Comment {}
@@ -2,10 +2,12 @@ use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::id::{LicenseId, UserId};
+
 
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct License {
-    pub unique_id: String,
-    pub user_id: String,
+    pub unique_id: LicenseId,
+    pub user_id: UserId,
     pub license: String,
 }
\ No newline at end of file
@@ -2,25 +2,31 @@ use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::{authentication::Authentication, token::Token};
+use crate::{authentication::Authentication, timestamp::Timestamp, token::Token};
 
 #[derive(Deserialize, Serialize, Debug, JsonSchema, Clone)]
 pub struct Login {
     ip: String,
-    timestamp: String,
+    timestamp: Timestamp,
     method: String,
     pub token: Token,
 }
 
 impl Login {
-    pub fn new(ip: String, timestamp: u128, method: Authentication) -> Self {
+    pub fn new(ip: String, timestamp: Timestamp, method: Authentication) -> Self {
         let token = Token::default();
 
         Self {
             ip,
-            timestamp: timestamp.to_string(),
+            timestamp,
             method: method.get_name(),
             token,
         }
     }
+
+    /// Returns whether the token of this login is older than the given lifetime
+    pub fn is_expired(&self, lifetime_hours: u64, now: Timestamp) -> bool {
+        let lifetime_millis = i64::try_from(lifetime_hours.saturating_mul(3_600_000)).unwrap_or(i64::MAX);
+        now.timestamp_millis().saturating_sub(self.timestamp.timestamp_millis()) > lifetime_millis
+    }
 }
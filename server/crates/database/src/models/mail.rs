@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{id::MailId, timestamp::Timestamp};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailStatus {
+    /// Waiting to be sent, or to be retried after a failure
+    Pending,
+    Sent,
+    /// Given up after too many failures
+    Failed,
+}
+
+/// A message of the outbox, sent in the background and retried until it is delivered.
+///
+/// The bodies are rendered before the message is queued, the outbox does not know the templates.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OutboxMessage {
+    pub unique_id: MailId,
+    pub creation_date: Timestamp,
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+    pub status: MailStatus,
+    pub attempts: u32,
+    /// The date the message is sent or retried, a sender claiming it pushes it back for the time of the attempt.
+    pub next_attempt: Timestamp,
+    pub last_error: Option<String>,
+    /// The date a sent or failed message is removed.
+    pub expires_at: Option<Timestamp>,
+}
+
+impl OutboxMessage {
+    pub fn new(to: String, subject: String, text: String, html: Option<String>, now: Timestamp) -> Self {
+        Self {
+            unique_id: MailId::generate(),
+            creation_date: now,
+            to,
+            subject,
+            text,
+            html,
+            status: MailStatus::Pending,
+            attempts: 0,
+            next_attempt: now,
+            last_error: None,
+            expires_at: None,
+        }
+    }
+}
@@ -9,4 +9,12 @@ pub mod license;
 pub mod permission;
 pub mod server;
 pub mod asset;
-pub mod comment;
\ No newline at end of file
+pub mod comment;
+pub mod moderation;
+pub mod audit;
+pub mod rate_limit;
+pub mod mail;
+pub mod password_reset;
+pub mod oidc_login;
+pub mod two_factor;
+pub mod access_token;
\ No newline at end of file
@@ -0,0 +1,56 @@
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    id::{ReportId, UserId},
+    timestamp::Timestamp,
+};
+
+/// Visibility of a user generated content.
+///
+/// Hidden contents are kept in the database but excluded from every listing.
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModerationStatus {
+    #[default]
+    Visible,
+    Hidden,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Asset,
+    Comment,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationAction {
+    /// Keep the content visible and close the report.
+    Approve,
+    /// Keep the content in the database but exclude it from listings.
+    Hide,
+    /// Delete the content.
+    Remove,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone, PartialEq, Eq)]
+pub enum ReportState {
+    Pending,
+    Resolved {
+        moderator_id: UserId,
+        action: ModerationAction,
+        author_banned: bool,
+    },
+}
+
+/// An entry of the moderation queue.
+#[derive(Deserialize, Serialize, Debug, JsonSchema, Clone)]
+pub struct Report {
+    pub unique_id: ReportId,
+    pub content_kind: ContentKind,
+    pub content_id: String,
+    pub reporter_id: UserId,
+    pub reason: String,
+    pub creation_date: Timestamp,
+    pub state: ReportState,
+}
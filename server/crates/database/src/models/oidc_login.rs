@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{id::UserId, timestamp::Timestamp};
+
+/// A sign in started at an OpenID Connect provider, used once when the provider redirects the user back.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OidcLogin {
+    /// The random state sent to the provider, which it sends back with the code
+    pub state: String,
+    /// The name of the provider in the settings
+    pub provider: String,
+    /// The random nonce the ID token must contain
+    pub nonce: String,
+    /// The PKCE verifier of the challenge sent to the provider
+    pub code_verifier: String,
+    /// The user the account is linked to, None when the account is used to sign in
+    pub link_user: Option<UserId>,
+    pub creation_date: Timestamp,
+    /// The date the sign in stops working, it is then removed.
+    pub expires_at: Timestamp,
+}
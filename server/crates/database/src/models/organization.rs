@@ -3,10 +3,16 @@ use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    id::{OrganizationId, ProjectId, UserId},
+    timestamp::Timestamp,
+};
+
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub enum OrganizationUpdate {
     Name(String),
-    OwnerId(String),
+    OwnerId(UserId),
+    RequireTwoFactor(bool),
 }
 
 impl OrganizationUpdate {
@@ -16,17 +22,21 @@ impl OrganizationUpdate {
             Self::OwnerId(name) => to_bson(name)
                 .map(|name| ("owner_id".to_string(), name))
                 .ok(),
+            Self::RequireTwoFactor(required) => Some(("require_two_factor".to_string(), Bson::Boolean(*required))),
         }
     }
 }
 
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct Organization {
-    pub unique_id: String,
-    pub creation_date: String,
+    pub unique_id: OrganizationId,
+    pub creation_date: Timestamp,
     pub name: String,
-    pub member_ids: Vec<String>,
-    pub owner_id: String,
-    pub server_ids: Vec<String>,
-    pub projects_ids: Vec<String>,
+    pub member_ids: Vec<UserId>,
+    pub owner_id: UserId,
+    pub server_ids: Vec<UserId>,
+    pub projects_ids: Vec<ProjectId>,
+    /// Whether the owner and the members must have enabled a second factor to act on the organization
+    #[serde(default)]
+    pub require_two_factor: bool,
 }
\ No newline at end of file
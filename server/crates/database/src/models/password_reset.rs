@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{id::UserId, timestamp::Timestamp};
+
+/// A pending password reset, the token sent to the user is only stored as its hash.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PasswordReset {
+    pub token_hash: String,
+    pub user_id: UserId,
+    pub creation_date: Timestamp,
+    /// The date the token stops working, it is then removed.
+    pub expires_at: Timestamp,
+}
@@ -3,12 +3,16 @@ use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    id::{RoomId, UserId},
+    timestamp::Timestamp,
+};
+
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct Peer {
-    // The room_id is a 128 characters length string.
-    pub room_id: String,
-    pub creation_date: String,
+    pub room_id: RoomId,
+    pub creation_date: Timestamp,
     pub signaling_hostname: String,
     pub signaling_port: u16,
-    pub server_unique_id: String,
+    pub server_unique_id: UserId,
 }
@@ -2,9 +2,11 @@ use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::id::PermissionId;
+
 
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct Permission {
-    pub unique_id: String,
+    pub unique_id: PermissionId,
     pub name: String,
 }
\ No newline at end of file
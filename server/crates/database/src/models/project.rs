@@ -3,18 +3,20 @@ use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use mongodb::bson::{doc, to_bson, Bson};
 
+use crate::id::{OrganizationId, ProjectId, UserId};
+
 
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct Project {
-    pub unique_id: String,
-    pub organization_id: String,
+    pub unique_id: ProjectId,
+    pub organization_id: OrganizationId,
     pub name: String,
-    pub member_ids: Vec<String>,
+    pub member_ids: Vec<UserId>,
 }
 
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct ProjectUpdateData {
-    pub project_id: String,
+    pub project_id: ProjectId,
     pub project_update: Vec<ProjectUpdate>,
 }
 
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::timestamp::Timestamp;
+
+/// The size and the refill rate of a token bucket.
+///
+/// A request takes a token, the bucket is refilled continuously up to its capacity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BucketLimit {
+    pub capacity: f64,
+    pub refill_per_second: f64,
+}
+
+impl BucketLimit {
+    /// Returns the tokens of a bucket holding `tokens` once refilled for `elapsed`.
+    pub fn refill(&self, tokens: f64, elapsed: Duration) -> f64 {
+        (tokens + elapsed.as_secs_f64() * self.refill_per_second).min(self.capacity)
+    }
+
+    /// Returns how long a bucket holding `tokens` takes to hold a whole token again.
+    pub fn retry_after(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64(((1.0 - tokens).max(0.0) / self.refill_per_second).max(0.0))
+    }
+
+    /// Returns how long an empty bucket takes to be full, after which it can be forgotten.
+    pub fn full_after(&self) -> Duration {
+        Duration::from_secs_f64(self.capacity / self.refill_per_second)
+    }
+}
+
+/// A token bucket, as stored by the shared backend.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Bucket {
+    pub key: String,
+    pub tokens: f64,
+    pub updated: Timestamp,
+    /// Whether the last request took a token.
+    #[serde(default)]
+    pub allowed: bool,
+    /// The date the bucket is full again, it is then removed.
+    pub expires_at: Timestamp,
+}
+
+/// How the accounts are locked after consecutive failed credential checks.
+///
+/// Once `max_failures` is reached every new failure locks the account, for `lockout` at first
+/// then for twice as long each time, up to `max_lockout`. The failures are forgotten after a success,
+/// or once no failure happened during `reset_after`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockoutPolicy {
+    pub max_failures: u32,
+    pub lockout: Duration,
+    pub max_lockout: Duration,
+    pub reset_after: Duration,
+}
+
+impl LockoutPolicy {
+    /// Returns how long the account is locked after its `failures` consecutive failures.
+    pub fn lockout_duration(&self, failures: u32) -> Option<Duration> {
+        let exponent = failures.checked_sub(self.max_failures)?;
+        let factor = 2u32.checked_pow(exponent).unwrap_or(u32::MAX);
+        Some(self.lockout.saturating_mul(factor).min(self.max_lockout))
+    }
+}
+
+/// The consecutive failed credential checks of an account.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Lockout {
+    pub account: String,
+    pub failures: u32,
+    pub locked_until: Option<Timestamp>,
+    /// The date the failures are forgotten, a day after the last one by default.
+    pub expires_at: Timestamp,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{BucketLimit, LockoutPolicy};
+
+    #[test]
+    fn test_bucket_limit() {
+        let limit = BucketLimit {
+            capacity: 5.0,
+            refill_per_second: 2.0,
+        };
+
+        assert_eq!(limit.refill(1.0, Duration::from_secs(1)), 3.0);
+        assert_eq!(limit.refill(4.0, Duration::from_secs(10)), 5.0);
+        assert_eq!(limit.retry_after(0.5), Duration::from_millis(250));
+        assert_eq!(limit.retry_after(2.0), Duration::ZERO);
+        assert_eq!(limit.full_after(), Duration::from_millis(2500));
+    }
+
+    #[test]
+    fn test_lockout_duration() {
+        let policy = LockoutPolicy {
+            max_failures: 3,
+            lockout: Duration::from_secs(60),
+            max_lockout: Duration::from_secs(200),
+            reset_after: Duration::from_secs(86_400),
+        };
+
+        assert_eq!(policy.lockout_duration(2), None);
+        assert_eq!(policy.lockout_duration(3), Some(Duration::from_secs(60)));
+        assert_eq!(policy.lockout_duration(4), Some(Duration::from_secs(120)));
+        assert_eq!(policy.lockout_duration(5), Some(Duration::from_secs(200)));
+        assert_eq!(policy.lockout_duration(100), Some(Duration::from_secs(200)));
+    }
+}
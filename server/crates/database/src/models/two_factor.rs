@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{id::UserId, timestamp::Timestamp};
+
+/// The TOTP second factor of a user, pending until a first code confirms the enrolment.
+///
+/// It is kept apart from the user document, which the users can read back.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TwoFactor {
+    pub user_id: UserId,
+    /// The shared secret, base32 encoded like the authenticator apps expect it.
+    pub secret: String,
+    pub enabled: bool,
+    /// The SHA-256 hashes of the recovery codes that were not used yet.
+    pub recovery_codes: Vec<String>,
+    /// The last time step a code was accepted for, the codes of this step and the previous ones are refused.
+    pub last_step: i64,
+    pub creation_date: Timestamp,
+    pub enabled_at: Option<Timestamp>,
+}
@@ -1,12 +1,15 @@
-use mongodb::{
-    bson::{doc, to_bson, Bson},
-    Collection,
-};
+use mongodb::bson::{to_bson, Bson};
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::{authentication::Authentication, login::Login};
+use crate::{
+    authentication::{Authentication, OidcAccount, OidcIdentity},
+    id::{PermissionId, UserId},
+    login::Login,
+    managers::UserRepository,
+    timestamp::Timestamp,
+};
 
 #[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub enum UserUpdate {
@@ -27,10 +30,25 @@ impl UserUpdate {
 #[derive(Deserialize, Serialize, Debug, JsonSchema, Clone)]
 pub struct User {
     pub authentication: Authentication,
-    pub unique_id: String,
-    pub creation_date: String,
+    pub unique_id: UserId,
+    pub creation_date: Timestamp,
     pub logins: Vec<Login>,
-    pub permissions: Vec<String>,
+    pub permissions: Vec<PermissionId>,
+    #[serde(default)]
+    pub banned: bool,
+    /// Whether the user proved owning their email, the permissions of an unverified user are not granted.
+    ///
+    /// The users without credentials have no email to verify, and the providers only share the verified emails.
+    /// The users stored before the verification have no value and are verified, like MongoDB matches `$ne: false`.
+    #[serde(default = "verified_before_verification")]
+    pub email_verified: bool,
+    /// The OpenID Connect accounts the user can sign in with, an account is linked to a single user.
+    #[serde(default)]
+    pub identities: Vec<OidcIdentity>,
+}
+
+fn verified_before_verification() -> bool {
+    true
 }
 
 impl User {
@@ -39,23 +57,39 @@ impl User {
         Some(&self.logins.last()?.token.0)
     }
 
-    pub async fn upload_token(&self, login: &Login, users: &Collection<Self>) {
-        let _ = users
-            .update_one(
-                doc! {"unique_id": &self.unique_id },
-                doc! {"$push": {"logins": to_bson(&login).unwrap()}},
-                None,
-            )
-            .await;
+    /// Returns the email of the credentials, or the one claimed by the provider the user signed up with
+    pub fn email(&self) -> Option<&str> {
+        match &self.authentication {
+            Authentication::Credentials(credentials) => Some(&credentials.email),
+            Authentication::Oidc(identity) => identity.email.as_deref(),
+            Authentication::None => None,
+        }
+    }
+
+    /// Returns the linked identity of the provider account
+    pub fn identity(&self, account: &OidcAccount) -> Option<&OidcIdentity> {
+        self.identities.iter().find(|identity| identity.account == *account)
+    }
+
+    /// Returns the login that created the given token
+    pub fn login_from_token(&self, token: &str) -> Option<&Login> {
+        self.logins.iter().find(|login| login.token.0 == token)
+    }
+
+    pub async fn upload_token(&self, login: &Login, users: &dyn UserRepository) {
+        let _ = users.add_login(self.unique_id, login).await;
     }
 
-    pub fn default_website_user(unique_id: String, timestamp: u128) -> Self {
+    pub fn default_website_user(unique_id: UserId, timestamp: Timestamp) -> Self {
         Self {
             authentication: Authentication::None,
             unique_id,
-            creation_date: timestamp.to_string(),
+            creation_date: timestamp,
             logins: vec![Login::new("127.0.0.1".to_string(), timestamp, Authentication::None)],
             permissions: vec![],
+            banned: false,
+            email_verified: true,
+            identities: vec![],
         }
     }
 }
@@ -0,0 +1,506 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, Bson, Document},
+    options::FindOptions,
+    Collection,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{document::to_document, timestamp::Timestamp};
+
+pub const DEFAULT_PAGE_LIMIT: u32 = 20;
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
+/// The parameters of a paginated listing.
+///
+/// `sort` is a field name, prefixed with `-` for a descending order.
+/// `filters` are equality filters on the fields allowed by the listing.
+/// `created_after` and `created_before` are exclusive bounds of the creation date of the items.
+#[derive(Debug, Clone, Default)]
+pub struct PageRequest {
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+    pub sort: Option<String>,
+    pub filters: HashMap<String, String>,
+    pub created_after: Option<Timestamp>,
+    pub created_before: Option<Timestamp>,
+}
+
+/// The envelope of every paginated listing.
+///
+/// `next_cursor` is `None` once the last page has been reached.
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Describes which fields of a collection a listing can be sorted and filtered on.
+///
+/// `id_field` must be unique, it breaks the ties between documents sharing the same sort value.
+/// Ids are stored as strings, both backends order them character by character whatever their kind, see `id`.
+/// `date_field` holds the creation date of the documents, listings without one cannot be filtered by date.
+pub struct PageSpec<'a> {
+    pub id_field: &'a str,
+    pub sort_fields: &'a [&'a str],
+    pub filter_fields: &'a [&'a str],
+    pub date_field: Option<&'a str>,
+}
+
+#[derive(Debug)]
+pub enum PaginationError {
+    InvalidCursor,
+    InvalidSort(String),
+    InvalidFilter(String),
+    Database(mongodb::error::Error),
+}
+
+impl fmt::Display for PaginationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCursor => write!(f, "Invalid cursor"),
+            Self::InvalidSort(field) => write!(f, "Cannot sort on field: {field}"),
+            Self::InvalidFilter(field) => write!(f, "Cannot filter on field: {field}"),
+            Self::Database(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for PaginationError {
+    fn from(value: mongodb::error::Error) -> Self {
+        Self::Database(value)
+    }
+}
+
+/// The position of the last item of a page.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Cursor {
+    sort: String,
+    value: Bson,
+    id: Bson,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        let bytes = mongodb::bson::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    fn decode(raw: &str) -> Result<Self, PaginationError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(raw)
+            .map_err(|_| PaginationError::InvalidCursor)?;
+        mongodb::bson::from_slice(&bytes).map_err(|_| PaginationError::InvalidCursor)
+    }
+}
+
+impl PageRequest {
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+}
+
+// Plan is a validated page request
+struct Plan<'a> {
+    sort: String,
+    sort_field: String,
+    direction: i32,
+    id_field: &'a str,
+    filters: Vec<(&'a String, &'a String)>,
+    // date_bounds are the position of the date field relative to each bound, `Greater` for `created_after`
+    date_bounds: Vec<(Ordering, Timestamp)>,
+    date_field: &'a str,
+    cursor: Option<Cursor>,
+    limit: usize,
+}
+
+impl<'a> Plan<'a> {
+    fn new(request: &'a PageRequest, spec: &PageSpec<'a>) -> Result<Self, PaginationError> {
+        let sort = request.sort.clone().unwrap_or_else(|| spec.id_field.to_string());
+        let (sort_field, direction) = match sort.strip_prefix('-') {
+            Some(field) => (field.to_string(), -1),
+            None => (sort.clone(), 1),
+        };
+        if sort_field != spec.id_field && !spec.sort_fields.contains(&sort_field.as_str()) {
+            return Err(PaginationError::InvalidSort(sort_field));
+        }
+
+        let mut filters = Vec::new();
+        for (field, value) in &request.filters {
+            if !spec.filter_fields.contains(&field.as_str()) {
+                return Err(PaginationError::InvalidFilter(field.clone()));
+            }
+            filters.push((field, value));
+        }
+
+        let mut date_bounds = Vec::new();
+        if let Some(after) = request.created_after {
+            date_bounds.push((Ordering::Greater, after));
+        }
+        if let Some(before) = request.created_before {
+            date_bounds.push((Ordering::Less, before));
+        }
+        let date_field = match spec.date_field {
+            Some(field) => field,
+            None if date_bounds.is_empty() => "",
+            None if request.created_after.is_some() => {
+                return Err(PaginationError::InvalidFilter("created_after".to_string()))
+            }
+            None => return Err(PaginationError::InvalidFilter("created_before".to_string())),
+        };
+
+        let cursor = match &request.cursor {
+            Some(cursor) => {
+                let cursor = Cursor::decode(cursor)?;
+                if cursor.sort != sort {
+                    return Err(PaginationError::InvalidCursor);
+                }
+                Some(cursor)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            sort,
+            sort_field,
+            direction,
+            id_field: spec.id_field,
+            filters,
+            date_bounds,
+            date_field,
+            cursor,
+            limit: request.limit() as usize,
+        })
+    }
+
+    // into_page keeps the first `limit` items, the plan fetched one more to know if another page exists
+    fn into_page<T: Serialize>(self, mut items: Vec<T>) -> Page<T> {
+        let next_cursor = if items.len() > self.limit {
+            items.truncate(self.limit);
+            items.last().and_then(|last| {
+                let document = to_document(last).ok()?;
+                Some(
+                    Cursor {
+                        sort: self.sort.clone(),
+                        value: get_path(&document, &self.sort_field)?,
+                        id: get_path(&document, self.id_field)?,
+                    }
+                    .encode(),
+                )
+            })
+        } else {
+            None
+        };
+
+        Page { items, next_cursor }
+    }
+}
+
+/// Runs a paginated find on the collection.
+///
+/// `filter` is the filter of the listing itself, the filters and the cursor of the request are added on top of it.
+pub async fn paginate<T>(
+    collection: &Collection<T>,
+    filter: Document,
+    request: &PageRequest,
+    spec: &PageSpec<'_>,
+) -> Result<Page<T>, PaginationError>
+where
+    T: DeserializeOwned + Serialize + Unpin + Send + Sync,
+{
+    let plan = Plan::new(request, spec)?;
+    let sort_field = plan.sort_field.as_str();
+
+    let mut conditions = vec![filter];
+    for (field, value) in &plan.filters {
+        conditions.push(doc! { *field: *value });
+    }
+    for (ordering, date) in &plan.date_bounds {
+        let operator = if *ordering == Ordering::Greater { "$gt" } else { "$lt" };
+        conditions.push(doc! { plan.date_field: { operator: Bson::from(*date) } });
+    }
+    if let Some(cursor) = &plan.cursor {
+        conditions.push(after_cursor(sort_field, plan.direction, plan.id_field, cursor));
+    }
+
+    let mut sort_document = doc! { sort_field: plan.direction };
+    if sort_field != plan.id_field {
+        sort_document.insert(plan.id_field, 1);
+    }
+    let options = FindOptions::builder()
+        .sort(sort_document)
+        .limit(plan.limit as i64 + 1)
+        .build();
+
+    let mut cursor = collection.find(doc! { "$and": conditions }, options).await?;
+    let mut items = Vec::new();
+    while let Some(item) = cursor.next().await {
+        items.push(item?);
+    }
+
+    Ok(plan.into_page(items))
+}
+
+/// Paginates items held in memory, in the same order and with the same cursors as `paginate`.
+///
+/// `items` are the items of the listing itself, the filters and the cursor of the request are applied on them.
+pub fn paginate_in_memory<T>(
+    items: Vec<T>,
+    request: &PageRequest,
+    spec: &PageSpec<'_>,
+) -> Result<Page<T>, PaginationError>
+where
+    T: Serialize,
+{
+    let plan = Plan::new(request, spec)?;
+    let sort_field = plan.sort_field.as_str();
+
+    let mut rows = Vec::new();
+    for item in items {
+        let document = to_document(&item)?;
+        let matches_filters = plan.filters.iter().all(|(field, value)| {
+            let expected = Bson::String(value.to_string());
+            match get_path(&document, field) {
+                Some(Bson::Array(values)) => values.contains(&expected),
+                Some(actual) => actual == expected,
+                None => false,
+            }
+        });
+        let date = get_path(&document, plan.date_field);
+        let matches_dates = plan.date_bounds.iter().all(|(ordering, bound)| match &date {
+            Some(date @ Bson::DateTime(_)) => compare_values(Some(date), Some(&Bson::from(*bound))) == *ordering,
+            _ => false,
+        });
+        if !matches_filters || !matches_dates {
+            continue;
+        }
+        let sort_value = get_path(&document, sort_field);
+        let id = get_path(&document, plan.id_field);
+        rows.push((sort_value, id, item));
+    }
+
+    let order = |sort_value: &Option<Bson>, id: &Option<Bson>, other_value: &Option<Bson>, other_id: &Option<Bson>| {
+        let ordering = compare_values(sort_value.as_ref(), other_value.as_ref());
+        let ordering = if plan.direction < 0 { ordering.reverse() } else { ordering };
+        if sort_field == plan.id_field {
+            return ordering;
+        }
+        ordering.then_with(|| compare_values(id.as_ref(), other_id.as_ref()))
+    };
+    rows.sort_by(|(value, id, _), (other_value, other_id, _)| order(value, id, other_value, other_id));
+
+    if let Some(cursor) = &plan.cursor {
+        let cursor_value = Some(cursor.value.clone());
+        let cursor_id = Some(cursor.id.clone());
+        let cursor_value = if sort_field == plan.id_field { &cursor_id } else { &cursor_value };
+        rows.retain(|(value, id, _)| order(value, id, cursor_value, &cursor_id) == Ordering::Greater);
+    }
+
+    let items = rows
+        .into_iter()
+        .take(plan.limit + 1)
+        .map(|(_, _, item)| item)
+        .collect();
+
+    Ok(plan.into_page(items))
+}
+
+// after_cursor matches the documents placed after the cursor in the sort order
+fn after_cursor(sort_field: &str, direction: i32, id_field: &str, cursor: &Cursor) -> Document {
+    let operator = if direction < 0 { "$lt" } else { "$gt" };
+
+    if sort_field == id_field {
+        return doc! { id_field: { operator: cursor.id.clone() } };
+    }
+    doc! {
+        "$or": [
+            { sort_field: { operator: cursor.value.clone() } },
+            { sort_field: cursor.value.clone(), id_field: { "$gt": cursor.id.clone() } },
+        ]
+    }
+}
+
+// compare_values orders values like MongoDB does for the types stored by the managers:
+// missing and null values first, then numbers, strings, booleans and dates
+fn compare_values(value: Option<&Bson>, other: Option<&Bson>) -> Ordering {
+    fn rank(value: Option<&Bson>) -> u8 {
+        match value {
+            None | Some(Bson::Null) => 0,
+            Some(Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_)) => 1,
+            Some(Bson::String(_)) => 2,
+            Some(Bson::Boolean(_)) => 3,
+            Some(Bson::DateTime(_)) => 4,
+            Some(_) => 5,
+        }
+    }
+    fn number(value: &Bson) -> f64 {
+        match value {
+            Bson::Int32(value) => *value as f64,
+            Bson::Int64(value) => *value as f64,
+            Bson::Double(value) => *value,
+            _ => 0.0,
+        }
+    }
+
+    match (value, other) {
+        (Some(Bson::String(value)), Some(Bson::String(other))) => value.cmp(other),
+        (Some(Bson::Boolean(value)), Some(Bson::Boolean(other))) => value.cmp(other),
+        (Some(Bson::DateTime(value)), Some(Bson::DateTime(other))) => value.cmp(other),
+        (Some(value), Some(other)) if rank(Some(value)) == 1 && rank(Some(other)) == 1 => {
+            number(value).total_cmp(&number(other))
+        }
+        _ => rank(value).cmp(&rank(other)),
+    }
+}
+
+fn get_path(document: &Document, path: &str) -> Option<Bson> {
+    let mut fields = path.split('.');
+    let mut value = document.get(fields.next()?)?;
+    for field in fields {
+        value = value.as_document()?.get(field)?;
+    }
+    Some(value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{doc, Bson, Document};
+
+    use super::{get_path, paginate_in_memory, Cursor, PageRequest, PageSpec, PaginationError, MAX_PAGE_LIMIT};
+    use crate::{id::UserId, timestamp::Timestamp};
+
+    const SPEC: PageSpec<'static> = PageSpec {
+        id_field: "id",
+        sort_fields: &["name"],
+        filter_fields: &["kind"],
+        date_field: Some("created"),
+    };
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            sort: "-name".to_string(),
+            value: Bson::String("organization".to_string()),
+            id: Bson::Int64(42),
+        };
+
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+        assert!(Cursor::decode("not a cursor").is_err());
+    }
+
+    #[test]
+    fn test_limit_is_clamped() {
+        let request = PageRequest {
+            limit: Some(10_000),
+            ..Default::default()
+        };
+
+        assert_eq!(request.limit(), MAX_PAGE_LIMIT);
+        assert_eq!(PageRequest { limit: Some(0), ..Default::default() }.limit(), 1);
+    }
+
+    #[test]
+    fn test_get_path() {
+        let document = doc! { "authentication": { "Credentials": { "email": "test@test.fr" } } };
+
+        assert_eq!(
+            get_path(&document, "authentication.Credentials.email"),
+            Some(Bson::String("test@test.fr".to_string()))
+        );
+        assert_eq!(get_path(&document, "authentication.None"), None);
+    }
+
+    #[test]
+    fn test_paginate_in_memory() {
+        let items = vec![
+            doc! { "id": 3, "name": "b", "kind": "tool" },
+            doc! { "id": 1, "name": "b", "kind": "tool" },
+            doc! { "id": 2, "name": "a", "kind": "tool" },
+            doc! { "id": 4, "name": "c", "kind": "model" },
+        ];
+        let mut request = PageRequest {
+            limit: Some(2),
+            sort: Some("name".to_string()),
+            filters: [("kind".to_string(), "tool".to_string())].into(),
+            ..Default::default()
+        };
+
+        let ids = |items: &[Document]| items.iter().map(|item| item.get_i32("id").unwrap()).collect::<Vec<_>>();
+        let page = paginate_in_memory(items.clone(), &request, &SPEC).unwrap();
+        assert_eq!(ids(&page.items), vec![2, 1]);
+
+        request.cursor = page.next_cursor;
+        let page = paginate_in_memory(items, &request, &SPEC).unwrap();
+        assert_eq!(ids(&page.items), vec![3]);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_paginate_in_memory_by_date() {
+        let date = |millis| Bson::from(Timestamp::from_millis(millis));
+        let items = vec![
+            doc! { "id": 1, "created": date(1_000) },
+            doc! { "id": 2, "created": date(2_000) },
+            doc! { "id": 3, "created": date(3_000) },
+            doc! { "id": 4, "created": "2000" },
+        ];
+        let request = PageRequest {
+            created_after: Some(Timestamp::from_millis(1_000)),
+            created_before: Some(Timestamp::from_millis(3_000)),
+            ..Default::default()
+        };
+
+        let page = paginate_in_memory(items, &request, &SPEC).unwrap();
+        assert_eq!(page.items, vec![doc! { "id": 2, "created": date(2_000) }]);
+
+        let spec = PageSpec { date_field: None, ..SPEC };
+        assert!(matches!(
+            paginate_in_memory(Vec::<Document>::new(), &request, &spec),
+            Err(PaginationError::InvalidFilter(field)) if field == "created_after"
+        ));
+    }
+
+    #[test]
+    fn test_paginate_in_memory_mixed_ids() {
+        // Legacy ids whose first characters place them before, between and after the UUIDv7
+        let mut ids: Vec<UserId> = ["0", "9", "10", "1708000000123"]
+            .into_iter()
+            .map(|id| id.parse().unwrap())
+            .chain((0..5).map(|_| UserId::generate()))
+            .collect();
+        let items: Vec<Document> = ids
+            .iter()
+            .rev()
+            .map(|id| doc! { "id": *id, "name": "same" })
+            .collect();
+        ids.sort();
+
+        for sort in [None, Some("name".to_string()), Some("-name".to_string())] {
+            let mut request = PageRequest {
+                limit: Some(2),
+                sort,
+                ..Default::default()
+            };
+            let mut listed = Vec::new();
+            loop {
+                let page = paginate_in_memory(items.clone(), &request, &SPEC).unwrap();
+                listed.extend(page.items.iter().map(|item| item.get_str("id").unwrap().parse::<UserId>().unwrap()));
+                match page.next_cursor {
+                    Some(cursor) => request.cursor = Some(cursor),
+                    None => break,
+                }
+            }
+            // Every item is listed once, in the order of the ids
+            assert_eq!(listed, ids);
+        }
+    }
+}
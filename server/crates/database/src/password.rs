@@ -0,0 +1,84 @@
+//! The passwords are stored as salted PBKDF2-SHA256 hashes, `pbkdf2-sha256$<rounds>$<salt>$<hash>`.
+
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use hmac::Hmac;
+use rand::RngCore;
+use sha2::Sha256;
+
+const SCHEME: &str = "pbkdf2-sha256";
+const ROUNDS: u32 = 600_000;
+const SALT_LENGTH: usize = 16;
+const HASH_LENGTH: usize = 32;
+
+/// Hashes the password with a new random salt.
+pub fn hash(password: &str) -> String {
+    let mut salt = [0u8; SALT_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let hash = derive(password, &salt, ROUNDS);
+    format!(
+        "{SCHEME}${ROUNDS}${}${}",
+        STANDARD_NO_PAD.encode(salt),
+        STANDARD_NO_PAD.encode(hash)
+    )
+}
+
+/// Returns whether the password matches the stored hash, a value that is not a hash matches nothing.
+pub fn verify(password: &str, stored: &str) -> bool {
+    let mut parts = stored.split('$');
+    let (Some(SCHEME), Some(rounds), Some(salt), Some(expected), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    let (Ok(rounds), Ok(salt), Ok(expected)) = (
+        rounds.parse(),
+        STANDARD_NO_PAD.decode(salt),
+        STANDARD_NO_PAD.decode(expected),
+    ) else {
+        return false;
+    };
+    // The hashes are compared in constant time, like the signatures
+    let hash = derive(password, &salt, rounds);
+    hash.len() == expected.len()
+        && hash
+            .iter()
+            .zip(&expected)
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Returns whether the stored value is already a hash, and not a password stored before the hashing.
+pub fn is_hash(stored: &str) -> bool {
+    stored.starts_with(&format!("{SCHEME}$"))
+}
+
+fn derive(password: &str, salt: &[u8], rounds: u32) -> [u8; HASH_LENGTH] {
+    let mut hash = [0u8; HASH_LENGTH];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, rounds, &mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let stored = hash("hunter2");
+
+        assert!(is_hash(&stored));
+        assert!(!stored.contains("hunter2"));
+        assert!(verify("hunter2", &stored));
+        assert!(!verify("hunter3", &stored));
+        // The salt differs on every hash
+        assert_ne!(stored, hash("hunter2"));
+    }
+
+    #[test]
+    fn test_verify_not_a_hash() {
+        assert!(!is_hash("hunter2"));
+        assert!(!verify("hunter2", "hunter2"));
+        assert!(!verify("hunter2", "pbkdf2-sha256$x$y$z"));
+        assert!(!verify("", ""));
+    }
+}
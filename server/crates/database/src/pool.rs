@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use mongodb::event::cmap::{
+    CmapEventHandler, ConnectionCheckedInEvent, ConnectionCheckedOutEvent, ConnectionCheckoutFailedEvent,
+    ConnectionClosedEvent, ConnectionCreatedEvent, PoolClearedEvent,
+};
+
+/// Counts the connections of the MongoDB pools from the events of the driver.
+#[derive(Default)]
+pub(crate) struct PoolStats {
+    created: AtomicU64,
+    closed: AtomicU64,
+    checked_out: AtomicU64,
+    checked_in: AtomicU64,
+    checkout_failures: AtomicU64,
+    cleared: AtomicU64,
+}
+
+/// The state of the connection pools of every server of the deployment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    /// The connections currently open
+    pub open: u64,
+    /// The open connections running an operation
+    pub in_use: u64,
+    /// The operations that could not get a connection since the start
+    pub checkout_failures: u64,
+    /// The times a pool was cleared after an error since the start
+    pub cleared: u64,
+}
+
+impl PoolStats {
+    pub(crate) fn snapshot(&self) -> PoolSnapshot {
+        let created = self.created.load(Ordering::Relaxed);
+        let checked_out = self.checked_out.load(Ordering::Relaxed);
+        PoolSnapshot {
+            open: created.saturating_sub(self.closed.load(Ordering::Relaxed)),
+            in_use: checked_out.saturating_sub(self.checked_in.load(Ordering::Relaxed)),
+            checkout_failures: self.checkout_failures.load(Ordering::Relaxed),
+            cleared: self.cleared.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl CmapEventHandler for PoolStats {
+    fn handle_connection_created_event(&self, _event: ConnectionCreatedEvent) {
+        self.created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn handle_connection_closed_event(&self, _event: ConnectionClosedEvent) {
+        self.closed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn handle_connection_checked_out_event(&self, _event: ConnectionCheckedOutEvent) {
+        self.checked_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn handle_connection_checked_in_event(&self, _event: ConnectionCheckedInEvent) {
+        self.checked_in.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn handle_connection_checkout_failed_event(&self, _event: ConnectionCheckoutFailedEvent) {
+        self.checkout_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn handle_pool_cleared_event(&self, _event: PoolClearedEvent) {
+        self.cleared.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,80 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use mongodb::{
+    bson::{Bson, Document},
+    event::command::{CommandEventHandler, CommandFailedEvent, CommandStartedEvent, CommandSucceededEvent},
+};
+use opentelemetry::{
+    global::{self, BoxedSpan},
+    trace::{Span, SpanKind, Status, Tracer},
+    KeyValue,
+};
+
+/// Opens a client span for every command sent to MongoDB.
+///
+/// The spans are children of the current OpenTelemetry context, which is the span of the request
+/// when the command is sent while handling one.
+#[derive(Default)]
+pub(crate) struct CommandSpans {
+    // The spans of the commands waiting for their reply, by request id
+    spans: Mutex<HashMap<i32, BoxedSpan>>,
+}
+
+impl CommandEventHandler for CommandSpans {
+    fn handle_command_started_event(&self, event: CommandStartedEvent) {
+        let tracer = global::tracer("database");
+        let mut attributes = vec![
+            KeyValue::new("db.system", "mongodb"),
+            KeyValue::new("db.name", event.db.clone()),
+            KeyValue::new("db.operation", event.command_name.clone()),
+        ];
+        let name = match collection(&event.command, &event.command_name) {
+            Some(collection) => {
+                attributes.push(KeyValue::new("db.mongodb.collection", collection.to_string()));
+                format!("{} {}.{collection}", event.command_name, event.db)
+            }
+            None => format!("{} {}", event.command_name, event.db),
+        };
+
+        let span = tracer
+            .span_builder(name)
+            .with_kind(SpanKind::Client)
+            .with_attributes(attributes)
+            .start(&tracer);
+        self.spans.lock().unwrap().insert(event.request_id, span);
+    }
+
+    fn handle_command_succeeded_event(&self, event: CommandSucceededEvent) {
+        if let Some(mut span) = self.spans.lock().unwrap().remove(&event.request_id) {
+            span.end();
+        }
+    }
+
+    fn handle_command_failed_event(&self, event: CommandFailedEvent) {
+        if let Some(mut span) = self.spans.lock().unwrap().remove(&event.request_id) {
+            span.set_status(Status::error(event.failure.to_string()));
+            span.end();
+        }
+    }
+}
+
+// collection returns the collection a command targets, which is the value of its first key, e.g. `{ find: "users" }`
+fn collection<'a>(command: &'a Document, command_name: &str) -> Option<&'a str> {
+    match command.get(command_name) {
+        Some(Bson::String(collection)) => Some(collection),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::doc;
+
+    use super::collection;
+
+    #[test]
+    fn test_collection() {
+        assert_eq!(collection(&doc! { "find": "users", "filter": {} }, "find"), Some("users"));
+        assert_eq!(collection(&doc! { "ping": 1 }, "ping"), None);
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use mongodb::bson::{Bson, DateTime};
+use rocket::form::{self, FromFormField, ValueField};
+use rocket_okapi::okapi::schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Schema, SchemaObject},
+    JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A point in time, with a millisecond precision.
+///
+/// It is stored as a BSON date, so that it can be range queried, sorted and TTL indexed,
+/// and exposed as an RFC 3339 string in JSON.
+///
+/// The dates stored before, strings of milliseconds since the Unix epoch, are still accepted when reading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Timestamp(DateTime);
+
+impl Timestamp {
+    pub fn now() -> Self {
+        Self(DateTime::now())
+    }
+
+    pub fn from_millis(millis: i64) -> Self {
+        Self(DateTime::from_millis(millis))
+    }
+
+    pub fn timestamp_millis(&self) -> i64 {
+        self.0.timestamp_millis()
+    }
+
+    /// Returns the point in time `duration` later.
+    pub fn plus(&self, duration: Duration) -> Self {
+        let millis = i64::try_from(duration.as_millis()).unwrap_or(i64::MAX);
+        Self::from_millis(self.timestamp_millis().saturating_add(millis))
+    }
+}
+
+/// The error of a string that is neither an RFC 3339 date nor a number of milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTimestamp(pub String);
+
+impl fmt::Display for InvalidTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid date: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidTimestamp {}
+
+impl FromStr for Timestamp {
+    type Err = InvalidTimestamp;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) {
+            return value
+                .parse()
+                .map(Self::from_millis)
+                .map_err(|_| InvalidTimestamp(value.to_string()));
+        }
+        DateTime::parse_rfc3339_str(value)
+            .map(Self)
+            .map_err(|_| InvalidTimestamp(value.to_string()))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Dates beyond the year 9999 have no RFC 3339 representation
+        match self.0.try_to_rfc3339_string() {
+            Ok(date) => f.write_str(&date),
+            Err(_) => write!(f, "{}", self.timestamp_millis()),
+        }
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The BSON serializer used by MongoDB is not human readable, see `crate::document`
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Bson::deserialize(deserializer)? {
+            Bson::DateTime(date) => Ok(Self(date)),
+            Bson::String(value) => value.parse().map_err(de::Error::custom),
+            other => Err(de::Error::custom(format!("Invalid date: {other}"))),
+        }
+    }
+}
+
+impl JsonSchema for Timestamp {
+    fn schema_name() -> String {
+        "Timestamp".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some("date-time".to_string()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl From<Timestamp> for Bson {
+    fn from(timestamp: Timestamp) -> Self {
+        Bson::DateTime(timestamp.0)
+    }
+}
+
+#[rocket::async_trait]
+impl<'v> FromFormField<'v> for Timestamp {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        field
+            .value
+            .parse()
+            .map_err(|err: InvalidTimestamp| form::Error::validation(err.to_string()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{doc, Bson};
+    use serde::{Deserialize, Serialize};
+
+    use super::Timestamp;
+    use crate::document::{from_document, to_document};
+
+    #[derive(Deserialize, Serialize)]
+    struct Dated {
+        date: Timestamp,
+    }
+
+    #[test]
+    fn test_parse() {
+        let timestamp: Timestamp = "2024-02-15T12:30:00.123Z".parse().unwrap();
+
+        assert_eq!(timestamp.timestamp_millis(), 1_708_000_200_123);
+        assert_eq!(timestamp.to_string(), "2024-02-15T12:30:00.123Z");
+        assert_eq!("1708000200123".parse::<Timestamp>().unwrap(), timestamp);
+        assert!("yesterday".parse::<Timestamp>().is_err());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let dated = Dated {
+            date: Timestamp::from_millis(1_708_000_200_123),
+        };
+
+        assert_eq!(serde_json::to_string(&dated).unwrap(), r#"{"date":"2024-02-15T12:30:00.123Z"}"#);
+        let document = to_document(&dated).unwrap();
+        assert!(matches!(document.get("date"), Some(Bson::DateTime(_))));
+        assert_eq!(from_document::<Dated>(document).unwrap().date, dated.date);
+
+        // Dates stored as strings of milliseconds are still readable
+        let legacy: Dated = from_document(doc! { "date": "1708000200123" }).unwrap();
+        assert_eq!(legacy.date, dated.date);
+    }
+}
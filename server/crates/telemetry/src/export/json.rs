@@ -0,0 +1,165 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use opentelemetry_proto::tonic::collector::logs::v1::ExportLogsServiceRequest;
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use prost::Message;
+use serde_json::{Map, Value};
+
+use super::Signal;
+
+/// Converts an OTLP protobuf payload to the OTLP JSON encoding
+///
+/// The generated messages serialize with the names of the Rust fields, so the fields are renamed to
+/// lowerCamelCase, the oneof fields are inlined and the trace and span ids are written in hexadecimal.
+pub fn encode(signal: Signal, body: &[u8]) -> Result<String, String> {
+    let value = match signal {
+        Signal::Traces => ExportTraceServiceRequest::decode(body)
+            .map_err(|err| err.to_string())
+            .and_then(|request| serde_json::to_value(request).map_err(|err| err.to_string()))?,
+        Signal::Metrics => ExportMetricsServiceRequest::decode(body)
+            .map_err(|err| err.to_string())
+            .and_then(|request| serde_json::to_value(request).map_err(|err| err.to_string()))?,
+        Signal::Logs => ExportLogsServiceRequest::decode(body)
+            .map_err(|err| err.to_string())
+            .and_then(|request| serde_json::to_value(request).map_err(|err| err.to_string()))?,
+    };
+    Ok(to_otlp(value).to_string())
+}
+
+/// Converts an OTLP protobuf payload to indented OTLP JSON, for people to read it
+pub fn encode_pretty(signal: Signal, body: &[u8]) -> Result<String, String> {
+    let value: Value = serde_json::from_str(&encode(signal, body)?).map_err(|err| err.to_string())?;
+    serde_json::to_string_pretty(&value).map_err(|err| err.to_string())
+}
+
+fn to_otlp(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut object = Map::new();
+            for (name, value) in fields {
+                match value {
+                    // The unset optional fields are left out
+                    Value::Null => {}
+                    // The oneof fields are serialized as enums, e.g. `"value": {"StringValue": "api"}`
+                    Value::Object(variant) if is_oneof(&variant) => {
+                        if let Some((name, value)) = variant.into_iter().next() {
+                            object.insert(camel_case(&name), encode_field(&name, value));
+                        }
+                    }
+                    value => {
+                        object.insert(camel_case(&name), encode_field(&name, value));
+                    }
+                }
+            }
+            Value::Object(object)
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(to_otlp).collect()),
+        value => value,
+    }
+}
+
+fn encode_field(name: &str, value: Value) -> Value {
+    match name {
+        "trace_id" | "span_id" | "parent_span_id" => {
+            Value::String(bytes(&value).iter().map(|byte| format!("{byte:02x}")).collect())
+        }
+        "BytesValue" => Value::String(STANDARD.encode(bytes(&value))),
+        _ => to_otlp(value),
+    }
+}
+
+fn is_oneof(variant: &Map<String, Value>) -> bool {
+    variant.len() == 1 && variant.keys().all(|name| name.starts_with(|c: char| c.is_ascii_uppercase()))
+}
+
+fn bytes(value: &Value) -> Vec<u8> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(|byte| byte.as_u64()).map(|byte| byte as u8).collect())
+        .unwrap_or_default()
+}
+
+// camel_case turns both snake_case and PascalCase names into lowerCamelCase
+fn camel_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut upper = false;
+    for (index, c) in name.chars().enumerate() {
+        match c {
+            '_' => upper = true,
+            c if index == 0 => result.push(c.to_ascii_lowercase()),
+            c if upper => {
+                result.push(c.to_ascii_uppercase());
+                upper = false;
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+    use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, KeyValue};
+    use opentelemetry_proto::tonic::metrics::v1::{
+        metric, number_data_point, Gauge, Metric, NumberDataPoint, ResourceMetrics, ScopeMetrics,
+    };
+    use prost::Message;
+    use serde_json::json;
+
+    use super::{camel_case, encode};
+    use crate::export::Signal;
+
+    #[test]
+    fn test_camel_case() {
+        assert_eq!(camel_case("time_unix_nano"), "timeUnixNano");
+        assert_eq!(camel_case("StringValue"), "stringValue");
+        assert_eq!(camel_case("name"), "name");
+    }
+
+    #[test]
+    fn test_oneof() {
+        let request = ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                scope_metrics: vec![ScopeMetrics {
+                    metrics: vec![Metric {
+                        name: "users".to_string(),
+                        data: Some(metric::Data::Gauge(Gauge {
+                            data_points: vec![NumberDataPoint {
+                                attributes: vec![KeyValue {
+                                    key: "organization".to_string(),
+                                    value: Some(AnyValue {
+                                        value: Some(any_value::Value::StringValue("areation".to_string())),
+                                    }),
+                                }],
+                                time_unix_nano: 42,
+                                value: Some(number_data_point::Value::AsInt(7)),
+                                ..Default::default()
+                            }],
+                        })),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&encode(Signal::Metrics, &request.encode_to_vec()).unwrap()).unwrap();
+
+        let metric = &json["resourceMetrics"][0]["scopeMetrics"][0]["metrics"][0];
+        assert_eq!(metric["name"], "users");
+        assert_eq!(
+            metric["gauge"]["dataPoints"][0],
+            json!({
+                "attributes": [{"key": "organization", "value": {"stringValue": "areation"}}],
+                "startTimeUnixNano": 0,
+                "timeUnixNano": 42,
+                "asInt": 7,
+                "exemplars": [],
+                "flags": 0,
+            })
+        );
+    }
+}
@@ -0,0 +1,427 @@
+mod json;
+mod otlp_grpc;
+mod otlp_http;
+mod spool;
+mod stdout;
+
+use std::fs;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use opentelemetry::metrics::{Counter, Meter};
+use opentelemetry::KeyValue;
+
+use crate::health::ExporterHealth;
+use crate::settings::{ExporterKind, TelemetrySettings};
+
+pub use otlp_grpc::OtlpGrpcExporter;
+pub use otlp_http::{HttpEncoding, OtlpHttpExporter};
+pub use spool::Spool;
+pub use stdout::StdoutExporter;
+
+/// The kind of telemetry data of a payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    Traces,
+    Metrics,
+    Logs,
+}
+
+impl Signal {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Traces => "traces",
+            Self::Metrics => "metrics",
+            Self::Logs => "logs",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "traces" => Some(Self::Traces),
+            "metrics" => Some(Self::Metrics),
+            "logs" => Some(Self::Logs),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &'static str {
+        match self {
+            Self::Traces => "/v1/traces",
+            Self::Metrics => "/v1/metrics",
+            Self::Logs => "/v1/logs",
+        }
+    }
+}
+
+/// Counts what the exporters export, drop and spool, in the exporter health and as metrics of the API.
+#[derive(Clone)]
+pub struct ExportStats {
+    health: ExporterHealth,
+    exported: Counter<u64>,
+    dropped: Counter<u64>,
+    spooled: Counter<u64>,
+}
+
+impl ExportStats {
+    pub fn new(health: ExporterHealth, meter: &Meter) -> Self {
+        Self {
+            health,
+            exported: meter
+                .u64_counter("telemetry.exporter.exported")
+                .with_description("Number of telemetry items exported")
+                .init(),
+            dropped: meter
+                .u64_counter("telemetry.exporter.dropped")
+                .with_description("Number of telemetry items dropped, because the queue was full or the endpoint rejected them")
+                .init(),
+            spooled: meter
+                .u64_counter("telemetry.exporter.spooled")
+                .with_description("Number of telemetry items stored on disk while the endpoint was unreachable")
+                .init(),
+        }
+    }
+
+    pub fn exported(&self, signal: Signal, items: u64) {
+        self.exported.add(items, &[KeyValue::new("signal", signal.name())]);
+        self.health.count(|state| state.exported += items);
+    }
+
+    pub fn dropped(&self, signal: Signal, items: u64) {
+        self.dropped.add(items, &[KeyValue::new("signal", signal.name())]);
+        self.health.count(|state| state.dropped += items);
+    }
+
+    fn spooled(&self, signal: Signal, items: u64) {
+        self.spooled.add(items, &[KeyValue::new("signal", signal.name())]);
+        self.health.count(|state| state.spooled += items);
+    }
+}
+
+/// Why a payload could not be exported.
+#[derive(Debug)]
+pub enum Failure {
+    // The endpoint could not be reached or is overloaded, the payload can be sent again later
+    Unreachable(String),
+    // The endpoint refused the payload, sending it again would fail the same way
+    Rejected(String),
+}
+
+impl Failure {
+    fn message(&self) -> &str {
+        match self {
+            Self::Unreachable(message) | Self::Rejected(message) => message,
+        }
+    }
+}
+
+/// Sends the payloads somewhere, a collector or the console.
+///
+/// The payloads are always encoded as OTLP protobuf, the exporters convert them to their own format.
+#[async_trait]
+pub trait Exporter: Send + Sync {
+    /// Describes where the payloads go, for the error messages
+    fn target(&self) -> &str;
+
+    /// Sends the payload once, the retries are handled by the `ExportClient`
+    async fn send(&self, signal: Signal, body: &[u8]) -> Result<(), Failure>;
+}
+
+/// Discards the payloads, for the API to run without a collector.
+pub struct NoopExporter;
+
+#[async_trait]
+impl Exporter for NoopExporter {
+    fn target(&self) -> &str {
+        "nowhere"
+    }
+
+    async fn send(&self, _signal: Signal, _body: &[u8]) -> Result<(), Failure> {
+        Ok(())
+    }
+}
+
+/// Creates the exporter selected by the settings
+pub fn build_exporter(settings: &TelemetrySettings) -> Box<dyn Exporter> {
+    let endpoint = settings.hostname.trim_end_matches('/').to_string();
+    let headers = settings.export_headers();
+    match settings.exporter {
+        ExporterKind::HttpProtobuf => Box::new(OtlpHttpExporter::new(endpoint, &headers, HttpEncoding::Protobuf)),
+        ExporterKind::HttpJson => Box::new(OtlpHttpExporter::new(endpoint, &headers, HttpEncoding::Json)),
+        ExporterKind::Grpc => Box::new(OtlpGrpcExporter::new(endpoint, &headers)),
+        ExporterKind::Stdout => Box::new(StdoutExporter),
+        ExporterKind::None => Box::new(NoopExporter),
+    }
+}
+
+/// Sends the OTLP payloads with the exporter, retrying with an exponential backoff while its endpoint is unreachable
+/// and storing them in the spool, when there is one, once the retries are exhausted.
+pub struct ExportClient {
+    exporter: Box<dyn Exporter>,
+    max_retries: u32,
+    retry_backoff: Duration,
+    max_backoff: Duration,
+    spool: Option<Spool>,
+    stats: ExportStats,
+}
+
+impl ExportClient {
+    pub fn new(settings: &TelemetrySettings, exporter: Box<dyn Exporter>, stats: ExportStats) -> Self {
+        // Only the exporters that send the payloads somewhere can fail
+        let spool_dir = settings.spool_dir.clone().filter(|_| settings.exporter.is_remote());
+        let spool = spool_dir.and_then(|dir| {
+            Spool::open(dir, settings.spool_max_bytes)
+                .map_err(|err| tracing::error!("Cannot open the telemetry spool, failed exports will be dropped: {err}"))
+                .ok()
+        });
+
+        Self {
+            exporter,
+            max_retries: settings.max_retries,
+            retry_backoff: Duration::from_millis(settings.retry_backoff_ms),
+            max_backoff: Duration::from_millis(settings.max_backoff_ms),
+            spool,
+            stats,
+        }
+    }
+
+    pub fn stats(&self) -> &ExportStats {
+        &self.stats
+    }
+
+    /// Exports a payload holding `items` spans, metrics or log records
+    pub async fn export(&self, signal: Signal, body: Vec<u8>, items: u64) {
+        match self.send_with_retry(signal, &body).await {
+            Ok(()) => {
+                self.stats.health.record(Ok(()));
+                self.stats.exported(signal, items);
+                self.replay().await;
+            }
+            Err(failure) => {
+                tracing::warn!("Failed to export {} to {}: {}", signal.name(), self.exporter.target(), failure.message());
+                self.stats.health.record(Err(failure.message().to_string()));
+                match (&failure, &self.spool) {
+                    (Failure::Unreachable(_), Some(spool)) if spool.store(signal, &body).is_ok() => {
+                        self.stats.spooled(signal, items)
+                    }
+                    _ => self.stats.dropped(signal, items),
+                }
+            }
+        }
+    }
+
+    async fn send_with_retry(&self, signal: Signal, body: &[u8]) -> Result<(), Failure> {
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            match self.exporter.send(signal, body).await {
+                Err(Failure::Unreachable(_)) if attempt < self.max_retries => {
+                    attempt += 1;
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2).min(self.max_backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // replay sends the spooled payloads again, in order, until one of them fails
+    async fn replay(&self) {
+        let Some(spool) = &self.spool else {
+            return;
+        };
+        let Ok(payloads) = spool.pending() else {
+            return;
+        };
+
+        for payload in payloads {
+            let Ok(body) = fs::read(&payload.path) else {
+                continue;
+            };
+            match self.exporter.send(payload.signal, &body).await {
+                Ok(()) => {
+                    let _ = fs::remove_file(&payload.path);
+                }
+                // Sending it again would fail the same way
+                Err(Failure::Rejected(_)) => {
+                    let _ = fs::remove_file(&payload.path);
+                }
+                Err(Failure::Unreachable(_)) => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    use opentelemetry::global;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::{build_exporter, ExportClient, ExportStats, Signal, Spool};
+    use crate::health::ExporterHealth;
+    use crate::settings::TelemetrySettings;
+
+    /// A request received by the mock collector.
+    #[derive(Clone)]
+    pub(crate) struct MockRequest {
+        pub(crate) path: String,
+        // The request line and the headers, lowercased
+        pub(crate) head: String,
+        pub(crate) body: Vec<u8>,
+    }
+
+    pub(crate) type Received = Arc<Mutex<Vec<MockRequest>>>;
+
+    // mock_collector answers the OTLP requests with the given statuses, then with 200
+    pub(crate) async fn mock_collector(statuses: Vec<u16>) -> (String, Received) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let received = Received::default();
+
+        let requests = received.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                let (head_length, content_length) = loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                        let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                        let content_length = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("content-length:"))
+                            .map_or(0, |length| length.trim().parse().unwrap());
+                        break (end + 4, content_length);
+                    }
+                };
+                while request.len() < head_length + content_length {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+
+                let head = String::from_utf8_lossy(&request[..head_length]).to_string();
+                requests.lock().unwrap().push(MockRequest {
+                    path: head.split_whitespace().nth(1).unwrap().to_string(),
+                    head: head.to_lowercase(),
+                    body: request[head_length..].to_vec(),
+                });
+
+                let status = statuses.next().unwrap_or(200);
+                let response = format!("HTTP/1.1 {status} Mock\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (endpoint, received)
+    }
+
+    pub(crate) fn settings(endpoint: String, spool_dir: Option<PathBuf>) -> TelemetrySettings {
+        TelemetrySettings {
+            hostname: endpoint,
+            max_retries: 2,
+            retry_backoff_ms: 1,
+            spool_dir,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn client(settings: TelemetrySettings, health: &ExporterHealth) -> ExportClient {
+        ExportClient::new(&settings, build_exporter(&settings), ExportStats::new(health.clone(), &global::meter("telemetry")))
+    }
+
+    fn spool_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("telemetry-spool-{}", rand_suffix()))
+    }
+
+    fn rand_suffix() -> u128 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let (endpoint, received) = mock_collector(vec![503, 502]).await;
+        let health = ExporterHealth::default();
+        let client = client(settings(endpoint, None), &health);
+
+        client.export(Signal::Traces, b"spans".to_vec(), 3).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        assert!(received.iter().all(|request| request.path == "/v1/traces" && request.body == b"spans"));
+        let state = health.state();
+        assert_eq!(state.exported, 3);
+        assert_eq!(state.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejected() {
+        let (endpoint, received) = mock_collector(vec![400]).await;
+        let health = ExporterHealth::default();
+        let client = client(settings(endpoint, None), &health);
+
+        client.export(Signal::Metrics, b"metrics".to_vec(), 2).await;
+
+        assert_eq!(received.lock().unwrap().len(), 1);
+        let state = health.state();
+        assert_eq!(state.dropped, 2);
+        assert_eq!(state.consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_spool_replay() {
+        // The three attempts of the first export fail
+        let (endpoint, received) = mock_collector(vec![503, 503, 503]).await;
+        let dir = spool_dir();
+        let health = ExporterHealth::default();
+        let client = client(settings(endpoint, Some(dir.clone())), &health);
+
+        client.export(Signal::Traces, b"first".to_vec(), 1).await;
+        let spool = Spool::open(dir.clone(), u64::MAX).unwrap();
+        assert_eq!(spool.pending().unwrap().len(), 1);
+        assert_eq!(health.state().spooled, 1);
+
+        client.export(Signal::Metrics, b"second".to_vec(), 1).await;
+
+        assert!(spool.pending().unwrap().is_empty());
+        let bodies: Vec<(String, Vec<u8>)> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .skip(3)
+            .map(|request| (request.path.clone(), request.body.clone()))
+            .collect();
+        assert_eq!(
+            bodies,
+            vec![
+                ("/v1/metrics".to_string(), b"second".to_vec()),
+                ("/v1/traces".to_string(), b"first".to_vec()),
+            ]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_spool_limit() {
+        let dir = spool_dir();
+        let spool = Spool::open(dir.clone(), 10).unwrap();
+
+        spool.store(Signal::Traces, b"123456").unwrap();
+        spool.store(Signal::Metrics, b"abcdef").unwrap();
+
+        let pending = spool.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].signal, Signal::Metrics);
+        assert_eq!(std::fs::read(&pending[0].path).unwrap(), b"abcdef");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use opentelemetry_proto::tonic::collector::logs::v1::logs_service_client::LogsServiceClient;
+use opentelemetry_proto::tonic::collector::logs::v1::ExportLogsServiceRequest;
+use opentelemetry_proto::tonic::collector::metrics::v1::metrics_service_client::MetricsServiceClient;
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::collector::trace::v1::trace_service_client::TraceServiceClient;
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use prost::Message;
+use tokio::sync::Mutex;
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tonic::{Code, Request, Status};
+
+use super::{Exporter, Failure, Signal};
+
+/// Calls the trace, metrics and logs services of an OTLP/gRPC endpoint.
+pub struct OtlpGrpcExporter {
+    endpoint: String,
+    metadata: MetadataMap,
+    // Connected on the first export, the channel reconnects by itself afterwards
+    channel: Mutex<Option<Channel>>,
+}
+
+impl OtlpGrpcExporter {
+    pub fn new(endpoint: String, headers: &BTreeMap<String, String>) -> Self {
+        let mut metadata = MetadataMap::new();
+        for (name, value) in headers {
+            match (
+                MetadataKey::from_bytes(name.to_lowercase().as_bytes()),
+                MetadataValue::try_from(value.as_str()),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    metadata.insert(name, value);
+                }
+                _ => tracing::warn!("Ignoring the invalid telemetry header {name:?}"),
+            }
+        }
+
+        Self {
+            endpoint,
+            metadata,
+            channel: Mutex::new(None),
+        }
+    }
+
+    async fn channel(&self) -> Result<Channel, Failure> {
+        let mut channel = self.channel.lock().await;
+        if let Some(channel) = channel.as_ref() {
+            return Ok(channel.clone());
+        }
+
+        let mut endpoint = Endpoint::from_shared(self.endpoint.clone())
+            .map_err(|err| Failure::Rejected(err.to_string()))?
+            .timeout(Duration::from_secs(10));
+        if self.endpoint.starts_with("https://") {
+            endpoint = endpoint
+                .tls_config(ClientTlsConfig::new())
+                .map_err(|err| Failure::Rejected(err.to_string()))?;
+        }
+        let connected = endpoint
+            .connect()
+            .await
+            .map_err(|err| Failure::Unreachable(err.to_string()))?;
+        *channel = Some(connected.clone());
+        Ok(connected)
+    }
+
+    fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        *request.metadata_mut() = self.metadata.clone();
+        request
+    }
+}
+
+#[async_trait]
+impl Exporter for OtlpGrpcExporter {
+    fn target(&self) -> &str {
+        &self.endpoint
+    }
+
+    async fn send(&self, signal: Signal, body: &[u8]) -> Result<(), Failure> {
+        let channel = self.channel().await?;
+        let result = match signal {
+            Signal::Traces => {
+                let request = ExportTraceServiceRequest::decode(body).map_err(|err| Failure::Rejected(err.to_string()))?;
+                TraceServiceClient::new(channel).export(self.request(request)).await.map(|_| ())
+            }
+            Signal::Metrics => {
+                let request =
+                    ExportMetricsServiceRequest::decode(body).map_err(|err| Failure::Rejected(err.to_string()))?;
+                MetricsServiceClient::new(channel).export(self.request(request)).await.map(|_| ())
+            }
+            Signal::Logs => {
+                let request = ExportLogsServiceRequest::decode(body).map_err(|err| Failure::Rejected(err.to_string()))?;
+                LogsServiceClient::new(channel).export(self.request(request)).await.map(|_| ())
+            }
+        };
+        result.map_err(failure)
+    }
+}
+
+// failure tells apart the statuses worth retrying, as listed by the OTLP specification
+fn failure(status: Status) -> Failure {
+    let message = format!("{:?}: {}", status.code(), status.message());
+    match status.code() {
+        Code::Cancelled
+        | Code::DeadlineExceeded
+        | Code::ResourceExhausted
+        | Code::Aborted
+        | Code::OutOfRange
+        | Code::Unavailable
+        | Code::DataLoss => Failure::Unreachable(message),
+        _ => Failure::Rejected(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use opentelemetry_proto::tonic::collector::trace::v1::trace_service_server::{TraceService, TraceServiceServer};
+    use opentelemetry_proto::tonic::collector::trace::v1::{ExportTraceServiceRequest, ExportTraceServiceResponse};
+    use prost::Message;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+    use tonic::{Request, Response, Status};
+
+    use crate::export::tests::{client, settings};
+    use crate::export::Signal;
+    use crate::health::ExporterHealth;
+    use crate::settings::ExporterKind;
+
+    #[derive(Clone, Default)]
+    struct MockTraceService {
+        // The authorization metadata of every request
+        received: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    #[tonic::async_trait]
+    impl TraceService for MockTraceService {
+        async fn export(
+            &self,
+            request: Request<ExportTraceServiceRequest>,
+        ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+            let authorization = request
+                .metadata()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            self.received.lock().unwrap().push(authorization);
+            Ok(Response::new(ExportTraceServiceResponse { partial_success: None }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let service = MockTraceService::default();
+        tokio::spawn(
+            Server::builder()
+                .add_service(TraceServiceServer::new(service.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut settings = settings(endpoint, None);
+        settings.exporter = ExporterKind::Grpc;
+        settings.headers.insert("Authorization".to_string(), "Bearer secret".to_string());
+        let health = ExporterHealth::default();
+
+        let body = ExportTraceServiceRequest { resource_spans: Vec::new() }.encode_to_vec();
+        client(settings, &health).export(Signal::Traces, body, 1).await;
+
+        assert_eq!(*service.received.lock().unwrap(), vec![Some("Bearer secret".to_string())]);
+        assert_eq!(health.state().exported, 1);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, ClientBuilder, StatusCode};
+
+use super::{json, Exporter, Failure, Signal};
+
+/// How the payloads are encoded in the body of the requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HttpEncoding {
+    Protobuf,
+    Json,
+}
+
+/// Posts the payloads to the `/v1/traces` and `/v1/metrics` paths of an OTLP/HTTP endpoint.
+pub struct OtlpHttpExporter {
+    client: Client,
+    endpoint: String,
+    encoding: HttpEncoding,
+}
+
+impl OtlpHttpExporter {
+    pub fn new(endpoint: String, headers: &BTreeMap<String, String>, encoding: HttpEncoding) -> Self {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+                (Ok(name), Ok(value)) => {
+                    header_map.insert(name, value);
+                }
+                _ => tracing::warn!("Ignoring the invalid telemetry header {name:?}"),
+            }
+        }
+
+        let client = ClientBuilder::new()
+            .timeout(Duration::from_secs(10))
+            .default_headers(header_map)
+            .build()
+            .unwrap();
+
+        Self {
+            client,
+            endpoint,
+            encoding,
+        }
+    }
+}
+
+#[async_trait]
+impl Exporter for OtlpHttpExporter {
+    fn target(&self) -> &str {
+        &self.endpoint
+    }
+
+    async fn send(&self, signal: Signal, body: &[u8]) -> Result<(), Failure> {
+        let (body, content_type) = match self.encoding {
+            HttpEncoding::Protobuf => (body.to_vec(), "application/x-protobuf"),
+            HttpEncoding::Json => {
+                let json = json::encode(signal, body).map_err(Failure::Rejected)?;
+                (json.into_bytes(), "application/json")
+            }
+        };
+
+        let response = self
+            .client
+            .post(format!("{}{}", self.endpoint, signal.path()))
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| Failure::Unreachable(err.to_string()))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status @ (StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT) => Err(Failure::Unreachable(status.to_string())),
+            status => Err(Failure::Rejected(status.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use opentelemetry_proto::tonic::trace::v1::{ResourceSpans, ScopeSpans, Span};
+    use prost::Message;
+
+    use crate::export::tests::{client, mock_collector, settings};
+    use crate::export::Signal;
+    use crate::health::ExporterHealth;
+    use crate::settings::ExporterKind;
+
+    #[tokio::test]
+    async fn test_json_with_headers() {
+        let (endpoint, received) = mock_collector(Vec::new()).await;
+        let mut settings = settings(endpoint, None);
+        settings.exporter = ExporterKind::HttpJson;
+        settings.headers.insert("Authorization".to_string(), "Bearer secret".to_string());
+        let health = ExporterHealth::default();
+
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                scope_spans: vec![ScopeSpans {
+                    spans: vec![Span {
+                        trace_id: vec![0xab; 16],
+                        name: "GET /health/live".to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        client(settings, &health).export(Signal::Traces, request.encode_to_vec(), 1).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].head.contains("content-type: application/json"));
+        assert!(received[0].head.contains("authorization: bearer secret"));
+        let body: serde_json::Value = serde_json::from_slice(&received[0].body).unwrap();
+        let span = &body["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "ab".repeat(16));
+        assert_eq!(span["name"], "GET /health/live");
+    }
+}
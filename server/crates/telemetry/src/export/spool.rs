@@ -0,0 +1,84 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Signal;
+
+/// Stores the payloads that could not be exported, to send them again once the endpoint is back.
+///
+/// Each payload is a file named after its signal and its creation time, so that they are replayed in order.
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    sequence: AtomicU64,
+}
+
+/// A payload waiting in the spool.
+pub struct SpooledPayload {
+    pub path: PathBuf,
+    pub signal: Signal,
+}
+
+impl Spool {
+    pub fn open(dir: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_bytes,
+            sequence: AtomicU64::new(0),
+        })
+    }
+
+    /// Stores the payload, then removes the oldest ones while the spool is too large
+    pub fn store(&self, signal: Signal, body: &[u8]) -> io::Result<()> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let name = format!("{millis:020}-{sequence:010}-{}.bin", signal.name());
+        fs::write(self.dir.join(name), body)?;
+        self.trim()
+    }
+
+    /// Returns the stored payloads, the oldest first
+    pub fn pending(&self) -> io::Result<Vec<SpooledPayload>> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "bin"))
+            .collect();
+        paths.sort();
+        Ok(paths
+            .into_iter()
+            .filter_map(|path| {
+                let signal = signal_of(&path)?;
+                Some(SpooledPayload { path, signal })
+            })
+            .collect())
+    }
+
+    fn trim(&self) -> io::Result<()> {
+        let mut payloads = Vec::new();
+        for payload in self.pending()? {
+            let size = fs::metadata(&payload.path)?.len();
+            payloads.push((payload.path, size));
+        }
+
+        let mut total: u64 = payloads.iter().map(|(_, size)| size).sum();
+        for (path, size) in payloads {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+}
+
+fn signal_of(path: &Path) -> Option<Signal> {
+    let stem = path.file_stem()?.to_str()?;
+    Signal::from_name(stem.rsplit('-').next()?)
+}
@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+
+use super::{json, Exporter, Failure, Signal};
+
+/// Prints the payloads as OTLP JSON, to look at the telemetry without a collector.
+pub struct StdoutExporter;
+
+#[async_trait]
+impl Exporter for StdoutExporter {
+    fn target(&self) -> &str {
+        "stdout"
+    }
+
+    async fn send(&self, signal: Signal, body: &[u8]) -> Result<(), Failure> {
+        let json = json::encode_pretty(signal, body).map_err(Failure::Rejected)?;
+        println!("{json}");
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+/// The outcome of the last exports, shared between the workers and the health checks.
+#[derive(Clone, Debug, Default)]
+pub struct ExporterHealth {
+    state: Arc<Mutex<ExporterState>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ExporterState {
+    // Whether the exporters were started, they are not when the telemetry is disabled
+    pub started: bool,
+    // The number of exports that failed since the last successful one
+    pub consecutive_failures: u32,
+    // The error of the last failed export
+    pub last_error: Option<String>,
+    // The number of spans and metrics exported
+    pub exported: u64,
+    // The number of spans and metrics lost, because the queue was full or the endpoint rejected them
+    pub dropped: u64,
+    // The number of spans and metrics stored in the spool while the endpoint was unreachable
+    pub spooled: u64,
+}
+
+impl ExporterHealth {
+    pub fn state(&self) -> ExporterState {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub(crate) fn mark_started(&self) {
+        self.state.lock().unwrap().started = true;
+    }
+
+    pub(crate) fn count(&self, update: impl FnOnce(&mut ExporterState)) {
+        update(&mut self.state.lock().unwrap());
+    }
+
+    pub(crate) fn record(&self, result: Result<(), String>) {
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Ok(()) => state.consecutive_failures = 0,
+            Err(err) => {
+                state.consecutive_failures += 1;
+                state.last_error = Some(err);
+            }
+        }
+    }
+}
@@ -1,15 +1,85 @@
+mod export;
+mod health;
+mod logging;
+mod prometheus;
+mod propagation;
 mod provider;
 mod worker;
 mod settings;
 
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use opentelemetry::metrics::{Meter, MeterProvider as _};
+use opentelemetry_sdk::metrics::MeterProvider;
+
+use crate::export::{build_exporter, ExportClient, ExportStats};
 use crate::provider::{SigNozMeter, SigNozTracer};
-use crate::worker::TelemetryWorker;
+use crate::logging::LogQueue;
+use crate::worker::{LogWorker, MeterWorker, TelemetryWorker, TraceWorker};
+
+pub use health::{ExporterHealth, ExporterState};
+pub use logging::{init_logging, is_sensitive, log_filter, redact, REDACTED};
+pub use prometheus::{PrometheusExporter, PROMETHEUS_CONTENT_TYPE};
+pub use propagation::{context_headers, extract_context, trace_response, WithTraceContext};
+pub use settings::{ExporterKind, LogFormat, LoggingSettings, TelemetrySettings};
+
+/// The handles on the started exporters
+pub struct Telemetry {
+    // Updated after every export
+    pub health: ExporterHealth,
+    // Set when the metrics are also exposed to Prometheus
+    pub prometheus: Option<PrometheusExporter>,
+    meter_provider: MeterProvider,
+}
+
+impl Telemetry {
+    /// Returns a meter of the provider started with the exporters
+    ///
+    /// Unlike the global meter, it keeps recording to these exporters when the global provider is replaced.
+    pub fn meter(&self, name: &'static str) -> Meter {
+        self.meter_provider.meter(name)
+    }
+}
+
+/// Starts the exporters, and the Prometheus reader when `prometheus` is set
+pub fn start_telemetry(telemetry_settings: TelemetrySettings, prometheus: bool) -> Telemetry {
+    let health = ExporterHealth::default();
+    // The W3C trace context is the format of the incoming and outgoing requests
+    opentelemetry::global::set_text_map_propagator(opentelemetry_sdk::propagation::TraceContextPropagator::new());
+    // The meter provider comes first, for the exporters to count what they export
+    let (meter_provider, reader, prometheus) = SigNozMeter::setup(prometheus);
+    let stats = ExportStats::new(health.clone(), &meter_provider.meter("telemetry"));
+    let exporter = build_exporter(&telemetry_settings);
+    let client = Arc::new(ExportClient::new(&telemetry_settings, exporter, stats.clone()));
 
-pub use settings::TelemetrySettings;
+    // The logs are already written to the console, they are only exported to a collector
+    let log_worker = telemetry_settings.exporter.is_remote().then(|| {
+        let (sender, receiver) = mpsc::sync_channel(telemetry_settings.queue_size);
+        LogQueue::global().connect(sender, stats.clone());
+        LogWorker::new(
+            receiver,
+            client.clone(),
+            telemetry_settings.batch_size,
+            Duration::from_millis(telemetry_settings.batch_timeout_ms),
+        )
+    });
 
-pub fn start_telemetry(telemetry_settings: TelemetrySettings)  {
-    let trace_worker = SigNozTracer::setup(telemetry_settings.hostname.clone());
-    let meter_worker = SigNozMeter::setup(telemetry_settings.hostname.clone(), telemetry_settings.token.clone());
+    let receiver = SigNozTracer::setup(telemetry_settings.queue_size, stats);
+    let trace_worker = TraceWorker::new(
+        receiver,
+        client.clone(),
+        telemetry_settings.batch_size,
+        Duration::from_millis(telemetry_settings.batch_timeout_ms),
+    );
+    let meter_worker = MeterWorker::new(reader, client, Duration::from_millis(telemetry_settings.metrics_interval_ms));
 
-    TelemetryWorker::new(trace_worker, meter_worker).launch();
+    TelemetryWorker::new(trace_worker, meter_worker, log_worker).launch();
+    health.mark_started();
+    Telemetry {
+        health,
+        prometheus,
+        meter_provider,
+    }
 }
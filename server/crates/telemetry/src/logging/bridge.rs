@@ -0,0 +1,203 @@
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, KeyValue};
+use opentelemetry_proto::tonic::logs::v1::{LogRecord, SeverityNumber};
+use serde_json::Value;
+use tracing::{Event, Level, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use super::fields::{scope_fields, FieldCollector};
+use super::redact::redact;
+use crate::export::{ExportStats, Signal};
+
+// The events of the exporters themselves and of their HTTP and gRPC clients are not exported,
+// exporting them would log new events and so on
+const NOT_EXPORTED: [&str; 6] = ["telemetry", "hyper", "h2", "tonic", "tower", "reqwest"];
+
+// The sending end of the queue, with the stats counting the records dropped when it is full
+type Sender = (SyncSender<LogRecord>, ExportStats);
+
+/// The queue of the log records waiting for the log worker, empty until the exporters are started
+#[derive(Clone, Default)]
+pub struct LogQueue(Arc<RwLock<Option<Sender>>>);
+
+impl LogQueue {
+    /// Returns the queue the logging layer of the process sends its records to
+    pub fn global() -> &'static LogQueue {
+        static QUEUE: OnceLock<LogQueue> = OnceLock::new();
+        QUEUE.get_or_init(LogQueue::default)
+    }
+
+    /// Starts sending the log records to `sender`, the records are dropped when it is full
+    pub fn connect(&self, sender: SyncSender<LogRecord>, stats: ExportStats) {
+        *self.0.write().unwrap() = Some((sender, stats));
+    }
+
+    fn send(&self, record: LogRecord) {
+        let queue = self.0.read().unwrap();
+        let Some((sender, stats)) = queue.as_ref() else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = sender.try_send(record) {
+            stats.dropped(Signal::Logs, 1);
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.0.read().unwrap().is_some()
+    }
+}
+
+/// Turns the events into OpenTelemetry log records, for the log worker to export them
+///
+/// The records carry the fields of the event and of its spans as attributes, and the trace context of the
+/// request when there is one, for the logs to be found from the traces.
+pub struct OtelLogLayer {
+    queue: LogQueue,
+}
+
+impl OtelLogLayer {
+    pub fn new(queue: LogQueue) -> Self {
+        Self { queue }
+    }
+}
+
+impl<S> Layer<S> for OtelLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !self.queue.is_connected() {
+            return;
+        }
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        if NOT_EXPORTED.iter().any(|target| metadata.target().starts_with(target)) {
+            return;
+        }
+
+        let mut fields = FieldCollector::default();
+        event.record(&mut fields);
+        let body = fields.take_message().map(any_value);
+        let mut attributes = vec![KeyValue {
+            key: "code.namespace".to_string(),
+            value: Some(any_value(Value::from(metadata.target()))),
+        }];
+        attributes.extend(
+            scope_fields(ctx.event_scope(event))
+                .into_iter()
+                .chain(fields.0)
+                .map(|(key, value)| KeyValue {
+                    key,
+                    value: Some(any_value(value)),
+                }),
+        );
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        let (severity_number, severity_text) = severity(metadata.level());
+        let mut record = LogRecord {
+            time_unix_nano: time,
+            observed_time_unix_nano: time,
+            severity_number: severity_number as i32,
+            severity_text: severity_text.to_string(),
+            body,
+            attributes,
+            ..Default::default()
+        };
+
+        // The handlers of the routes run within the OpenTelemetry context of their request
+        let context = opentelemetry::Context::current();
+        let span_context = context.span().span_context().clone();
+        if span_context.is_valid() {
+            record.trace_id = span_context.trace_id().to_bytes().to_vec();
+            record.span_id = span_context.span_id().to_bytes().to_vec();
+            record.flags = u32::from(span_context.trace_flags().to_u8());
+        }
+
+        self.queue.send(record);
+    }
+}
+
+fn severity(level: &Level) -> (SeverityNumber, &'static str) {
+    match *level {
+        Level::TRACE => (SeverityNumber::Trace, "TRACE"),
+        Level::DEBUG => (SeverityNumber::Debug, "DEBUG"),
+        Level::INFO => (SeverityNumber::Info, "INFO"),
+        Level::WARN => (SeverityNumber::Warn, "WARN"),
+        Level::ERROR => (SeverityNumber::Error, "ERROR"),
+    }
+}
+
+// any_value converts a field, the strings are redacted as the log lines are
+fn any_value(value: Value) -> AnyValue {
+    let value = match value {
+        Value::Bool(value) => any_value::Value::BoolValue(value),
+        Value::Number(number) => match (number.as_i64(), number.as_f64()) {
+            (Some(value), _) => any_value::Value::IntValue(value),
+            (None, Some(value)) => any_value::Value::DoubleValue(value),
+            (None, None) => any_value::Value::StringValue(number.to_string()),
+        },
+        Value::String(value) => any_value::Value::StringValue(redact(&value).into_owned()),
+        value => any_value::Value::StringValue(redact(&value.to_string()).into_owned()),
+    };
+    AnyValue { value: Some(value) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use opentelemetry::global;
+    use opentelemetry_proto::tonic::common::v1::any_value::Value;
+    use opentelemetry_proto::tonic::logs::v1::{LogRecord, SeverityNumber};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::{LogQueue, OtelLogLayer};
+    use crate::export::ExportStats;
+    use crate::health::ExporterHealth;
+    use crate::logging::fields::SpanFieldsLayer;
+
+    fn attribute<'a>(record: &'a LogRecord, key: &str) -> Option<&'a Value> {
+        let attribute = record.attributes.iter().find(|attribute| attribute.key == key)?;
+        attribute.value.as_ref()?.value.as_ref()
+    }
+
+    #[test]
+    fn test_log_records() {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let queue = LogQueue::default();
+        queue.connect(sender, ExportStats::new(ExporterHealth::default(), &global::meter("test")));
+        let subscriber = tracing_subscriber::registry()
+            .with(SpanFieldsLayer)
+            .with(OtelLogLayer::new(queue));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = "abc");
+            let _entered = span.enter();
+            // The events of the telemetry crate are not exported
+            tracing::warn!("not exported");
+            tracing::warn!(target: "api", user = 42, password = "hunter2", "login with token=xyz");
+            // The queue is full, the record is dropped
+            tracing::warn!(target: "api", "dropped");
+        });
+
+        let record = receiver.try_recv().unwrap();
+        assert_eq!(record.severity_number, SeverityNumber::Warn as i32);
+        assert_eq!(
+            record.body.as_ref().and_then(|body| body.value.as_ref()),
+            Some(&Value::StringValue("login with token=[REDACTED]".to_string()))
+        );
+        assert_eq!(attribute(&record, "request_id"), Some(&Value::StringValue("abc".to_string())));
+        assert_eq!(attribute(&record, "user"), Some(&Value::IntValue(42)));
+        assert_eq!(attribute(&record, "password"), Some(&Value::StringValue("[REDACTED]".to_string())));
+        assert!(record.trace_id.is_empty());
+        assert!(receiver.try_recv().is_err());
+    }
+}
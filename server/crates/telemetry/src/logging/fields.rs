@@ -0,0 +1,102 @@
+use std::fmt::Debug;
+
+use serde_json::Value;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Record};
+use tracing::{Id, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::{LookupSpan, Scope};
+
+use super::redact::{is_sensitive, REDACTED};
+
+/// Collects the fields of an event or a span, with the values of the sensitive fields redacted
+#[derive(Default)]
+pub(crate) struct FieldCollector(pub Vec<(String, Value)>);
+
+impl FieldCollector {
+    fn push(&mut self, field: &Field, value: Value) {
+        // The `log` records carry their metadata as fields, it is read from the normalized metadata instead
+        if field.name().starts_with("log.") {
+            return;
+        }
+        let value = if is_sensitive(field.name()) { Value::from(REDACTED) } else { value };
+        self.0.retain(|(name, _)| name != field.name());
+        self.0.push((field.name().to_string(), value));
+    }
+
+    /// Removes the `message` field and returns it
+    pub fn take_message(&mut self) -> Option<Value> {
+        let index = self.0.iter().position(|(name, _)| name == "message")?;
+        Some(self.0.remove(index).1)
+    }
+}
+
+impl Visit for FieldCollector {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.push(field, Value::from(format!("{value:?}")));
+    }
+}
+
+/// The fields of a span, kept in its extensions for the events within it to carry them, e.g. the request id
+#[derive(Default)]
+pub(crate) struct SpanFields(pub FieldCollector);
+
+/// Keeps the fields of the spans, for the JSON lines and the exported log records
+pub(crate) struct SpanFieldsLayer;
+
+impl<S> Layer<S> for SpanFieldsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = SpanFields::default();
+        attributes.record(&mut fields.0);
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<SpanFields>() {
+            values.record(&mut fields.0);
+        }
+    }
+}
+
+/// Returns the fields of the spans enclosing the event, the innermost last
+pub(crate) fn scope_fields<'a, R: LookupSpan<'a>>(scope: Option<Scope<'a, R>>) -> Vec<(String, Value)> {
+    let mut fields = Vec::new();
+    if let Some(scope) = scope {
+        for span in scope.from_root() {
+            if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+                fields.extend(span_fields.0 .0.iter().cloned());
+            }
+        }
+    }
+    fields
+}
@@ -0,0 +1,42 @@
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tracing::{Event, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+
+use super::fields::{scope_fields, FieldCollector};
+
+/// Writes the events as JSON objects, one per line, holding the `timestamp`, the `level`, the `target`,
+/// the `message` and the other fields of the event, and the fields of its spans such as the `request_id`
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), Value::from(timestamp));
+        line.insert("level".to_string(), Value::from(metadata.level().as_str()));
+        line.insert("target".to_string(), Value::from(metadata.target()));
+
+        // The fields of the event win over the ones of its spans, and the inner spans over the outer ones
+        let mut fields = FieldCollector::default();
+        event.record(&mut fields);
+        for (name, value) in scope_fields(ctx.event_scope()).into_iter().chain(fields.0) {
+            line.insert(name, value);
+        }
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
@@ -0,0 +1,152 @@
+mod bridge;
+mod fields;
+mod format;
+mod redact;
+
+use std::io::{self, Write};
+
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::settings::{LogFormat, LoggingSettings};
+
+pub use bridge::{LogQueue, OtelLogLayer};
+pub use format::JsonFormat;
+pub use redact::{is_sensitive, redact, REDACTED};
+
+use fields::SpanFieldsLayer;
+
+/// Validates the levels of the settings, e.g. `info,rocket=warn`
+pub fn log_filter(settings: &LoggingSettings) -> Result<EnvFilter, String> {
+    EnvFilter::builder()
+        .parse(settings.directives())
+        .map_err(|err| format!("invalid log levels {:?}: {err}", settings.directives()))
+}
+
+/// Installs the logger of the process, writing the events and the `log` records of the dependencies to stdout
+///
+/// The events are also exported as OpenTelemetry logs once `start_telemetry` starts a remote exporter.
+pub fn init_logging(settings: &LoggingSettings) -> Result<(), String> {
+    let filter = log_filter(settings)?;
+    // The colors are left out, they would stand between the names of the fields and their values
+    let (json, pretty) = match settings.format {
+        LogFormat::Json => (
+            Some(tracing_subscriber::fmt::layer().event_format(JsonFormat).with_writer(RedactingStdout)),
+            None,
+        ),
+        LogFormat::Pretty => (
+            None,
+            Some(tracing_subscriber::fmt::layer().pretty().with_ansi(false).with_writer(RedactingStdout)),
+        ),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(SpanFieldsLayer)
+        .with(json)
+        .with(pretty)
+        .with(OtelLogLayer::new(LogQueue::global().clone()))
+        .try_init()
+        .map_err(|err| err.to_string())
+}
+
+/// Writes the log lines to stdout, with their secrets redacted
+pub struct RedactingStdout;
+
+impl<'a> MakeWriter<'a> for RedactingStdout {
+    type Writer = RedactingWriter<io::Stdout>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(io::stdout())
+    }
+}
+
+/// Redacts the secrets of the lines written to the inner writer
+///
+/// The formatters write each event at once, so a secret is never split between two writes.
+pub struct RedactingWriter<W>(pub W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+    use tracing_subscriber::fmt::MakeWriter;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::{log_filter, JsonFormat, RedactingWriter, SpanFieldsLayer};
+    use crate::settings::LoggingSettings;
+
+    #[derive(Clone, Default)]
+    struct Lines(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Lines {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Lines {
+        type Writer = RedactingWriter<Lines>;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            RedactingWriter(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_log_filter() {
+        let mut settings = LoggingSettings::default();
+        settings.modules.insert("rocket".to_string(), "warn".to_string());
+        assert_eq!(settings.directives(), "info,rocket=warn");
+        assert!(log_filter(&settings).is_ok());
+
+        settings.modules.insert("api".to_string(), "loud".to_string());
+        assert!(log_filter(&settings).is_err());
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let lines = Lines::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(log_filter(&LoggingSettings::default()).unwrap())
+            .with(SpanFieldsLayer)
+            .with(tracing_subscriber::fmt::layer().event_format(JsonFormat).with_writer(lines.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = "abc", method = "GET");
+            let _entered = span.enter();
+            tracing::debug!("filtered out");
+            tracing::info!(status = 200, authorization = "Bearer xyz", "request completed with password=hunter2");
+        });
+
+        let output = String::from_utf8(lines.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["request_id"], "abc");
+        assert_eq!(line["method"], "GET");
+        assert_eq!(line["status"], 200);
+        assert_eq!(line["authorization"], "[REDACTED]");
+        assert_eq!(line["message"], "request completed with password=[REDACTED]");
+    }
+}
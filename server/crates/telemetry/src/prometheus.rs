@@ -0,0 +1,213 @@
+use std::{fmt::Write, sync::Arc};
+
+use opentelemetry::metrics::Result;
+use opentelemetry::{Key, Value};
+use opentelemetry_sdk::metrics::data::{DataPoint, Gauge, Histogram, HistogramDataPoint, Metric, ResourceMetrics, Sum};
+use opentelemetry_sdk::metrics::reader::MetricReader;
+use opentelemetry_sdk::metrics::ManualReader;
+use opentelemetry_sdk::Resource;
+
+/// The content type of the Prometheus text exposition format.
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Renders the metrics of the meter provider in the Prometheus text exposition format, when scraped.
+///
+/// It reads the same instruments as the OTLP exporter, with a cumulative temporality.
+#[derive(Clone, Debug)]
+pub struct PrometheusExporter {
+    reader: Arc<ManualReader>,
+}
+
+impl PrometheusExporter {
+    pub(crate) fn new(reader: Arc<ManualReader>) -> Self {
+        Self { reader }
+    }
+
+    pub fn render(&self) -> Result<String> {
+        let mut metrics = ResourceMetrics {
+            resource: Resource::default(),
+            scope_metrics: Vec::new(),
+        };
+        self.reader.collect(&mut metrics)?;
+        Ok(encode(&metrics))
+    }
+}
+
+// encode writes every metric, the aggregations Prometheus cannot represent are skipped
+fn encode(metrics: &ResourceMetrics) -> String {
+    let mut output = String::new();
+    for metric in metrics.scope_metrics.iter().flat_map(|scope| &scope.metrics) {
+        let name = sanitize_name(&metric.name);
+        let data = metric.data.as_any();
+
+        if let Some(sum) = data.downcast_ref::<Sum<u64>>() {
+            encode_sum(&mut output, metric, &name, sum);
+        } else if let Some(sum) = data.downcast_ref::<Sum<i64>>() {
+            encode_sum(&mut output, metric, &name, sum);
+        } else if let Some(sum) = data.downcast_ref::<Sum<f64>>() {
+            encode_sum(&mut output, metric, &name, sum);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<u64>>() {
+            encode_gauge(&mut output, metric, &name, gauge);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<i64>>() {
+            encode_gauge(&mut output, metric, &name, gauge);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<f64>>() {
+            encode_gauge(&mut output, metric, &name, gauge);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<u64>>() {
+            encode_histogram(&mut output, metric, &name, histogram);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<i64>>() {
+            encode_histogram(&mut output, metric, &name, histogram);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<f64>>() {
+            encode_histogram(&mut output, metric, &name, histogram);
+        }
+    }
+    output
+}
+
+trait SampleValue: Copy {
+    fn render(self) -> String;
+}
+
+impl SampleValue for u64 {
+    fn render(self) -> String {
+        self.to_string()
+    }
+}
+
+impl SampleValue for i64 {
+    fn render(self) -> String {
+        self.to_string()
+    }
+}
+
+impl SampleValue for f64 {
+    fn render(self) -> String {
+        match self {
+            value if value.is_nan() => "NaN".to_string(),
+            value if value == f64::INFINITY => "+Inf".to_string(),
+            value if value == f64::NEG_INFINITY => "-Inf".to_string(),
+            value => value.to_string(),
+        }
+    }
+}
+
+fn encode_sum<T: SampleValue>(output: &mut String, metric: &Metric, name: &str, sum: &Sum<T>) {
+    // Only the monotonic sums are counters, the others can go down
+    let (name, kind) = match sum.is_monotonic {
+        true if name.ends_with("_total") => (name.to_string(), "counter"),
+        true => (format!("{name}_total"), "counter"),
+        false => (name.to_string(), "gauge"),
+    };
+    write_header(output, &name, &metric.description, kind);
+    write_points(output, &name, &sum.data_points);
+}
+
+fn encode_gauge<T: SampleValue>(output: &mut String, metric: &Metric, name: &str, gauge: &Gauge<T>) {
+    write_header(output, name, &metric.description, "gauge");
+    write_points(output, name, &gauge.data_points);
+}
+
+fn encode_histogram<T: SampleValue>(output: &mut String, metric: &Metric, name: &str, histogram: &Histogram<T>) {
+    write_header(output, name, &metric.description, "histogram");
+    for point in &histogram.data_points {
+        write_histogram_point(output, name, point);
+    }
+}
+
+fn write_header(output: &mut String, name: &str, description: &str, kind: &str) {
+    if !description.is_empty() {
+        let _ = writeln!(output, "# HELP {name} {}", description.replace('\\', "\\\\").replace('\n', "\\n"));
+    }
+    let _ = writeln!(output, "# TYPE {name} {kind}");
+}
+
+fn write_points<T: SampleValue>(output: &mut String, name: &str, points: &[DataPoint<T>]) {
+    for point in points {
+        let labels = labels(point.attributes.iter(), None);
+        let _ = writeln!(output, "{name}{labels} {}", point.value.render());
+    }
+}
+
+fn write_histogram_point<T: SampleValue>(output: &mut String, name: &str, point: &HistogramDataPoint<T>) {
+    // The bucket counts of OpenTelemetry are per bucket, the ones of Prometheus are cumulative
+    let mut cumulative = 0;
+    for (index, count) in point.bucket_counts.iter().enumerate() {
+        cumulative += count;
+        let bound = point.bounds.get(index).map_or("+Inf".to_string(), |bound| bound.render());
+        let labels = labels(point.attributes.iter(), Some(&bound));
+        let _ = writeln!(output, "{name}_bucket{labels} {cumulative}");
+    }
+    let labels = labels(point.attributes.iter(), None);
+    let _ = writeln!(output, "{name}_sum{labels} {}", point.sum.render());
+    let _ = writeln!(output, "{name}_count{labels} {}", point.count);
+}
+
+fn labels<'a>(attributes: impl Iterator<Item = (&'a Key, &'a Value)>, le: Option<&str>) -> String {
+    let mut labels: Vec<String> = attributes
+        .map(|(key, value)| format!("{}=\"{}\"", sanitize_label(key.as_str()), escape(&value.as_str())))
+        .collect();
+    if let Some(le) = le {
+        labels.push(format!("le=\"{le}\""));
+    }
+    match labels.is_empty() {
+        true => String::new(),
+        false => format!("{{{}}}", labels.join(",")),
+    }
+}
+
+// sanitize_name replaces the characters a metric name cannot contain, such as the dots of OpenTelemetry names
+fn sanitize_name(name: &str) -> String {
+    sanitize(name, |c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+fn sanitize_label(name: &str) -> String {
+    sanitize(name, |c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn sanitize(name: &str, allowed: impl Fn(char) -> bool) -> String {
+    let sanitized: String = name.chars().map(|c| if allowed(c) { c } else { '_' }).collect();
+    match sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("_{sanitized}"),
+        false => sanitized,
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use opentelemetry::metrics::MeterProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::metrics::{ManualReader, MeterProvider};
+
+    use super::PrometheusExporter;
+    use crate::provider::SharedReader;
+
+    #[test]
+    fn test_render() {
+        let reader = Arc::new(ManualReader::builder().build());
+        let provider = MeterProvider::builder()
+            .with_reader(SharedReader(reader.clone()))
+            .build();
+        let meter = provider.meter("test");
+        let counter = meter
+            .u64_counter("http.requests")
+            .with_description("The handled requests")
+            .init();
+        let histogram = meter.f64_histogram("http.duration").init();
+        counter.add(2, &[KeyValue::new("route", "/user/\"get\"")]);
+        histogram.record(0.3, &[]);
+
+        let output = PrometheusExporter::new(reader).render().unwrap();
+
+        assert!(output.contains("# HELP http_requests_total The handled requests\n"));
+        assert!(output.contains("# TYPE http_requests_total counter\n"));
+        assert!(output.contains("http_requests_total{route=\"/user/\\\"get\\\"\"} 2\n"));
+        assert!(output.contains("# TYPE http_duration histogram\n"));
+        assert!(output.contains("http_duration_bucket{le=\"+Inf\"} 1\n"));
+        assert!(output.contains("http_duration_count 1\n"));
+    }
+}
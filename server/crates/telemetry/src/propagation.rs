@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::RequestBuilder;
+
+/// Reads the W3C trace context, `traceparent` and `tracestate`, of the incoming headers.
+///
+/// The context is empty when the headers are missing or malformed, the span then starts a new trace.
+pub fn extract_context<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Context {
+    let headers: HashMap<String, &str> = headers
+        .into_iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value))
+        .collect();
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+/// Returns the W3C trace context headers of the span of the given context, empty without a span.
+pub fn context_headers(context: &Context) -> Vec<(String, String)> {
+    let mut headers = HashMap::new();
+    TraceContextPropagator::new().inject_context(context, &mut headers);
+    headers.into_iter().collect()
+}
+
+/// Returns the `traceresponse` header value of the span of the given context, which tells the caller
+/// the trace it was recorded in.
+pub fn trace_response(context: &Context) -> Option<String> {
+    let span_context = context.span().span_context().clone();
+    span_context.is_valid().then(|| {
+        format!(
+            "00-{}-{}-{:02x}",
+            span_context.trace_id(),
+            span_context.span_id(),
+            span_context.trace_flags().to_u8()
+        )
+    })
+}
+
+/// Adds the trace context to outbound requests, so that the services they call join the trace.
+pub trait WithTraceContext {
+    fn with_trace_context(self, context: &Context) -> Self;
+}
+
+impl WithTraceContext for RequestBuilder {
+    fn with_trace_context(self, context: &Context) -> Self {
+        let mut headers = HeaderMap::new();
+        TraceContextPropagator::new().inject_context(context, &mut HeaderInjector(&mut headers));
+        self.headers(headers)
+    }
+}
+
+struct HeaderExtractor<'a>(HashMap<String, &'a str>);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).copied()
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::TraceContextExt;
+
+    use super::{context_headers, extract_context, trace_response};
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_round_trip() {
+        let context = extract_context([("Traceparent", TRACEPARENT), ("tracestate", "vendor=value")]);
+
+        let span_context = context.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(span_context.trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(trace_response(&context).as_deref(), Some(TRACEPARENT));
+        let headers = context_headers(&context);
+        assert!(headers.contains(&("traceparent".to_string(), TRACEPARENT.to_string())));
+        assert!(headers.contains(&("tracestate".to_string(), "vendor=value".to_string())));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let context = extract_context([("traceparent", "00-not-a-trace-01")]);
+
+        assert!(!context.span().span_context().is_valid());
+        assert!(trace_response(&context).is_none());
+        assert!(context_headers(&context).is_empty());
+    }
+}
@@ -4,39 +4,44 @@ use opentelemetry_sdk::metrics::{Aggregation, InstrumentKind, ManualReader, Mete
 use opentelemetry_sdk::metrics::data::{ResourceMetrics, Temporality};
 use opentelemetry_sdk::metrics::reader::{AggregationSelector, MetricReader, TemporalitySelector};
 use opentelemetry_sdk::Resource;
-use crate::worker::MeterWorker;
+use crate::prometheus::PrometheusExporter;
 
 pub struct SigNozMeter;
 
 impl SigNozMeter {
-    pub fn setup(endpoint: String, token: Option<String>) -> MeterWorker {
+    /// Starts the meter provider, and a second reader for the Prometheus endpoint when `prometheus` is set
+    pub fn setup(prometheus: bool) -> (MeterProvider, Arc<ManualReader>, Option<PrometheusExporter>) {
         // Initialize the provider
-        let reader = Self::setup_meter_provider();
+        let prometheus_reader = prometheus.then(|| Arc::new(ManualReader::builder().build()));
+        let (provider, reader) = Self::setup_meter_provider(prometheus_reader.clone());
 
-        MeterWorker::new(reader, endpoint, token)
+        (provider, reader, prometheus_reader.map(PrometheusExporter::new))
     }
 
-    fn setup_meter_provider() -> Arc<ManualReader> {
+    fn setup_meter_provider(prometheus_reader: Option<Arc<ManualReader>>) -> (MeterProvider, Arc<ManualReader>) {
         let resource = Resource::new(vec![
             KeyValue::new("service.name", "api"),
         ]);
 
         let reader = Arc::new(ManualReader::builder().build());
 
-        let provider = MeterProvider::builder()
+        let mut builder = MeterProvider::builder()
             .with_resource(resource)
-            .with_reader(SharedReader(reader.clone()))
-            .build();
+            .with_reader(SharedReader(reader.clone()));
+        if let Some(prometheus_reader) = prometheus_reader {
+            builder = builder.with_reader(SharedReader(prometheus_reader));
+        }
+        let provider = builder.build();
 
-        global::set_meter_provider(provider);
+        global::set_meter_provider(provider.clone());
 
-        reader
+        (provider, reader)
     }
 
 }
 
 #[derive(Clone, Debug)]
-struct SharedReader(Arc<dyn MetricReader>);
+pub(crate) struct SharedReader(pub(crate) Arc<dyn MetricReader>);
 
 impl TemporalitySelector for SharedReader {
     fn temporality(&self, kind: InstrumentKind) -> Temporality {
@@ -4,4 +4,6 @@ mod meter;
 
 pub use exporter_error::SigNozExportError;
 pub use tracer::SigNozTracer;
-pub use meter::SigNozMeter;
\ No newline at end of file
+pub use meter::SigNozMeter;
+#[cfg(test)]
+pub(crate) use meter::SharedReader;
\ No newline at end of file
@@ -1,9 +1,9 @@
 use crate::provider::exporter_error::SigNozExportError;
-use crate::worker::TraceWorker;
+use crate::export::{ExportStats, Signal};
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::sync::mpsc;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
 use futures_core::future::BoxFuture;
 use opentelemetry::{global, KeyValue};
 use opentelemetry::trace::TraceError;
@@ -12,27 +12,35 @@ use opentelemetry_sdk::Resource;
 use opentelemetry_sdk::trace::{Config, TracerProvider};
 
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct SigNozTracer {
-    sender: Sender<Vec<SpanData>>,
+    sender: SyncSender<SpanData>,
+    stats: ExportStats,
+}
+
+impl Debug for SigNozTracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigNozTracer").finish_non_exhaustive()
+    }
 }
 
 impl SigNozTracer {
-    fn new(sender: Sender<Vec<SpanData>>) -> Self {
+    fn new(sender: SyncSender<SpanData>, stats: ExportStats) -> Self {
         Self {
-            sender
+            sender,
+            stats,
         }
     }
 
-    pub fn setup(endpoint: String) -> TraceWorker {
-        // Create the channel
-        let (sender, receiver) = mpsc::channel();
+    /// Starts the tracer provider, the spans are queued until the trace worker receives them
+    pub fn setup(queue_size: usize, stats: ExportStats) -> Receiver<SpanData> {
+        // Create the channel, bounded so that the spans cannot pile up while the endpoint is down
+        let (sender, receiver) = mpsc::sync_channel(queue_size);
 
         // Initialize the provider
-        Self::new(sender).setup_provider();
+        Self::new(sender, stats).setup_provider();
 
-        // Create a trace worker
-        TraceWorker::new(receiver, endpoint)
+        receiver
     }
 
     fn setup_provider(self) {
@@ -53,11 +61,22 @@ impl SigNozTracer {
 impl SpanExporter for SigNozTracer {
     fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
         let sender = self.sender.clone();
+        let stats = self.stats.clone();
         Box::pin(async move {
-            match sender.send(batch) {
-                Ok(()) => Ok(()),
-                Err(err) => Err(TraceError::ExportFailed(Box::new(SigNozExportError::new(&err.to_string())))) // TODO Replace with appropriate error handling
+            let mut dropped = 0;
+            for span in batch {
+                match sender.try_send(span) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => dropped += 1,
+                    Err(err @ TrySendError::Disconnected(_)) => {
+                        return Err(TraceError::ExportFailed(Box::new(SigNozExportError::new(&err.to_string()))))
+                    }
+                }
+            }
+            if dropped > 0 {
+                stats.dropped(Signal::Traces, dropped);
             }
+            Ok(())
         })
     }
 }
@@ -1,34 +1,135 @@
-use std::env;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+/// Where the traces, metrics and logs go, named after the values of `OTEL_EXPORTER_OTLP_PROTOCOL`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ExporterKind {
+    #[default]
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+    #[serde(rename = "http/json")]
+    HttpJson,
+    #[serde(rename = "grpc")]
+    Grpc,
+    // Prints the payloads as OTLP JSON, to look at the telemetry without a collector
+    #[serde(rename = "stdout")]
+    Stdout,
+    // Discards the payloads
+    #[serde(rename = "none")]
+    None,
+}
+
+impl ExporterKind {
+    /// Whether the exporter sends the payloads to `hostname`
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Self::HttpProtobuf | Self::HttpJson | Self::Grpc)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct TelemetrySettings {
+    // The exporter the traces, metrics and logs are sent with
+    pub exporter: ExporterKind,
+    // The OTLP endpoint the traces, metrics and logs are exported to, usually on port 4318 over HTTP and 4317 over gRPC
     pub hostname: String,
+    // The resource attributes, as `key=value` pairs separated by commas
     pub attributes: String,
+    // The headers sent along with every export, e.g. for the authentication to the collector
+    pub headers: BTreeMap<String, String>,
+    // The SigNoz access token, sent as the `signoz-access-token` header
     pub token: Option<String>,
+    // The number of spans waiting to be exported, the new spans are dropped when it is full
+    pub queue_size: usize,
+    // The maximum number of spans sent at once
+    pub batch_size: usize,
+    // How long the spans wait for their batch to fill, in milliseconds
+    pub batch_timeout_ms: u64,
+    // How often the metrics are exported, in milliseconds
+    pub metrics_interval_ms: u64,
+    // How many times a failed export is retried
+    pub max_retries: u32,
+    // The delay before the first retry, doubled after each attempt, in milliseconds
+    pub retry_backoff_ms: u64,
+    // The longest delay between two retries, in milliseconds
+    pub max_backoff_ms: u64,
+    // The directory the failed exports are stored in, to be sent again once the endpoint is back
+    pub spool_dir: Option<PathBuf>,
+    // The size the spool directory cannot exceed, the oldest exports are removed first
+    pub spool_max_bytes: u64,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            exporter: ExporterKind::default(),
+            hostname: "http://localhost:4318".to_string(),
+            attributes: String::new(),
+            headers: BTreeMap::new(),
+            token: None,
+            queue_size: 2048,
+            batch_size: 512,
+            batch_timeout_ms: 5000,
+            metrics_interval_ms: 10_000,
+            max_retries: 5,
+            retry_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            spool_dir: None,
+            spool_max_bytes: 64 * 1024 * 1024,
+        }
+    }
 }
 
 impl TelemetrySettings {
-    
-    pub fn from_env() -> Self {
-        let hostname = env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT")
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-        let attributes: String = env::var_os("OTEL_RESOURCE_ATTRIBUTES")
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-        let token: Option<String> = env::var_os("OTEL_EXPORTER_OTLP_TOKEN")
-            .map(|token| token.to_str().unwrap().to_string());
+    /// Returns the headers sent along with every export, the token included
+    pub fn export_headers(&self) -> BTreeMap<String, String> {
+        let mut headers = self.headers.clone();
+        if let Some(token) = &self.token {
+            headers.insert("signoz-access-token".to_string(), token.clone());
+        }
+        headers
+    }
+}
+
+/// How the log lines are written to the console
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // Human readable lines, for the development
+    #[default]
+    Pretty,
+    // One JSON object per line, for the log collectors
+    Json,
+}
 
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LoggingSettings {
+    // The format of the log lines
+    pub format: LogFormat,
+    // The level of the events that are logged, from `trace` to `error`
+    pub level: String,
+    // The levels of some modules, overriding `level`, e.g. `rocket = "warn"`
+    pub modules: BTreeMap<String, String>,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
         Self {
-            hostname,
-            attributes,
-            token,
+            format: LogFormat::default(),
+            level: "info".to_string(),
+            modules: BTreeMap::new(),
         }
     }
+}
 
-
+impl LoggingSettings {
+    /// Returns the filter directives of the levels, e.g. `info,rocket=warn`
+    pub fn directives(&self) -> String {
+        let mut directives = vec![self.level.clone()];
+        directives.extend(self.modules.iter().map(|(module, level)| format!("{module}={level}")));
+        directives.join(",")
+    }
 }
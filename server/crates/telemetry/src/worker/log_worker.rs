@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::Duration;
+use opentelemetry_proto::tonic::collector::logs::v1::ExportLogsServiceRequest;
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::logs::v1::{LogRecord, ResourceLogs, ScopeLogs};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use prost::Message;
+use tokio::runtime::Runtime;
+use crate::export::{ExportClient, Signal};
+use super::receive_batches;
+
+pub struct LogWorker {
+    receiver: Receiver<LogRecord>,
+    client: Arc<ExportClient>,
+    batch_size: usize,
+    batch_timeout: Duration,
+}
+
+impl LogWorker {
+
+    pub fn new(receiver: Receiver<LogRecord>, client: Arc<ExportClient>, batch_size: usize, batch_timeout: Duration) -> Self {
+        Self {
+            receiver,
+            client,
+            batch_size,
+            batch_timeout,
+        }
+    }
+
+    pub fn launch(self) {
+        thread::spawn(move || {
+            let rt = Runtime::new().unwrap();
+
+            rt.block_on(receive_batches(&self.receiver, self.batch_size, self.batch_timeout, |batch| {
+                self.process_batch(batch)
+            }));
+        });
+    }
+
+    async fn process_batch(&self, batch: Vec<LogRecord>) {
+        let items = batch.len() as u64;
+        self.client.export(Signal::Logs, Self::build_body(batch), items).await;
+    }
+
+    fn build_body(log_records: Vec<LogRecord>) -> Vec<u8> {
+        // The same resource as the one of the spans, for the logs to be found with the traces of the API
+        let resource = Resource {
+            attributes: vec![KeyValue {
+                key: "service.name".to_string(),
+                value: Some(AnyValue {
+                    value: Some(any_value::Value::StringValue("api".to_string())),
+                }),
+            }],
+            dropped_attributes_count: 0,
+        };
+        let req = ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                resource: Some(resource),
+                scope_logs: vec![ScopeLogs {
+                    scope: Some(InstrumentationScope {
+                        name: "api".to_string(),
+                        ..Default::default()
+                    }),
+                    log_records,
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        };
+
+        req.encode_to_vec()
+    }
+}
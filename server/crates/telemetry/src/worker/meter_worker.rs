@@ -6,34 +6,23 @@ use opentelemetry_sdk::metrics::data::ResourceMetrics;
 use opentelemetry_sdk::metrics::ManualReader;
 use opentelemetry_sdk::metrics::reader::MetricReader;
 use opentelemetry_sdk::Resource;
-use reqwest::{Client, ClientBuilder, header::HeaderMap, header::HeaderValue};
 use tokio::runtime::Runtime;
+use crate::export::{ExportClient, Signal};
 use crate::provider::SigNozExportError;
 
 pub struct MeterWorker {
     reader: Arc<ManualReader>,
-    endpoint: String,
-    client: Client,
+    client: Arc<ExportClient>,
+    interval: Duration,
 }
 
 impl MeterWorker {
 
-    pub fn new(reader: Arc<ManualReader>, endpoint: String, token: Option<String>) -> Self {
-        
-        let mut client = ClientBuilder::new();
-        
-        if let Some(token) = token {
-            let mut headers = HeaderMap::new();
-            headers.insert("signoz-access-token", HeaderValue::from_str(&token).unwrap());
-            client = client.default_headers(headers);
-        }
-        
-        let client = client.build().unwrap();
-        
+    pub fn new(reader: Arc<ManualReader>, client: Arc<ExportClient>, interval: Duration) -> Self {
         Self {
             reader,
-            endpoint,
-            client
+            client,
+            interval,
         }
     }
 
@@ -46,49 +35,39 @@ impl MeterWorker {
                     resource: Resource::default(),
                     scope_metrics: Vec::new()
                 };
-                while let Ok(_) = self.reader.collect(&mut metrics) {
-                    self.process(&mut metrics).await;
-                    tokio::time::sleep(Duration::from_secs(1)).await;
+                loop {
+                    tokio::time::sleep(self.interval).await;
+                    if self.reader.collect(&mut metrics).is_err() {
+                        break;
+                    }
+                    self.process(&metrics).await;
                 }
             });
         });
     }
 
-    async fn process(&self, metrics: &mut ResourceMetrics) {
-        println!("metrics {:?}", metrics);
-        if let Ok(data) = Self::build_body(metrics) {
-            self.send_request(data).await;
+    async fn process(&self, metrics: &ResourceMetrics) {
+        let items: usize = metrics.scope_metrics.iter().map(|scope| scope.metrics.len()).sum();
+        if items == 0 {
+            return;
         }
-    }
-
-    // Send the serialized data to SigNoz
-    async fn send_request(&self, (data, content_type): (Vec<u8>, &'static str)) {
-        let endpoint = &self.endpoint;
-        
-        let res = self.client.post(format!("{endpoint}/v1/metrics"))
-            .header("Content-Type", content_type)
-            .body(data)
-            .send()
-            .await;
-
-        match res {
-            Ok(response ) => {
-                eprintln!("SUCCESS: {:?}", response);
-            }
+        match Self::build_body(metrics) {
+            Ok(data) => self.client.export(Signal::Metrics, data, items as u64).await,
             Err(err) => {
-                eprintln!("Failed to send telemetry data to SigNoz: {}", err);
+                tracing::error!("Failed to encode the metrics: {err}");
+                self.client.stats().dropped(Signal::Metrics, items as u64);
             }
         }
     }
 
-    fn build_body(metrics: &mut ResourceMetrics) -> TraceResult<(Vec<u8>, &'static str)> {
+    fn build_body(metrics: &ResourceMetrics) -> TraceResult<Vec<u8>> {
         use prost::Message;
 
         let req: opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest =
-            (&*metrics).into();
+            metrics.into();
         let mut buf = vec![];
         req.encode(&mut buf).map_err(|err| TraceError::ExportFailed(Box::new(SigNozExportError::new(&err.to_string()))))?;
 
-        Ok((buf, "application/x-protobuf"))
+        Ok(buf)
     }
-}
\ No newline at end of file
+}
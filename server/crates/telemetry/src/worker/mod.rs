@@ -1,26 +1,75 @@
 mod trace_worker;
 mod meter_worker;
+mod log_worker;
+
+use std::future::Future;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
 
 pub use trace_worker::TraceWorker;
 pub use meter_worker::MeterWorker;
+pub use log_worker::LogWorker;
 
 pub struct TelemetryWorker {
     span_worker: TraceWorker,
     meter_worker: MeterWorker,
+    // Only set when the logs are exported
+    log_worker: Option<LogWorker>,
 }
 
 impl TelemetryWorker {
 
-    pub fn new(span_worker: TraceWorker, meter_worker: MeterWorker) -> Self {
+    pub fn new(span_worker: TraceWorker, meter_worker: MeterWorker, log_worker: Option<LogWorker>) -> Self {
         Self {
             span_worker,
-            meter_worker
+            meter_worker,
+            log_worker,
         }
     }
 
     pub fn launch(self) {
         self.span_worker.launch();
         self.meter_worker.launch();
+        if let Some(log_worker) = self.log_worker {
+            log_worker.launch();
+        }
     }
 
-}
\ No newline at end of file
+}
+/// Receives the items in batches, processed once they hold `batch_size` items or `batch_timeout` after their first one,
+/// until the senders are dropped
+pub(crate) async fn receive_batches<T, F, Fut>(receiver: &Receiver<T>, batch_size: usize, batch_timeout: Duration, mut process: F)
+where
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut batch = Vec::new();
+    // The time the oldest item of the batch must be processed by
+    let mut deadline = Instant::now();
+    loop {
+        let received = if batch.is_empty() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        };
+
+        match received {
+            Ok(item) => {
+                if batch.is_empty() {
+                    deadline = Instant::now() + batch_timeout;
+                }
+                batch.push(item);
+                if batch.len() >= batch_size {
+                    process(std::mem::take(&mut batch)).await;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => process(std::mem::take(&mut batch)).await,
+            Err(RecvTimeoutError::Disconnected) => {
+                if !batch.is_empty() {
+                    process(batch).await;
+                }
+                break;
+            }
+        }
+    }
+}
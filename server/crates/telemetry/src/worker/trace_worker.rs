@@ -1,24 +1,29 @@
+use std::sync::Arc;
 use std::sync::mpsc::Receiver;
 use std::thread;
+use std::time::Duration;
 use opentelemetry::trace::{TraceError, TraceResult};
 use opentelemetry_sdk::export::trace::SpanData;
-use reqwest::Client;
 use tokio::runtime::Runtime;
+use crate::export::{ExportClient, Signal};
 use crate::provider::SigNozExportError;
+use super::receive_batches;
 
 pub struct TraceWorker {
-    receiver: Receiver<Vec<SpanData>>,
-    endpoint: String,
-    client: Client
+    receiver: Receiver<SpanData>,
+    client: Arc<ExportClient>,
+    batch_size: usize,
+    batch_timeout: Duration,
 }
 
 impl TraceWorker {
 
-    pub fn new(receiver: Receiver<Vec<SpanData>>, endpoint: String) -> Self {
+    pub fn new(receiver: Receiver<SpanData>, client: Arc<ExportClient>, batch_size: usize, batch_timeout: Duration) -> Self {
         Self {
             receiver,
-            endpoint,
-            client: Client::new()
+            client,
+            batch_size,
+            batch_timeout,
         }
     }
 
@@ -26,41 +31,24 @@ impl TraceWorker {
         thread::spawn(move || {
             let rt = Runtime::new().unwrap();
 
-            rt.block_on(async {
-                while let Ok(batch) = self.receiver.recv() {
-                    self.process_batch(batch).await;
-                }
-            });
+            rt.block_on(receive_batches(&self.receiver, self.batch_size, self.batch_timeout, |batch| {
+                self.process_batch(batch)
+            }));
         });
     }
 
     async fn process_batch(&self, batch: Vec<SpanData>) {
-        println!("data {:?}", batch);
-        if let Ok(data) = Self::build_body(batch) {
-            self.send_request(data).await;
-        }
-    }
-
-    // Send the serialized data to SigNoz
-    async fn send_request(&self, (data, content_type): (Vec<u8>, &'static str)) {
-        let endpoint = &self.endpoint;
-        let res = self.client.post(format!("{endpoint}/v1/traces"))
-            .header("Content-Type", content_type)
-            .body(data)
-            .send()
-            .await;
-
-        match res {
-            Ok(response ) => {
-                eprintln!("SUCCESS: {:?}", response);
-            }
+        let items = batch.len() as u64;
+        match Self::build_body(batch) {
+            Ok(data) => self.client.export(Signal::Traces, data, items).await,
             Err(err) => {
-                eprintln!("Failed to send telemetry data to SigNoz: {}", err);
+                tracing::error!("Failed to encode the spans: {err}");
+                self.client.stats().dropped(Signal::Traces, items);
             }
         }
     }
 
-    fn build_body(spans: Vec<SpanData>) -> TraceResult<(Vec<u8>, &'static str)> {
+    fn build_body(spans: Vec<SpanData>) -> TraceResult<Vec<u8>> {
         use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
         use prost::Message;
 
@@ -70,6 +58,6 @@ impl TraceWorker {
         let mut buf = vec![];
         req.encode(&mut buf).map_err(|err| TraceError::ExportFailed(Box::new(SigNozExportError::new(&err.to_string()))))?;
 
-        Ok((buf, "application/x-protobuf"))
+        Ok(buf)
     }
-}
\ No newline at end of file
+}
@@ -1,17 +1,41 @@
 //#![feature(coverage_attribute)]
 
-use clap::{Arg, Command};
+use api::settings::ApiSettings;
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use rocket::*;
 
 //#[coverage(off)]
-fn export_env() -> Result<(), String> {
-    let app = Command::new("").subcommand_negates_reqs(true).arg(
-        Arg::new("envfile")
-            .help("Export the .env file to environment variables before run")
-            .long("envfile")
-            .short('e'),
-    );
-    let matches = app.get_matches();
+fn parse_args() -> ArgMatches {
+    Command::new("")
+        .subcommand_negates_reqs(true)
+        .arg(
+            Arg::new("check-config")
+                .help("Validate the configuration and exit")
+                .long("check-config")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("envfile")
+                .help("Export the .env file to environment variables before run")
+                .long("envfile")
+                .short('e')
+                .global(true),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Apply the pending database migrations and exit")
+                .arg(
+                    Arg::new("dry-run")
+                        .help("Only list the pending migrations and the changes they would make")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .get_matches()
+}
+
+//#[coverage(off)]
+fn export_env(matches: &ArgMatches) -> Result<(), String> {
     let env_file: Option<&String> = matches.get_one("envfile");
 
     if let Some(env_file) = env_file {
@@ -20,15 +44,63 @@ fn export_env() -> Result<(), String> {
     Ok(())
 }
 
+//#[coverage(off)]
+fn retrieve_settings() -> ApiSettings {
+    match ApiSettings::retrieve() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("Invalid configuration:\n{err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+//#[coverage(off)]
+async fn migrate(settings: &ApiSettings, dry_run: bool) -> ! {
+    match api::migrate(settings, dry_run).await {
+        Ok(outcomes) if outcomes.is_empty() => println!("The database is up to date."),
+        Ok(outcomes) => {
+            for outcome in outcomes {
+                let state = if outcome.applied { "applied" } else { "pending" };
+                println!("{:>4} {} ({state})", outcome.version, outcome.name);
+                for change in outcome.changes {
+                    println!("       - {change}");
+                }
+            }
+        }
+        Err(err) => {
+            eprintln!("Migration failed: {err}");
+            std::process::exit(1);
+        }
+    }
+    std::process::exit(0);
+}
+
 //#[coverage(off)]
 #[launch]
 async fn rocket() -> _ {
-    let env = export_env();
-    
+    let matches = parse_args();
+    let env = export_env(&matches);
+
     if env.is_err() {
         eprintln!("{:?}", env.err());
         std::process::exit(0);
     }
 
-    api::get_rocket()
+    let settings = retrieve_settings();
+    if matches.get_flag("check-config") {
+        println!("The configuration is valid.");
+        std::process::exit(0);
+    }
+
+    // The configuration errors above are printed as they are, the logger needs a valid configuration
+    if let Err(err) = api::init_logging(&settings) {
+        eprintln!("Cannot install the logger: {err}");
+    }
+
+    if let Some(("migrate", migrate_matches)) = matches.subcommand() {
+        migrate(&settings, migrate_matches.get_flag("dry-run")).await;
+    }
+
+    api::get_rocket(settings)
 }